@@ -0,0 +1,197 @@
+//! Compares the sharded message cache against the previous single lock cache,
+//! whose queue reindexed every remaining message on each eviction.
+//!
+//! Run with `cargo bench --bench message_cache`.
+
+// the cache modules are compiled into this benchmark as is, most of their helpers are unused here
+#![allow(dead_code)]
+
+use std::{hint::black_box, thread};
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+
+#[path = "../src/utils/cache/message_cache.rs"]
+mod message_cache;
+#[path = "../src/utils/cache/partials.rs"]
+pub mod partials;
+
+/// Mirrors the module path the cache uses to reach the partials
+mod utils {
+    pub mod cache {
+        pub use crate::partials;
+    }
+}
+
+use message_cache::MessageCache;
+use partials::{PartialMessage, PartialUser};
+
+/// The cache as it was before sharding, kept to compare against
+mod legacy {
+    use std::collections::{HashMap, VecDeque};
+
+    use crate::partials::PartialMessage;
+
+    #[derive(Default)]
+    pub struct MessageCache {
+        sizes: HashMap<u64, usize>,
+        messages: HashMap<u64, MessageQueue>,
+    }
+
+    impl MessageCache {
+        pub fn assign_count(&mut self, channel: u64, count: usize) {
+            self.sizes.insert(channel, count);
+        }
+
+        pub fn insert(&mut self, channel_id: u64, message: PartialMessage) {
+            let queue_size = *self.sizes.entry(channel_id).or_insert(100);
+            let queue = self.messages.entry(channel_id).or_default();
+
+            if queue.items.len() >= queue_size {
+                queue.pop();
+            }
+
+            queue.insert(message);
+        }
+
+        pub fn get(&mut self, channel: u64, message: u64) -> Option<&PartialMessage> {
+            let queue = self.messages.entry(channel).or_default();
+            queue.index.get(&message).map(|&i| &queue.items[i])
+        }
+    }
+
+    #[derive(Default)]
+    struct MessageQueue {
+        items: VecDeque<PartialMessage>,
+        index: HashMap<u64, usize>,
+    }
+
+    impl MessageQueue {
+        fn insert(&mut self, msg: PartialMessage) {
+            if let Some(&idx) = self.index.get(&msg.id) {
+                self.items[idx] = msg;
+            } else {
+                self.index.insert(msg.id, self.items.len());
+                self.items.push_back(msg);
+            }
+        }
+
+        fn pop(&mut self) {
+            if let Some(msg) = self.items.pop_front() {
+                self.index.remove(&msg.id);
+
+                for (i, m) in self.items.iter().enumerate() {
+                    self.index.insert(m.id, i);
+                }
+            }
+        }
+    }
+}
+
+const THREADS: u64 = 8;
+const MESSAGES_PER_THREAD: u64 = 2_000;
+
+fn message(channel_id: u64, id: u64) -> PartialMessage {
+    PartialMessage {
+        id,
+        guild_id: Some(1),
+        channel_id,
+        content: String::from("the quick brown fox jumps over the lazy dog"),
+        author: PartialUser {
+            id: 2,
+            name: String::from("user"),
+            bot: false,
+        },
+        attachment_urls: vec![],
+        reply_to: None,
+    }
+}
+
+/// Inserts into a single full channel, so every insert evicts the oldest message
+fn eviction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("insert_full_channel");
+
+    for size in [100_usize, 1_000, 10_000] {
+        group.throughput(Throughput::Elements(1));
+
+        group.bench_with_input(BenchmarkId::new("legacy", size), &size, |b, &size| {
+            let mut cache = legacy::MessageCache::default();
+            cache.assign_count(1, size);
+
+            for id in 0..size as u64 {
+                cache.insert(1, message(1, id));
+            }
+
+            let mut id = size as u64;
+            b.iter(|| {
+                cache.insert(1, message(1, id));
+                black_box(cache.get(1, id));
+                id += 1;
+            });
+        });
+
+        group.bench_with_input(BenchmarkId::new("sharded", size), &size, |b, &size| {
+            let cache = MessageCache::new();
+            cache.assign_count(1, size);
+
+            for id in 0..size as u64 {
+                cache.insert(1, message(1, id));
+            }
+
+            let mut id = size as u64;
+            b.iter(|| {
+                cache.insert(1, message(1, id));
+                black_box(cache.get(1, id));
+                id += 1;
+            });
+        });
+    }
+
+    group.finish();
+}
+
+/// Several threads inserting into their own channels at once, like message events of a busy bot
+fn contention(c: &mut Criterion) {
+    let mut group = c.benchmark_group("concurrent_inserts");
+    group.throughput(Throughput::Elements(THREADS * MESSAGES_PER_THREAD));
+    group.sample_size(20);
+
+    group.bench_function("legacy", |b| {
+        b.iter(|| {
+            // the handler used to keep the cache behind a single tokio mutex
+            let cache = tokio::sync::Mutex::new(legacy::MessageCache::default());
+
+            thread::scope(|s| {
+                for channel in 0..THREADS {
+                    let cache = &cache;
+                    s.spawn(move || {
+                        for id in 0..MESSAGES_PER_THREAD {
+                            cache.blocking_lock().insert(channel, message(channel, id));
+                        }
+                    });
+                }
+            });
+        });
+    });
+
+    group.bench_function("sharded", |b| {
+        b.iter(|| {
+            let cache = MessageCache::new();
+
+            thread::scope(|s| {
+                for channel in 0..THREADS {
+                    let cache = &cache;
+                    s.spawn(move || {
+                        for id in 0..MESSAGES_PER_THREAD {
+                            cache.insert(channel, message(channel, id));
+                        }
+                    });
+                }
+            });
+        });
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, eviction, contention);
+criterion_main!(benches);
@@ -0,0 +1,228 @@
+use std::{collections::HashMap, sync::Arc};
+
+use ouroboros_macros::command;
+use serenity::{
+    all::{Context, CreateAllowedMentions, CreateEmbed, CreateMessage, Message, Permissions},
+    async_trait, json,
+};
+use sqlx::query;
+use tracing::warn;
+
+use crate::{
+    CommandNamesContainer, GUILD_SETTINGS, SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::CommandError,
+    lexer::Token,
+    transformers::Transformers,
+    utils::{
+        command_processing::{
+            ALIAS_USER_PLACEHOLDER, MAX_ALIASES, validate_alias_expansion, validate_alias_name,
+        },
+        consume_pgsql_error,
+    },
+};
+
+pub struct Alias;
+
+impl Alias {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for Alias {
+    fn get_name(&self) -> &'static str {
+        "alias"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Manages command aliases of the server"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Manages command aliases of the server. \
+        Available subcommands: list add remove;\n \
+        `list` lists all aliases\n \
+        `add <name> <command...>` adds an alias which runs the command line, i.e. `add spam mute {user} 1h spamming`\n \
+        `remove <name>` removes an alias\n \
+        `{user}` is replaced by the first argument given to the alias, or inferred when replying to a message. \
+        Any further arguments are appended to the command line."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::String("subcommand", false),
+            CommandSyntax::String("name", false),
+            CommandSyntax::Consume("command"),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::some_string] subcommand: Option<String>,
+        #[transformers::some_string] name: Option<String>,
+        #[transformers::consume] expansion: Option<String>,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        let mut aliases: HashMap<String, String> = {
+            let mut lock = GUILD_SETTINGS.lock().await;
+            lock.get(guild_id.get())
+                .await
+                .map(|s| s.commands.aliases)
+                .unwrap_or_default()
+        };
+
+        let subcommand = subcommand.unwrap_or(String::from("list")).to_lowercase();
+        let name = name.map(|n| n.to_lowercase());
+
+        let description = match subcommand.as_str() {
+            "list" => {
+                let mut list = aliases
+                    .iter()
+                    .map(|(name, expansion)| format!("`{name}` -> `{expansion}`"))
+                    .collect::<Vec<_>>();
+                list.sort();
+
+                if list.is_empty() {
+                    String::from("**ALIASES**\nThis server has no aliases.")
+                } else {
+                    format!("**ALIASES**\n{}", list.join("\n"))
+                }
+            }
+
+            "add" => {
+                let Some(name) = name else {
+                    return Err(CommandError::arg_not_found("String", Some("name")));
+                };
+
+                let expansion = expansion
+                    .map(|e| e.trim().to_string())
+                    .filter(|e| !e.is_empty())
+                    .ok_or(CommandError::arg_not_found("String", Some("command")))?;
+
+                let command_names = {
+                    let data = ctx.data.read().await;
+                    data.get::<CommandNamesContainer>().unwrap().clone()
+                };
+
+                if let Err(err) = validate_alias_name(&name, &command_names) {
+                    return Err(CommandError {
+                        title: err,
+                        hint: None,
+                        arg: Some(args[1].clone()),
+                    });
+                }
+
+                if let Err(err) = validate_alias_expansion(&expansion, &command_names) {
+                    return Err(CommandError {
+                        title: err,
+                        hint: None,
+                        arg: Some(args[2].clone()),
+                    });
+                }
+
+                if !aliases.contains_key(&name) && aliases.len() >= MAX_ALIASES {
+                    return Err(CommandError {
+                        title: format!("Servers can have at most {MAX_ALIASES} aliases"),
+                        hint: Some(String::from("remove an alias before adding a new one")),
+                        arg: None,
+                    });
+                }
+
+                let description = format!(
+                    "**ALIAS ADDED**\n`{name}` -> `{expansion}`{}",
+                    if expansion.contains(ALIAS_USER_PLACEHOLDER) {
+                        format!("\n-# `{ALIAS_USER_PLACEHOLDER}` is replaced by the first argument")
+                    } else {
+                        String::new()
+                    }
+                );
+
+                aliases.insert(name, expansion);
+                description
+            }
+
+            "remove" => {
+                let Some(name) = name else {
+                    return Err(CommandError::arg_not_found("String", Some("name")));
+                };
+
+                if aliases.remove(&name).is_none() {
+                    return Err(CommandError {
+                        title: String::from("Alias not found"),
+                        hint: Some(String::from("run the list subcommand to see all aliases")),
+                        arg: Some(args[1].clone()),
+                    });
+                }
+
+                format!("**ALIAS REMOVED**\n`{name}`")
+            }
+
+            _ => {
+                return Err(CommandError {
+                    title: String::from("Unknown subcommand"),
+                    hint: Some(String::from("available subcommands: list, add, remove")),
+                    arg: Some(args[0].clone()),
+                });
+            }
+        };
+
+        if subcommand != "list" {
+            let res = query!(
+                "INSERT INTO guild_settings (guild_id, aliases) VALUES ($1, $2) ON CONFLICT (guild_id) DO UPDATE SET aliases = EXCLUDED.aliases",
+                guild_id.get() as i64,
+                json::to_value(&aliases).unwrap()
+            )
+            .execute(&*SQL)
+            .await;
+
+            if let Err(err) = res {
+                consume_pgsql_error(String::from("ALIAS UPDATE"), err);
+                return Err(CommandError {
+                    title: String::from("Could not update the database"),
+                    hint: Some(String::from("please try again later")),
+                    arg: None,
+                });
+            }
+
+            let mut lock = GUILD_SETTINGS.lock().await;
+            lock.invalidate();
+        }
+
+        let reply = CreateMessage::new()
+            .add_embed(CreateEmbed::new().description(description).color(BRAND_BLUE))
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            warn!("Could not send message; err = {err:?}");
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use std::sync::Arc;
+
+use chrono::Duration;
+use ouroboros_macros::command;
+use serenity::{
+    all::{Context, CreateAllowedMentions, CreateEmbed, CreateMessage, Message, Permissions},
+    async_trait, json,
+};
+use sqlx::query;
+use tracing::warn;
+
+use crate::{
+    GUILD_SETTINGS, RaidDetectorContainer, SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerError, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::CommandError,
+    lexer::Token,
+    transformers::Transformers,
+    utils::{AntiRaidConfig, RaidAction, consume_pgsql_error, end_raid_mode, humanize_duration},
+};
+
+pub struct AntiRaid;
+
+impl AntiRaid {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Parses a duration argument using the duration transformer
+    async fn parse_duration(
+        &self,
+        ctx: &Context,
+        msg: &Message,
+        token: &Token,
+    ) -> Result<Duration, CommandError> {
+        let mut fake_args = vec![token.clone()].into_iter().peekable();
+
+        match Transformers::duration(ctx, msg, &mut fake_args).await {
+            Ok(Token {
+                contents: Some(CommandArgument::Duration(d)),
+                ..
+            }) => Ok(d),
+            Err(TransformerError::CommandError(err)) => Err(err),
+            _ => Err(CommandError {
+                title: String::from("Could not turn input to a <Duration>"),
+                hint: None,
+                arg: Some(token.clone()),
+            }),
+        }
+    }
+
+    fn describe(config: &AntiRaidConfig) -> String {
+        let status = if config.enabled {
+            "enabled"
+        } else {
+            "disabled"
+        };
+
+        format!(
+            "Anti-raid is **{status}**\n\
+            `joins` {} joins within `window` {}\n\
+            `age` accounts younger than {} count as new\n\
+            `ratio` {}% new accounts within the window\n\
+            `action` {}\n\
+            `verification` raise to high during raid mode: {}\n\
+            `cooldown` raid mode ends after {} without joins",
+            config.joins,
+            humanize_duration(&Duration::seconds(config.window)),
+            humanize_duration(&Duration::seconds(config.account_age)),
+            config.new_ratio,
+            config.describe_action(),
+            if config.raise_verification {
+                "yes"
+            } else {
+                "no"
+            },
+            humanize_duration(&Duration::seconds(config.cooldown)),
+        )
+    }
+}
+
+#[async_trait]
+impl Command for AntiRaid {
+    fn get_name(&self) -> &'static str {
+        "antiraid"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Configures raid detection and raid mode"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Configures raid detection. Raid mode starts when too many members join within a window, \
+        or when most of the recent joins are new accounts. During raid mode members joining are timed out or kicked by the bot, \
+        an alert with a button to end raid mode is posted to the member moderation log. \
+        Available subcommands: status enable disable end set;\n \
+        `status` shows the current configuration\n \
+        `enable` and `disable` toggle raid detection\n \
+        `end` ends an active raid mode\n \
+        `set <key> <value>` changes a setting, keys: joins window age ratio action duration verification cooldown, \
+        i.e. `set joins 8`, `set window 30s`, `set action kick` or `set verification on`"
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::String("subcommand", false),
+            CommandSyntax::String("key", false),
+            CommandSyntax::String("value", false),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::some_string] subcommand: Option<String>,
+        #[transformers::some_string] key: Option<String>,
+        #[transformers::some_string] value: Option<String>,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        let mut config = {
+            let mut lock = GUILD_SETTINGS.lock().await;
+            lock.get(guild_id.get())
+                .await
+                .map(|s| s.moderation.anti_raid)
+                .unwrap_or_default()
+        };
+
+        let subcommand = subcommand.unwrap_or(String::from("status")).to_lowercase();
+        let mut changed = true;
+
+        let description = match subcommand.as_str() {
+            "status" => {
+                changed = false;
+                format!("**ANTI-RAID**\n{}", Self::describe(&config))
+            }
+
+            "enable" => {
+                config.enabled = true;
+                format!("**ANTI-RAID ENABLED**\n{}", Self::describe(&config))
+            }
+
+            "disable" => {
+                config.enabled = false;
+                String::from("**ANTI-RAID DISABLED**")
+            }
+
+            "end" => {
+                changed = false;
+
+                let detector = {
+                    let data = ctx.data.read().await;
+                    data.get::<RaidDetectorContainer>().unwrap().clone()
+                };
+
+                if !end_raid_mode(&ctx, &detector, guild_id, Some(msg.author.id)).await {
+                    return Err(CommandError {
+                        title: String::from("Raid mode is not active"),
+                        hint: None,
+                        arg: Some(args[0].clone()),
+                    });
+                }
+
+                String::from("**RAID MODE ENDED**")
+            }
+
+            "set" => {
+                let Some(key) = key.map(|k| k.to_lowercase()) else {
+                    return Err(CommandError::arg_not_found("String", Some("key")));
+                };
+
+                let Some(value) = value.map(|v| v.to_lowercase()) else {
+                    return Err(CommandError::arg_not_found("String", Some("value")));
+                };
+
+                let invalid = |title: &str| CommandError {
+                    title: String::from(title),
+                    hint: None,
+                    arg: Some(args[2].clone()),
+                };
+
+                match key.as_str() {
+                    "joins" => {
+                        config.joins = value
+                            .parse::<u32>()
+                            .map_err(|_| invalid("The amount of joins must be a number"))?;
+                    }
+                    "ratio" => {
+                        config.new_ratio = value
+                            .trim_end_matches('%')
+                            .parse::<u8>()
+                            .map_err(|_| invalid("The ratio must be a percentage"))?;
+                    }
+                    "action" => {
+                        config.action = match value.as_str() {
+                            "timeout" | "mute" => RaidAction::Timeout,
+                            "kick" => RaidAction::Kick,
+                            _ => {
+                                return Err(CommandError {
+                                    title: String::from("Unknown action"),
+                                    hint: Some(String::from("available actions: timeout, kick")),
+                                    arg: Some(args[2].clone()),
+                                });
+                            }
+                        };
+                    }
+                    "verification" => {
+                        config.raise_verification = match value.as_str() {
+                            "on" | "true" | "yes" => true,
+                            "off" | "false" | "no" => false,
+                            _ => return Err(invalid("Expected on or off")),
+                        };
+                    }
+                    "window" | "age" | "duration" | "cooldown" => {
+                        let seconds = self
+                            .parse_duration(&ctx, &msg, &args[2])
+                            .await?
+                            .num_seconds();
+
+                        match key.as_str() {
+                            "window" => config.window = seconds,
+                            "age" => config.account_age = seconds,
+                            "duration" => config.duration = seconds,
+                            _ => config.cooldown = seconds,
+                        }
+                    }
+                    _ => {
+                        return Err(CommandError {
+                            title: String::from("Unknown setting"),
+                            hint: Some(String::from(
+                                "available settings: joins, window, age, ratio, action, duration, verification, cooldown",
+                            )),
+                            arg: Some(args[1].clone()),
+                        });
+                    }
+                }
+
+                config.validate().map_err(|err| invalid(&err))?;
+
+                format!("**ANTI-RAID UPDATED**\n{}", Self::describe(&config))
+            }
+
+            _ => {
+                return Err(CommandError {
+                    title: String::from("Unknown subcommand"),
+                    hint: Some(String::from(
+                        "available subcommands: status, enable, disable, end, set",
+                    )),
+                    arg: Some(args[0].clone()),
+                });
+            }
+        };
+
+        if changed {
+            let res = query!(
+                "UPDATE guild_settings SET anti_raid = $2 WHERE guild_id = $1",
+                guild_id.get() as i64,
+                json::to_value(&config).unwrap()
+            )
+            .execute(&*SQL)
+            .await;
+
+            if let Err(err) = res {
+                consume_pgsql_error(String::from("ANTI-RAID UPDATE"), err);
+                return Err(CommandError {
+                    title: String::from("Could not update the database"),
+                    hint: Some(String::from("please try again later")),
+                    arg: None,
+                });
+            }
+
+            let mut lock = GUILD_SETTINGS.lock().await;
+            lock.invalidate();
+        }
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(description)
+                    .color(BRAND_BLUE),
+            )
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            warn!("Could not send message; err = {err:?}");
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+        }
+    }
+}
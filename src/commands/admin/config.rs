@@ -1,44 +1,42 @@
-#![allow(dead_code)]
-use std::{collections::HashMap, sync::Arc};
+use std::sync::Arc;
 
+use ouroboros_macros::command;
 use serenity::{
-    all::{Context, CreateAllowedMentions, CreateEmbed, CreateMessage, Message, Permissions},
+    all::{
+        Context, CreateAllowedMentions, CreateAttachment, CreateEmbed, CreateMessage, GuildId,
+        Message, Permissions,
+    },
     async_trait,
-    json::{self, Value},
+    json::{self, JsonMap, Value},
 };
-use tracing::{error, warn};
+use sqlx::query;
+use tracing::warn;
 
 use crate::{
     GUILD_SETTINGS, SQL,
     commands::{
-        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions, CommandSyntax, TransformerError, TransformerFn, TransformerFnArc
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc,
     },
     constants::BRAND_BLUE,
     event_handler::CommandError,
     lexer::Token,
+    tasks::delete_opted_out_snapshot,
     transformers::Transformers,
-    utils::Settings,
+    utils::{SETTINGS, SettingContext, SettingDefinition, consume_pgsql_error, find_setting},
 };
 
-/// The signature of a Transformer function boxed
-type BoxedTransformerFn = Box<TransformerFn>;
+/// The maximum size of an imported configuration
+const MAX_IMPORT_BYTES: u32 = 256 * 1024;
+/// Values longer than this are cut off by `config get`, the export contains them in full
+const MAX_DISPLAY_LENGTH: usize = 1500;
 
-// Disabled until its actually needed
 pub struct Config;
 
 impl Config {
     pub fn new() -> Self {
         Self {}
     }
-
-    /// Matches a bot config option to a description string
-    fn get_option_desc(&self, opt: &str) -> &str {
-        match opt {
-            "log" => "Settings controlling guild event logging",
-            "log.log_bots" => "<Bool> Include bots in server activity logs",
-            _ => "",
-        }
-    }
 }
 
 #[async_trait]
@@ -53,18 +51,22 @@ impl Command for Config {
 
     fn get_full(&self) -> &'static str {
         "Configures functions of the bot. \
-        Available subcommands: list set get;\n \
-        `list [group]` lists all groups/keys in a group\n \
-        `set <group>.<key> <value>` sets a setting to value\n \
-        `get <group>.<key>` gets the value of a setting \
-        To clear a setting set its value to `none`."
+        Available subcommands: list get set reset export import;\n \
+        `list [group]` lists all settings or the settings of a group\n \
+        `get <group>.<key>` shows the value of a setting\n \
+        `set <group>.<key> <value>` sets a setting, text and channel settings can be cleared with `none`\n \
+        `reset <group>.<key>` restores the default of a setting\n \
+        `export` sends the whole configuration as a JSON file\n \
+        `import` applies a JSON file attached to the message or pasted after the subcommand, \
+        settings missing from it are left alone and `null` resets them. \
+        Managed settings can only be imported, they are edited with their own command."
     }
 
     fn get_syntax(&self) -> Vec<CommandSyntax> {
         vec![
-            CommandSyntax::String("subcommand", true),
-            CommandSyntax::String("argument1", false),
-            CommandSyntax::String("argument2", false),
+            CommandSyntax::String("subcommand", false),
+            CommandSyntax::String("key", false),
+            CommandSyntax::Consume("value"),
         ]
     }
 
@@ -76,270 +78,232 @@ impl Command for Config {
         vec![]
     }
 
+    #[command]
     async fn run(
         &self,
         ctx: Context,
         msg: Message,
-        args: Vec<Token>,
-        _params: HashMap<&str, (bool, CommandArgument)>,
+        #[transformers::some_string] subcommand: Option<String>,
+        #[transformers::some_string] key: Option<String>,
+        #[transformers::consume] value: Option<String>,
     ) -> Result<(), CommandError> {
-        // find the config subcommand first
-        let mut args_iter = args.into_iter();
-
-        let Some(subcommand_token) = args_iter.next() else {
-            return Err(CommandError::arg_not_found("String", Some("subcommand")));
-        };
-        let Token {
-            contents: Some(CommandArgument::String(subcommand)),
-            ..
-        } = subcommand_token.clone()
-        else {
-            return Err(CommandError::arg_not_found("String", Some("subcommand")));
-        };
-
-        // process the first/second arg for the subcommand
-        let arg1_token = args_iter.next();
-        let arg1: Option<String> = match arg1_token.clone() {
-            Some(arg) => match arg.contents {
-                Some(CommandArgument::String(inner)) => Some(inner),
-                _ => None,
-            },
-            None => None,
-        };
+        let guild_id = msg.guild_id.unwrap();
+        let subcommand = subcommand.unwrap_or(String::from("list")).to_lowercase();
 
-        let arg2_token = args_iter.next();
-        let arg2: Option<String> = match arg2_token.clone() {
-            Some(arg) => match arg.contents {
-                Some(CommandArgument::String(inner)) => Some(inner),
-                _ => None,
+        let setting = match (subcommand.as_str(), &key) {
+            ("get" | "set" | "reset", None) => {
+                return Err(CommandError::arg_not_found("String", Some("key")));
+            }
+            ("get" | "set" | "reset", Some(key)) => match find_setting(key) {
+                Some(s) => Some(s),
+                None => {
+                    return Err(CommandError {
+                        title: String::from("Could not find setting"),
+                        hint: Some(String::from(
+                            "run `config list` for a list of valid settings",
+                        )),
+                        arg: Some(args[1].clone()),
+                    });
+                }
             },
-            None => None,
+            _ => None,
         };
 
-        // grab the settings struct
-        let mut global = GUILD_SETTINGS.get().unwrap().lock().await;
-        let settings = match global.get(msg.guild_id.map(|g| g.get()).unwrap_or(1)).await {
-            Ok(s) => s,
-            Err(_) => Settings::default(),
-        };
-
-        if subcommand == "list" {
-            // the list subcommand which lists all available config options
-            let Ok(Value::Object(json_rep)) = json::to_value(&settings) else {
-                error!("Json serialization went wrong on guild settings");
-                return Err(CommandError {
-                    title: String::from("Could not fetch guild settings"),
-                    hint: Some(String::from("please try again later")),
-                    arg: None,
-                });
-            };
-
-            let description = if let Some(group_key) = arg1 {
-                let Some(Value::Object(group)) = json_rep.get(&group_key) else {
+        let description = match subcommand.as_str() {
+            "list" => {
+                let group = key.map(|g| g.to_lowercase());
+                let settings = SETTINGS
+                    .iter()
+                    .filter(|s| {
+                        group
+                            .as_ref()
+                            .is_none_or(|g| s.key.split('.').next() == Some(g.as_str()))
+                    })
+                    .map(|s| format!("`{}` <{}> {}", s.key, s.kind, s.description))
+                    .collect::<Vec<_>>();
+
+                if settings.is_empty() {
                     return Err(CommandError {
                         title: String::from("Could not find group"),
-                        hint: Some(String::from("run `config list` for a list of all groups")),
-                        arg: None,
+                        hint: Some(String::from("run `config list` for a list of all settings")),
+                        arg: Some(args[1].clone()),
                     });
-                };
-
-                format!(
-                    "**Available Settings In Group**\n{}",
-                    group
-                        .keys()
-                        .map(|k| format!(
-                            "`{k}` - {}",
-                            self.get_option_desc(format!("{group_key}.{k}").as_str())
-                        ))
-                        .collect::<Vec<String>>()
-                        .join("\n")
-                )
-            } else {
-                format!(
-                    "**Available Config Groups**\n{}",
-                    json_rep
-                        .keys()
-                        .map(|k| format!("`{k}` - {}", self.get_option_desc(k)))
-                        .collect::<Vec<String>>()
-                        .join("\n")
-                )
-            };
-
-            let reply = CreateMessage::new()
-                .add_embed(
-                    CreateEmbed::new()
-                        .description(description)
-                        .color(BRAND_BLUE),
-                )
-                .reference_message(&msg)
-                .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+                }
 
-            if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
-                warn!("Could not send message; err = {err:?}");
+                format!("**SETTINGS**\n{}", settings.join("\n"))
             }
 
-            Ok(())
-        } else if subcommand == "get" {
-            // the get subcommand which gets the current value of a config option
-            let Some(setting) = arg1 else {
-                return Err(CommandError::arg_not_found("String", Some("arg1")));
-            };
+            "get" => {
+                let setting = setting.unwrap();
+                let config = fetch_config(guild_id).await?;
+                let value = config[setting.key].clone();
 
-            let value = match setting.as_str() {
-                "log.log_bots" => settings
-                    .log
-                    .log_bots
-                    .map(|c| format!("{c}"))
-                    .unwrap_or(String::from("false")),
-                _ => {
-                    return Err(CommandError {
-                        title: String::from("Could not find setting"),
-                        hint: Some(String::from("run config list for a list of valid settings")),
-                        arg: Some(arg1_token.unwrap()),
-                    });
+                let mut shown = setting.display(&value);
+                if shown.chars().count() > MAX_DISPLAY_LENGTH {
+                    shown = shown.chars().take(MAX_DISPLAY_LENGTH).collect::<String>() + "…`";
                 }
-            };
 
-            let reply = CreateMessage::new()
-                .add_embed(
-                    CreateEmbed::new()
-                        .description(format!("{setting}: {value}"))
-                        .color(BRAND_BLUE),
+                format!(
+                    "**{}**\n-# <{}> {}\n{shown}\n-# Default: {}",
+                    setting.key,
+                    setting.kind,
+                    setting.description,
+                    setting.display(&(setting.default)())
                 )
-                .reference_message(&msg)
-                .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
-
-            if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
-                warn!("Could not send message; err = {err:?}");
             }
 
-            Ok(())
-        } else if subcommand == "set" {
-            // the set subcommand which sets the value of a config option
-            let Some(setting) = arg1 else {
-                return Err(CommandError::arg_not_found("String", Some("arg1")));
-            };
-
-            let Some(value) = arg2 else {
-                return Err(CommandError::arg_not_found("String", Some("arg1")));
-            };
+            "set" | "reset" => {
+                let setting = setting.unwrap();
 
-            let mut iter = vec![arg2_token.clone().unwrap()].into_iter().peekable();
+                let new_value = if subcommand == "set" {
+                    let Some(input) = value.filter(|v| !v.trim().is_empty()) else {
+                        return Err(CommandError::arg_not_found("String", Some("value")));
+                    };
 
-            // a map of config options to their correct argument transformers and sqlx statements
-            let setting_info: (
-                BoxedTransformerFn,
-                sqlx::query::Query<'_, sqlx::Postgres, sqlx::postgres::PgArguments>,
-            ) = match setting.as_str() {
-                "log.log_bots" => (
-                    Box::new(Transformers::bool),
-                    sqlx::query("UPDATE guild_settings SET log_bot = $2 WHERE guild_id = $1"),
-                ),
-                _ => {
-                    return Err(CommandError {
-                        title: String::from("Could not find setting"),
-                        hint: Some(String::from(
-                            "run `config list` for a list of valid settings",
-                        )),
-                        arg: Some(arg1_token.unwrap()),
+                    let setting_ctx = fetch_setting_context(&ctx, guild_id).await?;
+                    let parsed = setting.parse_input(&input).and_then(|v| {
+                        setting.check(&v, &setting_ctx)?;
+                        Ok(v)
                     });
-                }
-            };
 
-            let res = if iter
-                .peek()
-                .map(|t| t.raw.clone())
-                .unwrap_or_default()
-                .to_lowercase()
-                == "none"
-            {
-                setting_info
-                    .1
-                    .bind(msg.guild_id.map(|g| g.get()).unwrap_or(1) as i64)
-                    .bind(None as Option<i32>)
-                    .execute(&*SQL)
-                    .await
-            } else {
-                match setting_info.0(&ctx, &msg, &mut iter).await {
-                    Ok(Token {
-                        contents: Some(CommandArgument::GuildChannel(channel)),
-                        ..
-                    }) => {
-                        setting_info
-                            .1
-                            .bind(msg.guild_id.map(|g| g.get()).unwrap_or(1) as i64)
-                            .bind(channel.id.get() as i64)
-                            .execute(&*SQL)
-                            .await
+                    match parsed {
+                        Ok(v) => v,
+                        Err(err) => {
+                            return Err(CommandError {
+                                title: format!("Invalid value for {}", setting.key),
+                                hint: Some(err),
+                                arg: Some(args[2].clone()),
+                            });
+                        }
                     }
+                } else {
+                    Value::Null
+                };
 
-                    Ok(Token {
-                        contents: Some(CommandArgument::bool(b)),
-                        ..
-                    }) => {
-                        setting_info
-                            .1
-                            .bind(msg.guild_id.map(|g| g.get()).unwrap_or(1) as i64)
-                            .bind(b)
-                            .execute(&*SQL)
-                            .await
-                    }
+                store_settings(guild_id, vec![(setting, new_value.clone())]).await?;
 
-                    Err(TransformerError::CommandError(mut err)) => {
-                        err.arg = Some(arg2_token.unwrap());
-                        return Err(err);
-                    }
+                let shown = if new_value.is_null() {
+                    setting.display(&(setting.default)())
+                } else {
+                    setting.display(&new_value)
+                };
+
+                format!("**SETTING UPDATED**\n`{}` is now {shown}", setting.key)
+            }
+
+            "export" => {
+                let config = fetch_config(guild_id).await?;
+                let contents = json::to_string_pretty(&config).unwrap_or_default();
+
+                let reply = CreateMessage::new()
+                    .add_embed(
+                        CreateEmbed::new()
+                            .description(format!(
+                                "**CONFIG EXPORTED**\n{} settings, apply them with `config import`",
+                                SETTINGS.len()
+                            ))
+                            .color(BRAND_BLUE),
+                    )
+                    .add_file(CreateAttachment::bytes(
+                        contents.into_bytes(),
+                        format!("config-{}.json", guild_id.get()),
+                    ))
+                    .reference_message(&msg)
+                    .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+                if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+                    warn!("Could not send message; err = {err:?}");
+                }
+
+                return Ok(());
+            }
 
-                    _ => {
+            "import" => {
+                let input = match msg.attachments.first() {
+                    Some(attachment) if attachment.size > MAX_IMPORT_BYTES => {
                         return Err(CommandError {
-                            title: String::from("Could not insert value into settings."),
-                            hint: None,
-                            arg: Some(arg2_token.unwrap()),
+                            title: String::from("The attached file is too large"),
+                            hint: Some(format!(
+                                "configurations can be at most {} KiB",
+                                MAX_IMPORT_BYTES / 1024
+                            )),
+                            arg: None,
                         });
                     }
+                    Some(attachment) => match attachment.download().await {
+                        Ok(bytes) => String::from_utf8_lossy(&bytes).to_string(),
+                        Err(err) => {
+                            warn!("Could not download config import; err = {err:?}");
+                            return Err(CommandError {
+                                title: String::from("Could not download the attached file"),
+                                hint: Some(String::from("please try again later")),
+                                arg: None,
+                            });
+                        }
+                    },
+                    // the lexer splits JSON at its quotes, take everything after the subcommand instead
+                    None => msg
+                        .content
+                        .trim_start()
+                        .split_once(char::is_whitespace)
+                        .and_then(|(_, r)| r.trim_start().split_once(char::is_whitespace))
+                        .map(|(_, r)| r.to_string())
+                        .unwrap_or_default(),
+                };
+
+                let input = input
+                    .trim()
+                    .trim_start_matches("```json")
+                    .trim_matches('`')
+                    .trim();
+
+                if input.is_empty() {
+                    return Err(CommandError::arg_not_found("String", Some("value")));
                 }
-            };
 
-            if let Err(err) = res {
-                warn!("Could not update guild settings; err = {err:?}");
-                return Err(CommandError {
-                    title: String::from("Could not update settings"),
-                    hint: Some(String::from("please try again later.")),
-                    arg: Some(arg1_token.unwrap()),
-                });
-            }
+                let setting_ctx = fetch_setting_context(&ctx, guild_id).await?;
+                let changes = match parse_import(input, &setting_ctx) {
+                    Ok(c) => c,
+                    Err(err) => {
+                        return Err(CommandError {
+                            title: String::from("Could not import the configuration"),
+                            hint: Some(err),
+                            arg: None,
+                        });
+                    }
+                };
 
-            global.invalidate();
+                let count = changes.len();
+                store_settings(guild_id, changes).await?;
 
-            let reply = CreateMessage::new()
-                .add_embed(
-                    CreateEmbed::new()
-                        .description(format!("Successfully set {setting} to {value}"))
-                        .color(BRAND_BLUE),
-                )
-                .reference_message(&msg)
-                .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+                format!("**CONFIG IMPORTED**\nUpdated {count} settings")
+            }
 
-            if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
-                warn!("Could not send message; err = {err:?}");
+            _ => {
+                return Err(CommandError {
+                    title: String::from("Unknown subcommand"),
+                    hint: Some(String::from(
+                        "available subcommands: list, get, set, reset, export, import",
+                    )),
+                    arg: Some(args[0].clone()),
+                });
             }
+        };
 
-            Ok(())
-        } else {
-            Err(CommandError {
-                title: String::from("Subcommand not found"),
-                hint: Some(String::from("available subcommands: list, get, set")),
-                arg: Some(subcommand_token),
-            })
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(description)
+                    .color(BRAND_BLUE),
+            )
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            warn!("Could not send message; err = {err:?}");
         }
-    }
 
-    fn get_transformers(&self) -> Vec<TransformerFnArc> {
-        vec![
-            Arc::new(Transformers::some_string),
-            Arc::new(Transformers::string),
-            Arc::new(Transformers::some_string),
-        ]
+        Ok(())
     }
 
     fn get_permissions(&self) -> CommandPermissions {
@@ -350,3 +314,356 @@ impl Command for Config {
         }
     }
 }
+
+/// Parses an exported configuration, returning the settings it changes
+fn parse_import(
+    input: &str,
+    setting_ctx: &SettingContext,
+) -> Result<Vec<(&'static SettingDefinition, Value)>, String> {
+    let Ok(Value::Object(config)) = json::from_str::<Value>(input) else {
+        return Err(String::from(
+            "expected a JSON object of settings, like the one `config export` sends",
+        ));
+    };
+
+    config
+        .into_iter()
+        .map(|(key, value)| {
+            let Some(setting) = find_setting(&key) else {
+                return Err(format!("`{key}` is not a setting"));
+            };
+
+            setting
+                .check(&value, setting_ctx)
+                .map_err(|err| format!("`{key}`: {err}"))?;
+
+            Ok((setting, value))
+        })
+        .collect()
+}
+
+/// Fetches the roles, channels and commands settings are validated against
+async fn fetch_setting_context(
+    ctx: &Context,
+    guild_id: GuildId,
+) -> Result<SettingContext, CommandError> {
+    SettingContext::fetch(ctx, guild_id)
+        .await
+        .map_err(|err| CommandError {
+            title: String::from("Could not validate the settings"),
+            hint: Some(err),
+            arg: None,
+        })
+}
+
+/// The value of every setting of a guild keyed by setting, defaults included
+async fn fetch_config(guild_id: GuildId) -> Result<JsonMap, CommandError> {
+    let row = query!(
+        r#"SELECT to_jsonb(g) AS "row!" FROM guild_settings g WHERE guild_id = $1"#,
+        guild_id.get() as i64
+    )
+    .fetch_optional(&*SQL)
+    .await;
+
+    let row = match row {
+        Ok(r) => r.map(|r| r.row).unwrap_or_default(),
+        Err(err) => {
+            consume_pgsql_error(String::from("CONFIG FETCH"), err);
+            return Err(CommandError {
+                title: String::from("Unable to query the database"),
+                hint: Some(String::from("try again later")),
+                arg: None,
+            });
+        }
+    };
+
+    Ok(SETTINGS
+        .iter()
+        .map(|s| {
+            let value = match s.column {
+                Some(column) => &row[column],
+                None => &row["options"][s.key],
+            };
+
+            let value = if value.is_null() {
+                (s.default)()
+            } else {
+                value.clone()
+            };
+
+            (String::from(s.key), value)
+        })
+        .collect())
+}
+
+/// Writes settings in one transaction, `null` resets a setting to its default
+async fn store_settings(
+    guild_id: GuildId,
+    changes: Vec<(&'static SettingDefinition, Value)>,
+) -> Result<(), CommandError> {
+    let guild_id = guild_id.get() as i64;
+
+    let res: Result<(), sqlx::Error> = async {
+        let mut tx = SQL.begin().await?;
+
+        // guilds joined while the bot was running may not have a row yet
+        query!(
+            "INSERT INTO guild_settings (guild_id) VALUES ($1) ON CONFLICT (guild_id) DO NOTHING",
+            guild_id
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        for (setting, value) in changes {
+            match setting.column {
+                Some(column) => {
+                    let value = if value.is_null() {
+                        (setting.default)()
+                    } else {
+                        value
+                    };
+
+                    // converts the JSON value to the type of the column
+                    sqlx::query(&format!(
+                        "UPDATE guild_settings SET {column} = (jsonb_populate_record(NULL::guild_settings, $2)).{column} WHERE guild_id = $1"
+                    ))
+                    .bind(guild_id)
+                    .bind(Value::Object(JsonMap::from_iter([(
+                        String::from(column),
+                        value,
+                    )])))
+                    .execute(&mut *tx)
+                    .await?;
+                }
+
+                None if value.is_null() => {
+                    query!(
+                        "UPDATE guild_settings SET options = options - $2 WHERE guild_id = $1",
+                        guild_id,
+                        setting.key
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                }
+
+                None => {
+                    query!(
+                        "UPDATE guild_settings SET options = options || jsonb_build_object($2::text, $3::jsonb) WHERE guild_id = $1",
+                        guild_id,
+                        setting.key,
+                        value
+                    )
+                    .execute(&mut *tx)
+                    .await?;
+                }
+            }
+        }
+
+        delete_opted_out_snapshot(&mut *tx, GuildId::new(guild_id as u64)).await?;
+
+        tx.commit().await
+    }
+    .await;
+
+    if let Err(err) = res {
+        consume_pgsql_error(String::from("CONFIG UPDATE"), err);
+        return Err(CommandError {
+            title: String::from("Could not update the database"),
+            hint: Some(String::from("please try again later")),
+            arg: None,
+        });
+    }
+
+    GUILD_SETTINGS.lock().await.invalidate();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::json::Value;
+
+    use crate::{
+        SQL,
+        testing::{self, TestBot, description, snowflake},
+    };
+
+    #[test]
+    fn disables_dms_through_an_option() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            bot.command(bot.owner_id, "!config set moderation.dm_members off")
+                .await;
+            bot.command(
+                bot.moderator_id,
+                &format!("!ban {} spamming", bot.member_id),
+            )
+            .await;
+
+            assert!(bot.discord.sent_dms(bot.member_id).is_empty());
+
+            let replies = bot.replies();
+            assert!(description(&replies[0]).contains("`moderation.dm_members` is now `false`"));
+            assert!(description(&replies[1]).contains("silent"));
+
+            bot.command(bot.owner_id, "!config get moderation.dm_members")
+                .await;
+            assert!(description(&bot.replies()[2]).contains("-# Default: `true`"));
+        });
+    }
+
+    #[test]
+    fn imports_a_configuration() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            bot.command(
+                bot.owner_id,
+                &format!(
+                    "!config import ```json\n{{\"log.log_bots\": true, \"commands.prefix\": \"?\", \"moderation.lockdown_channels\": [{}]}}\n```",
+                    bot.channel_id
+                ),
+            )
+            .await;
+
+            let (log_bot, prefix, lockdown_channels): (Option<bool>, Option<String>, Vec<i64>) =
+                sqlx::query_as(
+                    "SELECT log_bot, prefix, lockdown_channels FROM guild_settings WHERE guild_id = $1",
+                )
+                .bind(bot.guild_id as i64)
+                .fetch_one(&*SQL)
+                .await
+                .unwrap();
+
+            assert_eq!(log_bot, Some(true));
+            assert_eq!(prefix.as_deref(), Some("?"));
+            assert_eq!(lockdown_channels, vec![bot.channel_id as i64]);
+            assert!(description(&bot.replies()[0]).contains("Updated 3 settings"));
+
+            // nothing is applied if a single setting is invalid
+            bot.command(
+                bot.owner_id,
+                "?config import {\"commands.prefix\": null, \"moderation.lockdown_channels\": [1]}",
+            )
+            .await;
+            bot.command(
+                bot.owner_id,
+                "?config import {\"commands.prefix\": null, \"log.colour\": \"blue\"}",
+            )
+            .await;
+
+            let replies = bot.replies();
+            assert!(description(&replies[1]).contains("is not a channel of this server"));
+            assert!(description(&replies[2]).contains("`log.colour` is not a setting"));
+
+            bot.command(bot.owner_id, "?config reset commands.prefix")
+                .await;
+
+            let prefix: Option<String> =
+                sqlx::query_scalar("SELECT prefix FROM guild_settings WHERE guild_id = $1")
+                    .bind(bot.guild_id as i64)
+                    .fetch_one(&*SQL)
+                    .await
+                    .unwrap();
+            assert_eq!(prefix, None);
+        });
+    }
+
+    #[test]
+    fn validates_managed_values_like_their_commands() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            bot.command(
+                bot.owner_id,
+                &format!(
+                    "!config import {{\"moderation.restriction_roles\": {{\"muted\": {}}}}}",
+                    bot.guild_id
+                ),
+            )
+            .await;
+            bot.command(
+                bot.owner_id,
+                "!config import {\"commands.aliases\": {\"ban\": \"kick {user}\"}}",
+            )
+            .await;
+
+            let replies = bot.replies();
+            assert!(description(&replies[0]).contains("@everyone"));
+            assert!(description(&replies[1]).contains("same name as a command"));
+
+            let (restrictions, aliases): (Option<Value>, Option<Value>) = sqlx::query_as(
+                "SELECT restriction_roles, aliases FROM guild_settings WHERE guild_id = $1",
+            )
+            .bind(bot.guild_id as i64)
+            .fetch_one(&*SQL)
+            .await
+            .unwrap();
+
+            assert_eq!(restrictions, None);
+            assert_eq!(aliases, None);
+        });
+    }
+
+    #[test]
+    fn creates_missing_settings_rows() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            sqlx::query("DELETE FROM guild_settings WHERE guild_id = $1")
+                .bind(bot.guild_id as i64)
+                .execute(&*SQL)
+                .await
+                .unwrap();
+
+            bot.command(bot.owner_id, "!config set log.log_bots on").await;
+
+            let log_bot: Option<bool> =
+                sqlx::query_scalar("SELECT log_bot FROM guild_settings WHERE guild_id = $1")
+                    .bind(bot.guild_id as i64)
+                    .fetch_one(&*SQL)
+                    .await
+                    .unwrap();
+            assert_eq!(log_bot, Some(true));
+        });
+    }
+    #[test]
+    fn deletes_stored_messages_when_opting_out() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            sqlx::query(
+                "INSERT INTO message_cache_snapshot (message_id, channel_id, guild_id, author_id, author_name, content) VALUES ($1, $2, $3, $4, 'member', 'hello')",
+            )
+            .bind(snowflake() as i64)
+            .bind(bot.channel_id as i64)
+            .bind(bot.guild_id as i64)
+            .bind(bot.member_id as i64)
+            .execute(&*SQL)
+            .await
+            .unwrap();
+
+            bot.command(bot.owner_id, "!config set log.message_cache_opt_out on")
+                .await;
+
+            let stored: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM message_cache_snapshot WHERE guild_id = $1",
+            )
+            .bind(bot.guild_id as i64)
+            .fetch_one(&*SQL)
+            .await
+            .unwrap();
+            assert_eq!(stored, 0);
+        });
+    }
+}
@@ -0,0 +1,280 @@
+use std::sync::Arc;
+
+use chrono::Duration;
+use ouroboros_macros::command;
+use serenity::{
+    all::{Context, CreateAllowedMentions, CreateEmbed, CreateMessage, Message, Permissions},
+    async_trait, json,
+};
+use sqlx::query;
+use tracing::warn;
+
+use crate::{
+    GUILD_SETTINGS, SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerError, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::CommandError,
+    lexer::Token,
+    transformers::Transformers,
+    utils::{
+        EscalationAction, EscalationPolicy, MAX_ESCALATION_POLICIES, consume_pgsql_error,
+    },
+};
+
+pub struct Escalation;
+
+impl Escalation {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Parses a duration argument using the duration transformer
+    async fn parse_duration(
+        &self,
+        ctx: &Context,
+        msg: &Message,
+        token: &Token,
+    ) -> Result<Duration, CommandError> {
+        let mut fake_args = vec![token.clone()].into_iter().peekable();
+
+        match Transformers::duration(ctx, msg, &mut fake_args).await {
+            Ok(Token {
+                contents: Some(CommandArgument::Duration(d)),
+                ..
+            }) => Ok(d),
+            Err(TransformerError::CommandError(err)) => Err(err),
+            _ => Err(CommandError {
+                title: String::from("Could not turn input to a <Duration>"),
+                hint: None,
+                arg: Some(token.clone()),
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl Command for Escalation {
+    fn get_name(&self) -> &'static str {
+        "escalation"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Manages automatic actions for members collecting warns"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Manages automatic actions for members collecting warns. \
+        Policies are checked after every warn, when a member reaches the amount of active warns of a policy its action is applied by the bot. \
+        Available subcommands: list add remove;\n \
+        `list` lists all policies\n \
+        `add <warns> <within|all> <mute|ban> [duration]` adds a policy, i.e. `add 3 30d mute 1d` or `add 5 all ban`\n \
+        `remove <number>` removes a policy by its number in the list"
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::String("subcommand", false),
+            CommandSyntax::String("warns", false),
+            CommandSyntax::String("within", false),
+            CommandSyntax::String("action", false),
+            CommandSyntax::Duration("duration", false),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::some_string] subcommand: Option<String>,
+        #[transformers::some_string] warns: Option<String>,
+        #[transformers::some_string] within: Option<String>,
+        #[transformers::some_string] action: Option<String>,
+        #[transformers::some_string] duration: Option<String>,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        let mut policies = {
+            let mut lock = GUILD_SETTINGS.lock().await;
+            lock.get(guild_id.get())
+                .await
+                .map(|s| s.moderation.escalation_policies)
+                .unwrap_or_default()
+        };
+
+        let subcommand = subcommand.unwrap_or(String::from("list")).to_lowercase();
+
+        let description = match subcommand.as_str() {
+            "list" => {
+                if policies.is_empty() {
+                    String::from("**ESCALATION POLICIES**\nThis server has no escalation policies.")
+                } else {
+                    format!(
+                        "**ESCALATION POLICIES**\n{}",
+                        policies
+                            .iter()
+                            .enumerate()
+                            .map(|(i, p)| format!("`{}` {}", i + 1, p.describe()))
+                            .collect::<Vec<_>>()
+                            .join("\n")
+                    )
+                }
+            }
+
+            "add" => {
+                let Some(warns) = warns else {
+                    return Err(CommandError::arg_not_found("Number", Some("warns")));
+                };
+
+                let Some(warns) = warns.parse::<u32>().ok().filter(|w| *w > 0) else {
+                    return Err(CommandError {
+                        title: String::from("The amount of warns must be a positive number"),
+                        hint: None,
+                        arg: Some(args[1].clone()),
+                    });
+                };
+
+                let Some(within) = within else {
+                    return Err(CommandError::arg_not_found("Duration", Some("within")));
+                };
+
+                let within = if within == "all" {
+                    None
+                } else {
+                    Some(self.parse_duration(&ctx, &msg, &args[2]).await?)
+                };
+
+                let action = match action.map(|a| a.to_lowercase()).as_deref() {
+                    Some("mute") => EscalationAction::Mute,
+                    Some("ban") => EscalationAction::Ban,
+                    Some(_) => {
+                        return Err(CommandError {
+                            title: String::from("Unknown action"),
+                            hint: Some(String::from("available actions: mute, ban")),
+                            arg: Some(args[3].clone()),
+                        });
+                    }
+                    None => return Err(CommandError::arg_not_found("String", Some("action"))),
+                };
+
+                let duration = match duration {
+                    Some(_) => Some(self.parse_duration(&ctx, &msg, &args[4]).await?),
+                    None => None,
+                }
+                .filter(|d| !d.is_zero());
+
+                if policies.len() >= MAX_ESCALATION_POLICIES {
+                    return Err(CommandError {
+                        title: format!(
+                            "Servers can have at most {MAX_ESCALATION_POLICIES} escalation policies"
+                        ),
+                        hint: Some(String::from("remove a policy before adding a new one")),
+                        arg: None,
+                    });
+                }
+
+                let policy = EscalationPolicy {
+                    warns,
+                    within: within.map(|w| w.num_seconds()),
+                    action,
+                    duration: duration.map(|d| d.num_seconds()),
+                };
+
+                if let Err(err) = policy.validate() {
+                    return Err(CommandError {
+                        title: err,
+                        hint: None,
+                        arg: Some(args[4].clone()),
+                    });
+                }
+
+                let description = format!("**ESCALATION POLICY ADDED**\n{}", policy.describe());
+
+                policies.push(policy);
+                policies.sort_by_key(|p| (p.warns, p.action));
+                description
+            }
+
+            "remove" => {
+                let Some(index) = warns else {
+                    return Err(CommandError::arg_not_found("Number", Some("number")));
+                };
+
+                let Some(index) = index
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|i| *i > 0 && *i <= policies.len())
+                else {
+                    return Err(CommandError {
+                        title: String::from("Escalation policy not found"),
+                        hint: Some(String::from("run the list subcommand to see all policies")),
+                        arg: Some(args[1].clone()),
+                    });
+                };
+
+                let policy = policies.remove(index - 1);
+                format!("**ESCALATION POLICY REMOVED**\n{}", policy.describe())
+            }
+
+            _ => {
+                return Err(CommandError {
+                    title: String::from("Unknown subcommand"),
+                    hint: Some(String::from("available subcommands: list, add, remove")),
+                    arg: Some(args[0].clone()),
+                });
+            }
+        };
+
+        if subcommand != "list" {
+            let res = query!(
+                "UPDATE guild_settings SET escalation_policies = $2 WHERE guild_id = $1",
+                guild_id.get() as i64,
+                json::to_value(&policies).unwrap()
+            )
+            .execute(&*SQL)
+            .await;
+
+            if let Err(err) = res {
+                consume_pgsql_error(String::from("ESCALATION UPDATE"), err);
+                return Err(CommandError {
+                    title: String::from("Could not update the database"),
+                    hint: Some(String::from("please try again later")),
+                    arg: None,
+                });
+            }
+
+            let mut lock = GUILD_SETTINGS.lock().await;
+            lock.invalidate();
+        }
+
+        let reply = CreateMessage::new()
+            .add_embed(CreateEmbed::new().description(description).color(BRAND_BLUE))
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            warn!("Could not send message; err = {err:?}");
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+        }
+    }
+}
@@ -1,5 +1,29 @@
-// mod config;
-// pub use config::Config;
+mod config;
+pub use config::Config;
+
+mod alias;
+pub use alias::Alias;
+
+mod antiraid;
+pub use antiraid::AntiRaid;
 
 mod define_log;
 pub use define_log::DefineLog;
+
+mod escalation;
+pub use escalation::Escalation;
+
+mod prefix;
+pub use prefix::Prefix;
+
+mod privacy;
+pub use privacy::Privacy;
+
+mod overrides;
+pub use overrides::Overrides;
+
+mod restrictions;
+pub use restrictions::Restrictions;
+
+mod templates;
+pub use templates::Templates;
@@ -0,0 +1,453 @@
+use std::sync::Arc;
+
+use ouroboros_macros::command;
+use serenity::{
+    all::{
+        Context, CreateAllowedMentions, CreateEmbed, CreateMessage, GuildId, Message, Permissions,
+        UserId,
+    },
+    async_trait, json,
+};
+use sqlx::query;
+use tracing::warn;
+
+use crate::{
+    CommandNamesContainer, GUILD_SETTINGS, SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::CommandError,
+    lexer::Token,
+    transformers::Transformers,
+    utils::{
+        CommandOverride, MAX_COMMAND_OVERRIDES, OverrideScope, OverrideTarget, consume_pgsql_error,
+        get_command_overrides,
+    },
+};
+
+pub struct Overrides;
+
+impl Overrides {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for Overrides {
+    fn get_name(&self) -> &'static str {
+        "overrides"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Grants or denies commands to users, roles and channels"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Grants or denies commands or whole categories to users, roles and channels regardless of their Discord permissions, \
+        i.e. allowing a trial mod role to warn and mute without giving it the ban members permission. \
+        User overrides beat role overrides which beat channel overrides, overrides of a command beat overrides of its category and denies beat grants. \
+        Administrators are never affected. \
+        Available subcommands: list allow deny remove;\n \
+        `list` lists all overrides\n \
+        `allow <command|category> <target>` grants a command to a user, role or channel, i.e. `allow warn @Trial Mod`\n \
+        `deny <command|category> <target>` denies a command, i.e. `deny moderation #general`\n \
+        `remove <command|category> <target>` removes an override"
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::String("subcommand", false),
+            CommandSyntax::String("command", false),
+            CommandSyntax::Consume("target"),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::some_string] subcommand: Option<String>,
+        #[transformers::some_string] scope: Option<String>,
+        #[transformers::consume] target: Option<String>,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        let mut overrides = get_command_overrides(guild_id).await.to_vec();
+
+        let subcommand = subcommand.unwrap_or(String::from("list")).to_lowercase();
+
+        if subcommand == "list" {
+            let description = if overrides.is_empty() {
+                String::from("**OVERRIDES**\nThis server has no command overrides.")
+            } else {
+                let list = overrides
+                    .iter()
+                    .map(|o| describe(o, guild_id))
+                    .collect::<Vec<_>>();
+
+                format!("**OVERRIDES**\n{}", list.join("\n"))
+            };
+
+            return reply(&ctx, &msg, description).await;
+        }
+
+        if !["allow", "deny", "remove"].contains(&subcommand.as_str()) {
+            return Err(CommandError {
+                title: String::from("Unknown subcommand"),
+                hint: Some(String::from(
+                    "available subcommands: list, allow, deny, remove",
+                )),
+                arg: Some(args[0].clone()),
+            });
+        }
+
+        let Some(scope) = scope.map(|s| s.to_lowercase()) else {
+            return Err(CommandError::arg_not_found("String", Some("command")));
+        };
+
+        let command_names = {
+            let data = ctx.data.read().await;
+            data.get::<CommandNamesContainer>().unwrap().clone()
+        };
+
+        // help is always available, it only lists the commands a member can run
+        let scope = if command_names.contains(&scope.as_str()) && scope != "help" {
+            OverrideScope::Command(scope)
+        } else {
+            match CommandCategory::from_name(&scope) {
+                Some(CommandCategory::Developer) => {
+                    return Err(CommandError {
+                        title: String::from("Developer commands can't be overridden"),
+                        hint: None,
+                        arg: Some(args[1].clone()),
+                    });
+                }
+                Some(category) => OverrideScope::Category(category),
+                None => {
+                    return Err(CommandError {
+                        title: String::from("Unknown command or category"),
+                        hint: Some(String::from(
+                            "categories are misc, utilities, moderation and admin",
+                        )),
+                        arg: Some(args[1].clone()),
+                    });
+                }
+            }
+        };
+
+        let Some(input) = target
+            .map(|t| t.trim().to_string())
+            .filter(|t| !t.is_empty())
+        else {
+            return Err(CommandError::arg_not_found("String", Some("target")));
+        };
+
+        let Some(target) = find_target(&ctx, guild_id, &input).await else {
+            return Err(CommandError {
+                title: String::from("Could not find a user, role or channel"),
+                hint: Some(String::from(
+                    "make sure to input a mention, an id or the exact name of a role or channel.",
+                )),
+                arg: Some(args[2].clone()),
+            });
+        };
+
+        let existing = overrides
+            .iter()
+            .position(|o| o.scope == scope && o.target == target);
+
+        let description = if subcommand == "remove" {
+            let Some(index) = existing else {
+                return Err(CommandError {
+                    title: String::from("There is no such override"),
+                    hint: Some(String::from("run `overrides list` to see all overrides")),
+                    arg: Some(args[2].clone()),
+                });
+            };
+
+            let removed = overrides.remove(index);
+            format!("**OVERRIDE REMOVED**\n{}", describe(&removed, guild_id))
+        } else {
+            let new_override = CommandOverride {
+                scope,
+                target,
+                allow: subcommand == "allow",
+            };
+
+            match existing {
+                Some(index) => overrides[index] = new_override.clone(),
+                None if overrides.len() >= MAX_COMMAND_OVERRIDES => {
+                    return Err(CommandError {
+                        title: format!(
+                            "Servers can have at most {MAX_COMMAND_OVERRIDES} overrides"
+                        ),
+                        hint: Some(String::from("remove an override before adding a new one")),
+                        arg: None,
+                    });
+                }
+                None => overrides.push(new_override.clone()),
+            }
+
+            format!("**OVERRIDE SET**\n{}", describe(&new_override, guild_id))
+        };
+
+        // overrides are a registry setting without a column, guilds joined while running may not have a row yet
+        let res = query!(
+            r#"
+                INSERT INTO guild_settings (guild_id, options)
+                VALUES ($1, jsonb_build_object('commands.overrides', $2::jsonb))
+                ON CONFLICT (guild_id) DO UPDATE SET options = guild_settings.options || EXCLUDED.options
+            "#,
+            guild_id.get() as i64,
+            json::to_value(&overrides).unwrap()
+        )
+        .execute(&*SQL)
+        .await;
+
+        if let Err(err) = res {
+            consume_pgsql_error(String::from("OVERRIDES UPDATE"), err);
+            return Err(CommandError {
+                title: String::from("Could not update the database"),
+                hint: Some(String::from("please try again later")),
+                arg: None,
+            });
+        }
+
+        let mut lock = GUILD_SETTINGS.lock().await;
+        lock.invalidate();
+        drop(lock);
+
+        reply(&ctx, &msg, description).await
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+        }
+    }
+}
+
+/// A line like `` `ban` denied for @Trial Mod ``
+fn describe(o: &CommandOverride, guild_id: GuildId) -> String {
+    let state = if o.allow { "allowed" } else { "denied" };
+    let preposition = match o.target {
+        OverrideTarget::Channel(_) => "in",
+        _ => "for",
+    };
+
+    format!(
+        "{} {state} {preposition} {}",
+        o.scope,
+        o.target.mention(guild_id.get())
+    )
+}
+
+/// Resolves a mention, id or name into the target of an override, roles are preferred over channels and users
+async fn find_target(ctx: &Context, guild_id: GuildId, input: &str) -> Option<OverrideTarget> {
+    if input.eq_ignore_ascii_case("everyone") || input.eq_ignore_ascii_case("@everyone") {
+        return Some(OverrideTarget::Role(guild_id.get()));
+    }
+
+    let mention = |prefixes: &[&str]| {
+        prefixes.iter().find_map(|p| {
+            input
+                .strip_prefix(p)
+                .and_then(|i| i.strip_suffix('>'))
+                .and_then(|i| i.parse::<u64>().ok())
+        })
+    };
+
+    if let Some(id) = mention(&["<@&"]) {
+        return Some(OverrideTarget::Role(id));
+    }
+
+    if let Some(id) = mention(&["<#"]) {
+        return Some(OverrideTarget::Channel(id));
+    }
+
+    if let Some(id) = mention(&["<@!", "<@"]) {
+        return Some(OverrideTarget::User(id));
+    }
+
+    let id = input.parse::<u64>().unwrap_or(0);
+
+    if let Ok(roles) = guild_id.roles(ctx).await
+        && let Some(role) = roles
+            .values()
+            .find(|r| r.id.get() == id || r.name.eq_ignore_ascii_case(input))
+    {
+        return Some(OverrideTarget::Role(role.id.get()));
+    }
+
+    let name = input.trim_start_matches('#');
+    if let Ok(channels) = guild_id.channels(ctx).await
+        && let Some(channel) = channels
+            .values()
+            .find(|c| c.id.get() == id || c.name.eq_ignore_ascii_case(name))
+    {
+        return Some(OverrideTarget::Channel(channel.id.get()));
+    }
+
+    if id == 0 {
+        return None;
+    }
+
+    UserId::new(id)
+        .to_user(ctx)
+        .await
+        .ok()
+        .map(|u| OverrideTarget::User(u.id.get()))
+}
+
+async fn reply(ctx: &Context, msg: &Message, description: String) -> Result<(), CommandError> {
+    let reply = CreateMessage::new()
+        .add_embed(
+            CreateEmbed::new()
+                .description(description)
+                .color(BRAND_BLUE),
+        )
+        .reference_message(msg)
+        .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+    if let Err(err) = msg.channel_id.send_message(ctx, reply).await {
+        warn!("Could not send message; err = {err:?}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Method;
+
+    use crate::testing::{
+        self, TestBot, description,
+        fake_discord::{member_json, role_json, user_json},
+        snowflake,
+    };
+
+    #[test]
+    fn grants_commands_to_roles() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            bot.command(bot.moderator_id, &format!("!warn {} spam", bot.member_id))
+                .await;
+            bot.command(bot.owner_id, "!overrides allow warn Mod").await;
+            bot.command(bot.moderator_id, &format!("!warn {} spam", bot.member_id))
+                .await;
+
+            let replies = bot.replies();
+            assert!(description(&replies[0]).contains("You do not have"));
+            assert!(
+                description(&replies[1]).starts_with("**OVERRIDE SET**\n`warn` allowed for <@&")
+            );
+            assert_eq!(bot.discord.sent_dms(bot.member_id).len(), 1);
+        });
+    }
+
+    #[test]
+    fn grants_targeting_by_role_hierarchy() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            // a role without any moderation permission, positioned like the Mod role
+            let trial_role_id = snowflake();
+            let trial_id = snowflake();
+            {
+                let mut state = bot.discord.state();
+                let guild = state.guilds.get_mut(&bot.guild_id).unwrap();
+                guild["roles"].as_array_mut().unwrap().push(role_json(
+                    trial_role_id,
+                    "Trial Mod",
+                    1,
+                    0,
+                ));
+
+                let user = user_json(trial_id, "trial", false);
+                state.users.insert(trial_id, user.clone());
+                state.members.insert(
+                    (bot.guild_id, trial_id),
+                    member_json(bot.guild_id, user, &[trial_role_id]),
+                );
+            }
+
+            bot.command(bot.owner_id, "!overrides allow warn Trial Mod")
+                .await;
+            bot.command(trial_id, &format!("!warn {} spam", bot.member_id))
+                .await;
+            bot.command(trial_id, &format!("!warn {} spam", bot.moderator_id))
+                .await;
+
+            let replies = bot.replies();
+            assert!(
+                description(&replies[0]).starts_with("**OVERRIDE SET**\n`warn` allowed for <@&")
+            );
+            assert!(!description(&replies[1]).contains("You may not target"));
+            assert_eq!(bot.discord.sent_dms(bot.member_id).len(), 1);
+            assert!(description(&replies[2]).contains("You may not target this member"));
+            assert!(bot.discord.sent_dms(bot.moderator_id).is_empty());
+        });
+    }
+
+    #[test]
+    fn denies_commands_to_users() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            bot.command(
+                bot.owner_id,
+                &format!("!overrides deny moderation <@{}>", bot.moderator_id),
+            )
+            .await;
+            bot.command(
+                bot.owner_id,
+                &format!("!overrides allow kick {}", bot.moderator_id),
+            )
+            .await;
+            bot.command(bot.moderator_id, &format!("!ban {} spam", bot.member_id))
+                .await;
+
+            let replies = bot.replies();
+            assert!(description(&replies[2]).contains("disabled"));
+            assert!(
+                bot.discord
+                    .requests(
+                        Method::PUT,
+                        &format!("/guilds/{}/bans/{}", bot.guild_id, bot.member_id)
+                    )
+                    .is_empty()
+            );
+
+            bot.command(bot.owner_id, "!overrides list").await;
+            let replies = bot.replies();
+            assert_eq!(
+                description(&replies[3]),
+                format!(
+                    "**OVERRIDES**\nModeration commands denied for <@{0}>\n`kick` allowed for <@{0}>",
+                    bot.moderator_id
+                )
+            );
+        });
+    }
+}
@@ -0,0 +1,160 @@
+use std::sync::Arc;
+
+use ouroboros_macros::command;
+use serenity::{
+    all::{Context, CreateAllowedMentions, CreateEmbed, CreateMessage, Message, Permissions},
+    async_trait,
+};
+use sqlx::query;
+use tracing::warn;
+
+use crate::{
+    BOT_CONFIG, GUILD_SETTINGS, SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::CommandError,
+    lexer::Token,
+    transformers::Transformers,
+    utils::{consume_pgsql_error, get_guild_prefix},
+};
+
+/// The maximum length of a custom prefix
+const MAX_PREFIX_LENGTH: usize = 5;
+
+pub struct Prefix;
+
+impl Prefix {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for Prefix {
+    fn get_name(&self) -> &'static str {
+        "prefix"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Shows or changes the command prefix of the server"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Shows or changes the command prefix of the server. \
+        Run without a prefix to show the current one, use `reset` to go back to the default prefix. \
+        Prefixes can be up to 5 characters long and must not contain whitespace or backticks."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![CommandSyntax::String("prefix", false)]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::some_string] prefix: Option<String>,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+        let default = BOT_CONFIG.prefix.clone();
+
+        let Some(prefix) = prefix else {
+            let current = get_guild_prefix(guild_id).await;
+
+            let description = match current {
+                Some(p) => format!("**PREFIX**\nCurrent prefix: `{p}`\n-# Default prefix: `{default}`"),
+                None => format!("**PREFIX**\nCurrent prefix: `{default}` (default)"),
+            };
+
+            let reply = CreateMessage::new()
+                .add_embed(CreateEmbed::new().description(description).color(BRAND_BLUE))
+                .reference_message(&msg)
+                .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+            if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+                warn!("Could not send message; err = {err:?}");
+            }
+
+            return Ok(());
+        };
+
+        let new_prefix = if prefix == "reset" || prefix == default {
+            None
+        } else {
+            if prefix.chars().count() > MAX_PREFIX_LENGTH
+                || prefix.contains(char::is_whitespace)
+                || prefix.contains('`')
+            {
+                return Err(CommandError {
+                    title: format!(
+                        "Prefixes must be at most {MAX_PREFIX_LENGTH} characters long and not contain whitespace or backticks"
+                    ),
+                    hint: None,
+                    arg: Some(args[0].clone()),
+                });
+            }
+
+            Some(prefix)
+        };
+
+        let res = query!(
+            "INSERT INTO guild_settings (guild_id, prefix) VALUES ($1, $2) ON CONFLICT (guild_id) DO UPDATE SET prefix = EXCLUDED.prefix",
+            guild_id.get() as i64,
+            new_prefix
+        )
+        .execute(&*SQL)
+        .await;
+
+        if let Err(err) = res {
+            consume_pgsql_error(String::from("PREFIX UPDATE"), err);
+            return Err(CommandError {
+                title: String::from("Could not update the database"),
+                hint: Some(String::from("please try again later")),
+                arg: None,
+            });
+        }
+
+        {
+            let mut lock = GUILD_SETTINGS.lock().await;
+            lock.invalidate();
+        }
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(format!(
+                        "**PREFIX UPDATED**\nNew prefix: `{}`",
+                        new_prefix.unwrap_or(default)
+                    ))
+                    .color(BRAND_BLUE),
+            )
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            warn!("Could not send message; err = {err:?}");
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+        }
+    }
+}
@@ -0,0 +1,296 @@
+use std::sync::Arc;
+
+use ouroboros_macros::command;
+use serenity::{
+    all::{
+        ChannelId, Context, CreateAllowedMentions, CreateEmbed, CreateMessage, GuildChannel,
+        Mentionable, Message, Permissions,
+    },
+    async_trait,
+};
+use sqlx::query;
+use tracing::warn;
+
+use crate::{
+    BOT_CONFIG, GUILD_SETTINGS, SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::CommandError,
+    lexer::Token,
+    tasks::delete_opted_out_snapshot,
+    transformers::Transformers,
+    utils::consume_pgsql_error,
+};
+
+/// Archiving downloads every attachment of a channel, so it is limited to a few channels
+const MAX_ARCHIVE_CHANNELS: usize = 10;
+
+pub struct Privacy;
+
+impl Privacy {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for Privacy {
+    fn get_name(&self) -> &'static str {
+        "privacy"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Controls which message data of the server is stored by the bot"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Controls whether cached messages of the server are stored in the database so deleted and edited messages \
+        can still be logged after the bot restarts. Stored messages are removed once they are older than the retention period. \
+        Attachments of archived channels are downloaded as soon as they are sent, so they can be attached to delete and edit logs. \
+        Available subcommands: status cache archive;\n \
+        `status` shows the current settings\n \
+        `cache <on/off>` opts the server in or out of storing cached messages, opting out deletes everything stored immediately\n \
+        `archive <add/remove> <channel>` starts or stops archiving the attachments of a channel"
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::String("subcommand", false),
+            CommandSyntax::String("value", false),
+            CommandSyntax::Channel("channel", false),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::some_string] subcommand: Option<String>,
+        #[transformers::some_string] value: Option<String>,
+        #[transformers::maybe_guild_channel] channel: Option<GuildChannel>,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        let (opted_out, mut archive_channels) = {
+            let mut lock = GUILD_SETTINGS.lock().await;
+            lock.get(guild_id.get())
+                .await
+                .map(|s| (s.log.message_cache_opt_out, s.log.archive_channels))
+                .unwrap_or_default()
+        };
+
+        let subcommand = subcommand.unwrap_or(String::from("status")).to_lowercase();
+
+        let description = match subcommand.as_str() {
+            "status" => {
+                let stored = query!(
+                    "SELECT COUNT(*) as \"count!\" FROM message_cache_snapshot WHERE guild_id = $1",
+                    guild_id.get() as i64
+                )
+                .fetch_one(&*SQL)
+                .await
+                .map(|r| r.count)
+                .unwrap_or_default();
+
+                let persistence = if BOT_CONFIG.persist_message_cache.unwrap_or(false) {
+                    "enabled"
+                } else {
+                    "disabled for this bot"
+                };
+
+                let archived = if archive_channels.is_empty() {
+                    String::from("none")
+                } else {
+                    archive_channels
+                        .iter()
+                        .map(|c| ChannelId::new(*c).mention().to_string())
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                };
+
+                format!(
+                    "**PRIVACY**\nMessage cache storage: {}\n-# Bot wide: {persistence} | Stored messages: {stored}\nArchived attachment channels: {archived}",
+                    if opted_out { "off" } else { "on" }
+                )
+            }
+
+            "cache" => {
+                let opt_out = match value.map(|v| v.to_lowercase()).as_deref() {
+                    Some("on") => false,
+                    Some("off") => true,
+                    _ => {
+                        return Err(CommandError {
+                            title: String::from("Expected on or off"),
+                            hint: Some(String::from("i.e. `privacy cache off`")),
+                            arg: args.get(1).cloned(),
+                        });
+                    }
+                };
+
+                let res = query!(
+                    "UPDATE guild_settings SET message_cache_opt_out = $2 WHERE guild_id = $1",
+                    guild_id.get() as i64,
+                    opt_out
+                )
+                .execute(&*SQL)
+                .await;
+
+                if let Err(err) = res {
+                    consume_pgsql_error(String::from("PRIVACY UPDATE"), err);
+                    return Err(CommandError {
+                        title: String::from("Could not update the database"),
+                        hint: Some(String::from("please try again later")),
+                        arg: None,
+                    });
+                }
+
+                {
+                    let mut lock = GUILD_SETTINGS.lock().await;
+                    lock.invalidate();
+                }
+
+                if opt_out {
+                    if let Err(err) = delete_opted_out_snapshot(&*SQL, guild_id).await {
+                        consume_pgsql_error(String::from("PRIVACY SNAPSHOT DELETE"), err);
+                        return Err(CommandError {
+                            title: String::from("Could not delete stored messages"),
+                            hint: Some(String::from("please try again later")),
+                            arg: None,
+                        });
+                    }
+
+                    String::from(
+                        "**PRIVACY UPDATED**\nMessage cache storage: off\n-# All stored messages of this server were deleted",
+                    )
+                } else {
+                    String::from("**PRIVACY UPDATED**\nMessage cache storage: on")
+                }
+            }
+
+            "archive" => {
+                let add = match value.map(|v| v.to_lowercase()).as_deref() {
+                    Some("add") => true,
+                    Some("remove") => false,
+                    _ => {
+                        return Err(CommandError {
+                            title: String::from("Expected add or remove"),
+                            hint: Some(String::from("i.e. `privacy archive add #general`")),
+                            arg: args.get(1).cloned(),
+                        });
+                    }
+                };
+
+                let Some(channel) = channel else {
+                    return Err(CommandError::arg_not_found("Channel", Some("channel")));
+                };
+
+                let description = if add {
+                    if archive_channels.contains(&channel.id.get()) {
+                        return Err(CommandError {
+                            title: String::from("Attachments of this channel are already archived"),
+                            hint: None,
+                            arg: args.get(2).cloned(),
+                        });
+                    }
+
+                    if archive_channels.len() >= MAX_ARCHIVE_CHANNELS {
+                        return Err(CommandError {
+                            title: format!(
+                                "Servers can archive at most {MAX_ARCHIVE_CHANNELS} channels"
+                            ),
+                            hint: None,
+                            arg: None,
+                        });
+                    }
+
+                    archive_channels.push(channel.id.get());
+                    format!("**ARCHIVE CHANNEL ADDED**\n{}", channel.mention())
+                } else {
+                    let Some(index) = archive_channels.iter().position(|c| *c == channel.id.get())
+                    else {
+                        return Err(CommandError {
+                            title: String::from("Attachments of this channel are not archived"),
+                            hint: None,
+                            arg: args.get(2).cloned(),
+                        });
+                    };
+
+                    archive_channels.remove(index);
+                    format!("**ARCHIVE CHANNEL REMOVED**\n{}", channel.mention())
+                };
+
+                let res = query!(
+                    "UPDATE guild_settings SET archive_channels = $2 WHERE guild_id = $1",
+                    guild_id.get() as i64,
+                    &archive_channels
+                        .iter()
+                        .map(|c| *c as i64)
+                        .collect::<Vec<_>>()
+                )
+                .execute(&*SQL)
+                .await;
+
+                if let Err(err) = res {
+                    consume_pgsql_error(String::from("PRIVACY ARCHIVE UPDATE"), err);
+                    return Err(CommandError {
+                        title: String::from("Could not update the database"),
+                        hint: Some(String::from("please try again later")),
+                        arg: None,
+                    });
+                }
+
+                {
+                    let mut lock = GUILD_SETTINGS.lock().await;
+                    lock.invalidate();
+                }
+
+                description
+            }
+
+            _ => {
+                return Err(CommandError {
+                    title: String::from("Unknown subcommand"),
+                    hint: Some(String::from(
+                        "available subcommands: status, cache, archive",
+                    )),
+                    arg: Some(args[0].clone()),
+                });
+            }
+        };
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(description)
+                    .color(BRAND_BLUE),
+            )
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            warn!("Could not send message; err = {err:?}");
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use std::sync::Arc;
+
+use ouroboros_macros::command;
+use serenity::{
+    all::{
+        Context, CreateAllowedMentions, CreateEmbed, CreateMessage, Mentionable, Message,
+        Permissions, RoleId,
+    },
+    async_trait, json,
+};
+use sqlx::query;
+use tracing::warn;
+
+use crate::{
+    GUILD_SETTINGS, SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::CommandError,
+    lexer::Token,
+    transformers::Transformers,
+    utils::{
+        MAX_RESTRICTIONS, consume_pgsql_error, validate_restriction_name, validate_restriction_role,
+    },
+};
+
+pub struct Restrictions;
+
+impl Restrictions {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for Restrictions {
+    fn get_name(&self) -> &'static str {
+        "restrictions"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Manages the restriction roles used by the restrict command"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Manages the restriction roles of the server, i.e. a mute role or a media ban role. \
+        Restrictions are applied with `restrict` and removed with `unrestrict` or once they expire, members rejoining get them back. \
+        Available subcommands: list add remove;\n \
+        `list` lists all restrictions\n \
+        `add <name> <role>` adds a restriction which applies the role, i.e. `add media @Media Banned`\n \
+        `remove <name>` removes a restriction, roles already applied are kept until they expire"
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::String("subcommand", false),
+            CommandSyntax::String("name", false),
+            CommandSyntax::Consume("role"),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::some_string] subcommand: Option<String>,
+        #[transformers::some_string] name: Option<String>,
+        #[transformers::consume] role: Option<String>,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        let mut restrictions = {
+            let mut lock = GUILD_SETTINGS.lock().await;
+            lock.get(guild_id.get())
+                .await
+                .map(|s| s.moderation.restriction_roles)
+                .unwrap_or_default()
+        };
+
+        let subcommand = subcommand.unwrap_or(String::from("list")).to_lowercase();
+
+        let description = match subcommand.as_str() {
+            "list" => {
+                if restrictions.is_empty() {
+                    String::from("**RESTRICTIONS**\nThis server has no restrictions.")
+                } else {
+                    let mut list = restrictions
+                        .iter()
+                        .map(|(name, role)| format!("`{name}` {}", RoleId::new(*role).mention()))
+                        .collect::<Vec<_>>();
+                    list.sort();
+
+                    format!("**RESTRICTIONS**\n{}", list.join("\n"))
+                }
+            }
+
+            "add" => {
+                let Some(name) = name.map(|n| n.to_lowercase()) else {
+                    return Err(CommandError::arg_not_found("String", Some("name")));
+                };
+
+                if let Err(err) = validate_restriction_name(&name) {
+                    return Err(CommandError {
+                        title: String::from("Invalid restriction name"),
+                        hint: Some(err),
+                        arg: Some(args[1].clone()),
+                    });
+                }
+
+                let Some(input) = role.map(|r| r.trim().to_string()).filter(|r| !r.is_empty())
+                else {
+                    return Err(CommandError::arg_not_found("Role", Some("role")));
+                };
+
+                let Ok(roles) = guild_id.roles(&ctx).await else {
+                    return Err(CommandError {
+                        title: String::from("Couldn't get server roles"),
+                        hint: Some(String::from("please try again later.")),
+                        arg: None,
+                    });
+                };
+
+                let id = input
+                    .strip_prefix("<@&")
+                    .and_then(|r| r.strip_suffix(">"))
+                    .unwrap_or(&input)
+                    .parse::<u64>()
+                    .unwrap_or(0);
+
+                let Some(role) = roles
+                    .values()
+                    .find(|r| r.id.get() == id || r.name.eq_ignore_ascii_case(&input))
+                else {
+                    return Err(CommandError {
+                        title: String::from("Could not find role in server"),
+                        hint: Some(String::from(
+                            "make sure to input the role id, a mention or the exact name.",
+                        )),
+                        arg: Some(args[2].clone()),
+                    });
+                };
+
+                if let Err(err) = validate_restriction_role(role) {
+                    return Err(CommandError {
+                        title: String::from("This role can not be assigned"),
+                        hint: Some(err),
+                        arg: Some(args[2].clone()),
+                    });
+                }
+
+                if !restrictions.contains_key(&name) && restrictions.len() >= MAX_RESTRICTIONS {
+                    return Err(CommandError {
+                        title: format!("Servers can have at most {MAX_RESTRICTIONS} restrictions"),
+                        hint: Some(String::from("remove a restriction before adding a new one")),
+                        arg: None,
+                    });
+                }
+
+                restrictions.insert(name.clone(), role.id.get());
+                format!("**RESTRICTION ADDED**\n`{name}` {}", role.id.mention())
+            }
+
+            "remove" => {
+                let Some(name) = name.map(|n| n.to_lowercase()) else {
+                    return Err(CommandError::arg_not_found("String", Some("name")));
+                };
+
+                let Some(role) = restrictions.remove(&name) else {
+                    return Err(CommandError {
+                        title: String::from("Restriction not found"),
+                        hint: Some(String::from(
+                            "run the list subcommand to see all restrictions",
+                        )),
+                        arg: Some(args[1].clone()),
+                    });
+                };
+
+                format!(
+                    "**RESTRICTION REMOVED**\n`{name}` {}",
+                    RoleId::new(role).mention()
+                )
+            }
+
+            _ => {
+                return Err(CommandError {
+                    title: String::from("Unknown subcommand"),
+                    hint: Some(String::from("available subcommands: list, add, remove")),
+                    arg: Some(args[0].clone()),
+                });
+            }
+        };
+
+        if subcommand != "list" {
+            let res = query!(
+                "UPDATE guild_settings SET restriction_roles = $2 WHERE guild_id = $1",
+                guild_id.get() as i64,
+                json::to_value(&restrictions).unwrap()
+            )
+            .execute(&*SQL)
+            .await;
+
+            if let Err(err) = res {
+                consume_pgsql_error(String::from("RESTRICTIONS UPDATE"), err);
+                return Err(CommandError {
+                    title: String::from("Could not update the database"),
+                    hint: Some(String::from("please try again later")),
+                    arg: None,
+                });
+            }
+
+            let mut lock = GUILD_SETTINGS.lock().await;
+            lock.invalidate();
+        }
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(description)
+                    .color(BRAND_BLUE),
+            )
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            warn!("Could not send message; err = {err:?}");
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+        }
+    }
+}
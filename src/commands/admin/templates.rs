@@ -0,0 +1,372 @@
+use std::sync::Arc;
+
+use chrono::Duration;
+use ouroboros_macros::command;
+use serenity::{
+    all::{
+        Context, CreateAllowedMentions, CreateEmbed, CreateMessage, Mentionable, Message,
+        Permissions,
+    },
+    async_trait, json,
+};
+use sqlx::query;
+use tracing::warn;
+
+use crate::{
+    GUILD_SETTINGS, SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::CommandError,
+    lexer::Token,
+    transformers::Transformers,
+    utils::{
+        TemplateKind, TemplateValues, consume_pgsql_error, default_template, humanize_expiry,
+        template_key, templated_actions, validate_template,
+    },
+};
+
+pub struct Templates;
+
+impl Templates {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for Templates {
+    fn get_name(&self) -> &'static str {
+        "templates"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Customizes the DMs and responses of moderation commands"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Customizes the DM sent to members and the response posted in the channel for warns, kicks, softbans, bans, mutes and restrictions. \
+        Templates can use the placeholders {server} {duration} {reason} {log_id} {moderator} and {user}, \
+        restrict templates additionally {restriction}. \
+        Responses can use {status} to place details like a failed DM, otherwise they are appended. \
+        Available subcommands: list set reset preview;\n \
+        `list` lists all templates and whether they are customized\n \
+        `set <action> <dm|response> <template>` sets a template, i.e. `set ban dm You were banned from {server}: {reason}`\n \
+        `reset <action> <dm|response>` restores the default template\n \
+        `preview <action> <dm|response>` shows a template filled in with example values"
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::String("subcommand", false),
+            CommandSyntax::String("action", false),
+            CommandSyntax::String("kind", false),
+            CommandSyntax::Consume("template"),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Admin
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::some_string] subcommand: Option<String>,
+        #[transformers::some_string] action: Option<String>,
+        #[transformers::some_string] kind: Option<String>,
+        #[transformers::consume] template: Option<String>,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        let (mut templates, restrictions) = {
+            let mut lock = GUILD_SETTINGS.lock().await;
+            lock.get(guild_id.get())
+                .await
+                .map(|s| {
+                    (
+                        s.moderation.message_templates,
+                        s.moderation.restriction_roles,
+                    )
+                })
+                .unwrap_or_default()
+        };
+
+        let subcommand = subcommand.unwrap_or(String::from("list")).to_lowercase();
+
+        if subcommand == "list" {
+            let list = templated_actions()
+                .iter()
+                .map(|action| {
+                    let kinds = TemplateKind::all()
+                        .into_iter()
+                        .map(|kind| {
+                            let state = if templates.contains_key(&template_key(action, kind)) {
+                                "custom"
+                            } else {
+                                "default"
+                            };
+
+                            format!("{kind}: {state}")
+                        })
+                        .collect::<Vec<_>>();
+
+                    format!("`{action}` {}", kinds.join(" | "))
+                })
+                .collect::<Vec<_>>();
+
+            return reply(&ctx, &msg, format!("**TEMPLATES**\n{}", list.join("\n"))).await;
+        }
+
+        if !["set", "reset", "preview"].contains(&subcommand.as_str()) {
+            return Err(CommandError {
+                title: String::from("Unknown subcommand"),
+                hint: Some(String::from(
+                    "available subcommands: list, set, reset, preview",
+                )),
+                arg: Some(args[0].clone()),
+            });
+        }
+
+        let Some(action) = action else {
+            return Err(CommandError::arg_not_found("String", Some("action")));
+        };
+
+        let Some(action) = templated_actions()
+            .into_iter()
+            .find(|a| a.to_string().eq_ignore_ascii_case(&action))
+        else {
+            return Err(CommandError {
+                title: String::from("This action has no templates"),
+                hint: Some(String::from(
+                    "templates exist for warn, kick, softban, ban, mute and restrict",
+                )),
+                arg: Some(args[1].clone()),
+            });
+        };
+
+        let Some(kind) = kind else {
+            return Err(CommandError::arg_not_found("String", Some("kind")));
+        };
+
+        let Some(kind) = TemplateKind::from_name(&kind) else {
+            return Err(CommandError {
+                title: String::from("Unknown template kind"),
+                hint: Some(String::from("templates are either dm or response")),
+                arg: Some(args[2].clone()),
+            });
+        };
+
+        let key = template_key(&action, kind);
+
+        let description = match subcommand.as_str() {
+            "set" => {
+                let Some(template) = template
+                    .map(|t| t.trim().to_string())
+                    .filter(|t| !t.is_empty())
+                else {
+                    return Err(CommandError::arg_not_found("String", Some("template")));
+                };
+
+                if let Err(err) = validate_template(&template, &action, kind) {
+                    return Err(CommandError {
+                        title: err,
+                        hint: Some(String::from(
+                            "run `help templates` to see the available placeholders",
+                        )),
+                        arg: Some(args[3].clone()),
+                    });
+                }
+
+                templates.insert(key.clone(), template);
+                format!(
+                    "**TEMPLATE SET**\n`{key}`, run `templates preview {action} {kind}` to see it"
+                )
+            }
+
+            "reset" => {
+                if templates.remove(&key).is_none() {
+                    return Err(CommandError {
+                        title: String::from("This template is not customized"),
+                        hint: Some(String::from("it already uses the default template")),
+                        arg: Some(args[2].clone()),
+                    });
+                }
+
+                format!("**TEMPLATE RESET**\n`{key}` uses the default template again")
+            }
+
+            _ => {
+                let (template, state) = match templates.get(&key) {
+                    Some(t) => (t.clone(), "custom"),
+                    None => (String::from(default_template(&action, kind)), "default"),
+                };
+
+                let server = match guild_id.to_partial_guild(&ctx).await {
+                    Ok(p) => p.name.clone(),
+                    Err(_) => String::from("UNKNOWN_GUILD"),
+                };
+
+                let values = TemplateValues {
+                    server,
+                    duration: format!("for {}", humanize_expiry(&Duration::days(1))),
+                    reason: String::from("Example reason"),
+                    log_id: String::from("preview"),
+                    moderator: msg.author.mention().to_string(),
+                    user: msg.author.mention().to_string(),
+                    restriction: restrictions
+                        .keys()
+                        .min()
+                        .cloned()
+                        .unwrap_or(String::from("media")),
+                };
+
+                let preview = match kind {
+                    TemplateKind::Dm => values.render(&template),
+                    TemplateKind::Response => {
+                        (*values.render_response(&template, String::new()))(String::new())
+                    }
+                };
+
+                let header = CreateEmbed::new()
+                    .description(format!("**TEMPLATE PREVIEW**\n-# `{key}` | {state}"))
+                    .color(BRAND_BLUE);
+                let preview = CreateEmbed::new().description(preview).color(BRAND_BLUE);
+
+                let reply = CreateMessage::new()
+                    .add_embeds(vec![header, preview])
+                    .reference_message(&msg)
+                    .allowed_mentions(
+                        CreateAllowedMentions::new()
+                            .empty_users()
+                            .replied_user(false),
+                    );
+
+                if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+                    warn!("Could not send message; err = {err:?}");
+                }
+
+                return Ok(());
+            }
+        };
+
+        let res = query!(
+            "UPDATE guild_settings SET message_templates = $2 WHERE guild_id = $1",
+            guild_id.get() as i64,
+            json::to_value(&templates).unwrap()
+        )
+        .execute(&*SQL)
+        .await;
+
+        if let Err(err) = res {
+            consume_pgsql_error(String::from("TEMPLATES UPDATE"), err);
+            return Err(CommandError {
+                title: String::from("Could not update the database"),
+                hint: Some(String::from("please try again later")),
+                arg: None,
+            });
+        }
+
+        let mut lock = GUILD_SETTINGS.lock().await;
+        lock.invalidate();
+        drop(lock);
+
+        reply(&ctx, &msg, description).await
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::ADMINISTRATOR],
+            one_of: vec![],
+            bot: CommandPermissions::baseline(),
+        }
+    }
+}
+
+async fn reply(ctx: &Context, msg: &Message, description: String) -> Result<(), CommandError> {
+    let reply = CreateMessage::new()
+        .add_embed(
+            CreateEmbed::new()
+                .description(description)
+                .color(BRAND_BLUE),
+        )
+        .reference_message(msg)
+        .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+    if let Err(err) = msg.channel_id.send_message(ctx, reply).await {
+        warn!("Could not send message; err = {err:?}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::{self, TestBot, description};
+
+    #[test]
+    fn uses_custom_templates() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            bot.command(
+                bot.owner_id,
+                "!templates set ban dm Banned from {server} by {moderator} ({duration})\n{reason}",
+            )
+            .await;
+            bot.command(
+                bot.owner_id,
+                "!templates set ban response {user} is gone `{log_id}`",
+            )
+            .await;
+
+            bot.command(
+                bot.moderator_id,
+                &format!("!ban {} being rude", bot.member_id),
+            )
+            .await;
+
+            let dms = bot.discord.sent_dms(bot.member_id);
+            assert_eq!(dms.len(), 1);
+            assert_eq!(
+                description(&dms[0]),
+                format!(
+                    "Banned from Test Server by <@{}> (permanent)\nbeing rude",
+                    bot.moderator_id
+                )
+            );
+
+            let replies = bot.replies();
+            let response = description(replies.last().unwrap());
+            assert!(response.starts_with(&format!("<@{}> is gone `", bot.member_id)));
+            assert!(!response.contains("{status}"));
+        });
+    }
+
+    #[test]
+    fn rejects_unknown_placeholders() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            bot.command(bot.owner_id, "!templates set ban dm Bye {nickname}")
+                .await;
+            bot.command(bot.owner_id, "!templates list").await;
+
+            let replies = bot.replies();
+            assert!(description(&replies[0]).contains("{nickname}"));
+            assert!(description(&replies[1]).contains("`ban` dm: default | response: default"));
+        });
+    }
+}
@@ -6,7 +6,7 @@ use serenity::{
 use tracing::warn;
 
 use crate::{
-    BOT_CONFIG,
+    BOT_CONFIG, MessageCacheContainer,
     commands::{
         Command, CommandArgument, CommandCategory, CommandParameter, CommandSyntax,
         TransformerFnArc,
@@ -14,6 +14,7 @@ use crate::{
     constants::BRAND_BLUE,
     event_handler::CommandError,
     lexer::Token,
+    tasks::snapshot_message_cache,
     transformers::Transformers,
     utils::{LogType, get_all_guilds, guild_log, is_developer},
 };
@@ -336,6 +337,13 @@ impl Command for Update {
                 return Ok(());
             }
 
+            // keep the cached messages across the restart
+            let message_cache = ctx.data.read().await.get::<MessageCacheContainer>().cloned();
+
+            if let Some(cache) = message_cache {
+                snapshot_message_cache(&cache).await;
+            }
+
             // cleanup before exiting the process, to be restarted by task scheduler or systemd or whatever
             #[cfg(not(target_os = "windows"))]
             {
@@ -5,6 +5,7 @@ use crate::{
     event_handler::{CommandError, MissingArgumentError},
     lexer::Token,
 };
+use serde::{Deserialize, Serialize};
 use serenity::{
     all::{Context, GuildChannel, Member, Message, Permissions, User},
     async_trait,
@@ -35,7 +36,7 @@ pub type TransformerFnArc = Arc<TransformerFn>;
 pub enum CommandArgument {
     String(String),
     User(User),
-    // ManyUsers(Vec<User>), // may be used in the future
+    ManyUsers(Vec<User>),
     Member(Member),
     Duration(chrono::Duration),
     None,
@@ -49,6 +50,8 @@ pub enum CommandSyntax {
     /// Consume the remaining text as a String argument
     Consume(&'static str),
     User(&'static str, bool),
+    /// Many users, separated by spaces, commas or new lines
+    Users(&'static str, bool),
     Member(&'static str, bool),
     Channel(&'static str, bool),
     String(&'static str, bool),
@@ -72,7 +75,8 @@ pub struct CommandParameter<'a> {
     pub desc: &'a str,
 }
 
-#[derive(PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum CommandCategory {
     Misc,
     Utilities,
@@ -81,6 +85,24 @@ pub enum CommandCategory {
     Developer,
 }
 
+impl CommandCategory {
+    pub fn all() -> [CommandCategory; 5] {
+        [
+            CommandCategory::Misc,
+            CommandCategory::Utilities,
+            CommandCategory::Moderation,
+            CommandCategory::Admin,
+            CommandCategory::Developer,
+        ]
+    }
+
+    pub fn from_name(name: &str) -> Option<CommandCategory> {
+        Self::all()
+            .into_iter()
+            .find(|c| c.to_string().eq_ignore_ascii_case(name))
+    }
+}
+
 impl fmt::Display for CommandCategory {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -104,6 +126,7 @@ impl CommandSyntax {
             Self::Consume(name) | Self::Reason(name) => (format!("...[{name}]"), None),
             Self::Or(a, b) => (format!("({} || {})", a.get_def(), b.get_def()), None),
             Self::User(name, opt) => (format!("{name}: Discord User"), Some(opt)),
+            Self::Users(name, opt) => (format!("{name}: Discord Users"), Some(opt)),
             Self::Member(name, opt) => (format!("{name}: Discord Member"), Some(opt)),
             Self::String(name, opt) => (format!("{name}: String"), Some(opt)),
             Self::Duration(name, opt) => (format!("{name}: Duration"), Some(opt)),
@@ -123,11 +146,44 @@ impl CommandSyntax {
         }
     }
 
+    /// The name of the argument, `Or` syntaxes join both names (i.e. `user_or_id`)
+    pub fn get_name(&self) -> String {
+        match self {
+            Self::Consume(name)
+            | Self::Reason(name)
+            | Self::User(name, _)
+            | Self::Users(name, _)
+            | Self::Member(name, _)
+            | Self::Channel(name, _)
+            | Self::String(name, _)
+            | Self::Duration(name, _)
+            | Self::Number(name, _) => name.to_string(),
+            Self::Filters => String::from("filters"),
+            Self::Or(a, b) => format!("{}_or_{}", a.get_name(), b.get_name()),
+        }
+    }
+
+    /// Whether the argument has to be provided, `Consume`, `Reason` and `Filters` syntaxes are always optional
+    pub fn is_required(&self) -> bool {
+        match self {
+            Self::User(_, required)
+            | Self::Users(_, required)
+            | Self::Member(_, required)
+            | Self::Channel(_, required)
+            | Self::String(_, required)
+            | Self::Duration(_, required)
+            | Self::Number(_, required) => *required,
+            Self::Or(a, b) => a.is_required() || b.is_required(),
+            Self::Consume(_) | Self::Reason(_) | Self::Filters => false,
+        }
+    }
+
     /// A short example of what input could be passed
     pub fn get_example(&self) -> String {
         match self {
             CommandSyntax::Consume(_) => String::from("Some Text"),
             CommandSyntax::User(_, _) => String::from("123456789"),
+            CommandSyntax::Users(_, _) => String::from("123456789 987654321"),
             CommandSyntax::Member(_, _) => String::from("123456789"),
             CommandSyntax::String(_, _) => String::from("\"something\""),
             CommandSyntax::Duration(_, _) => String::from("15m"),
@@ -226,8 +282,16 @@ pub trait Command: Send + Sync {
 }
 
 mod admin;
-// pub use admin::Config;
+pub use admin::Config;
+pub use admin::Alias;
+pub use admin::AntiRaid;
 pub use admin::DefineLog;
+pub use admin::Escalation;
+pub use admin::Prefix;
+pub use admin::Privacy;
+pub use admin::Overrides;
+pub use admin::Restrictions;
+pub use admin::Templates;
 
 mod developer;
 pub use developer::MsgDbg;
@@ -244,15 +308,37 @@ pub use misc::Stats;
 
 mod moderation;
 pub use moderation::Ban;
+pub use moderation::ban_user;
 pub use moderation::Duration;
 pub use moderation::Kick;
+pub use moderation::kick_member;
+pub use moderation::Lock;
+pub use moderation::lock_channel;
+pub use moderation::Lockdown;
 pub use moderation::Log;
+pub use moderation::LogRecord;
+pub use moderation::Massban;
+pub use moderation::Masskick;
+pub use moderation::Massmute;
 pub use moderation::Mute;
+pub use moderation::mute_member;
+pub use moderation::Note;
 pub use moderation::Purge;
 pub use moderation::Reason;
+pub use moderation::Restrict;
+pub use moderation::restriction_role;
+pub use moderation::Search;
+pub use moderation::Slowmode;
+pub use moderation::revert_slowmode;
 pub use moderation::Softban;
+pub use moderation::Transcript;
 pub use moderation::Unban;
+pub use moderation::unban_user;
+pub use moderation::Unlock;
+pub use moderation::unlock_channel;
 pub use moderation::Unmute;
+pub use moderation::unmute_member;
+pub use moderation::Unrestrict;
 pub use moderation::Warn;
 
 mod utilities;
@@ -1,8 +1,11 @@
 use std::sync::Arc;
 
-use chrono::{Duration, Utc};
+use chrono::{Duration, NaiveDateTime, Utc};
 use serenity::{
-    all::{Context, CreateEmbed, CreateMessage, GuildId, Mentionable, Message, Permissions},
+    all::{
+        Context, CreateEmbed, CreateMessage, GuildId, Mentionable, Message, Permissions, User,
+        UserId,
+    },
     async_trait,
 };
 use sqlx::query;
@@ -15,10 +18,14 @@ use crate::{
         CommandSyntax, TransformerFnArc,
     },
     constants::BRAND_BLUE,
+    database::ActionType,
     event_handler::CommandError,
     lexer::{InferType, Token},
     transformers::Transformers,
-    utils::{CommandMessageResponse, LogType, can_target, guild_log, tinyid},
+    utils::{
+        CommandMessageResponse, LogType, TemplateValues, appeal_components, can_target,
+        get_guild_option, guild_log, guild_templates, humanize_expiry, tinyid,
+    },
 };
 use ouroboros_macros::command;
 
@@ -110,6 +117,8 @@ impl Command for Ban {
                 &author_member,
                 &target_member,
                 Permissions::MODERATE_MEMBERS,
+                self,
+                msg.channel_id,
             )
             .await;
             if !res {
@@ -162,33 +171,7 @@ impl Command for Ban {
         let db_id = tinyid().await;
 
         let time_string = if !duration.is_zero() {
-            let (time, mut unit) = match () {
-                _ if (duration.num_days() as f64 / 365.0).fract() == 0.0
-                    && duration.num_days() >= 365 =>
-                {
-                    (duration.num_days() / 365, String::from("year"))
-                }
-                _ if (duration.num_days() as f64 / 30.0).fract() == 0.0
-                    && duration.num_days() >= 30 =>
-                {
-                    (duration.num_days() / 30, String::from("month"))
-                }
-                _ if duration.num_days() != 0 => (duration.num_days(), String::from("day")),
-                _ if duration.num_hours() != 0 => (duration.num_hours(), String::from("hour")),
-                _ if duration.num_minutes() != 0 => {
-                    (duration.num_minutes(), String::from("minute"))
-                }
-                _ if duration.num_seconds() != 0 => {
-                    (duration.num_seconds(), String::from("second"))
-                }
-                _ => (0, String::new()),
-            };
-
-            if time > 1 {
-                unit += "s";
-            }
-
-            format!("for {time} {unit}")
+            format!("for {}", humanize_expiry(&duration))
         } else {
             String::from("permanent")
         };
@@ -199,42 +182,6 @@ impl Command for Ban {
             Some((Utc::now() + duration).naive_utc())
         };
 
-        let disable_past = query!(
-            "UPDATE actions SET active = false WHERE guild_id = $1 AND user_id = $2 AND type = 'ban'",
-            msg.guild_id.map(|g| g.get() as i64).unwrap_or(0),
-            user.id.get() as i64,
-        ).execute(&*SQL);
-
-        let insert_ban = query!(
-            "INSERT INTO actions (id, type, guild_id, user_id, moderator_id, reason, expires_at) VALUES ($1, 'ban', $2, $3, $4, $5, $6)",
-            db_id,
-            msg.guild_id.map(|g| g.get() as i64).unwrap_or(0),
-            user.id.get() as i64,
-            msg.author.id.get() as i64,
-            reason.as_str(),
-            duration
-        ).execute(&*SQL);
-
-        let (res1, res2) = tokio::join!(disable_past, insert_ban);
-
-        if let Err(err) = res1 {
-            warn!("Got error while banning; err = {err:?}");
-            return Err(CommandError {
-                title: String::from("Could not ban member"),
-                hint: Some(String::from("please try again later")),
-                arg: None,
-            });
-        }
-
-        if let Err(err) = res2 {
-            warn!("Got error while banning; err = {err:?}");
-            return Err(CommandError {
-                title: String::from("Could not ban member"),
-                hint: Some(String::from("please try again later")),
-                arg: None,
-            });
-        }
-
         let mut clear_msg = String::new();
 
         if days != 0 {
@@ -253,56 +200,43 @@ impl Command for Ban {
             }
         };
 
-        let static_server_contents = (
-            format!(
-                "**{} BANNED**\n-# Log ID: `{db_id}` | Duration: {time_string}{clear_msg}",
-                user.mention()
-            ),
-            format!("\n```\n{reason}\n```"),
-        );
-
-        let mut cmd_response = CommandMessageResponse::new(user.id)
-            .dm_content(format!(
-                "**BANNED**\n-# Server: {} | Duration: {}\n```\n{}\n```",
-                guild_name, time_string, reason
-            ))
-            .server_content(Box::new(move |a| {
-                format!(
-                    "{}{a}{}",
-                    static_server_contents.0, static_server_contents.1
-                )
-            }))
-            .automatically_delete(inferred)
-            .mark_silent(params.contains_key("silent"));
-
-        cmd_response.send_dm(&ctx).await;
+        let (dm_template, response_template) = guild_templates(guild_id, &ActionType::Ban).await;
+        let values = TemplateValues {
+            server: guild_name,
+            duration: time_string.clone(),
+            reason: reason.clone(),
+            log_id: db_id.clone(),
+            moderator: msg.author.mention().to_string(),
+            user: user.mention().to_string(),
+            ..Default::default()
+        };
 
-        if let Err(err) = msg
-            .guild_id
-            .unwrap()
-            .ban_with_reason(&ctx, &user, days, &reason)
+        let dm_members = get_guild_option(guild_id, "moderation.dm_members")
             .await
-        {
-            warn!("Got error while banning; err = {err:?}");
+            .as_bool()
+            .unwrap_or(true);
 
-            if query!("DELETE FROM actions WHERE id = $1", db_id)
-                .execute(&*SQL)
-                .await
-                .is_err()
-            {
-                error!(
-                    "Got an error while banning and an error with the database! Stray ban entry in DB & manual action required; id = {db_id}; err = {err:?}"
-                );
-            }
+        let mut cmd_response = CommandMessageResponse::new(user.id)
+            .dm_content(values.render(&dm_template))
+            .server_content(values.render_response(&response_template, clear_msg.clone()))
+            .dm_components(appeal_components(guild_id, &db_id).await)
+            .automatically_delete(inferred)
+            .mark_silent(params.contains_key("silent") || !dm_members);
 
-            return Err(CommandError {
-                title: String::from("Could not ban member"),
-                hint: Some(String::from(
-                    "check if the bot has the ban members permission or try again later",
-                )),
-                arg: None,
-            });
-        }
+        ban_user(
+            &ctx,
+            guild_id,
+            &user,
+            &db_id,
+            msg.author.id,
+            &reason,
+            duration,
+            days,
+            None,
+            None,
+            Some(&cmd_response),
+        )
+        .await?;
 
         let ctx_clone = ctx.clone();
         let msg_clone = msg.clone();
@@ -350,3 +284,249 @@ impl Command for Ban {
         }
     }
 }
+
+/// Stores a ban (deactivating previous bans of the user), DMs the user using `response` (if any) and bans them.
+/// The entry is rolled back if the ban could not be applied. `batch_id` groups the bans of mass actions and raids.
+#[allow(clippy::too_many_arguments)]
+pub async fn ban_user(
+    ctx: &Context,
+    guild_id: GuildId,
+    user: &User,
+    db_id: &str,
+    moderator_id: UserId,
+    reason: &str,
+    expires_at: Option<NaiveDateTime>,
+    days: u8,
+    triggered_by: Option<&str>,
+    batch_id: Option<&str>,
+    response: Option<&CommandMessageResponse>,
+) -> Result<(), CommandError> {
+    // previous bans have to be deactivated before the new one is inserted, running both at once could deactivate it
+    let disable_past = query!(
+        "UPDATE actions SET active = false WHERE guild_id = $1 AND user_id = $2 AND type = 'ban'",
+        guild_id.get() as i64,
+        user.id.get() as i64,
+    ).execute(&*SQL).await;
+
+    if let Err(err) = disable_past {
+        warn!("Got error while banning; err = {err:?}");
+        return Err(CommandError {
+            title: String::from("Could not ban member"),
+            hint: Some(String::from("please try again later")),
+            arg: None,
+        });
+    }
+
+    let insert_ban = query!(
+        "INSERT INTO actions (id, type, guild_id, user_id, moderator_id, reason, expires_at, triggered_by, batch_id) VALUES ($1, 'ban', $2, $3, $4, $5, $6, $7, $8)",
+        db_id,
+        guild_id.get() as i64,
+        user.id.get() as i64,
+        moderator_id.get() as i64,
+        reason,
+        expires_at,
+        triggered_by,
+        batch_id,
+    ).execute(&*SQL).await;
+
+    if let Err(err) = insert_ban {
+        warn!("Got error while banning; err = {err:?}");
+        return Err(CommandError {
+            title: String::from("Could not ban member"),
+            hint: Some(String::from("please try again later")),
+            arg: None,
+        });
+    }
+
+    if let Some(response) = response {
+        response.send_dm(ctx).await;
+    }
+
+    if let Err(err) = guild_id.ban_with_reason(ctx, user, days, reason).await {
+        warn!("Got error while banning; err = {err:?}");
+
+        if query!("DELETE FROM actions WHERE id = $1", db_id)
+            .execute(&*SQL)
+            .await
+            .is_err()
+        {
+            error!(
+                "Got an error while banning and an error with the database! Stray ban entry in DB & manual action required; id = {db_id}; err = {err:?}"
+            );
+        }
+
+        return Err(CommandError {
+            title: String::from("Could not ban member"),
+            hint: Some(String::from(
+                "check if the bot has the ban members permission or try again later",
+            )),
+            arg: None,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Method;
+    use chrono::NaiveDateTime;
+
+    use crate::{
+        SQL,
+        testing::{self, TestBot, description},
+        utils::application_commands::quote_reason,
+    };
+
+    #[test]
+    fn bans_member_and_logs() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            bot.command(
+                bot.moderator_id,
+                &format!("!ban {} 1d spamming", bot.member_id),
+            )
+            .await;
+
+            let bans = bot.discord.requests(
+                Method::PUT,
+                &format!("/guilds/{}/bans/{}", bot.guild_id, bot.member_id),
+            );
+            assert_eq!(bans.len(), 1);
+
+            let (id, reason, active, expires_at): (String, String, bool, Option<NaiveDateTime>) =
+                sqlx::query_as(
+                    "SELECT id, reason, active, expires_at FROM actions WHERE guild_id = $1 AND user_id = $2 AND type = 'ban'",
+                )
+                .bind(bot.guild_id as i64)
+                .bind(bot.member_id as i64)
+                .fetch_one(&*SQL)
+                .await
+                .unwrap();
+
+            assert_eq!(reason, "spamming");
+            assert!(active);
+            assert!(expires_at.is_some());
+
+            let dms = bot.discord.sent_dms(bot.member_id);
+            assert_eq!(dms.len(), 1);
+            assert!(description(&dms[0]).contains("**BANNED**"));
+
+            let replies = bot.replies();
+            assert!(description(&replies[0]).contains(&format!("Log ID: `{id}`")));
+
+            let logs = bot.logs();
+            assert_eq!(logs.len(), 1);
+            assert!(description(&logs[0]).contains("**MEMBER BANNED**"));
+            assert!(description(&logs[0]).contains(&id));
+        });
+    }
+
+    #[test]
+    fn keeps_quoted_reasons_whole() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            // context menus and slash commands pass the reason quoted
+            for reason in ["+s", "1d"] {
+                bot.command(
+                    bot.moderator_id,
+                    &format!("!ban {} {}", bot.member_id, quote_reason(reason)),
+                )
+                .await;
+            }
+
+            let bans: Vec<(String, Option<NaiveDateTime>)> = sqlx::query_as(
+                "SELECT reason, expires_at FROM actions WHERE guild_id = $1 AND user_id = $2 AND type = 'ban' ORDER BY created_at",
+            )
+            .bind(bot.guild_id as i64)
+            .bind(bot.member_id as i64)
+            .fetch_all(&*SQL)
+            .await
+            .unwrap();
+
+            assert_eq!(
+                bans,
+                vec![(String::from("+s"), None), (String::from("1d"), None)]
+            );
+            assert_eq!(bot.discord.sent_dms(bot.member_id).len(), 2);
+        });
+    }
+
+    #[test]
+    fn keeps_the_latest_ban_active() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            for reason in ["first", "second", "third"] {
+                bot.command(
+                    bot.moderator_id,
+                    &format!("!ban {} {reason}", bot.member_id),
+                )
+                .await;
+            }
+
+            let active: Vec<String> = sqlx::query_scalar(
+                "SELECT reason FROM actions WHERE guild_id = $1 AND user_id = $2 AND type = 'ban' AND active",
+            )
+            .bind(bot.guild_id as i64)
+            .bind(bot.member_id as i64)
+            .fetch_all(&*SQL)
+            .await
+            .unwrap();
+
+            assert_eq!(active, vec![String::from("third")]);
+        });
+    }
+
+    #[test]
+    fn refuses_members_without_permissions() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            bot.command(bot.member_id, &format!("!ban {}", bot.moderator_id))
+                .await;
+
+            assert!(bot.discord.state().bans.is_empty());
+            assert!(bot.logs().is_empty());
+
+            let replies = bot.replies();
+            assert_eq!(replies.len(), 1);
+            assert!(description(&replies[0]).contains("You do not have the required"));
+        });
+    }
+
+    #[test]
+    fn rolls_back_when_the_ban_fails() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            bot.discord.state().failing.insert((
+                Method::PUT,
+                format!("/guilds/{}/bans/{}", bot.guild_id, bot.member_id),
+            ));
+            bot.command(bot.moderator_id, &format!("!ban {}", bot.member_id))
+                .await;
+
+            let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM actions WHERE guild_id = $1")
+                .bind(bot.guild_id as i64)
+                .fetch_one(&*SQL)
+                .await
+                .unwrap();
+
+            assert_eq!(count, 0);
+            assert!(bot.logs().is_empty());
+        });
+    }
+}
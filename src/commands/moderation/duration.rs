@@ -23,7 +23,7 @@ use crate::{
     event_handler::CommandError,
     lexer::Token,
     transformers::Transformers,
-    utils::{LogType, guild_log},
+    utils::{LogType, ParsedDuration, guild_log, parse_duration},
 };
 
 pub struct Duration;
@@ -48,7 +48,8 @@ impl Command for Duration {
         "Modifies the duration of a moderation action. \
         Run the log command for the id. \
         The action must be one that accepts a duration, such as ban or mute. \
-        The new duration is relative to the time the action has taken place."
+        The new duration is relative to the time the action has taken place, \
+        dates like `2026-12-01` or `until friday` set the expiry directly."
     }
 
     fn get_syntax(&self) -> Vec<CommandSyntax> {
@@ -96,6 +97,12 @@ impl Command for Duration {
 
         let now = Utc::now().naive_utc();
 
+        // dates like `until friday` set the expiry directly instead of counting from the action
+        let new_expiry_date = match args.get(1).and_then(|a| parse_duration(&a.raw, Utc::now())) {
+            Some(ParsedDuration::Until(date)) => date.naive_utc(),
+            _ => data.created_at + duration,
+        };
+
         if data.expires_at.unwrap_or_default() <= now {
             return Err(CommandError {
                 title: String::from("Already expired"),
@@ -104,7 +111,7 @@ impl Command for Duration {
             });
         }
 
-        if data.created_at + duration <= now {
+        if new_expiry_date <= now {
             return Err(CommandError {
                 title: String::from("This action would set the action duration to the past"),
                 hint: Some(String::from(
@@ -115,10 +122,10 @@ impl Command for Duration {
         }
 
         match data.r#type {
-            ActionType::Ban => {
+            ActionType::Ban | ActionType::Restrict => {
                 if let Err(err) = query!(
                     "UPDATE actions SET expires_at = $1 WHERE guild_id = $2 AND id = $3",
-                    data.created_at + duration,
+                    new_expiry_date,
                     msg.guild_id.map(|g| g.get()).unwrap_or(0) as i64,
                     id
                 )
@@ -134,7 +141,7 @@ impl Command for Duration {
                 }
             }
             ActionType::Mute => {
-                let time = data.created_at + duration;
+                let time = new_expiry_date;
                 let edit = EditMember::new()
                     .audit_log_reason(&data.reason)
                     .disable_communication_until_datetime(time.and_utc().into());
@@ -174,7 +181,7 @@ impl Command for Duration {
 
                 if let Err(err) = query!(
                     "UPDATE actions SET expires_at = $1 WHERE guild_id = $2 AND id = $3",
-                    data.created_at + duration,
+                    new_expiry_date,
                     msg.guild_id.map(|g| g.get()).unwrap_or(0) as i64,
                     id
                 )
@@ -200,8 +207,6 @@ impl Command for Duration {
             }
         };
 
-        let new_expiry_date = data.created_at + duration;
-
         let reply = CreateMessage::new()
             .add_embed(
                 CreateEmbed::new()
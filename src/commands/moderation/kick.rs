@@ -1,7 +1,10 @@
 use std::sync::Arc;
 
 use serenity::{
-    all::{Context, CreateEmbed, CreateMessage, GuildId, Mentionable, Message, Permissions},
+    all::{
+        Context, CreateEmbed, CreateMessage, GuildId, Member, Mentionable, Message, Permissions,
+        UserId,
+    },
     async_trait,
 };
 use sqlx::query;
@@ -14,10 +17,14 @@ use crate::{
         CommandSyntax, TransformerFnArc,
     },
     constants::BRAND_BLUE,
+    database::ActionType,
     event_handler::CommandError,
     lexer::{InferType, Token},
     transformers::Transformers,
-    utils::{CommandMessageResponse, LogType, can_target, guild_log, tinyid},
+    utils::{
+        CommandMessageResponse, LogType, TemplateValues, can_target, get_guild_option, guild_log,
+        guild_templates, tinyid,
+    },
 };
 use ouroboros_macros::command;
 
@@ -79,7 +86,15 @@ impl Command for Kick {
             });
         };
 
-        let res = can_target(&ctx, &author_member, &member, Permissions::MODERATE_MEMBERS).await;
+        let res = can_target(
+            &ctx,
+            &author_member,
+            &member,
+            Permissions::MODERATE_MEMBERS,
+            self,
+            msg.channel_id,
+        )
+        .await;
 
         if !res {
             return Err(CommandError {
@@ -110,24 +125,6 @@ impl Command for Kick {
 
         let db_id = tinyid().await;
 
-        let res = query!(
-            "INSERT INTO actions (id, type, guild_id, user_id, moderator_id, reason) VALUES ($1, 'kick', $2, $3, $4, $5)",
-            db_id,
-            msg.guild_id.map(|g| g.get() as i64).unwrap_or(0),
-            member.user.id.get() as i64,
-            msg.author.id.get() as i64,
-            reason.as_str()
-        ).execute(&*SQL).await;
-
-        if let Err(err) = res {
-            warn!("Got error while kicking; err = {err:?}");
-            return Err(CommandError {
-                title: String::from("Could not kick member"),
-                hint: Some(String::from("please try again later")),
-                arg: None,
-            });
-        }
-
         if inferred && let Some(reply) = msg.referenced_message.clone() {
             let _ = reply.delete(&ctx).await;
         }
@@ -144,45 +141,38 @@ impl Command for Kick {
             }
         };
 
-        let static_response_parts = (
-            format!("**{} KICKED**\n-# Log ID: `{db_id}`", member.mention()),
-            format!("\n```\n{reason}\n```"),
-        );
+        let (dm_template, response_template) =
+            guild_templates(member.guild_id, &ActionType::Kick).await;
+        let values = TemplateValues {
+            server: guild_name,
+            reason: reason.clone(),
+            log_id: db_id.clone(),
+            moderator: msg.author.mention().to_string(),
+            user: member.mention().to_string(),
+            ..Default::default()
+        };
+
+        let dm_members = get_guild_option(member.guild_id, "moderation.dm_members")
+            .await
+            .as_bool()
+            .unwrap_or(true);
 
         let mut cmd_response = CommandMessageResponse::new(member.user.id)
-            .dm_content(format!(
-                "**KICKED**\n-# Server: {}\n```\n{}\n```",
-                guild_name, reason
-            ))
-            .server_content(Box::new(move |a| {
-                format!("{}{a}{}", static_response_parts.0, static_response_parts.1)
-            }))
+            .dm_content(values.render(&dm_template))
+            .server_content(values.render_response(&response_template, String::new()))
             .automatically_delete(inferred)
-            .mark_silent(params.contains_key("silent"));
-
-        cmd_response.send_dm(&ctx).await;
-
-        if let Err(err) = member.kick_with_reason(&ctx, &reason).await {
-            warn!("Got error while kicking; err = {err:?}");
-
-            if query!("DELETE FROM actions WHERE id = $1", db_id)
-                .execute(&*SQL)
-                .await
-                .is_err()
-            {
-                error!(
-                    "Got an error while kicking and an error with the database! Stray kick entry in DB & manual action required; id = {db_id}; err = {err:?}"
-                );
-            }
+            .mark_silent(params.contains_key("silent") || !dm_members);
 
-            return Err(CommandError {
-                title: String::from("Could not kick member"),
-                hint: Some(String::from(
-                    "check if the bot has the kick members permission or try again later",
-                )),
-                arg: None,
-            });
-        }
+        kick_member(
+            &ctx,
+            &member,
+            &db_id,
+            msg.author.id,
+            &reason,
+            None,
+            Some(&cmd_response),
+        )
+        .await?;
 
         cmd_response.send_response(&ctx, &msg).await;
 
@@ -219,3 +209,62 @@ impl Command for Kick {
         }
     }
 }
+
+/// Stores a kick, DMs the member using `response` (if any) and kicks them.
+/// The entry is rolled back if the kick could not be applied. `batch_id` groups the kicks of mass actions and raids.
+pub async fn kick_member(
+    ctx: &Context,
+    member: &Member,
+    db_id: &str,
+    moderator_id: UserId,
+    reason: &str,
+    batch_id: Option<&str>,
+    response: Option<&CommandMessageResponse>,
+) -> Result<(), CommandError> {
+    let res = query!(
+        "INSERT INTO actions (id, type, guild_id, user_id, moderator_id, reason, batch_id) VALUES ($1, 'kick', $2, $3, $4, $5, $6)",
+        db_id,
+        member.guild_id.get() as i64,
+        member.user.id.get() as i64,
+        moderator_id.get() as i64,
+        reason,
+        batch_id
+    ).execute(&*SQL).await;
+
+    if let Err(err) = res {
+        warn!("Got error while kicking; err = {err:?}");
+        return Err(CommandError {
+            title: String::from("Could not kick member"),
+            hint: Some(String::from("please try again later")),
+            arg: None,
+        });
+    }
+
+    if let Some(response) = response {
+        response.send_dm(ctx).await;
+    }
+
+    if let Err(err) = member.kick_with_reason(ctx, reason).await {
+        warn!("Got error while kicking; err = {err:?}");
+
+        if query!("DELETE FROM actions WHERE id = $1", db_id)
+            .execute(&*SQL)
+            .await
+            .is_err()
+        {
+            error!(
+                "Got an error while kicking and an error with the database! Stray kick entry in DB & manual action required; id = {db_id}; err = {err:?}"
+            );
+        }
+
+        return Err(CommandError {
+            title: String::from("Could not kick member"),
+            hint: Some(String::from(
+                "check if the bot has the kick members permission or try again later",
+            )),
+            arg: None,
+        });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,372 @@
+use std::sync::Arc;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use ouroboros_macros::command;
+use serenity::{
+    all::{
+        CacheHttp, Context, CreateAllowedMentions, CreateEmbed, CreateMessage, GuildChannel,
+        Mentionable, Message, PermissionOverwrite, PermissionOverwriteType, Permissions, RoleId,
+        UserId,
+    },
+    async_trait,
+};
+use sqlx::query;
+use tracing::{error, warn};
+
+use crate::{
+    SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::CommandError,
+    lexer::Token,
+    transformers::Transformers,
+    utils::{LogType, can_use_in_channel, guild_log, humanize_expiry},
+};
+
+/// The permissions denied to `@everyone` while a channel is locked
+const LOCK_PERMISSIONS: Permissions = Permissions::SEND_MESSAGES
+    .union(Permissions::SEND_MESSAGES_IN_THREADS)
+    .union(Permissions::CREATE_PUBLIC_THREADS)
+    .union(Permissions::CREATE_PRIVATE_THREADS)
+    .union(Permissions::ADD_REACTIONS);
+
+pub struct Lock;
+
+impl Lock {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for Lock {
+    fn get_name(&self) -> &'static str {
+        "lock"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Stops members from sending messages in a channel"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Locks a channel by denying @everyone to send messages, create threads and add reactions. \
+        Defaults to the current channel. The previous permissions are remembered and restored exactly by `unlock`. \
+        If a duration is provided the channel is unlocked automatically."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::Channel("channel", false),
+            CommandSyntax::Duration("duration", false),
+            CommandSyntax::Reason("reason"),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Moderation
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::maybe_guild_channel] channel: Option<GuildChannel>,
+        #[transformers::maybe_duration] duration: Option<Duration>,
+        #[transformers::consume] reason: Option<String>,
+    ) -> Result<(), CommandError> {
+        let channel = match channel {
+            Some(c) => c,
+            None => match msg.channel(&ctx).await.ok().and_then(|c| c.guild()) {
+                Some(c) => c,
+                None => {
+                    return Err(CommandError {
+                        title: String::from("Unexpected error has occured."),
+                        hint: Some(String::from("could not get current channel")),
+                        arg: None,
+                    });
+                }
+            },
+        };
+
+        if channel.id != msg.channel_id {
+            let allowed = match msg.member(&ctx).await {
+                Ok(member) => {
+                    can_use_in_channel(&ctx, &member, &channel, Permissions::MANAGE_CHANNELS, self)
+                        .await
+                }
+                Err(_) => false,
+            };
+
+            if !allowed {
+                return Err(CommandError {
+                    title: String::from("You do not have permissions to execute this command."),
+                    hint: Some(format!(
+                        "you need the manage channels permission in {}",
+                        channel.mention()
+                    )),
+                    arg: None,
+                });
+            }
+        }
+
+        let duration = duration.unwrap_or(Duration::zero());
+        let mut reason = reason
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .unwrap_or(String::from("No reason provided"));
+
+        if reason.len() > 500 {
+            reason.truncate(500);
+            reason.push_str("...");
+        }
+
+        let time_string = if !duration.is_zero() {
+            humanize_expiry(&duration)
+        } else {
+            String::from("until unlocked")
+        };
+
+        let expires_at = if duration.is_zero() {
+            None
+        } else {
+            Some((Utc::now() + duration).naive_utc())
+        };
+
+        lock_channel(&ctx, &channel, msg.author.id, &reason, expires_at, false).await?;
+
+        if channel.id != msg.channel_id {
+            let notice = CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .description(format!(
+                        "**CHANNEL LOCKED**\n-# Duration: {time_string}\n```\n{reason}\n```"
+                    ))
+                    .color(BRAND_BLUE),
+            );
+
+            if let Err(err) = channel.id.send_message(&ctx, notice).await {
+                warn!("Could not send lock notice; err = {err:?}");
+            }
+        }
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(format!(
+                        "**{} LOCKED**\n-# Duration: {time_string}\n```\n{reason}\n```",
+                        channel.mention()
+                    ))
+                    .color(BRAND_BLUE),
+            )
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            warn!("Could not send message; err = {err:?}");
+        }
+
+        guild_log(
+            &ctx,
+            LogType::ChannelModeration,
+            channel.guild_id,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .description(format!(
+                        "**CHANNEL LOCKED**\n-# Actor: {} `{}` | Channel: {} `{}` | Duration: {time_string}\n```\n{reason}\n```",
+                        msg.author.mention(),
+                        msg.author.id.get(),
+                        channel.mention(),
+                        channel.id.get()
+                    ))
+                    .color(BRAND_BLUE),
+            ),
+        )
+        .await;
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::MANAGE_CHANNELS],
+            one_of: vec![],
+            bot: [
+                CommandPermissions::baseline().as_slice(),
+                &[Permissions::MANAGE_CHANNELS, Permissions::MANAGE_ROLES],
+            ]
+            .concat(),
+        }
+    }
+}
+
+/// Denies `@everyone` the lock permissions in a channel. The previous overwrite is stored first so `unlock_channel`
+/// can restore it exactly, the entry is rolled back if the overwrite could not be edited.
+pub async fn lock_channel(
+    cache_http: impl CacheHttp,
+    channel: &GuildChannel,
+    moderator_id: UserId,
+    reason: &str,
+    expires_at: Option<NaiveDateTime>,
+    lockdown: bool,
+) -> Result<(), CommandError> {
+    let everyone = RoleId::new(channel.guild_id.get());
+    let previous = channel
+        .permission_overwrites
+        .iter()
+        .find(|o| o.kind == PermissionOverwriteType::Role(everyone));
+
+    let res = query!(
+        r#"
+            INSERT INTO channel_locks (channel_id, type, guild_id, moderator_id, reason, lockdown, had_overwrite, previous_allow, previous_deny, expires_at)
+            VALUES ($1, 'lock', $2, $3, $4, $5, $6, $7, $8, $9)
+            ON CONFLICT (channel_id, type) DO NOTHING
+        "#,
+        channel.id.get() as i64,
+        channel.guild_id.get() as i64,
+        moderator_id.get() as i64,
+        reason,
+        lockdown,
+        previous.is_some(),
+        previous.map(|o| o.allow.bits() as i64).unwrap_or(0),
+        previous.map(|o| o.deny.bits() as i64).unwrap_or(0),
+        expires_at
+    )
+    .execute(&*SQL)
+    .await;
+
+    match res {
+        Ok(r) if r.rows_affected() == 0 => {
+            return Err(CommandError {
+                title: String::from("This channel is already locked"),
+                hint: None,
+                arg: None,
+            });
+        }
+        Ok(_) => {}
+        Err(err) => {
+            warn!("Got error while locking channel; err = {err:?}");
+            return Err(CommandError {
+                title: String::from("Could not lock channel"),
+                hint: Some(String::from("please try again later")),
+                arg: None,
+            });
+        }
+    }
+
+    let overwrite = PermissionOverwrite {
+        allow: previous.map(|o| o.allow).unwrap_or_default() - LOCK_PERMISSIONS,
+        deny: previous.map(|o| o.deny).unwrap_or_default() | LOCK_PERMISSIONS,
+        kind: PermissionOverwriteType::Role(everyone),
+    };
+
+    if let Err(err) = channel
+        .id
+        .create_permission(cache_http.http(), overwrite)
+        .await
+    {
+        warn!("Got error while locking channel; err = {err:?}");
+
+        if query!(
+            "DELETE FROM channel_locks WHERE channel_id = $1 AND type = 'lock'",
+            channel.id.get() as i64
+        )
+        .execute(&*SQL)
+        .await
+        .is_err()
+        {
+            error!(
+                "Got an error while locking and an error with the database! Stray lock entry in DB & manual action required; channel = {}; err = {err:?}",
+                channel.id.get()
+            );
+        }
+
+        return Err(CommandError {
+            title: String::from("Could not lock channel"),
+            hint: Some(String::from(
+                "check if the bot has the manage channels and manage roles permissions in this channel",
+            )),
+            arg: None,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Method;
+    use serenity::json::json;
+
+    use crate::testing::{
+        self, TestBot, description,
+        fake_discord::{channel_json, member_json, role_json, user_json},
+        snowflake,
+    };
+
+    #[test]
+    fn checks_permissions_in_the_target_channel() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            // may manage channels, except in the staff channel
+            let role_id = snowflake();
+            let user_id = snowflake();
+            let (staff_id, open_id) = (snowflake(), snowflake());
+            {
+                let mut state = bot.discord.state();
+                let guild = state.guilds.get_mut(&bot.guild_id).unwrap();
+                guild["roles"].as_array_mut().unwrap().push(role_json(
+                    role_id,
+                    "Channel Mod",
+                    1,
+                    1 << 4, // MANAGE_CHANNELS
+                ));
+
+                let user = user_json(user_id, "channel mod", false);
+                state.users.insert(user_id, user.clone());
+                state.members.insert(
+                    (bot.guild_id, user_id),
+                    member_json(bot.guild_id, user, &[role_id]),
+                );
+
+                let mut staff = channel_json(staff_id, bot.guild_id, "staff");
+                staff["permission_overwrites"] = json!([{
+                    "id": role_id.to_string(),
+                    "type": 0,
+                    "allow": "0",
+                    "deny": (1u64 << 4).to_string(),
+                }]);
+                state.channels.insert(staff_id, staff);
+                state
+                    .channels
+                    .insert(open_id, channel_json(open_id, bot.guild_id, "open"));
+            }
+
+            bot.command(user_id, &format!("!lock <#{staff_id}> raid"))
+                .await;
+            bot.command(user_id, &format!("!lock <#{open_id}> raid"))
+                .await;
+
+            let path = |channel: u64| format!("/channels/{channel}/permissions/{}", bot.guild_id);
+            assert!(
+                bot.discord
+                    .requests(Method::PUT, &path(staff_id))
+                    .is_empty()
+            );
+            assert_eq!(bot.discord.requests(Method::PUT, &path(open_id)).len(), 1);
+
+            let replies = bot.replies();
+            assert!(description(&replies[0]).contains("manage channels permission in"));
+            assert!(description(&replies[1]).contains("LOCKED"));
+        });
+    }
+}
@@ -0,0 +1,393 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use ouroboros_macros::command;
+use serenity::{
+    all::{
+        ChannelId, Context, CreateAllowedMentions, CreateEmbed, CreateMessage, GuildChannel,
+        Mentionable, Message, Permissions,
+    },
+    async_trait,
+};
+use sqlx::query;
+use tracing::warn;
+
+use crate::{
+    GUILD_SETTINGS, SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc, lock_channel, unlock_channel,
+    },
+    constants::BRAND_BLUE,
+    event_handler::CommandError,
+    lexer::Token,
+    transformers::Transformers,
+    utils::{LogType, consume_pgsql_error, guild_log, humanize_expiry},
+};
+
+/// The maximum amount of channels in the lockdown set of a guild
+const MAX_LOCKDOWN_CHANNELS: usize = 50;
+
+pub struct Lockdown;
+
+impl Lockdown {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for Lockdown {
+    fn get_name(&self) -> &'static str {
+        "lockdown"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Locks a configured set of channels at once"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Locks every channel in the lockdown set of the server, the same way `lock` does. \
+        Available subcommands: list start end add remove;\n \
+        `list` lists the channels in the lockdown set\n \
+        `start [duration] [reason]` locks all of them, they are unlocked automatically if a duration is provided\n \
+        `end [reason]` unlocks all channels locked by the lockdown\n \
+        `add <channel>` and `remove <channel>` change the lockdown set"
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::String("subcommand", false),
+            CommandSyntax::Channel("channel", false),
+            CommandSyntax::Duration("duration", false),
+            CommandSyntax::Reason("reason"),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Moderation
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::some_string] subcommand: Option<String>,
+        #[transformers::maybe_guild_channel] channel: Option<GuildChannel>,
+        #[transformers::maybe_duration] duration: Option<Duration>,
+        #[transformers::consume] reason: Option<String>,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        let mut channels = {
+            let mut lock = GUILD_SETTINGS.lock().await;
+            lock.get(guild_id.get())
+                .await
+                .map(|s| s.moderation.lockdown_channels)
+                .unwrap_or_default()
+        };
+
+        let mut reason = reason
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .unwrap_or(String::from("No reason provided"));
+
+        if reason.len() > 500 {
+            reason.truncate(500);
+            reason.push_str("...");
+        }
+
+        let subcommand = subcommand.unwrap_or(String::from("list")).to_lowercase();
+
+        let description = match subcommand.as_str() {
+            "list" => {
+                if channels.is_empty() {
+                    String::from(
+                        "**LOCKDOWN CHANNELS**\nThis server has no lockdown channels, add some with `lockdown add <channel>`.",
+                    )
+                } else {
+                    format!(
+                        "**LOCKDOWN CHANNELS**\n{}",
+                        channels
+                            .iter()
+                            .map(|c| ChannelId::new(*c).mention().to_string())
+                            .collect::<Vec<_>>()
+                            .join(" ")
+                    )
+                }
+            }
+
+            "add" | "remove" => {
+                let Some(channel) = channel else {
+                    return Err(CommandError::arg_not_found("Channel", Some("channel")));
+                };
+
+                let description = if subcommand == "add" {
+                    if channels.contains(&channel.id.get()) {
+                        return Err(CommandError {
+                            title: String::from("This channel is already a lockdown channel"),
+                            hint: None,
+                            arg: Some(args[1].clone()),
+                        });
+                    }
+
+                    if channels.len() >= MAX_LOCKDOWN_CHANNELS {
+                        return Err(CommandError {
+                            title: format!(
+                                "Servers can have at most {MAX_LOCKDOWN_CHANNELS} lockdown channels"
+                            ),
+                            hint: None,
+                            arg: None,
+                        });
+                    }
+
+                    channels.push(channel.id.get());
+                    format!("**LOCKDOWN CHANNEL ADDED**\n{}", channel.mention())
+                } else {
+                    let Some(index) = channels.iter().position(|c| *c == channel.id.get()) else {
+                        return Err(CommandError {
+                            title: String::from("This channel is not a lockdown channel"),
+                            hint: None,
+                            arg: Some(args[1].clone()),
+                        });
+                    };
+
+                    channels.remove(index);
+                    format!("**LOCKDOWN CHANNEL REMOVED**\n{}", channel.mention())
+                };
+
+                let res = query!(
+                    "UPDATE guild_settings SET lockdown_channels = $2 WHERE guild_id = $1",
+                    guild_id.get() as i64,
+                    &channels.iter().map(|c| *c as i64).collect::<Vec<_>>()
+                )
+                .execute(&*SQL)
+                .await;
+
+                if let Err(err) = res {
+                    consume_pgsql_error(String::from("LOCKDOWN CHANNELS UPDATE"), err);
+                    return Err(CommandError {
+                        title: String::from("Could not update the database"),
+                        hint: Some(String::from("please try again later")),
+                        arg: None,
+                    });
+                }
+
+                let mut lock = GUILD_SETTINGS.lock().await;
+                lock.invalidate();
+
+                description
+            }
+
+            "start" => {
+                if channels.is_empty() {
+                    return Err(CommandError {
+                        title: String::from("This server has no lockdown channels"),
+                        hint: Some(String::from("add some with lockdown add <channel>")),
+                        arg: None,
+                    });
+                }
+
+                let Ok(guild_channels) = guild_id.channels(&ctx).await else {
+                    return Err(CommandError {
+                        title: String::from("Couldn't get guild channels"),
+                        hint: Some(String::from("please try again later.")),
+                        arg: None,
+                    });
+                };
+
+                let duration = duration.unwrap_or(Duration::zero());
+
+                let time_string = if !duration.is_zero() {
+                    humanize_expiry(&duration)
+                } else {
+                    String::from("until ended")
+                };
+
+                let expires_at = if duration.is_zero() {
+                    None
+                } else {
+                    Some((Utc::now() + duration).naive_utc())
+                };
+
+                let mut locked = 0;
+                let mut failed = vec![];
+
+                for channel_id in &channels {
+                    let Some(channel) = guild_channels.get(&ChannelId::new(*channel_id)) else {
+                        failed.push(format!("`{channel_id}` channel not found"));
+                        continue;
+                    };
+
+                    let notice = CreateMessage::new().add_embed(
+                        CreateEmbed::new()
+                            .description(format!(
+                                "**SERVER LOCKDOWN**\n-# Duration: {time_string}\n```\n{reason}\n```"
+                            ))
+                            .color(BRAND_BLUE),
+                    );
+
+                    match lock_channel(&ctx, channel, msg.author.id, &reason, expires_at, true)
+                        .await
+                    {
+                        Ok(_) => {
+                            locked += 1;
+
+                            if let Err(err) = channel.id.send_message(&ctx, notice).await {
+                                warn!("Could not send lockdown notice; err = {err:?}");
+                            }
+                        }
+                        Err(err) => failed.push(format!("{} {}", channel.mention(), err.title)),
+                    }
+                }
+
+                let summary = format!(
+                    "Locked: {locked}/{} | Duration: {time_string}",
+                    channels.len()
+                );
+
+                log_lockdown(&ctx, &msg, "LOCKDOWN STARTED", &summary, &failed, &reason).await;
+
+                format!(
+                    "**LOCKDOWN STARTED**\n-# {summary}{}\n```\n{reason}\n```",
+                    failures(&failed)
+                )
+            }
+
+            "end" => {
+                let locked = match query!(
+                    "SELECT channel_id FROM channel_locks WHERE guild_id = $1 AND type = 'lock' AND lockdown = true",
+                    guild_id.get() as i64
+                )
+                .fetch_all(&*SQL)
+                .await
+                {
+                    Ok(rows) => rows,
+                    Err(err) => {
+                        consume_pgsql_error(String::from("LOCKDOWN END"), err);
+                        return Err(CommandError {
+                            title: String::from("Could not fetch locked channels"),
+                            hint: Some(String::from("please try again later")),
+                            arg: None,
+                        });
+                    }
+                };
+
+                if locked.is_empty() {
+                    return Err(CommandError {
+                        title: String::from("There is no active lockdown"),
+                        hint: None,
+                        arg: None,
+                    });
+                }
+
+                let mut unlocked = 0;
+                let mut failed = vec![];
+
+                for row in &locked {
+                    let channel_id = ChannelId::new(row.channel_id as u64);
+
+                    match unlock_channel(&ctx, guild_id, channel_id).await {
+                        Ok(_) => {
+                            unlocked += 1;
+
+                            let notice = CreateMessage::new().add_embed(
+                                CreateEmbed::new()
+                                    .description(format!("**LOCKDOWN ENDED**\n```\n{reason}\n```"))
+                                    .color(BRAND_BLUE),
+                            );
+
+                            let _ = channel_id.send_message(&ctx, notice).await;
+                        }
+                        Err(err) => failed.push(format!("{} {}", channel_id.mention(), err.title)),
+                    }
+                }
+
+                let summary = format!("Unlocked: {unlocked}/{}", locked.len());
+
+                log_lockdown(&ctx, &msg, "LOCKDOWN ENDED", &summary, &failed, &reason).await;
+
+                format!(
+                    "**LOCKDOWN ENDED**\n-# {summary}{}\n```\n{reason}\n```",
+                    failures(&failed)
+                )
+            }
+
+            _ => {
+                return Err(CommandError {
+                    title: String::from("Unknown subcommand"),
+                    hint: Some(String::from(
+                        "available subcommands: list, start, end, add, remove",
+                    )),
+                    arg: Some(args[0].clone()),
+                });
+            }
+        };
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(description)
+                    .color(BRAND_BLUE),
+            )
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            warn!("Could not send message; err = {err:?}");
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::MANAGE_GUILD, Permissions::MANAGE_CHANNELS],
+            one_of: vec![],
+            bot: [
+                CommandPermissions::baseline().as_slice(),
+                &[Permissions::MANAGE_CHANNELS, Permissions::MANAGE_ROLES],
+            ]
+            .concat(),
+        }
+    }
+}
+
+fn failures(failed: &[String]) -> String {
+    if failed.is_empty() {
+        String::new()
+    } else {
+        format!("\n{}", failed.join("\n"))
+    }
+}
+
+async fn log_lockdown(
+    ctx: &Context,
+    msg: &Message,
+    title: &str,
+    summary: &str,
+    failed: &[String],
+    reason: &str,
+) {
+    guild_log(
+        ctx,
+        LogType::ChannelModeration,
+        msg.guild_id.unwrap(),
+        CreateMessage::new().add_embed(
+            CreateEmbed::new()
+                .description(format!(
+                    "**{title}**\n-# Actor: {} `{}` | {summary}{}\n```\n{reason}\n```",
+                    msg.author.mention(),
+                    msg.author.id.get(),
+                    failures(failed)
+                ))
+                .color(BRAND_BLUE),
+        ),
+    )
+    .await;
+}
@@ -3,7 +3,8 @@ use std::{collections::HashMap, time::Duration, vec};
 use chrono::Utc;
 use serenity::{
     all::{
-        ButtonStyle, Context, CreateActionRow, CreateAllowedMentions, CreateButton, CreateEmbed,
+        ButtonStyle, Context, CreateActionRow, CreateAllowedMentions, CreateAttachment,
+        CreateButton, CreateEmbed,
         CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, EditMessage,
         Message, Permissions,
     },
@@ -23,17 +24,24 @@ use crate::{
     event_handler::CommandError,
     lexer::Token,
     transformers::Transformers,
+    utils::{describe_not_attached, split_upload_budget},
 };
 
+/// The maximum amount of note files sent along with a single log entry
+const MAX_LOG_FILES: usize = 10;
+/// The maximum length of the rendered notes, keeping the embed below Discords description limit
+const MAX_NOTES_LENGTH: usize = 3000;
+
 #[derive(Debug, Clone)]
-struct LogRecord {
-    id: String,
-    r#type: ActionType,
-    moderator_id: i64,
-    created_at: sqlx::types::chrono::NaiveDateTime,
-    updated_at: ::std::option::Option<sqlx::types::chrono::NaiveDateTime>,
-    expires_at: ::std::option::Option<sqlx::types::chrono::NaiveDateTime>,
-    reason: String,
+pub struct LogRecord {
+    pub id: String,
+    pub r#type: ActionType,
+    pub user_id: i64,
+    pub moderator_id: i64,
+    pub created_at: sqlx::types::chrono::NaiveDateTime,
+    pub updated_at: ::std::option::Option<sqlx::types::chrono::NaiveDateTime>,
+    pub expires_at: ::std::option::Option<sqlx::types::chrono::NaiveDateTime>,
+    pub reason: String,
 }
 
 pub struct Log;
@@ -43,10 +51,15 @@ impl Log {
         Self {}
     }
 
-    async fn get_one_response(&self, guild_id: i64, log: String) -> Result<String, CommandError> {
+    /// Renders a single action including the timeline of its notes, returns the stored note files alongside
+    async fn get_one_response(
+        &self,
+        guild_id: i64,
+        log: String,
+    ) -> Result<(String, Vec<CreateAttachment>), CommandError> {
         let res = query!(
             r#"
-                SELECT id, type as "type!: ActionType", moderator_id, user_id, created_at, updated_at, active, expires_at, reason FROM actions WHERE guild_id = $1 AND id = $2;
+                SELECT id, type as "type!: ActionType", moderator_id, user_id, created_at, updated_at, active, expires_at, reason, triggered_by FROM actions WHERE guild_id = $1 AND id = $2;
             "#,
             guild_id,
             log
@@ -73,13 +86,17 @@ impl Log {
             });
         };
 
-        let update_string = if let Some(t) = data.updated_at {
+        let mut update_string = if let Some(t) = data.updated_at {
             format!(" | Updated <t:{0}:d> <t:{0}:T>", t.and_utc().timestamp())
         } else {
             String::new()
         };
 
-        let response = if let Some(expiry) = data.expires_at {
+        if let Some(trigger) = data.triggered_by {
+            update_string.push_str(&format!(" | Triggered by `{trigger}`"));
+        }
+
+        let mut response = if let Some(expiry) = data.expires_at {
             let now = Utc::now().naive_utc();
             let expire_tag = if expiry < now { "Expired" } else { "Expires" };
 
@@ -106,179 +123,160 @@ impl Log {
             )
         };
 
-        Ok(response)
+        let (notes, files) = self.get_notes(&data.id).await?;
+        response.push_str(&notes);
+
+        Ok((response, files))
     }
 
-    async fn run_one(&self, ctx: Context, msg: Message, log: String) -> Result<(), CommandError> {
-        let reply = CreateMessage::new()
-            .add_embed(
-                CreateEmbed::new()
-                    .description(
-                        self.get_one_response(msg.guild_id.unwrap().get() as i64, log)
-                            .await?,
-                    )
-                    .color(BRAND_BLUE),
-            )
-            .reference_message(&msg)
-            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+    /// Renders the notes of an action oldest first, dropping the oldest notes if the embed would get too long
+    async fn get_notes(&self, log: &str) -> Result<(String, Vec<CreateAttachment>), CommandError> {
+        let notes = query!(
+            r#"
+                SELECT n.id, n.moderator_id, n.content, n.created_at, COUNT(a.id) as "files!"
+                FROM action_notes n LEFT JOIN action_note_attachments a ON a.note_id = n.id
+                WHERE n.action_id = $1
+                GROUP BY n.id
+                ORDER BY n.created_at ASC;
+            "#,
+            log
+        )
+        .fetch_all(&*SQL);
 
-        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
-            warn!("Could not send message; err = {err:?}");
-        }
+        let attachments = query!(
+            r#"
+                SELECT a.id, a.filename, a.url, octet_length(a.data) as "size!" FROM action_note_attachments a
+                JOIN action_notes n ON n.id = a.note_id
+                WHERE n.action_id = $1
+                ORDER BY n.created_at DESC, a.id ASC
+                LIMIT $2;
+            "#,
+            log,
+            MAX_LOG_FILES as i64
+        )
+        .fetch_all(&*SQL);
 
-        Ok(())
-    }
+        let (notes, attachments) = match tokio::join!(notes, attachments) {
+            (Ok(n), Ok(a)) => (n, a),
+            (Err(err), _) | (_, Err(err)) => {
+                warn!("Couldn't fetch log notes; err = {err:?}");
+                return Err(CommandError {
+                    title: String::from("Unable to query the database"),
+                    hint: Some(String::from("try again later")),
+                    arg: None,
+                });
+            }
+        };
 
-    fn create_chunked_response(&self, chunk: &[LogRecord]) -> String {
-        let mut response = String::new();
+        if notes.is_empty() {
+            return Ok((String::new(), vec![]));
+        }
 
-        chunk.iter().for_each(|data| {
-            let mut record = data.clone();
+        // the newest files are sent as long as they fit into the upload limit, the others are linked
+        let (attached, over) = split_upload_budget(attachments, |a| a.size as usize);
+        let not_attached =
+            describe_not_attached(over.iter().map(|a| (a.filename.as_str(), a.url.as_deref())));
 
-            if record.reason.len() > 100 {
-                record.reason.truncate(100);
-                record.reason.push_str("...");
+        let files = if attached.is_empty() {
+            vec![]
+        } else {
+            let ids = attached.iter().map(|a| a.id).collect::<Vec<_>>();
+
+            match query!(
+                "SELECT id, filename, data FROM action_note_attachments WHERE id = ANY($1) ORDER BY array_position($1, id)",
+                &ids
+            )
+            .fetch_all(&*SQL)
+            .await
+            {
+                Ok(files) => files
+                    .into_iter()
+                    .map(|f| CreateAttachment::bytes(f.data, f.filename))
+                    .collect(),
+                Err(err) => {
+                    warn!("Couldn't fetch log note files; err = {err:?}");
+                    return Err(CommandError {
+                        title: String::from("Unable to query the database"),
+                        hint: Some(String::from("try again later")),
+                        arg: None,
+                    });
+                }
             }
+        };
 
-            let reason =
-                if record.reason.chars().all(char::is_whitespace) || record.reason.is_empty() {
-                    String::new()
+        let mut rendered = notes
+            .iter()
+            .enumerate()
+            .map(|(i, note)| {
+                let files = if note.files > 0 {
+                    format!(" | {} files", note.files)
                 } else {
-                    format!("```\n{}\n```\n", record.reason)
+                    String::new()
                 };
 
-            let update_string = if let Some(t) = record.updated_at {
-                format!(" | Updated <t:{0}:d> <t:{0}:T>", t.and_utc().timestamp())
-            } else {
                 format!(
-                    " | At <t:{0}:d> <t:{0}:T>",
-                    record.created_at.and_utc().timestamp()
+                    "-# #{0} | Mod: <@{1}> | At <t:{2}:d> <t:{2}:T>{files}\n{3}\n",
+                    i + 1,
+                    note.moderator_id,
+                    note.created_at.and_utc().timestamp(),
+                    note.content
                 )
-            };
-
-            if let Some(expiry) = record.expires_at {
-                let now = Utc::now().naive_utc();
-                let expire_tag = if expiry < now { "Expired" } else { "Expires" };
-
-                response.push_str(
-                    format!(
-                        "**{0}**\n-# Mod: <@{1}>{6} | {2}: <t:{3}:d> <t:{3}:T>\n`{4}`\n{5}\n",
-                        record.r#type.to_string().to_uppercase(),
-                        record.moderator_id,
-                        expire_tag,
-                        expiry.and_utc().timestamp(),
-                        record.id,
-                        reason,
-                        update_string
-                    )
-                    .as_str(),
-                );
-            } else {
-                response.push_str(
-                    format!(
-                        "**{0}**\n-# Mod: <@{1}>{4}\n`{2}`\n```\n{3}\n```\n",
-                        record.r#type.to_string().to_uppercase(),
-                        record.moderator_id,
-                        record.id,
-                        record.reason,
-                        update_string
-                    )
-                    .as_str(),
-                );
-            }
-        });
-
-        response
-    }
-}
+            })
+            .collect::<Vec<_>>();
+
+        let mut hidden = 0;
+
+        while rendered.iter().map(|n| n.chars().count()).sum::<usize>()
+            + not_attached.chars().count()
+            > MAX_NOTES_LENGTH
+            && rendered.len() > 1
+        {
+            rendered.remove(0);
+            hidden += 1;
+        }
 
-#[async_trait]
-impl Command for Log {
-    fn get_name(&self) -> &'static str {
-        "log"
-    }
+        let hidden = if hidden > 0 {
+            format!("-# {hidden} earlier notes not shown\n")
+        } else {
+            String::new()
+        };
 
-    fn get_short(&self) -> &'static str {
-        "Shows actions taken on a member"
+        Ok((
+            format!(
+                "**NOTES**\n{hidden}{}{not_attached}",
+                rendered.join("\n")
+            ),
+            files,
+        ))
     }
 
-    fn get_full(&self) -> &'static str {
-        "Shows the moderation actions taken on a member. This includes warns, bans, kicks, etc."
-    }
+    async fn run_one(&self, ctx: Context, msg: Message, log: String) -> Result<(), CommandError> {
+        let (response, files) = self
+            .get_one_response(msg.guild_id.unwrap().get() as i64, log)
+            .await?;
 
-    fn get_syntax(&self) -> Vec<CommandSyntax> {
-        vec![CommandSyntax::Or(
-            Box::new(CommandSyntax::User("user", true)),
-            Box::new(CommandSyntax::String("id", false)),
-        )]
-    }
+        let reply = CreateMessage::new()
+            .add_embed(CreateEmbed::new().description(response).color(BRAND_BLUE))
+            .add_files(files)
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
 
-    fn get_category(&self) -> CommandCategory {
-        CommandCategory::Moderation
-    }
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            warn!("Could not send message; err = {err:?}");
+        }
 
-    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
-        vec![]
+        Ok(())
     }
 
-    async fn run(
+    /// Sends log records as pages of 5 with buttons to switch pages and to view a single record in detail.
+    /// `show_target` adds the targeted user to every record, for lists which are not limited to one user.
+    pub async fn send_paginated(
         &self,
-        ctx: Context,
-        msg: Message,
-        args: Vec<Token>,
-        _params: HashMap<&str, (bool, CommandArgument)>,
+        ctx: &Context,
+        msg: &Message,
+        data: Vec<LogRecord>,
+        show_target: bool,
     ) -> Result<(), CommandError> {
-        let mut args_iter = args.clone().into_iter().peekable();
-        let Ok(token) = Transformers::user(&ctx, &msg, &mut args_iter).await else {
-            match Transformers::string(&ctx, &msg, &mut args.into_iter().peekable()).await {
-                Ok(log) => {
-                    let Some(CommandArgument::String(id)) = log.contents else {
-                        unreachable!()
-                    };
-                    return self.run_one(ctx, msg, id).await;
-                }
-                Err(_) => {
-                    return Err(CommandError::arg_not_found(
-                        "user or id",
-                        Some("User || String"),
-                    ));
-                }
-            }
-        };
-
-        let Token {
-            contents: Some(CommandArgument::User(user)),
-            ..
-        } = token
-        else {
-            return Err(CommandError::arg_not_found(
-                "user or id",
-                Some("User || String"),
-            ));
-        };
-
-        let res = query_as!(
-            LogRecord,
-            r#"
-                SELECT id, type as "type!: ActionType", moderator_id, created_at, updated_at, expires_at, reason FROM actions WHERE user_id = $1 AND guild_id = $2;
-            "#,
-            user.id.get() as i64,
-            msg.guild_id.map(|g| g.get()).unwrap_or(0) as i64
-        )
-        .fetch_all(&*SQL).await;
-
-        let data = match res {
-            Ok(d) => d,
-            Err(err) => {
-                warn!("Couldn't fetch log data; err = {err:?}");
-                return Err(CommandError {
-                    title: String::from("Unable to query the database"),
-                    hint: Some(String::from("try again later")),
-                    arg: None,
-                });
-            }
-        };
-
         let chunks: Vec<Vec<LogRecord>> = data.chunks(5).map(|c| c.to_vec()).collect();
 
         let Some(chunk) = chunks.first() else {
@@ -288,10 +286,10 @@ impl Command for Log {
                         .description("No log entries found.")
                         .color(BRAND_BLUE),
                 )
-                .reference_message(&msg)
+                .reference_message(msg)
                 .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
 
-            if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            if let Err(err) = msg.channel_id.send_message(ctx, reply).await {
                 warn!("Could not send message; err = {err:?}");
             }
 
@@ -345,7 +343,7 @@ impl Command for Log {
             page_buttons = page_buttons.into_iter().map(|b| b.disabled(true)).collect();
         }
 
-        let response = self.create_chunked_response(chunk);
+        let response = self.create_chunked_response(chunk, show_target);
 
         let reply = CreateMessage::new()
             .add_embed(CreateEmbed::new().description(response).color(BRAND_BLUE))
@@ -353,10 +351,10 @@ impl Command for Log {
                 CreateActionRow::Buttons(page_buttons.clone()),
                 CreateActionRow::Buttons(log_buttons.clone()),
             ])
-            .reference_message(&msg)
+            .reference_message(msg)
             .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
 
-        let mut new_msg = match msg.channel_id.send_message(&ctx, reply.clone()).await {
+        let mut new_msg = match msg.channel_id.send_message(ctx, reply.clone()).await {
             Ok(m) => m,
             Err(err) => {
                 warn!("Could not send message; err = {err:?}");
@@ -454,7 +452,7 @@ impl Command for Log {
             match interaction.data.custom_id.as_str() {
                 "first" => {
                     page = 0;
-                    let response = self.create_chunked_response(chunks.first().unwrap());
+                    let response = self.create_chunked_response(chunks.first().unwrap(), show_target);
                     if interaction
                         .create_response(
                             &ctx,
@@ -481,7 +479,7 @@ impl Command for Log {
 
                 "prev" => {
                     page -= 1;
-                    let response = self.create_chunked_response(chunks.get(page).unwrap());
+                    let response = self.create_chunked_response(chunks.get(page).unwrap(), show_target);
                     let none_prev = if page == 0 {
                         true
                     } else {
@@ -513,7 +511,7 @@ impl Command for Log {
 
                 "next" => {
                     page += 1;
-                    let response = self.create_chunked_response(chunks.get(page).unwrap());
+                    let response = self.create_chunked_response(chunks.get(page).unwrap(), show_target);
                     let none_next = chunks.get(page + 1).is_none();
                     if interaction
                         .create_response(
@@ -541,7 +539,7 @@ impl Command for Log {
 
                 "last" => {
                     page = chunks.len() - 1;
-                    let response = self.create_chunked_response(chunks.last().unwrap());
+                    let response = self.create_chunked_response(chunks.last().unwrap(), show_target);
                     if interaction
                         .create_response(
                             &ctx,
@@ -576,7 +574,7 @@ impl Command for Log {
                         .id
                         .to_string();
 
-                    let response = self
+                    let (response, files) = self
                         .get_one_response(interaction.guild_id.unwrap().get() as i64, id)
                         .await?;
 
@@ -584,9 +582,11 @@ impl Command for Log {
                         .create_response(
                             &ctx,
                             CreateInteractionResponse::Message(
-                                CreateInteractionResponseMessage::new().add_embed(
-                                    CreateEmbed::new().description(response).color(BRAND_BLUE),
-                                ),
+                                CreateInteractionResponseMessage::new()
+                                    .add_embed(
+                                        CreateEmbed::new().description(response).color(BRAND_BLUE),
+                                    )
+                                    .add_files(files),
                             ),
                         )
                         .await
@@ -601,6 +601,167 @@ impl Command for Log {
         }
     }
 
+    fn create_chunked_response(&self, chunk: &[LogRecord], show_target: bool) -> String {
+        let mut response = String::new();
+
+        chunk.iter().for_each(|data| {
+            let mut record = data.clone();
+
+            if record.reason.len() > 100 {
+                record.reason.truncate(100);
+                record.reason.push_str("...");
+            }
+
+            let reason =
+                if record.reason.chars().all(char::is_whitespace) || record.reason.is_empty() {
+                    String::new()
+                } else {
+                    format!("```\n{}\n```\n", record.reason)
+                };
+
+            let target = if show_target {
+                format!(" | Target: <@{}>", record.user_id)
+            } else {
+                String::new()
+            };
+
+            let update_string = if let Some(t) = record.updated_at {
+                format!(" | Updated <t:{0}:d> <t:{0}:T>", t.and_utc().timestamp())
+            } else {
+                format!(
+                    " | At <t:{0}:d> <t:{0}:T>",
+                    record.created_at.and_utc().timestamp()
+                )
+            };
+
+            if let Some(expiry) = record.expires_at {
+                let now = Utc::now().naive_utc();
+                let expire_tag = if expiry < now { "Expired" } else { "Expires" };
+
+                response.push_str(
+                    format!(
+                        "**{0}**\n-# Mod: <@{1}>{7}{6} | {2}: <t:{3}:d> <t:{3}:T>\n`{4}`\n{5}\n",
+                        record.r#type.to_string().to_uppercase(),
+                        record.moderator_id,
+                        expire_tag,
+                        expiry.and_utc().timestamp(),
+                        record.id,
+                        reason,
+                        update_string,
+                        target
+                    )
+                    .as_str(),
+                );
+            } else {
+                response.push_str(
+                    format!(
+                        "**{0}**\n-# Mod: <@{1}>{5}{4}\n`{2}`\n```\n{3}\n```\n",
+                        record.r#type.to_string().to_uppercase(),
+                        record.moderator_id,
+                        record.id,
+                        record.reason,
+                        update_string,
+                        target
+                    )
+                    .as_str(),
+                );
+            }
+        });
+
+        response
+    }
+}
+
+#[async_trait]
+impl Command for Log {
+    fn get_name(&self) -> &'static str {
+        "log"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Shows actions taken on a member"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Shows the moderation actions taken on a member. This includes warns, bans, kicks, etc."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![CommandSyntax::Or(
+            Box::new(CommandSyntax::User("user", true)),
+            Box::new(CommandSyntax::String("id", false)),
+        )]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Moderation
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        args: Vec<Token>,
+        _params: HashMap<&str, (bool, CommandArgument)>,
+    ) -> Result<(), CommandError> {
+        let mut args_iter = args.clone().into_iter().peekable();
+        let Ok(token) = Transformers::user(&ctx, &msg, &mut args_iter).await else {
+            match Transformers::string(&ctx, &msg, &mut args.into_iter().peekable()).await {
+                Ok(log) => {
+                    let Some(CommandArgument::String(id)) = log.contents else {
+                        unreachable!()
+                    };
+                    return self.run_one(ctx, msg, id).await;
+                }
+                Err(_) => {
+                    return Err(CommandError::arg_not_found(
+                        "user or id",
+                        Some("User || String"),
+                    ));
+                }
+            }
+        };
+
+        let Token {
+            contents: Some(CommandArgument::User(user)),
+            ..
+        } = token
+        else {
+            return Err(CommandError::arg_not_found(
+                "user or id",
+                Some("User || String"),
+            ));
+        };
+
+        let res = query_as!(
+            LogRecord,
+            r#"
+                SELECT id, type as "type!: ActionType", user_id, moderator_id, created_at, updated_at, expires_at, reason FROM actions WHERE user_id = $1 AND guild_id = $2;
+            "#,
+            user.id.get() as i64,
+            msg.guild_id.map(|g| g.get()).unwrap_or(0) as i64
+        )
+        .fetch_all(&*SQL).await;
+
+        let data = match res {
+            Ok(d) => d,
+            Err(err) => {
+                warn!("Couldn't fetch log data; err = {err:?}");
+                return Err(CommandError {
+                    title: String::from("Unable to query the database"),
+                    hint: Some(String::from("try again later")),
+                    arg: None,
+                });
+            }
+        };
+
+        self.send_paginated(&ctx, &msg, data, false).await
+    }
+
     fn get_transformers(&self) -> Vec<TransformerFnArc> {
         vec![]
     }
@@ -618,3 +779,62 @@ impl Command for Log {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        SQL,
+        testing::{self, TestBot, description},
+    };
+
+    #[test]
+    fn shows_a_single_entry_with_notes() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            bot.command(
+                bot.moderator_id,
+                &format!("!ban {} 1d spamming", bot.member_id),
+            )
+            .await;
+
+            let id: String =
+                sqlx::query_scalar("SELECT id FROM actions WHERE guild_id = $1 AND user_id = $2")
+                    .bind(bot.guild_id as i64)
+                    .bind(bot.member_id as i64)
+                    .fetch_one(&*SQL)
+                    .await
+                    .unwrap();
+
+            bot.command(bot.moderator_id, &format!("!note {id} appealed twice"))
+                .await;
+            bot.command(bot.moderator_id, &format!("!log {id}")).await;
+
+            let replies = bot.replies();
+            let log = description(replies.last().unwrap());
+            assert!(log.starts_with("**BAN**"));
+            assert!(log.contains(&format!("Mod: <@{}>", bot.moderator_id)));
+            assert!(log.contains("spamming"));
+            assert!(log.contains("**NOTES**"));
+            assert!(log.contains("appealed twice"));
+        });
+    }
+
+    #[test]
+    fn reports_unknown_entries() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            bot.command(bot.moderator_id, "!log nothere").await;
+            assert!(description(&bot.replies()[0]).contains("Log not found"));
+
+            bot.command(bot.moderator_id, &format!("!log {}", bot.member_id))
+                .await;
+            assert!(description(&bot.replies()[1]).contains("No log entries found."));
+        });
+    }
+}
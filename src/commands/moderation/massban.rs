@@ -0,0 +1,199 @@
+use std::sync::Arc;
+
+use chrono::{Duration, Utc};
+use ouroboros_macros::command;
+use serenity::{
+    all::{Context, Message, Permissions},
+    async_trait,
+};
+
+use crate::{
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc, ban_user,
+    },
+    event_handler::CommandError,
+    lexer::Token,
+    transformers::Transformers,
+    utils::{
+        can_target, confirm_mass_action, describe_targets, finish_mass_action, humanize_expiry,
+        run_mass_action, tinyid,
+    },
+};
+
+pub struct Massban;
+
+impl Massban {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for Massban {
+    fn get_name(&self) -> &'static str {
+        "massban"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Bans many users at once, i.e. during a raid"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Bans many users at once after a confirmation. \
+        Accepts IDs and mentions separated by spaces, commas or new lines, so a pasted list works too. \
+        Every user gets their own log entry, all of them share a batch ID and are summarized in a single log message. \
+        Targets are not DMed. Clears one day of messages by default."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::Users("users", true),
+            CommandSyntax::Duration("duration", false),
+            CommandSyntax::Reason("reason"),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Moderation
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![&CommandParameter {
+            name: "clear",
+            short: "c",
+            transformer: &Transformers::i32,
+            desc: "Amount of messages to clear (in days 0-7)",
+        }]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::many_users] users: ManyUsers,
+        #[transformers::maybe_duration] duration: Option<Duration>,
+        #[transformers::consume] reason: Option<String>,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        let Ok(author_member) = msg.member(&ctx).await else {
+            return Err(CommandError {
+                title: String::from("Unexpected error has occured."),
+                hint: Some(String::from("could not get author member")),
+                arg: None,
+            });
+        };
+
+        let duration = duration.unwrap_or(Duration::zero());
+        let mut reason = reason
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .unwrap_or(String::from("No reason provided"));
+
+        if reason.len() > 500 {
+            reason.truncate(500);
+            reason.push_str("...");
+        }
+
+        let days = match params.get("clear") {
+            Some((false, _)) => 0,
+            Some((true, CommandArgument::i32(days))) => (*days).clamp(0, 7) as u8,
+            _ => 1,
+        };
+
+        let time_string = if !duration.is_zero() {
+            format!("for {}", humanize_expiry(&duration))
+        } else {
+            String::from("permanent")
+        };
+
+        let expires_at = if duration.is_zero() {
+            None
+        } else {
+            Some((Utc::now() + duration).naive_utc())
+        };
+
+        let details = format!(" | Duration: {time_string} | Cleared {days} days of messages");
+
+        let Some(prompt) = confirm_mass_action(
+            &ctx,
+            &msg,
+            format!(
+                "**CONFIRM MASS BAN**\n-# Targets: {}{details}\n{}\n```\n{reason}\n```",
+                users.len(),
+                describe_targets(&users)
+            ),
+        )
+        .await?
+        else {
+            return Ok(());
+        };
+
+        let batch_id = tinyid().await;
+
+        let results = run_mass_action(users, |user| {
+            let (ctx, author_member, reason, batch_id) =
+                (&ctx, &author_member, &reason, batch_id.as_str());
+
+            async move {
+                if let Ok(target) = guild_id.member(ctx, user.id).await
+                    && !can_target(
+                        ctx,
+                        author_member,
+                        &target,
+                        Permissions::BAN_MEMBERS,
+                        self,
+                        msg.channel_id,
+                    )
+                    .await
+                {
+                    return Err(CommandError {
+                        title: String::from("You may not target this member."),
+                        hint: None,
+                        arg: None,
+                    });
+                }
+
+                let db_id = tinyid().await;
+
+                ban_user(
+                    ctx,
+                    guild_id,
+                    &user,
+                    &db_id,
+                    author_member.user.id,
+                    reason,
+                    expires_at,
+                    days,
+                    None,
+                    Some(batch_id),
+                    None,
+                )
+                .await
+                .map(|_| db_id)
+            }
+        })
+        .await;
+
+        finish_mass_action(
+            &ctx, &msg, prompt, &batch_id, "BAN", &details, &reason, results,
+        )
+        .await;
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::BAN_MEMBERS],
+            one_of: vec![],
+            bot: [
+                CommandPermissions::baseline().as_slice(),
+                CommandPermissions::moderation().as_slice(),
+            ]
+            .concat(),
+        }
+    }
+}
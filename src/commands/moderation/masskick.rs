@@ -0,0 +1,170 @@
+use std::sync::Arc;
+
+use ouroboros_macros::command;
+use serenity::{
+    all::{Context, Message, Permissions},
+    async_trait,
+};
+
+use crate::{
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc, kick_member,
+    },
+    event_handler::CommandError,
+    lexer::Token,
+    transformers::Transformers,
+    utils::{
+        can_target, confirm_mass_action, describe_targets, finish_mass_action, run_mass_action,
+        tinyid,
+    },
+};
+
+pub struct Masskick;
+
+impl Masskick {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for Masskick {
+    fn get_name(&self) -> &'static str {
+        "masskick"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Kicks many members at once, i.e. during a raid"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Kicks many members at once after a confirmation. \
+        Accepts IDs and mentions separated by spaces, commas or new lines, so a pasted list works too. \
+        Every member gets their own log entry, all of them share a batch ID and are summarized in a single log message. \
+        Targets are not DMed."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::Users("members", true),
+            CommandSyntax::Reason("reason"),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Moderation
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::many_users] users: ManyUsers,
+        #[transformers::consume] reason: Option<String>,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        let Ok(author_member) = msg.member(&ctx).await else {
+            return Err(CommandError {
+                title: String::from("Unexpected error has occured."),
+                hint: Some(String::from("could not get author member")),
+                arg: None,
+            });
+        };
+
+        let mut reason = reason
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .unwrap_or(String::from("No reason provided"));
+
+        if reason.len() > 500 {
+            reason.truncate(500);
+            reason.push_str("...");
+        }
+
+        let Some(prompt) = confirm_mass_action(
+            &ctx,
+            &msg,
+            format!(
+                "**CONFIRM MASS KICK**\n-# Targets: {}\n{}\n```\n{reason}\n```",
+                users.len(),
+                describe_targets(&users)
+            ),
+        )
+        .await?
+        else {
+            return Ok(());
+        };
+
+        let batch_id = tinyid().await;
+
+        let results = run_mass_action(users, |user| {
+            let (ctx, author_member, reason, batch_id) =
+                (&ctx, &author_member, &reason, batch_id.as_str());
+
+            async move {
+                let Ok(target) = guild_id.member(ctx, user.id).await else {
+                    return Err(CommandError {
+                        title: String::from("Not a member of this server"),
+                        hint: None,
+                        arg: None,
+                    });
+                };
+
+                if !can_target(
+                    ctx,
+                    author_member,
+                    &target,
+                    Permissions::KICK_MEMBERS,
+                    self,
+                    msg.channel_id,
+                )
+                .await
+                {
+                    return Err(CommandError {
+                        title: String::from("You may not target this member."),
+                        hint: None,
+                        arg: None,
+                    });
+                }
+
+                let db_id = tinyid().await;
+
+                kick_member(
+                    ctx,
+                    &target,
+                    &db_id,
+                    author_member.user.id,
+                    reason,
+                    Some(batch_id),
+                    None,
+                )
+                .await
+                .map(|_| db_id)
+            }
+        })
+        .await;
+
+        finish_mass_action(&ctx, &msg, prompt, &batch_id, "KICK", "", &reason, results).await;
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::KICK_MEMBERS],
+            one_of: vec![],
+            bot: [
+                CommandPermissions::baseline().as_slice(),
+                CommandPermissions::moderation().as_slice(),
+            ]
+            .concat(),
+        }
+    }
+}
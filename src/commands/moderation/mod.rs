@@ -1,17 +1,35 @@
 mod ban;
-pub use ban::Ban;
+pub use ban::{Ban, ban_user};
 
 mod duration;
 pub use duration::Duration;
 
 mod kick;
-pub use kick::Kick;
+pub use kick::{Kick, kick_member};
+
+mod lock;
+pub use lock::{Lock, lock_channel};
+
+mod lockdown;
+pub use lockdown::Lockdown;
 
 mod log;
-pub use log::Log;
+pub use log::{Log, LogRecord};
+
+mod massban;
+pub use massban::Massban;
+
+mod masskick;
+pub use masskick::Masskick;
+
+mod massmute;
+pub use massmute::Massmute;
 
 mod mute;
-pub use mute::Mute;
+pub use mute::{Mute, mute_member};
+
+mod note;
+pub use note::Note;
 
 mod purge;
 pub use purge::Purge;
@@ -19,14 +37,32 @@ pub use purge::Purge;
 mod reason;
 pub use reason::Reason;
 
+mod restrict;
+pub use restrict::{Restrict, restriction_role};
+
+mod search;
+pub use search::Search;
+
+mod slowmode;
+pub use slowmode::{Slowmode, revert_slowmode};
+
 mod softban;
 pub use softban::Softban;
 
+mod transcript;
+pub use transcript::Transcript;
+
 mod unban;
-pub use unban::Unban;
+pub use unban::{Unban, unban_user};
+
+mod unlock;
+pub use unlock::{Unlock, unlock_channel};
 
 mod unmute;
-pub use unmute::Unmute;
+pub use unmute::{Unmute, unmute_member};
+
+mod unrestrict;
+pub use unrestrict::Unrestrict;
 
 mod warn;
 pub use warn::Warn;
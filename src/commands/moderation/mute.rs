@@ -1,9 +1,10 @@
 use std::sync::Arc;
 
-use chrono::{Duration, Utc};
+use chrono::{DateTime, Duration, Utc};
 use serenity::{
     all::{
-        Context, CreateEmbed, CreateMessage, EditMember, GuildId, Mentionable, Message, Permissions,
+        Context, CreateEmbed, CreateMessage, EditMember, GuildId, Member, Mentionable, Message,
+        Permissions, UserId,
     },
     async_trait,
 };
@@ -17,10 +18,14 @@ use crate::{
         CommandSyntax, TransformerFnArc,
     },
     constants::BRAND_BLUE,
+    database::ActionType,
     event_handler::CommandError,
     lexer::{InferType, Token},
     transformers::Transformers,
-    utils::{CommandMessageResponse, LogType, can_target, guild_log, tinyid},
+    utils::{
+        CommandMessageResponse, LogType, TemplateValues, appeal_components, can_target,
+        get_guild_option, guild_log, guild_templates, humanize_expiry, tinyid,
+    },
 };
 use ouroboros_macros::command;
 
@@ -85,7 +90,15 @@ impl Command for Mute {
             });
         };
 
-        let res = can_target(&ctx, &author_member, &member, Permissions::MODERATE_MEMBERS).await;
+        let res = can_target(
+            &ctx,
+            &author_member,
+            &member,
+            Permissions::MODERATE_MEMBERS,
+            self,
+            msg.channel_id,
+        )
+        .await;
 
         if !res {
             return Err(CommandError {
@@ -118,33 +131,7 @@ impl Command for Mute {
         let db_id = tinyid().await;
 
         let time_string = if !duration.is_zero() {
-            let (time, mut unit) = match () {
-                _ if (duration.num_days() as f64 / 365.0).fract() == 0.0
-                    && duration.num_days() >= 365 =>
-                {
-                    (duration.num_days() / 365, String::from("year"))
-                }
-                _ if (duration.num_days() as f64 / 30.0).fract() == 0.0
-                    && duration.num_days() >= 30 =>
-                {
-                    (duration.num_days() / 30, String::from("month"))
-                }
-                _ if duration.num_days() != 0 => (duration.num_days(), String::from("day")),
-                _ if duration.num_hours() != 0 => (duration.num_hours(), String::from("hour")),
-                _ if duration.num_minutes() != 0 => {
-                    (duration.num_minutes(), String::from("minute"))
-                }
-                _ if duration.num_seconds() != 0 => {
-                    (duration.num_seconds(), String::from("second"))
-                }
-                _ => (0, String::new()),
-            };
-
-            if time > 1 {
-                unit += "s";
-            }
-
-            format!("{time} {unit}")
+            humanize_expiry(&duration)
         } else {
             String::from("permanent")
         };
@@ -155,60 +142,7 @@ impl Command for Mute {
             Some(Utc::now() + duration)
         };
 
-        let res = query!(
-            "INSERT INTO actions (id, type, guild_id, user_id, moderator_id, reason, expires_at, last_reapplied_at) VALUES ($1, 'mute', $2, $3, $4, $5, $6, NOW())",
-            db_id,
-            msg.guild_id.map(|g| g.get() as i64).unwrap_or(0),
-            member.user.id.get() as i64,
-            msg.author.id.get() as i64,
-            reason.as_str(),
-            duration.map(|d| d.naive_utc()),
-        ).execute(&*SQL).await;
-
-        if let Err(err) = res {
-            warn!("Got error while timing out; err = {err:?}");
-            return Err(CommandError {
-                title: String::from("Could not time member out"),
-                hint: Some(String::from("please try again later")),
-                arg: None,
-            });
-        }
-
-        let audit_reason = format!(
-            "Ouroboros Managed Mute: log id `{db_id}`. Please use Ouroboros to unmute to avoid accidental re-application!"
-        );
-
-        let edit = if let Some(duration) = duration {
-            EditMember::new()
-                .audit_log_reason(&reason)
-                .disable_communication_until_datetime(duration.into())
-        } else {
-            EditMember::new()
-                .audit_log_reason(audit_reason.as_str())
-                .disable_communication_until_datetime((Utc::now() + Duration::days(27)).into())
-        };
-
-        if let Err(err) = member.guild_id.edit_member(&ctx, &member, edit).await {
-            warn!("Got error while timinng out; err = {err:?}");
-
-            if query!("DELETE FROM actions WHERE id = $1", db_id)
-                .execute(&*SQL)
-                .await
-                .is_err()
-            {
-                error!(
-                    "Got an error while timing out and an error with the database! Stray timeout entry in DB & manual action required; id = {db_id}; err = {err:?}"
-                );
-            }
-
-            return Err(CommandError {
-                title: String::from("Could not time member out"),
-                hint: Some(String::from(
-                    "check if the bot has the timeout members permission or try again later",
-                )),
-                arg: None,
-            });
-        }
+        mute_member(&ctx, &member, &db_id, msg.author.id, &reason, duration, None, None).await?;
 
         if inferred && let Some(reply) = msg.referenced_message.clone() {
             let _ = reply.delete(&ctx).await;
@@ -226,24 +160,29 @@ impl Command for Mute {
             }
         };
 
-        let static_response_parts = (
-            format!(
-                "**{} TIMEOUT**\n-# Log ID: `{db_id}` | Duration: {time_string}",
-                member.mention()
-            ),
-            format!("\n```\n{reason}\n```"),
-        );
+        let (dm_template, response_template) =
+            guild_templates(member.guild_id, &ActionType::Mute).await;
+        let values = TemplateValues {
+            server: guild_name,
+            duration: time_string.clone(),
+            reason: reason.clone(),
+            log_id: db_id.clone(),
+            moderator: msg.author.mention().to_string(),
+            user: member.mention().to_string(),
+            ..Default::default()
+        };
+
+        let dm_members = get_guild_option(member.guild_id, "moderation.dm_members")
+            .await
+            .as_bool()
+            .unwrap_or(true);
 
         let mut cmd_response = CommandMessageResponse::new(member.user.id)
-            .dm_content(format!(
-                "**TIMEOUT**\n-# Server: {} | Duration: {}\n```\n{}\n```",
-                guild_name, time_string, reason
-            ))
-            .server_content(Box::new(move |a| {
-                format!("{}{a}{}", static_response_parts.0, static_response_parts.1)
-            }))
+            .dm_content(values.render(&dm_template))
+            .server_content(values.render_response(&response_template, String::new()))
+            .dm_components(appeal_components(member.guild_id, &db_id).await)
             .automatically_delete(inferred)
-            .mark_silent(params.contains_key("silent"));
+            .mark_silent(params.contains_key("silent") || !dm_members);
 
         cmd_response.send_dm(&ctx).await;
         cmd_response.send_response(&ctx, &msg).await;
@@ -277,3 +216,171 @@ impl Command for Mute {
         }
     }
 }
+
+/// Times a member out and stores the mute, rolling the entry back if the timeout could not be applied.
+/// Mutes without an expiry (or longer than 28 days) are kept alive by the expiring actions task.
+#[allow(clippy::too_many_arguments)]
+pub async fn mute_member(
+    ctx: &Context,
+    member: &Member,
+    db_id: &str,
+    moderator_id: UserId,
+    reason: &str,
+    expires_at: Option<DateTime<Utc>>,
+    triggered_by: Option<&str>,
+    batch_id: Option<&str>,
+) -> Result<(), CommandError> {
+    let res = query!(
+        "INSERT INTO actions (id, type, guild_id, user_id, moderator_id, reason, expires_at, last_reapplied_at, triggered_by, batch_id) VALUES ($1, 'mute', $2, $3, $4, $5, $6, NOW(), $7, $8)",
+        db_id,
+        member.guild_id.get() as i64,
+        member.user.id.get() as i64,
+        moderator_id.get() as i64,
+        reason,
+        expires_at.map(|d| d.naive_utc()),
+        triggered_by,
+        batch_id,
+    ).execute(&*SQL).await;
+
+    if let Err(err) = res {
+        warn!("Got error while timing out; err = {err:?}");
+        return Err(CommandError {
+            title: String::from("Could not time member out"),
+            hint: Some(String::from("please try again later")),
+            arg: None,
+        });
+    }
+
+    let audit_reason = format!(
+        "Ouroboros Managed Mute: log id `{db_id}`. Please use Ouroboros to unmute to avoid accidental re-application!"
+    );
+
+    let edit = if let Some(expires_at) = expires_at {
+        EditMember::new()
+            .audit_log_reason(reason)
+            .disable_communication_until_datetime(expires_at.into())
+    } else {
+        EditMember::new()
+            .audit_log_reason(audit_reason.as_str())
+            .disable_communication_until_datetime((Utc::now() + Duration::days(27)).into())
+    };
+
+    if let Err(err) = member.guild_id.edit_member(ctx, member, edit).await {
+        warn!("Got error while timinng out; err = {err:?}");
+
+        if query!("DELETE FROM actions WHERE id = $1", db_id)
+            .execute(&*SQL)
+            .await
+            .is_err()
+        {
+            error!(
+                "Got an error while timing out and an error with the database! Stray timeout entry in DB & manual action required; id = {db_id}; err = {err:?}"
+            );
+        }
+
+        return Err(CommandError {
+            title: String::from("Could not time member out"),
+            hint: Some(String::from(
+                "check if the bot has the timeout members permission or try again later",
+            )),
+            arg: None,
+        });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Method;
+    use chrono::NaiveDateTime;
+
+    use crate::{
+        SQL,
+        testing::{self, TestBot, description},
+    };
+
+    #[test]
+    fn times_member_out_and_logs() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            bot.command(
+                bot.moderator_id,
+                &format!("!mute {} 2h being rude", bot.member_id),
+            )
+            .await;
+
+            let edits = bot.discord.requests(
+                Method::PATCH,
+                &format!("/guilds/{}/members/{}", bot.guild_id, bot.member_id),
+            );
+            assert_eq!(edits.len(), 1);
+            assert!(edits[0].payload()["communication_disabled_until"].is_string());
+
+            let (id, reason, expires_at): (String, String, Option<NaiveDateTime>) =
+                sqlx::query_as(
+                    "SELECT id, reason, expires_at FROM actions WHERE guild_id = $1 AND user_id = $2 AND type = 'mute'",
+                )
+                .bind(bot.guild_id as i64)
+                .bind(bot.member_id as i64)
+                .fetch_one(&*SQL)
+                .await
+                .unwrap();
+
+            assert_eq!(reason, "being rude");
+            assert!(expires_at.is_some());
+
+            let dms = bot.discord.sent_dms(bot.member_id);
+            assert_eq!(dms.len(), 1);
+            assert!(description(&dms[0]).contains("**TIMEOUT**"));
+            assert!(!description(&bot.replies()[0]).contains("DM failed"));
+
+            let logs = bot.logs();
+            assert_eq!(logs.len(), 1);
+            assert!(description(&logs[0]).contains("**MEMBER TIMEOUT**"));
+            assert!(description(&logs[0]).contains(&id));
+        });
+    }
+
+    #[test]
+    fn refuses_to_target_the_owner() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            bot.command(bot.moderator_id, &format!("!mute {} 2h", bot.owner_id))
+                .await;
+
+            let edits = bot.discord.requests(
+                Method::PATCH,
+                &format!("/guilds/{}/members/{}", bot.guild_id, bot.owner_id),
+            );
+            assert!(edits.is_empty());
+
+            let replies = bot.replies();
+            assert_eq!(replies.len(), 1);
+            assert!(description(&replies[0]).contains("You may not target this member."));
+        });
+    }
+
+    #[test]
+    fn reports_closed_dms() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            bot.discord.state().closed_dms.insert(bot.member_id);
+            bot.command(bot.moderator_id, &format!("!mute {} 2h", bot.member_id))
+                .await;
+
+            let replies = bot.replies();
+            assert_eq!(replies.len(), 1);
+            assert!(description(&replies[0]).contains("DM failed"));
+        });
+    }
+}
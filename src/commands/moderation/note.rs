@@ -0,0 +1,612 @@
+use std::sync::Arc;
+
+use ouroboros_macros::command;
+use serenity::{
+    all::{
+        Attachment, ChannelId, Context, CreateAllowedMentions, CreateAttachment, CreateEmbed,
+        CreateMessage, Member, Mentionable, Message, MessageId, PartialGuild, Permissions,
+    },
+    async_trait,
+    utils::parse_message_url,
+};
+use sqlx::query;
+use tracing::warn;
+
+use crate::{
+    MessageCacheContainer, SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::CommandError,
+    lexer::Token,
+    transformers::Transformers,
+    utils::{
+        LogType, MAX_TRANSCRIPT_MESSAGES, Transcript, TranscriptMessage,
+        cache::partials::PartialMessage, consume_pgsql_error, describe_not_attached,
+        fetch_transcript_messages, guild_log, permissions_for_channel, split_upload_budget,
+    },
+};
+
+/// The maximum amount of files stored per note
+const MAX_ATTACHMENTS: usize = 10;
+/// The maximum size of a single stored file (8 MiB)
+const MAX_ATTACHMENT_SIZE: usize = 8 * 1024 * 1024;
+
+/// A file downloaded to be stored alongside a note
+struct Evidence {
+    filename: String,
+    content_type: Option<String>,
+    data: Vec<u8>,
+    /// Where the file was downloaded from, linked when it can't be uploaded with the log
+    url: Option<String>,
+}
+
+pub struct Note;
+
+impl Note {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Downloads the files of a Discord message, skipping any which are too large to store
+    async fn download_attachments(
+        &self,
+        attachments: &[Attachment],
+        files: &mut Vec<Evidence>,
+        skipped: &mut usize,
+    ) {
+        for attachment in attachments {
+            if files.len() >= MAX_ATTACHMENTS || attachment.size as usize > MAX_ATTACHMENT_SIZE {
+                *skipped += 1;
+                continue;
+            }
+
+            match attachment.download().await {
+                Ok(data) => files.push(Evidence {
+                    filename: attachment.filename.clone(),
+                    content_type: attachment.content_type.clone(),
+                    data,
+                    url: Some(attachment.url.clone()),
+                }),
+                Err(err) => {
+                    warn!("Could not download note attachment; err = {err:?}");
+                    *skipped += 1;
+                }
+            }
+        }
+    }
+
+    /// Quotes a message as evidence and downloads its files
+    async fn quote_message(
+        &self,
+        message: PartialMessage,
+        files: &mut Vec<Evidence>,
+        skipped: &mut usize,
+    ) -> String {
+        for attachment in message.attachment_urls.iter() {
+            if files.len() >= MAX_ATTACHMENTS {
+                *skipped += 1;
+                continue;
+            }
+
+            match attachment.download().await {
+                Ok(data) if data.len() <= MAX_ATTACHMENT_SIZE => files.push(Evidence {
+                    filename: attachment.name.clone(),
+                    content_type: None,
+                    data,
+                    url: Some(attachment.url.clone()),
+                }),
+                _ => *skipped += 1,
+            }
+        }
+
+        let content = if message.content.is_empty() {
+            String::from("(no content)")
+        } else {
+            message.content.replace("```", "\\`\\`\\`")
+        };
+
+        format!(
+            "Message `{}` by <@{}> in <#{}>:\n```\n{}\n```",
+            message.id, message.author.id, message.channel_id, content
+        )
+    }
+
+    /// Stores a transcript of the messages before the command, or up to the replied message, as evidence
+    async fn transcript(
+        &self,
+        ctx: &Context,
+        msg: &Message,
+        count: i32,
+        files: &mut Vec<Evidence>,
+        skipped: &mut usize,
+    ) -> Result<String, CommandError> {
+        if !(1..=MAX_TRANSCRIPT_MESSAGES as i32).contains(&count) {
+            return Err(CommandError {
+                title: format!(
+                    "Transcripts can include between 1 and {MAX_TRANSCRIPT_MESSAGES} messages"
+                ),
+                hint: None,
+                arg: None,
+            });
+        }
+
+        let before = msg
+            .referenced_message
+            .as_ref()
+            .map(|r| MessageId::new(r.id.get() + 1))
+            .unwrap_or(msg.id);
+
+        let messages =
+            match fetch_transcript_messages(ctx, msg.channel_id, before, count as usize).await {
+                Ok(m) => m,
+                Err(err) => {
+                    warn!("Got error while fetching messages; err = {err:?}");
+                    return Err(CommandError {
+                        title: String::from("Could not get channel messages"),
+                        hint: Some(String::from(
+                            "make sure the bot can read the message history of this channel",
+                        )),
+                        arg: None,
+                    });
+                }
+            };
+
+        let transcript = Transcript::new(
+            format!(
+                "Transcript of #{}",
+                msg.channel_id.name(ctx).await.unwrap_or_default()
+            ),
+            messages.iter().map(TranscriptMessage::from),
+        );
+        let name = format!("transcript-{}-{}", msg.channel_id.get(), msg.id.get());
+
+        for (data, extension, content_type) in [
+            (transcript.to_html(), "html", "text/html"),
+            (transcript.to_json(), "json", "application/json"),
+        ] {
+            if files.len() >= MAX_ATTACHMENTS || data.len() > MAX_ATTACHMENT_SIZE {
+                *skipped += 1;
+                continue;
+            }
+
+            files.push(Evidence {
+                filename: format!("{name}.{extension}"),
+                content_type: Some(String::from(content_type)),
+                data: data.into_bytes(),
+                url: None,
+            });
+        }
+
+        Ok(format!(
+            "Transcript of {} messages in <#{}>",
+            transcript.messages.len(),
+            msg.channel_id.get()
+        ))
+    }
+
+    /// Checks if the author can read a linked channel, otherwise notes would leak cached and deleted messages of it
+    async fn can_read(
+        &self,
+        ctx: &Context,
+        guild: &PartialGuild,
+        member: &Member,
+        channel_id: ChannelId,
+    ) -> bool {
+        if guild.owner_id == member.user.id {
+            return true;
+        }
+
+        let Ok(Some(mut channel)) = channel_id.to_channel(ctx).await.map(|c| c.guild()) else {
+            return false;
+        };
+
+        // threads use the permissions of the channel they were created in
+        if channel.thread_metadata.is_some()
+            && let Some(parent_id) = channel.parent_id
+        {
+            let Ok(Some(parent)) = parent_id.to_channel(ctx).await.map(|c| c.guild()) else {
+                return false;
+            };
+            channel = parent;
+        }
+
+        let permissions = permissions_for_channel(guild, &channel, member);
+        permissions.contains(Permissions::ADMINISTRATOR)
+            || permissions.contains(Permissions::VIEW_CHANNEL | Permissions::READ_MESSAGE_HISTORY)
+    }
+
+    /// Finds a message using the message cache first so deleted messages can still be used, falling back to the API
+    async fn find_message(
+        &self,
+        ctx: &Context,
+        channel_id: u64,
+        message_id: u64,
+    ) -> Option<PartialMessage> {
+        let cached = {
+            let data = ctx.data.read().await;
+            let cache = data.get::<MessageCacheContainer>()?;
+            cache.get(channel_id, message_id)
+        };
+
+        if cached.is_some() {
+            return cached;
+        }
+
+        ctx.http
+            .get_message(channel_id.into(), message_id.into())
+            .await
+            .ok()
+            .map(PartialMessage::from)
+    }
+}
+
+#[async_trait]
+impl Command for Note {
+    fn get_name(&self) -> &'static str {
+        "note"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Adds a note or evidence to a moderation action"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Adds a note or evidence to a moderation action. Run the log command for the id. \
+        Files attached to the command are stored with the note. \
+        Replying to a message or linking messages quotes them (including their files), \
+        deleted messages are taken from the message cache. \
+        The transcript parameter stores an HTML and JSON transcript of the given amount of messages."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::String("id", true),
+            CommandSyntax::Consume("note"),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Moderation
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![&CommandParameter {
+            name: "transcript",
+            short: "t",
+            transformer: &Transformers::i32,
+            desc: "Stores a transcript of the last messages of the channel (up to the replied message) with the note",
+        }]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::some_string] id: String,
+        #[transformers::consume] note: Option<String>,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        let res = query!(
+            "SELECT id FROM actions WHERE guild_id = $1 AND id = $2",
+            guild_id.get() as i64,
+            id
+        )
+        .fetch_optional(&*SQL)
+        .await;
+
+        match res {
+            Ok(Some(_)) => {}
+            Ok(None) => {
+                return Err(CommandError {
+                    title: String::from("Log not found"),
+                    hint: Some(String::from("check if you have copied the ID correctly!")),
+                    arg: Some(args[0].clone()),
+                });
+            }
+            Err(err) => {
+                warn!("Couldn't fetch log data; err = {err:?}");
+                return Err(CommandError {
+                    title: String::from("Unable to query the database"),
+                    hint: Some(String::from("try again later")),
+                    arg: None,
+                });
+            }
+        }
+
+        let note = note.map(|n| n.trim().to_string()).unwrap_or_default();
+        let mut files: Vec<Evidence> = vec![];
+        let mut skipped = 0;
+        let mut quotes: Vec<String> = vec![];
+
+        self.download_attachments(&msg.attachments, &mut files, &mut skipped)
+            .await;
+
+        if let Some(reply) = msg.referenced_message.clone() {
+            let message = self
+                .find_message(&ctx, reply.channel_id.get(), reply.id.get())
+                .await
+                .unwrap_or(PartialMessage::from(*reply));
+            quotes.push(self.quote_message(message, &mut files, &mut skipped).await);
+        }
+
+        let links = note
+            .split_whitespace()
+            .filter_map(|word| {
+                parse_message_url(word.trim_start_matches('<').trim_end_matches('>'))
+            })
+            .filter(|(link_guild, _, _)| *link_guild == guild_id)
+            .collect::<Vec<_>>();
+
+        if !links.is_empty() {
+            let (Ok(guild), Ok(member)) = (
+                guild_id.to_partial_guild(&ctx).await,
+                msg.member(&ctx).await,
+            ) else {
+                return Err(CommandError {
+                    title: String::from("Unexpected error has occured."),
+                    hint: Some(String::from("could not get server or author member")),
+                    arg: None,
+                });
+            };
+
+            for (_, channel_id, message_id) in links {
+                if !self.can_read(&ctx, &guild, &member, channel_id).await {
+                    return Err(CommandError {
+                        title: String::from("You can not read the messages of a linked channel"),
+                        hint: Some(format!("remove the link to the message in <#{channel_id}>")),
+                        arg: None,
+                    });
+                }
+
+                if let Some(message) = self
+                    .find_message(&ctx, channel_id.get(), message_id.get())
+                    .await
+                {
+                    quotes.push(self.quote_message(message, &mut files, &mut skipped).await);
+                }
+            }
+        }
+
+        if let Some((_, CommandArgument::i32(count))) = params.get("transcript") {
+            quotes.push(
+                self.transcript(&ctx, &msg, *count, &mut files, &mut skipped)
+                    .await?,
+            );
+        }
+
+        if note.is_empty() && quotes.is_empty() && files.is_empty() {
+            return Err(CommandError::arg_not_found("String", Some("note")));
+        }
+
+        let mut content = note.replace("```", "\\`\\`\\`");
+
+        for quote in quotes {
+            if !content.is_empty() {
+                content.push('\n');
+            }
+
+            content.push_str(&quote);
+        }
+
+        if content.chars().count() > 1500 {
+            content = content.chars().take(1500).collect();
+            content.push_str("...");
+        }
+
+        let mut tx = match SQL.begin().await {
+            Ok(t) => t,
+            Err(err) => {
+                consume_pgsql_error(String::from("NOTE BEGIN"), err);
+                return Err(CommandError {
+                    title: String::from("Could not add note"),
+                    hint: Some(String::from("please try again later")),
+                    arg: None,
+                });
+            }
+        };
+
+        let note_id = match query!(
+            "INSERT INTO action_notes (action_id, guild_id, moderator_id, content) VALUES ($1, $2, $3, $4) RETURNING id",
+            id,
+            guild_id.get() as i64,
+            msg.author.id.get() as i64,
+            content
+        )
+        .fetch_one(&mut *tx)
+        .await
+        {
+            Ok(r) => r.id,
+            Err(err) => {
+                consume_pgsql_error(String::from("NOTE INSERT"), err);
+                return Err(CommandError {
+                    title: String::from("Could not add note"),
+                    hint: Some(String::from("please try again later")),
+                    arg: None,
+                });
+            }
+        };
+
+        for file in files.iter() {
+            if let Err(err) = query!(
+                "INSERT INTO action_note_attachments (note_id, filename, content_type, data, url) VALUES ($1, $2, $3, $4, $5)",
+                note_id,
+                file.filename,
+                file.content_type,
+                file.data,
+                file.url
+            )
+            .execute(&mut *tx)
+            .await
+            {
+                consume_pgsql_error(String::from("NOTE ATTACHMENT INSERT"), err);
+                return Err(CommandError {
+                    title: String::from("Could not store note attachments"),
+                    hint: Some(String::from("please try again later")),
+                    arg: None,
+                });
+            }
+        }
+
+        if let Err(err) = tx.commit().await {
+            consume_pgsql_error(String::from("NOTE COMMIT"), err);
+            return Err(CommandError {
+                title: String::from("Could not add note"),
+                hint: Some(String::from("please try again later")),
+                arg: None,
+            });
+        }
+
+        let file_info = match (files.len(), skipped) {
+            (0, 0) => String::new(),
+            (n, 0) => format!(" | {n} files"),
+            (n, s) => format!(" | {n} files ({s} skipped)"),
+        };
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(format!("**NOTE ADDED TO `{id}`**{file_info}\n{content}"))
+                    .color(BRAND_BLUE),
+            )
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            warn!("Could not send message; err = {err:?}");
+        }
+
+        // all files are stored, but only the ones within the upload limit can be sent with the log
+        let (attached, over) = split_upload_budget(files, |f| f.data.len());
+        let not_attached =
+            describe_not_attached(over.iter().map(|f| (f.filename.as_str(), f.url.as_deref())));
+        let not_attached = if not_attached.is_empty() {
+            not_attached
+        } else {
+            format!("\n{not_attached}")
+        };
+
+        guild_log(
+            &ctx,
+            LogType::ActionUpdate,
+            guild_id,
+            CreateMessage::new()
+                .add_embed(
+                    CreateEmbed::new()
+                        .description(format!(
+                            "**NOTE ADDED**\n-# Log ID: `{id}` | Actor: {} `{}`{file_info}\n{content}{not_attached}",
+                            msg.author.mention(),
+                            msg.author.id.get(),
+                        ))
+                        .color(BRAND_BLUE),
+                )
+                .add_files(
+                    attached
+                        .into_iter()
+                        .map(|f| CreateAttachment::bytes(f.data, f.filename)),
+                ),
+        )
+        .await;
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![],
+            one_of: vec![
+                Permissions::MANAGE_NICKNAMES,
+                Permissions::KICK_MEMBERS,
+                Permissions::MODERATE_MEMBERS,
+                Permissions::BAN_MEMBERS,
+            ],
+            bot: CommandPermissions::baseline(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::json::json;
+
+    use crate::{
+        SQL,
+        testing::{
+            self, TestBot, description,
+            fake_discord::{channel_json, message_json},
+            snowflake,
+        },
+    };
+
+    #[test]
+    fn quotes_only_readable_channels() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            let private_id = snowflake();
+            let message_id = snowflake();
+            {
+                let mut state = bot.discord.state();
+                let mut channel = channel_json(private_id, bot.guild_id, "staff");
+                channel["permission_overwrites"] = json!([{
+                    "id": bot.guild_id.to_string(),
+                    "type": 0,
+                    "allow": "0",
+                    "deny": (1u64 << 10).to_string(), // VIEW_CHANNEL
+                }]);
+                state.channels.insert(private_id, channel);
+
+                let author = state.users[&bot.owner_id].clone();
+                state
+                    .messages
+                    .entry(private_id)
+                    .or_default()
+                    .push(message_json(
+                        message_id,
+                        private_id,
+                        Some(bot.guild_id),
+                        author,
+                        "staff only",
+                    ));
+            }
+
+            bot.command(bot.moderator_id, &format!("!ban {} spam", bot.member_id))
+                .await;
+            let id: String =
+                sqlx::query_scalar("SELECT id FROM actions WHERE guild_id = $1 AND user_id = $2")
+                    .bind(bot.guild_id as i64)
+                    .bind(bot.member_id as i64)
+                    .fetch_one(&*SQL)
+                    .await
+                    .unwrap();
+
+            let link = format!(
+                "https://discord.com/channels/{}/{private_id}/{message_id}",
+                bot.guild_id
+            );
+            bot.command(bot.moderator_id, &format!("!note {id} see {link}"))
+                .await;
+            bot.command(bot.owner_id, &format!("!note {id} see {link}"))
+                .await;
+
+            let replies = bot.replies();
+            assert!(description(&replies[1]).contains("You can not read"));
+            assert!(description(&replies[2]).starts_with("**NOTE ADDED"));
+            assert!(description(&replies[2]).contains("staff only"));
+
+            let notes: i64 =
+                sqlx::query_scalar("SELECT COUNT(*) FROM action_notes WHERE action_id = $1")
+                    .bind(&id)
+                    .fetch_one(&*SQL)
+                    .await
+                    .unwrap();
+            assert_eq!(notes, 1);
+        });
+    }
+}
@@ -1,13 +1,18 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{collections::HashSet, sync::Arc};
 
 use chrono::{Duration, Utc};
+use regex::Regex;
 use serenity::{
-    all::{Context, CreateEmbed, CreateMessage, GetMessages, Mentionable, Message, Permissions},
+    all::{
+        Context, CreateEmbed, CreateMessage, GetMessages, Mentionable, Message,
+        MessageId, Permissions, UserId,
+    },
     async_trait,
 };
 use tracing::warn;
 
 use crate::{
+    MessageCacheContainer,
     commands::{
         Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
         CommandSyntax, TransformerFnArc,
@@ -16,10 +21,15 @@ use crate::{
     event_handler::CommandError,
     lexer::{Token, lex},
     transformers::Transformers,
-    utils::{LogType, guild_log},
+    utils::{LogType, Transcript, TranscriptMessage, guild_log},
 };
 use ouroboros_macros::command;
 
+/// The maximum amount of messages a single purge can delete
+const MAX_PURGE: i32 = 1000;
+/// The maximum amount of messages looked at while searching for matching messages
+const MAX_SCAN: usize = 5000;
+
 pub struct Purge;
 
 impl Purge {
@@ -28,6 +38,81 @@ impl Purge {
     }
 }
 
+#[derive(Default)]
+struct PurgeFilters {
+    user: Option<UserId>,
+    string: Option<String>,
+    regex: Option<Regex>,
+    bots: bool,
+    embeds: bool,
+    attachments: bool,
+    links: bool,
+    invites: bool,
+    mentions: bool,
+    before: Option<MessageId>,
+    after: Option<MessageId>,
+}
+
+impl PurgeFilters {
+    fn matches(&self, m: &Message) -> bool {
+        if let Some(user) = self.user
+            && m.author.id != user
+        {
+            return false;
+        }
+
+        if let Some(content) = &self.string
+            && !m.content.contains(content)
+        {
+            return false;
+        }
+
+        if let Some(regex) = &self.regex
+            && !regex.is_match(&m.content)
+        {
+            return false;
+        }
+
+        if self.bots && !m.author.bot {
+            return false;
+        }
+
+        if self.embeds && m.embeds.is_empty() {
+            return false;
+        }
+
+        if self.attachments && m.attachments.is_empty() {
+            return false;
+        }
+
+        if self.links && !(m.content.contains("http://") || m.content.contains("https://")) {
+            return false;
+        }
+
+        if self.invites
+            && ![
+                "discord.gg/",
+                "discord.com/invite/",
+                "discordapp.com/invite/",
+            ]
+            .iter()
+            .any(|i| m.content.contains(i))
+        {
+            return false;
+        }
+
+        if self.mentions
+            && m.mentions.is_empty()
+            && m.mention_roles.is_empty()
+            && !m.mention_everyone
+        {
+            return false;
+        }
+
+        true
+    }
+}
+
 #[async_trait]
 impl Command for Purge {
     fn get_name(&self) -> &'static str {
@@ -39,12 +124,21 @@ impl Command for Purge {
     }
 
     fn get_full(&self) -> &'static str {
-        "Mass deletes a specific amount of messages from a channel. \
-        Messages older than 2 weeks are ignored. \
-        Count must be between 2 and 99. \
+        "Mass deletes a specific amount of messages from a channel, a transcript of the deleted messages is attached to the log. \
+        Messages older than 2 weeks are deleted one by one, which is a lot slower. \
+        Count must be between 2 and 1000, at most 5000 messages are searched for matches. \
         Optional filters can be applied after the count: \
         \n`+user/+u @ouroboros` -> Message Author \
-        \n`+string/+s \"content\"` -> Message Content"
+        \n`+string/+s \"content\"` -> Message Content \
+        \n`+regex/+r \"pattern\"` -> Message Content matches the pattern, backslashes have to be doubled \
+        \n`+bots/+b` -> Messages sent by bots \
+        \n`+embeds/+e` -> Messages with embeds \
+        \n`+attachments/+a` -> Messages with attachments \
+        \n`+links/+l` -> Messages with links \
+        \n`+invites/+i` -> Messages with server invites \
+        \n`+mentions/+m` -> Messages mentioning users, roles or everyone \
+        \n`+before <message id>` -> Messages sent before the message \
+        \n`+after <message id>` -> Messages sent after the message"
     }
 
     fn get_syntax(&self) -> Vec<CommandSyntax> {
@@ -67,116 +161,195 @@ impl Command for Purge {
         #[transformers::i32] count: i32,
         #[transformers::consume] filters: String,
     ) -> Result<(), CommandError> {
-        if !(2..=99).contains(&count) {
+        if !(2..=MAX_PURGE).contains(&count) {
             return Err(CommandError {
-                title: String::from("Message count must be between 2 and 99"),
+                title: format!("Message count must be between 2 and {MAX_PURGE}"),
                 hint: None,
                 arg: Some(args.first().unwrap().clone()),
             });
         }
 
+        let filter_error = |title: &str, hint: &str| CommandError {
+            title: String::from(title),
+            hint: Some(String::from(hint)),
+            arg: args.get(1).cloned(),
+        };
+
         let mut lex = lex(filters).into_iter().peekable();
-        let mut filters: HashMap<&str, CommandArgument> = HashMap::new();
+        let mut filters = PurgeFilters::default();
 
         while let Some(token) = lex.next() {
             match token.raw.as_str() {
                 "+u" | "+user" => {
-                    if let Ok(Token {
-                        contents: Some(cmd_arg),
+                    let Ok(Token {
+                        contents: Some(CommandArgument::User(user)),
                         ..
                     }) = Transformers::user(&ctx, &msg, &mut lex).await
-                    {
-                        filters.insert("user", cmd_arg);
-                    }
+                    else {
+                        return Err(filter_error(
+                            "Could not find the user filter",
+                            "provide the id or mention of a user",
+                        ));
+                    };
+
+                    filters.user = Some(user.id);
                 }
 
                 "+s" | "+string" => {
-                    filters.insert(
-                        "string",
-                        CommandArgument::String(lex.next().map(|t| t.raw).unwrap_or_default()),
-                    );
+                    let Some(content) = lex.next().map(|t| t.raw).filter(|s| !s.is_empty()) else {
+                        return Err(filter_error(
+                            "Missing string filter",
+                            "provide the text to search for, wrapped in quotes if it contains spaces",
+                        ));
+                    };
+
+                    filters.string = Some(content);
                 }
 
-                _ => {}
-            }
-        }
+                "+r" | "+regex" => {
+                    let pattern = lex.next().map(|t| t.raw).unwrap_or_default();
+
+                    match Regex::new(&pattern) {
+                        Ok(regex) => filters.regex = Some(regex),
+                        Err(_) => {
+                            return Err(filter_error(
+                                "Invalid regex filter",
+                                "make sure the pattern is valid and wrapped in quotes",
+                            ));
+                        }
+                    }
+                }
 
-        let mut messages = match msg
-            .channel_id
-            .messages(&ctx, GetMessages::new().limit(100))
-            .await
-        {
-            Ok(m) => m,
-            Err(err) => {
-                warn!("Got error while fetching messages; err = {err:?}");
-                return Err(CommandError {
-                    title: String::from("Could not get channel messages"),
-                    hint: Some(String::from(
-                        "there is currently a bug where this command fails if the last 100 messages in the channel have messages with components in them. Will be fixed soon sorry!",
-                    )),
-                    arg: None,
-                });
+                "+b" | "+bots" => filters.bots = true,
+                "+e" | "+embeds" => filters.embeds = true,
+                "+a" | "+attachments" => filters.attachments = true,
+                "+l" | "+links" => filters.links = true,
+                "+i" | "+invites" => filters.invites = true,
+                "+m" | "+mentions" => filters.mentions = true,
+
+                "+before" | "+after" => {
+                    // message links are accepted as well, the message id is always the last segment
+                    let Some(id) = lex
+                        .next()
+                        .and_then(|t| t.raw.rsplit('/').next()?.parse::<u64>().ok())
+                        .filter(|id| *id != 0)
+                    else {
+                        return Err(filter_error(
+                            "Invalid message id",
+                            "provide the id or link of a message in this channel",
+                        ));
+                    };
+
+                    if token.raw == "+before" {
+                        filters.before = Some(MessageId::new(id));
+                    } else {
+                        filters.after = Some(MessageId::new(id));
+                    }
+                }
+
+                _ => {
+                    return Err(filter_error(
+                        "Unknown filter",
+                        "run the help command on purge to see all filters",
+                    ));
+                }
             }
-        };
+        }
 
-        let now = Utc::now();
-        let two_weeks = Duration::weeks(2);
+        let count = count as usize;
+        let mut cursor = filters.before.unwrap_or(msg.id);
+        let mut scanned = 0;
+        let mut matched: Vec<Message> = vec![];
+
+        'paging: while matched.len() < count && scanned < MAX_SCAN {
+            let page = match msg
+                .channel_id
+                .messages(&ctx, GetMessages::new().before(cursor).limit(100))
+                .await
+            {
+                Ok(m) => m,
+                Err(err) => {
+                    warn!("Got error while fetching messages; err = {err:?}");
+                    return Err(CommandError {
+                        title: String::from("Could not get channel messages"),
+                        hint: Some(String::from(
+                            "make sure the bot can read the message history of this channel",
+                        )),
+                        arg: None,
+                    });
+                }
+            };
 
-        messages.remove(0);
+            let Some(last) = page.last() else {
+                break;
+            };
 
-        let mut filtered = messages
-            .iter()
-            .filter(|m| {
-                let diff = (now - *m.timestamp).num_seconds().abs();
+            cursor = last.id;
 
-                if diff <= two_weeks.num_seconds() {
-                    return true;
+            for m in page {
+                if filters.after.is_some_and(|after| m.id <= after) {
+                    break 'paging;
                 }
 
-                false
-            })
-            .collect::<Vec<_>>();
+                scanned += 1;
+
+                if filters.matches(&m) {
+                    matched.push(m);
 
-        if let Some(CommandArgument::User(user)) = filters.get("user") {
-            filtered = filtered
-                .into_iter()
-                .filter(|m| {
-                    if m.author.id.get() == user.id.get() {
-                        return true;
+                    if matched.len() >= count {
+                        break 'paging;
                     }
+                }
+            }
+        }
 
-                    false
-                })
-                .collect::<Vec<_>>();
+        if matched.is_empty() {
+            return Err(CommandError {
+                title: String::from("No messages matched the filters"),
+                hint: Some(format!("searched {scanned} messages")),
+                arg: None,
+            });
         }
 
-        if let Some(CommandArgument::String(content)) = filters.get("string") {
-            filtered = filtered
-                .into_iter()
-                .filter(|m| {
-                    if m.content.contains(content) {
-                        return true;
-                    }
+        // bulk deletes only work on messages younger than 2 weeks, leave some leeway for slow deletions
+        let bulk_cutoff = Utc::now() - Duration::weeks(2) + Duration::minutes(10);
+        let (recent, old): (Vec<&Message>, Vec<&Message>) =
+            matched.iter().partition(|m| *m.timestamp > bulk_cutoff);
+
+        // messages which could not be deleted are left out of the transcript
+        let mut failed: HashSet<MessageId> = HashSet::new();
 
-                    false
-                })
-                .collect::<Vec<_>>();
+        for chunk in recent.chunks(100) {
+            if let Err(err) = msg.channel_id.delete_messages(&ctx, chunk).await {
+                warn!("Got error while bulk deleting messages; err = {err:?}");
+                failed.extend(chunk.iter().map(|m| m.id));
+            }
         }
 
-        let ids = filtered
-            .clone()
-            .into_iter()
-            .map(|m| m.id.get().to_string())
-            .collect::<Vec<_>>();
+        // the transcript below covers these, their delete events are not logged again
+        let message_cache = {
+            let data = ctx.data.read().await;
+            data.get::<MessageCacheContainer>().cloned()
+        };
 
-        let final_count = ids.len();
+        for m in &old {
+            if let Some(cache) = &message_cache {
+                cache.expect_own_deletion(m.channel_id.get(), m.id.get());
+            }
 
-        if msg
-            .channel_id
-            .delete_messages(&ctx, filtered)
-            .await
-            .is_err()
-        {
+            if let Err(err) = msg.channel_id.delete_message(&ctx, m.id).await {
+                warn!("Got error while deleting an old message; err = {err:?}");
+                failed.insert(m.id);
+
+                if let Some(cache) = &message_cache {
+                    cache.take_own_deletion(m.channel_id.get(), m.id.get());
+                }
+            }
+        }
+
+        let deleted = matched.len() - failed.len();
+
+        if deleted == 0 {
             return Err(CommandError {
                 title: String::from("Could not delete channel messages"),
                 hint: Some(String::from(
@@ -184,7 +357,20 @@ impl Command for Purge {
                 )),
                 arg: None,
             });
-        };
+        }
+
+        let channel_name = msg
+            .channel_id
+            .name(&ctx)
+            .await
+            .unwrap_or(msg.channel_id.get().to_string());
+        let transcript = Transcript::new(
+            format!("Purged messages in #{channel_name}"),
+            matched
+                .iter()
+                .filter(|m| !failed.contains(&m.id))
+                .map(TranscriptMessage::from),
+        );
 
         guild_log(
             &ctx,
@@ -194,15 +380,20 @@ impl Command for Purge {
                 .add_embed(
                     CreateEmbed::new()
                         .description(format!(
-                            "**MESSAGES PURGED**\n-# Actor: {} `{}` | Channel: <#{}> | Count: {}\n```\n{}\n```",
+                            "**MESSAGES PURGED**\n-# Actor: {} `{}` | Channel: <#{}> | Count: {deleted} | Older than 2 weeks: {} | Failed: {} | Searched: {scanned}",
                             msg.author.mention(),
                             msg.author.id.get(),
                             msg.channel_id.get(),
-                            final_count,
-                            ids.join("\n")
+                            old.len(),
+                            failed.len(),
                         ))
                         .color(BRAND_BLUE)
                 )
+                .add_files(transcript.to_attachments(&format!(
+                    "purge-{}-{}",
+                    msg.channel_id.get(),
+                    msg.id.get()
+                )))
         ).await;
 
         let _ = msg.delete(&ctx).await;
@@ -222,3 +413,186 @@ impl Command for Purge {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use axum::http::Method;
+    use chrono::{Duration, Utc};
+
+    use serenity::{
+        all::{EventHandler, Message},
+        json::from_value,
+    };
+
+    use crate::{
+        testing::{self, TestBot, description, fake_discord::message_json, snowflake_at},
+        utils::cache::partials::PartialMessage,
+    };
+
+    #[test]
+    fn bulk_deletes_matching_messages_with_a_transcript() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            let mut spam = vec![];
+            for i in 0..4 {
+                spam.push(
+                    bot.seed_message(bot.member_id, &format!("spam {i}"))
+                        .id
+                        .get(),
+                );
+                bot.seed_message(bot.moderator_id, "please stop");
+            }
+
+            bot.command(
+                bot.moderator_id,
+                &format!("!purge 3 +user {}", bot.member_id),
+            )
+            .await;
+
+            let deletes = bot.discord.requests(
+                Method::POST,
+                &format!("/channels/{}/messages/bulk-delete", bot.channel_id),
+            );
+            assert_eq!(deletes.len(), 1);
+
+            let deleted = deletes[0].payload()["messages"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter_map(|m| m.as_str()?.parse::<u64>().ok())
+                .collect::<HashSet<_>>();
+            assert_eq!(deleted, spam[1..].iter().copied().collect());
+
+            let logs = bot.discord.requests(
+                Method::POST,
+                &format!("/channels/{}/messages", bot.log_channel_id),
+            );
+            assert_eq!(logs.len(), 1);
+            assert!(description(&logs[0].payload()).contains("**MESSAGES PURGED**"));
+            assert!(description(&logs[0].payload()).contains("Count: 3"));
+            assert!(logs[0].body.contains("spam 3"));
+            assert!(!logs[0].body.contains("please stop"));
+        });
+    }
+
+    #[test]
+    fn deletes_old_messages_one_by_one() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            let old = snowflake_at(Utc::now() - Duration::weeks(3));
+            {
+                let mut state = bot.discord.state();
+                let author = state.users[&bot.member_id].clone();
+                let message =
+                    message_json(old, bot.channel_id, Some(bot.guild_id), author, "ancient");
+                bot.handler.message_cache.insert(
+                    bot.channel_id,
+                    PartialMessage::from(from_value::<Message>(message.clone()).unwrap()),
+                );
+                state
+                    .messages
+                    .entry(bot.channel_id)
+                    .or_default()
+                    .push(message);
+            }
+            let recent = bot.seed_message(bot.member_id, "recent").id.get();
+
+            bot.command(bot.moderator_id, "!purge 2").await;
+
+            let single = bot.discord.requests(
+                Method::DELETE,
+                &format!("/channels/{}/messages/{old}", bot.channel_id),
+            );
+            assert_eq!(single.len(), 1);
+
+            let remaining = bot.discord.state().messages[&bot.channel_id]
+                .iter()
+                .filter(|m| m["id"] == recent.to_string().as_str())
+                .count();
+            assert_eq!(remaining, 0);
+
+            assert!(description(&bot.logs()[0]).contains("Older than 2 weeks: 1"));
+
+            // the delete event of the old message is covered by the purge log
+            bot.handler
+                .message_delete(
+                    bot.ctx.clone(),
+                    bot.channel_id.into(),
+                    old.into(),
+                    Some(bot.guild_id.into()),
+                )
+                .await;
+            assert_eq!(bot.logs().len(), 1);
+        });
+    }
+
+    #[test]
+    fn leaves_failed_deletions_out_of_the_transcript() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            let old = snowflake_at(Utc::now() - Duration::weeks(3));
+            {
+                let mut state = bot.discord.state();
+                let author = state.users[&bot.member_id].clone();
+                let message =
+                    message_json(old, bot.channel_id, Some(bot.guild_id), author, "ancient");
+                state
+                    .messages
+                    .entry(bot.channel_id)
+                    .or_default()
+                    .push(message);
+                state.failing.insert((
+                    Method::DELETE,
+                    format!("/channels/{}/messages/{old}", bot.channel_id),
+                ));
+            }
+            bot.seed_message(bot.member_id, "recent 1");
+            bot.seed_message(bot.member_id, "recent 2");
+
+            bot.command(bot.moderator_id, "!purge 3").await;
+
+            let logs = bot.discord.requests(
+                Method::POST,
+                &format!("/channels/{}/messages", bot.log_channel_id),
+            );
+            assert_eq!(logs.len(), 1);
+            assert!(description(&logs[0].payload()).contains("Count: 2"));
+            assert!(description(&logs[0].payload()).contains("Failed: 1"));
+            assert!(logs[0].body.contains("recent 2"));
+            assert!(!logs[0].body.contains("ancient"));
+        });
+    }
+
+    #[test]
+    fn rejects_unknown_filters() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            bot.seed_message(bot.member_id, "hello");
+            bot.command(bot.moderator_id, "!purge 5 +nonsense").await;
+            bot.command(bot.moderator_id, "!purge 5 +u typo").await;
+            bot.command(bot.moderator_id, "!purge 5 +s").await;
+            bot.command(bot.moderator_id, "!purge 5 +s \"\"").await;
+
+            assert!(bot.logs().is_empty());
+            let replies = bot.replies();
+            assert!(description(&replies[0]).contains("Unknown filter"));
+            assert!(description(&replies[1]).contains("Could not find the user filter"));
+            assert!(description(&replies[2]).contains("Missing string filter"));
+            assert!(description(&replies[3]).contains("Missing string filter"));
+        });
+    }
+}
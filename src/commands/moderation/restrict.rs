@@ -0,0 +1,380 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Duration, Utc};
+use serenity::{
+    all::{
+        Context, CreateEmbed, CreateMessage, GuildId, Member, Mentionable, Message, Permissions,
+        RoleId, UserId,
+    },
+    async_trait,
+};
+use sqlx::query;
+use tracing::{error, warn};
+
+use crate::{
+    GUILD_SETTINGS, SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    database::ActionType,
+    event_handler::CommandError,
+    lexer::{InferType, Token},
+    transformers::Transformers,
+    utils::{
+        CommandMessageResponse, LogType, TemplateValues, can_target, get_guild_option, guild_log,
+        guild_templates, humanize_expiry, tinyid,
+    },
+};
+use ouroboros_macros::command;
+
+pub struct Restrict;
+
+impl Restrict {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for Restrict {
+    fn get_name(&self) -> &'static str {
+        "restrict"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Gives a member a restriction role, i.e. a mute or media ban role"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Gives a member one of the restriction roles of the server and leaves a note in the users log. \
+        Unlike timeouts restrictions can last any amount of time, defaults to permanent if no duration is provided. \
+        The role is removed once the restriction expires and re-applied if the member rejoins. \
+        Restrictions are configured with the `restrictions` command."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::Member("member", true),
+            CommandSyntax::String("restriction", true),
+            CommandSyntax::Duration("duration", false),
+            CommandSyntax::Reason("reason"),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Moderation
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![&CommandParameter {
+            name: "silent",
+            short: "s",
+            transformer: &Transformers::none,
+            desc: "Disables DMing the target with the reason",
+        }]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::reply_member] member: Member,
+        #[transformers::string] restriction: String,
+        #[transformers::maybe_duration] duration: Option<Duration>,
+        #[transformers::reply_consume] reason: Option<String>,
+    ) -> Result<(), CommandError> {
+        let Ok(author_member) = msg.member(&ctx).await else {
+            return Err(CommandError {
+                title: String::from("Unexpected error has occured."),
+                hint: Some(String::from("could not get author member")),
+                arg: None,
+            });
+        };
+
+        let res = can_target(
+            &ctx,
+            &author_member,
+            &member,
+            Permissions::MODERATE_MEMBERS,
+            self,
+            msg.channel_id,
+        )
+        .await;
+
+        if !res {
+            return Err(CommandError {
+                title: String::from("You may not target this member."),
+                hint: None,
+                arg: None,
+            });
+        }
+
+        let (restriction, role_id) = restriction_role(member.guild_id, &restriction)
+            .await
+            .map_err(|err| CommandError {
+                arg: Some(args[1].clone()),
+                ..err
+            })?;
+
+        let inferred = args
+            .first()
+            .map(|a| matches!(a.inferred, Some(InferType::Message)))
+            .unwrap_or(false);
+        let duration = duration.unwrap_or(Duration::zero());
+        let mut reason = reason
+            .map(|s| {
+                if s.is_empty() || s.chars().all(char::is_whitespace) {
+                    String::from("No reason provided")
+                } else {
+                    s
+                }
+            })
+            .unwrap_or(String::from("No reason provided"));
+
+        if reason.len() > 500 {
+            reason.truncate(500);
+            reason.push_str("...");
+        }
+
+        let db_id = tinyid().await;
+
+        let time_string = if !duration.is_zero() {
+            humanize_expiry(&duration)
+        } else {
+            String::from("permanent")
+        };
+
+        let expires_at = if duration.is_zero() {
+            None
+        } else {
+            Some(Utc::now() + duration)
+        };
+
+        restrict_member(
+            &ctx,
+            &member,
+            &db_id,
+            msg.author.id,
+            role_id,
+            &reason,
+            expires_at,
+        )
+        .await?;
+
+        if inferred && let Some(reply) = msg.referenced_message.clone() {
+            let _ = reply.delete(&ctx).await;
+        }
+
+        let guild_name = {
+            match msg
+                .guild_id
+                .unwrap_or(GuildId::new(1))
+                .to_partial_guild(&ctx)
+                .await
+            {
+                Ok(p) => p.name.clone(),
+                Err(_) => String::from("UNKNOWN_GUILD"),
+            }
+        };
+
+        let (dm_template, response_template) =
+            guild_templates(member.guild_id, &ActionType::Restrict).await;
+        let values = TemplateValues {
+            server: guild_name,
+            duration: time_string.clone(),
+            reason: reason.clone(),
+            log_id: db_id.clone(),
+            moderator: msg.author.mention().to_string(),
+            user: member.mention().to_string(),
+            restriction: restriction.clone(),
+        };
+
+        let dm_members = get_guild_option(member.guild_id, "moderation.dm_members")
+            .await
+            .as_bool()
+            .unwrap_or(true);
+
+        let mut cmd_response = CommandMessageResponse::new(member.user.id)
+            .dm_content(values.render(&dm_template))
+            .server_content(values.render_response(&response_template, String::new()))
+            .automatically_delete(inferred)
+            .mark_silent(params.contains_key("silent") || !dm_members);
+
+        cmd_response.send_dm(&ctx).await;
+        cmd_response.send_response(&ctx, &msg).await;
+
+        guild_log(
+            &ctx,
+            LogType::MemberModeration,
+            msg.guild_id.unwrap(),
+            CreateMessage::new()
+                .add_embed(
+                    CreateEmbed::new()
+                        .description(format!(
+                            "**MEMBER RESTRICTED**\n-# Log ID: `{db_id}` | Actor: {} `{}` | Target: {} `{}` | Restriction: {restriction} {} | Duration: {time_string}\n```\n{reason}\n```",
+                            msg.author.mention(),
+                            msg.author.id.get(),
+                            member.mention(),
+                            member.user.id.get(),
+                            role_id.mention()
+                        ))
+                        .color(BRAND_BLUE)
+                )
+        ).await;
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::MODERATE_MEMBERS],
+            one_of: vec![],
+            bot: [
+                CommandPermissions::baseline().as_slice(),
+                &[Permissions::MANAGE_ROLES],
+            ]
+            .concat(),
+        }
+    }
+}
+
+/// Looks up a restriction of a guild by name, returns the normalized name and its role
+pub async fn restriction_role(
+    guild_id: GuildId,
+    name: &str,
+) -> Result<(String, RoleId), CommandError> {
+    let name = name.to_lowercase();
+
+    let restrictions = {
+        let mut lock = GUILD_SETTINGS.lock().await;
+        lock.get(guild_id.get())
+            .await
+            .map(|s| s.moderation.restriction_roles)
+            .unwrap_or_default()
+    };
+
+    match restrictions.get(&name) {
+        Some(role) => Ok((name, RoleId::new(*role))),
+        None => Err(CommandError {
+            title: String::from("Unknown restriction"),
+            hint: Some(if restrictions.is_empty() {
+                String::from(
+                    "this server has no restrictions, an admin can add some with the restrictions command",
+                )
+            } else {
+                let mut names = restrictions.keys().cloned().collect::<Vec<_>>();
+                names.sort();
+                format!("available restrictions: {}", names.join(", "))
+            }),
+            arg: None,
+        }),
+    }
+}
+
+/// Gives a member a restriction role and stores the restriction, rolling the entry back if the role could not be added.
+/// Expired restrictions are lifted by the expiring actions task.
+pub async fn restrict_member(
+    ctx: &Context,
+    member: &Member,
+    db_id: &str,
+    moderator_id: UserId,
+    role_id: RoleId,
+    reason: &str,
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<(), CommandError> {
+    let res = query!(
+        r#"
+            SELECT id FROM actions
+            WHERE guild_id = $1 AND user_id = $2 AND role_id = $3
+              AND type = 'restrict' AND active = true
+              AND (expires_at IS NULL OR expires_at > NOW())
+        "#,
+        member.guild_id.get() as i64,
+        member.user.id.get() as i64,
+        role_id.get() as i64
+    )
+    .fetch_optional(&*SQL)
+    .await;
+
+    match res {
+        Ok(Some(active)) => {
+            return Err(CommandError {
+                title: String::from("This member already has this restriction"),
+                hint: Some(format!(
+                    "use the duration command on `{}` to change how long it lasts",
+                    active.id
+                )),
+                arg: None,
+            });
+        }
+        Ok(None) => {}
+        Err(err) => {
+            warn!("Got error while restricting; err = {err:?}");
+            return Err(CommandError {
+                title: String::from("Could not restrict member"),
+                hint: Some(String::from("please try again later")),
+                arg: None,
+            });
+        }
+    }
+
+    let res = query!(
+        "INSERT INTO actions (id, type, guild_id, user_id, moderator_id, reason, expires_at, role_id) VALUES ($1, 'restrict', $2, $3, $4, $5, $6, $7)",
+        db_id,
+        member.guild_id.get() as i64,
+        member.user.id.get() as i64,
+        moderator_id.get() as i64,
+        reason,
+        expires_at.map(|d| d.naive_utc()),
+        role_id.get() as i64,
+    ).execute(&*SQL).await;
+
+    if let Err(err) = res {
+        warn!("Got error while restricting; err = {err:?}");
+        return Err(CommandError {
+            title: String::from("Could not restrict member"),
+            hint: Some(String::from("please try again later")),
+            arg: None,
+        });
+    }
+
+    let audit_reason = format!(
+        "Ouroboros Managed Restriction: log id `{db_id}`. Please use Ouroboros to unrestrict to avoid accidental re-application!"
+    );
+
+    if let Err(err) = ctx
+        .http
+        .add_member_role(
+            member.guild_id,
+            member.user.id,
+            role_id,
+            Some(&audit_reason),
+        )
+        .await
+    {
+        warn!("Got error while restricting; err = {err:?}");
+
+        if query!("DELETE FROM actions WHERE id = $1", db_id)
+            .execute(&*SQL)
+            .await
+            .is_err()
+        {
+            error!(
+                "Got an error while restricting and an error with the database! Stray restriction entry in DB & manual action required; id = {db_id}; err = {err:?}"
+            );
+        }
+
+        return Err(CommandError {
+            title: String::from("Could not restrict member"),
+            hint: Some(String::from(
+                "check if the bot has the manage roles permission and its role is above the restriction role",
+            )),
+            arg: None,
+        });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,273 @@
+use std::sync::Arc;
+
+use chrono::{NaiveDate, NaiveDateTime, Utc};
+use ouroboros_macros::command;
+use serenity::{
+    all::{Context, Message, Permissions},
+    async_trait,
+};
+use sqlx::query_as;
+use tracing::warn;
+
+use crate::{
+    SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, Log, LogRecord, TransformerFnArc,
+    },
+    database::ActionType,
+    event_handler::CommandError,
+    lexer::Token,
+    transformers::Transformers,
+};
+
+/// The maximum amount of results a search returns
+const MAX_RESULTS: i64 = 250;
+
+pub struct Search;
+
+impl Search {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Parses a date filter, either a date (`2025-04-01`) or a duration counting back from now (`30d`)
+    async fn parse_date(
+        &self,
+        ctx: &Context,
+        msg: &Message,
+        name: &str,
+        input: &str,
+    ) -> Result<NaiveDateTime, CommandError> {
+        if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+            return Ok(date.and_hms_opt(0, 0, 0).unwrap());
+        }
+
+        let token = Token {
+            contents: None,
+            raw: input.to_string(),
+            position: 0,
+            length: input.len(),
+            iteration: 0,
+            quoted: false,
+            inferred: None,
+        };
+        let mut fake_args = vec![token].into_iter().peekable();
+
+        match Transformers::duration(ctx, msg, &mut fake_args).await {
+            Ok(Token {
+                contents: Some(CommandArgument::Duration(d)),
+                ..
+            }) => Ok((Utc::now() - d).naive_utc()),
+            _ => Err(CommandError {
+                title: format!("Could not turn +{name} into a date"),
+                hint: Some(String::from(
+                    "provide a date (i.e. 2025-04-01) or how long ago (i.e. 30d)",
+                )),
+                arg: None,
+            }),
+        }
+    }
+}
+
+#[async_trait]
+impl Command for Search {
+    fn get_name(&self) -> &'static str {
+        "search"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Searches the moderation history of the server"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Searches the reasons of all moderation actions in the server. \
+        Supports quoted phrases, `or` and excluding words with `-`, i.e. `crypto scam -nitro`. \
+        Results can be narrowed down using the parameters, the query may be left empty when filtering."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![CommandSyntax::Consume("query")]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Moderation
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![
+            &CommandParameter {
+                name: "user",
+                short: "u",
+                transformer: &Transformers::user,
+                desc: "Only actions targeting this user",
+            },
+            &CommandParameter {
+                name: "moderator",
+                short: "m",
+                transformer: &Transformers::user,
+                desc: "Only actions taken by this moderator",
+            },
+            &CommandParameter {
+                name: "type",
+                short: "t",
+                transformer: &Transformers::string,
+                desc: "Only actions of this type (warn, kick, ban, softban, mute, unban, unmute)",
+            },
+            &CommandParameter {
+                name: "after",
+                short: "a",
+                transformer: &Transformers::string,
+                desc: "Only actions after a date (2025-04-01) or duration ago (30d)",
+            },
+            &CommandParameter {
+                name: "before",
+                short: "b",
+                transformer: &Transformers::string,
+                desc: "Only actions before a date (2025-04-01) or duration ago (30d)",
+            },
+            &CommandParameter {
+                name: "active",
+                short: "ac",
+                transformer: &Transformers::none,
+                desc: "Only actions which are still active",
+            },
+            &CommandParameter {
+                name: "expired",
+                short: "ex",
+                transformer: &Transformers::none,
+                desc: "Only actions which have expired or were revoked",
+            },
+        ]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::consume] search: Option<String>,
+    ) -> Result<(), CommandError> {
+        let search = search.map(|s| s.trim().to_string()).unwrap_or_default();
+
+        let get_user = |name: &str| match params.get(name) {
+            Some((_, CommandArgument::User(u))) => Some(u.id.get() as i64),
+            _ => None,
+        };
+
+        let get_string = |name: &str| match params.get(name) {
+            Some((_, CommandArgument::String(s))) => Some(s.clone()),
+            _ => None,
+        };
+
+        let user = get_user("user");
+        let moderator = get_user("moderator");
+
+        let action_type = match get_string("type") {
+            Some(t) => {
+                let t = t.to_lowercase();
+
+                if !ActionType::all().iter().any(|a| a.to_string() == t) {
+                    return Err(CommandError {
+                        title: String::from("Unknown action type"),
+                        hint: Some(format!(
+                            "available types: {}",
+                            ActionType::all()
+                                .iter()
+                                .map(|a| a.to_string())
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        )),
+                        arg: None,
+                    });
+                }
+
+                Some(t)
+            }
+            None => None,
+        };
+
+        let after = match get_string("after") {
+            Some(a) => Some(self.parse_date(&ctx, &msg, "after", &a).await?),
+            None => None,
+        };
+
+        let before = match get_string("before") {
+            Some(b) => Some(self.parse_date(&ctx, &msg, "before", &b).await?),
+            None => None,
+        };
+
+        let active = match (params.contains_key("active"), params.contains_key("expired")) {
+            (true, false) => Some(true),
+            (false, true) => Some(false),
+            _ => None,
+        };
+
+        if search.is_empty()
+            && user.is_none()
+            && moderator.is_none()
+            && action_type.is_none()
+            && after.is_none()
+            && before.is_none()
+            && active.is_none()
+        {
+            return Err(CommandError::arg_not_found("String", Some("query")));
+        }
+
+        let res = query_as!(
+            LogRecord,
+            r#"
+                SELECT id, type as "type!: ActionType", user_id, moderator_id, created_at, updated_at, expires_at, reason
+                FROM actions
+                WHERE guild_id = $1
+                  AND ($2::text = '' OR to_tsvector('english', reason) @@ websearch_to_tsquery('english', $2))
+                  AND ($3::bigint IS NULL OR user_id = $3)
+                  AND ($4::bigint IS NULL OR moderator_id = $4)
+                  AND ($5::text IS NULL OR type::text = $5)
+                  AND ($6::timestamp IS NULL OR created_at >= $6)
+                  AND ($7::timestamp IS NULL OR created_at < $7)
+                  AND ($8::boolean IS NULL OR (active AND (expires_at IS NULL OR expires_at > NOW())) = $8)
+                ORDER BY ts_rank(to_tsvector('english', reason), websearch_to_tsquery('english', $2)) DESC, created_at DESC
+                LIMIT $9;
+            "#,
+            msg.guild_id.map(|g| g.get()).unwrap_or(0) as i64,
+            search,
+            user,
+            moderator,
+            action_type,
+            after,
+            before,
+            active,
+            MAX_RESULTS
+        )
+        .fetch_all(&*SQL)
+        .await;
+
+        let data = match res {
+            Ok(d) => d,
+            Err(err) => {
+                warn!("Couldn't search log data; err = {err:?}");
+                return Err(CommandError {
+                    title: String::from("Unable to query the database"),
+                    hint: Some(String::from("try again later")),
+                    arg: None,
+                });
+            }
+        };
+
+        Log::new().send_paginated(&ctx, &msg, data, true).await
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![],
+            one_of: vec![
+                Permissions::MANAGE_NICKNAMES,
+                Permissions::KICK_MEMBERS,
+                Permissions::MODERATE_MEMBERS,
+                Permissions::BAN_MEMBERS,
+            ],
+            bot: CommandPermissions::baseline(),
+        }
+    }
+}
@@ -0,0 +1,378 @@
+use std::sync::Arc;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use ouroboros_macros::command;
+use serenity::{
+    all::{
+        CacheHttp, ChannelId, Context, CreateAllowedMentions, CreateEmbed, CreateMessage,
+        EditChannel, GuildChannel, GuildId, HttpError, Mentionable, Message, Permissions, UserId,
+    },
+    async_trait,
+};
+use sqlx::query;
+use tracing::warn;
+
+use crate::{
+    SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerError, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::CommandError,
+    lexer::Token,
+    transformers::Transformers,
+    utils::{LogType, can_use_in_channel, guild_log, humanize_duration, humanize_expiry},
+};
+
+/// The longest slowmode Discord allows, 6 hours
+const MAX_SLOWMODE: i64 = 60 * 60 * 6;
+
+pub struct Slowmode;
+
+impl Slowmode {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for Slowmode {
+    fn get_name(&self) -> &'static str {
+        "slowmode"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Sets the slowmode of a channel"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Sets how long members have to wait between messages in a channel, up to 6 hours. `off` disables slowmode. \
+        Defaults to the current channel. If a duration is provided the previous slowmode is restored automatically, \
+        i.e. `slowmode 30s 1h spam wave`."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::Channel("channel", false),
+            CommandSyntax::String("interval", true),
+            CommandSyntax::Duration("duration", false),
+            CommandSyntax::Reason("reason"),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Moderation
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::maybe_guild_channel] channel: Option<GuildChannel>,
+        #[transformers::string] interval: String,
+        #[transformers::maybe_duration] duration: Option<Duration>,
+        #[transformers::consume] reason: Option<String>,
+    ) -> Result<(), CommandError> {
+        let channel = match channel {
+            Some(c) => c,
+            None => match msg.channel(&ctx).await.ok().and_then(|c| c.guild()) {
+                Some(c) => c,
+                None => {
+                    return Err(CommandError {
+                        title: String::from("Unexpected error has occured."),
+                        hint: Some(String::from("could not get current channel")),
+                        arg: None,
+                    });
+                }
+            },
+        };
+
+        if channel.id != msg.channel_id {
+            let allowed = match msg.member(&ctx).await {
+                Ok(member) => {
+                    can_use_in_channel(&ctx, &member, &channel, Permissions::MANAGE_CHANNELS, self)
+                        .await
+                }
+                Err(_) => false,
+            };
+
+            if !allowed {
+                return Err(CommandError {
+                    title: String::from("You do not have permissions to execute this command."),
+                    hint: Some(format!(
+                        "you need the manage channels permission in {}",
+                        channel.mention()
+                    )),
+                    arg: None,
+                });
+            }
+        }
+
+        let interval = match interval.to_lowercase().as_str() {
+            "off" | "0" => Duration::zero(),
+            _ => {
+                let mut fake_args = vec![args[1].clone()].into_iter().peekable();
+
+                match Transformers::duration(&ctx, &msg, &mut fake_args).await {
+                    Ok(Token {
+                        contents: Some(CommandArgument::Duration(d)),
+                        ..
+                    }) => d,
+                    Err(TransformerError::CommandError(err)) => return Err(err),
+                    _ => {
+                        return Err(CommandError {
+                            title: String::from("Could not turn input to a <Duration>"),
+                            hint: Some(String::from("use off to disable slowmode")),
+                            arg: Some(args[1].clone()),
+                        });
+                    }
+                }
+            }
+        };
+
+        if interval.num_seconds() > MAX_SLOWMODE {
+            return Err(CommandError {
+                title: String::from("Slowmode can be at most 6 hours"),
+                hint: None,
+                arg: Some(args[1].clone()),
+            });
+        }
+
+        let duration = duration.unwrap_or(Duration::zero());
+        let mut reason = reason
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .unwrap_or(String::from("No reason provided"));
+
+        if reason.len() > 500 {
+            reason.truncate(500);
+            reason.push_str("...");
+        }
+
+        let expires_at = if duration.is_zero() {
+            None
+        } else {
+            Some((Utc::now() + duration).naive_utc())
+        };
+
+        set_slowmode(
+            &ctx,
+            &channel,
+            interval.num_seconds() as u16,
+            msg.author.id,
+            &reason,
+            expires_at,
+        )
+        .await?;
+
+        let interval_string = if interval.is_zero() {
+            String::from("off")
+        } else {
+            humanize_duration(&interval)
+        };
+
+        let time_string = if !duration.is_zero() {
+            humanize_expiry(&duration)
+        } else {
+            String::from("permanent")
+        };
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(format!(
+                        "**{} SLOWMODE SET**\n-# Interval: {interval_string} | Duration: {time_string}\n```\n{reason}\n```",
+                        channel.mention()
+                    ))
+                    .color(BRAND_BLUE),
+            )
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            warn!("Could not send message; err = {err:?}");
+        }
+
+        guild_log(
+            &ctx,
+            LogType::ChannelModeration,
+            channel.guild_id,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .description(format!(
+                        "**SLOWMODE SET**\n-# Actor: {} `{}` | Channel: {} `{}` | Interval: {interval_string} | Duration: {time_string}\n```\n{reason}\n```",
+                        msg.author.mention(),
+                        msg.author.id.get(),
+                        channel.mention(),
+                        channel.id.get()
+                    ))
+                    .color(BRAND_BLUE),
+            ),
+        )
+        .await;
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::MANAGE_CHANNELS],
+            one_of: vec![],
+            bot: [
+                CommandPermissions::baseline().as_slice(),
+                &[Permissions::MANAGE_CHANNELS],
+            ]
+            .concat(),
+        }
+    }
+}
+
+/// Sets the slowmode of a channel. With `expires_at` the slowmode the channel had before is stored so `revert_slowmode`
+/// can restore it, repeated temporary changes keep the original slowmode. Permanent changes drop any pending revert.
+pub async fn set_slowmode(
+    cache_http: impl CacheHttp,
+    channel: &GuildChannel,
+    seconds: u16,
+    moderator_id: UserId,
+    reason: &str,
+    expires_at: Option<NaiveDateTime>,
+) -> Result<(), CommandError> {
+    if let Err(err) = channel
+        .id
+        .edit(
+            &cache_http,
+            EditChannel::new()
+                .rate_limit_per_user(seconds)
+                .audit_log_reason(reason),
+        )
+        .await
+    {
+        warn!("Got error while setting slowmode; err = {err:?}");
+        return Err(CommandError {
+            title: String::from("Could not set slowmode"),
+            hint: Some(String::from(
+                "check if the bot has the manage channels permission in this channel",
+            )),
+            arg: None,
+        });
+    }
+
+    let res = match expires_at {
+        Some(expires_at) => {
+            query!(
+                r#"
+                    INSERT INTO channel_locks (channel_id, type, guild_id, moderator_id, reason, previous_slowmode, expires_at)
+                    VALUES ($1, 'slowmode', $2, $3, $4, $5, $6)
+                    ON CONFLICT (channel_id, type) DO UPDATE SET moderator_id = $3, reason = $4, expires_at = $6
+                "#,
+                channel.id.get() as i64,
+                channel.guild_id.get() as i64,
+                moderator_id.get() as i64,
+                reason,
+                channel.rate_limit_per_user.unwrap_or(0) as i32,
+                expires_at
+            )
+            .execute(&*SQL)
+            .await
+        }
+        None => {
+            query!(
+                "DELETE FROM channel_locks WHERE channel_id = $1 AND type = 'slowmode'",
+                channel.id.get() as i64
+            )
+            .execute(&*SQL)
+            .await
+        }
+    };
+
+    if let Err(err) = res {
+        warn!("Got error while storing slowmode; err = {err:?}");
+        return Err(CommandError {
+            title: String::from("Slowmode was set but could not be stored"),
+            hint: Some(String::from(
+                "the previous slowmode will not be restored automatically",
+            )),
+            arg: None,
+        });
+    }
+
+    Ok(())
+}
+
+/// Restores the slowmode a channel had before a temporary slowmode and removes the entry.
+/// Returns the restored interval in seconds, entries of channels which no longer exist are removed as well.
+pub async fn revert_slowmode(
+    cache_http: impl CacheHttp,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> Result<u16, CommandError> {
+    let previous = match query!(
+        "SELECT previous_slowmode FROM channel_locks WHERE channel_id = $1 AND guild_id = $2 AND type = 'slowmode'",
+        channel_id.get() as i64,
+        guild_id.get() as i64
+    )
+    .fetch_optional(&*SQL)
+    .await
+    {
+        Ok(Some(r)) => r.previous_slowmode as u16,
+        Ok(None) => {
+            return Err(CommandError {
+                title: String::from("This channel has no temporary slowmode"),
+                hint: None,
+                arg: None,
+            });
+        }
+        Err(err) => {
+            warn!("Got error while reverting slowmode; err = {err:?}");
+            return Err(CommandError {
+                title: String::from("Could not revert slowmode"),
+                hint: Some(String::from("please try again later")),
+                arg: None,
+            });
+        }
+    };
+
+    if let Err(err) = channel_id
+        .edit(
+            &cache_http,
+            EditChannel::new()
+                .rate_limit_per_user(previous)
+                .audit_log_reason("Temporary slowmode expired"),
+        )
+        .await
+    {
+        let deleted = matches!(
+            &err,
+            serenity::Error::Http(HttpError::UnsuccessfulRequest(e)) if e.status_code.as_u16() == 404
+        );
+
+        if !deleted {
+            warn!("Got error while reverting slowmode; err = {err:?}");
+            return Err(CommandError {
+                title: String::from("Could not revert slowmode"),
+                hint: Some(String::from(
+                    "check if the bot has the manage channels permission in this channel",
+                )),
+                arg: None,
+            });
+        }
+    }
+
+    if let Err(err) = query!(
+        "DELETE FROM channel_locks WHERE channel_id = $1 AND type = 'slowmode'",
+        channel_id.get() as i64
+    )
+    .execute(&*SQL)
+    .await
+    {
+        warn!("Got error while removing slowmode entry; err = {err:?}");
+    }
+
+    Ok(previous)
+}
@@ -0,0 +1,163 @@
+use std::sync::Arc;
+
+use ouroboros_macros::command;
+use serenity::{
+    all::{
+        Context, CreateAllowedMentions, CreateEmbed, CreateMessage, Mentionable, Message,
+        Permissions,
+    },
+    async_trait,
+};
+use tracing::warn;
+
+use crate::{
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::CommandError,
+    lexer::Token,
+    transformers::Transformers,
+    utils::{
+        self, MAX_TRANSCRIPT_MESSAGES, TranscriptMessage, fetch_transcript_messages,
+        permissions_for_channel,
+    },
+};
+
+pub struct Transcript;
+
+impl Transcript {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for Transcript {
+    fn get_name(&self) -> &'static str {
+        "transcript"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Exports the latest messages of a channel as HTML and JSON files"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Exports the latest messages of a channel into a self-contained HTML file and a JSON file, \
+        including authors, timestamps, attachments and replies. Count must be between 1 and 1000. \
+        Use the transcript parameter of the note command to store a transcript with a moderation action."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::Channel("channel", true),
+            CommandSyntax::Number("count", true),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Moderation
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::guild_channel] channel: GuildChannel,
+        #[transformers::i32] count: i32,
+    ) -> Result<(), CommandError> {
+        if !(1..=MAX_TRANSCRIPT_MESSAGES as i32).contains(&count) {
+            return Err(CommandError {
+                title: format!("Message count must be between 1 and {MAX_TRANSCRIPT_MESSAGES}"),
+                hint: None,
+                arg: Some(args[1].clone()),
+            });
+        }
+
+        let (Ok(guild), Ok(member)) = (
+            channel.guild_id.to_partial_guild(&ctx).await,
+            msg.member(&ctx).await,
+        ) else {
+            return Err(CommandError {
+                title: String::from("Unexpected error has occured."),
+                hint: Some(String::from("could not get server or author member")),
+                arg: None,
+            });
+        };
+
+        let permissions = permissions_for_channel(&guild, &channel, &member);
+
+        if guild.owner_id != member.user.id
+            && !permissions.contains(Permissions::ADMINISTRATOR)
+            && !permissions.contains(Permissions::VIEW_CHANNEL | Permissions::READ_MESSAGE_HISTORY)
+        {
+            return Err(CommandError {
+                title: String::from("You can not read the messages of this channel"),
+                hint: None,
+                arg: Some(args[0].clone()),
+            });
+        }
+
+        let messages =
+            match fetch_transcript_messages(&ctx, channel.id, msg.id, count as usize).await {
+                Ok(m) => m,
+                Err(err) => {
+                    warn!("Got error while fetching messages; err = {err:?}");
+                    return Err(CommandError {
+                        title: String::from("Could not get channel messages"),
+                        hint: Some(String::from(
+                            "make sure the bot can read the message history of this channel",
+                        )),
+                        arg: None,
+                    });
+                }
+            };
+
+        let transcript = utils::Transcript::new(
+            format!("#{} in {}", channel.name, guild.name),
+            messages.iter().map(TranscriptMessage::from),
+        );
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(format!(
+                        "**TRANSCRIPT**\n-# Channel: {} | Messages: {}",
+                        channel.mention(),
+                        transcript.messages.len()
+                    ))
+                    .color(BRAND_BLUE),
+            )
+            .add_files(transcript.to_attachments(&format!(
+                "transcript-{}-{}",
+                channel.id.get(),
+                msg.id.get()
+            )))
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            warn!("Could not send message; err = {err:?}");
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::MANAGE_MESSAGES],
+            one_of: vec![],
+            bot: [
+                CommandPermissions::baseline().as_slice(),
+                &[Permissions::READ_MESSAGE_HISTORY, Permissions::ATTACH_FILES],
+            ]
+            .concat(),
+        }
+    }
+}
@@ -3,8 +3,8 @@ use std::{sync::Arc, time::Duration};
 use ouroboros_macros::command;
 use serenity::{
     all::{
-        Context, CreateAllowedMentions, CreateEmbed, CreateMessage, Mentionable, Message,
-        Permissions,
+        Context, CreateAllowedMentions, CreateEmbed, CreateMessage, GuildId, Mentionable,
+        Message, Permissions, UserId,
     },
     async_trait,
 };
@@ -89,67 +89,17 @@ impl Command for Unban {
             reason.push_str("...");
         }
 
-        let res = query!(
-            "UPDATE actions SET active = false, expires_at = NULL WHERE guild_id = $1 AND user_id = $2 AND type = 'ban' AND active = true;",
-            msg.guild_id.map(|g| g.get() as i64).unwrap_or(0),
-            user.id.get() as i64,
-        ).execute(&*SQL).await;
-
-        if let Err(err) = res {
-            warn!("Got error while unbanning; err = {err:?}");
-            return Err(CommandError {
-                title: String::from("Could not unban member"),
-                hint: Some(String::from("please try again later")),
-                arg: None,
-            });
-        }
-
         let db_id = tinyid().await;
 
-        let res = query!(
-            "INSERT INTO actions (id, type, guild_id, user_id, moderator_id, reason) VALUES ($1, 'unban', $2, $3, $4, $5)",
-            db_id,
-            msg.guild_id.map(|g| g.get() as i64).unwrap_or(0),
-            user.id.get() as i64,
-            msg.author.id.get() as i64,
-            reason.clone()
-        ).execute(&*SQL).await;
-
-        if let Err(err) = res {
-            warn!("Got error while unbanning; err = {err:?}");
-            return Err(CommandError {
-                title: String::from("Could not unban member"),
-                hint: Some(String::from("please try again later")),
-                arg: None,
-            });
-        }
-
-        if let Err(err) = ctx
-            .http
-            .as_ref()
-            .remove_ban(msg.guild_id.unwrap(), user.id, Some(&reason))
-            .await
-        {
-            warn!("Got error while unbanning; err = {err:?}");
-
-            if query!("DELETE FROM actions WHERE id = $1", db_id)
-                .execute(&*SQL)
-                .await
-                .is_err()
-            {
-                error!(
-                    "Got an error while unbanning and an error with the database! Stray unban entry in DB & manual action required; id = {db_id}; err = {err:?}"
-                );
-            }
-
-            return Err(CommandError {
-                title: String::from("Could not unban member"),
-                hint: Some(String::from(
-                    "check if the bot has the ban members permission or try again later",
-                )),
-                arg: None,
-            });
-        }
+        unban_user(
+            &ctx,
+            msg.guild_id.unwrap(),
+            user.id,
+            &db_id,
+            msg.author.id,
+            &reason,
+        )
+        .await?;
 
         let reply = CreateMessage::new()
             .add_embed(
@@ -218,3 +168,76 @@ impl Command for Unban {
         }
     }
 }
+
+/// Deactivates the active bans of a user, stores the unban and lifts the ban.
+/// The entry is rolled back if the ban could not be lifted.
+pub async fn unban_user(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+    db_id: &str,
+    moderator_id: UserId,
+    reason: &str,
+) -> Result<(), CommandError> {
+    let res = query!(
+        "UPDATE actions SET active = false, expires_at = NULL WHERE guild_id = $1 AND user_id = $2 AND type = 'ban' AND active = true;",
+        guild_id.get() as i64,
+        user_id.get() as i64,
+    ).execute(&*SQL).await;
+
+    if let Err(err) = res {
+        warn!("Got error while unbanning; err = {err:?}");
+        return Err(CommandError {
+            title: String::from("Could not unban member"),
+            hint: Some(String::from("please try again later")),
+            arg: None,
+        });
+    }
+
+    let res = query!(
+        "INSERT INTO actions (id, type, guild_id, user_id, moderator_id, reason) VALUES ($1, 'unban', $2, $3, $4, $5)",
+        db_id,
+        guild_id.get() as i64,
+        user_id.get() as i64,
+        moderator_id.get() as i64,
+        reason
+    ).execute(&*SQL).await;
+
+    if let Err(err) = res {
+        warn!("Got error while unbanning; err = {err:?}");
+        return Err(CommandError {
+            title: String::from("Could not unban member"),
+            hint: Some(String::from("please try again later")),
+            arg: None,
+        });
+    }
+
+    if let Err(err) = ctx
+        .http
+        .as_ref()
+        .remove_ban(guild_id, user_id, Some(reason))
+        .await
+    {
+        warn!("Got error while unbanning; err = {err:?}");
+
+        if query!("DELETE FROM actions WHERE id = $1", db_id)
+            .execute(&*SQL)
+            .await
+            .is_err()
+        {
+            error!(
+                "Got an error while unbanning and an error with the database! Stray unban entry in DB & manual action required; id = {db_id}; err = {err:?}"
+            );
+        }
+
+        return Err(CommandError {
+            title: String::from("Could not unban member"),
+            hint: Some(String::from(
+                "check if the bot has the ban members permission or try again later",
+            )),
+            arg: None,
+        });
+    }
+
+    Ok(())
+}
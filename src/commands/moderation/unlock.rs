@@ -0,0 +1,256 @@
+use std::sync::Arc;
+
+use ouroboros_macros::command;
+use serenity::{
+    all::{
+        CacheHttp, ChannelId, Context, CreateAllowedMentions, CreateEmbed, CreateMessage,
+        GuildChannel, GuildId, HttpError, Mentionable, Message, PermissionOverwrite,
+        PermissionOverwriteType, Permissions, RoleId,
+    },
+    async_trait,
+};
+use sqlx::query;
+use tracing::warn;
+
+use crate::{
+    SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc,
+    },
+    constants::BRAND_BLUE,
+    event_handler::CommandError,
+    lexer::Token,
+    transformers::Transformers,
+    utils::{LogType, can_use_in_channel, guild_log},
+};
+
+pub struct Unlock;
+
+impl Unlock {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for Unlock {
+    fn get_name(&self) -> &'static str {
+        "unlock"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Unlocks a locked channel"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Unlocks a channel locked by `lock` or `lockdown`, restoring the exact permissions @everyone had before. \
+        Defaults to the current channel."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::Channel("channel", false),
+            CommandSyntax::Reason("reason"),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Moderation
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::maybe_guild_channel] channel: Option<GuildChannel>,
+        #[transformers::consume] reason: Option<String>,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        if let Some(channel) = &channel
+            && channel.id != msg.channel_id
+        {
+            let allowed = match msg.member(&ctx).await {
+                Ok(member) => {
+                    can_use_in_channel(&ctx, &member, channel, Permissions::MANAGE_CHANNELS, self)
+                        .await
+                }
+                Err(_) => false,
+            };
+
+            if !allowed {
+                return Err(CommandError {
+                    title: String::from("You do not have permissions to execute this command."),
+                    hint: Some(format!(
+                        "you need the manage channels permission in {}",
+                        channel.mention()
+                    )),
+                    arg: None,
+                });
+            }
+        }
+
+        let channel_id = channel.map(|c| c.id).unwrap_or(msg.channel_id);
+
+        let mut reason = reason
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .unwrap_or(String::from("No reason provided"));
+
+        if reason.len() > 500 {
+            reason.truncate(500);
+            reason.push_str("...");
+        }
+
+        unlock_channel(&ctx, guild_id, channel_id).await?;
+
+        if channel_id != msg.channel_id {
+            let notice = CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .description(format!("**CHANNEL UNLOCKED**\n```\n{reason}\n```"))
+                    .color(BRAND_BLUE),
+            );
+
+            if let Err(err) = channel_id.send_message(&ctx, notice).await {
+                warn!("Could not send unlock notice; err = {err:?}");
+            }
+        }
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(format!(
+                        "**{} UNLOCKED**\n```\n{reason}\n```",
+                        channel_id.mention()
+                    ))
+                    .color(BRAND_BLUE),
+            )
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        if let Err(err) = msg.channel_id.send_message(&ctx, reply).await {
+            warn!("Could not send message; err = {err:?}");
+        }
+
+        guild_log(
+            &ctx,
+            LogType::ChannelModeration,
+            guild_id,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .description(format!(
+                        "**CHANNEL UNLOCKED**\n-# Actor: {} `{}` | Channel: {} `{}`\n```\n{reason}\n```",
+                        msg.author.mention(),
+                        msg.author.id.get(),
+                        channel_id.mention(),
+                        channel_id.get()
+                    ))
+                    .color(BRAND_BLUE),
+            ),
+        )
+        .await;
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::MANAGE_CHANNELS],
+            one_of: vec![],
+            bot: [
+                CommandPermissions::baseline().as_slice(),
+                &[Permissions::MANAGE_CHANNELS, Permissions::MANAGE_ROLES],
+            ]
+            .concat(),
+        }
+    }
+}
+
+/// Restores the `@everyone` overwrite a channel had before it was locked and removes the lock entry.
+/// Locks of channels which no longer exist are removed as well.
+pub async fn unlock_channel(
+    cache_http: impl CacheHttp,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> Result<(), CommandError> {
+    let lock = match query!(
+        "SELECT had_overwrite, previous_allow, previous_deny FROM channel_locks WHERE channel_id = $1 AND guild_id = $2 AND type = 'lock'",
+        channel_id.get() as i64,
+        guild_id.get() as i64
+    )
+    .fetch_optional(&*SQL)
+    .await
+    {
+        Ok(Some(l)) => l,
+        Ok(None) => {
+            return Err(CommandError {
+                title: String::from("This channel is not locked"),
+                hint: None,
+                arg: None,
+            });
+        }
+        Err(err) => {
+            warn!("Got error while unlocking channel; err = {err:?}");
+            return Err(CommandError {
+                title: String::from("Could not unlock channel"),
+                hint: Some(String::from("please try again later")),
+                arg: None,
+            });
+        }
+    };
+
+    let everyone = PermissionOverwriteType::Role(RoleId::new(guild_id.get()));
+
+    let res = if lock.had_overwrite {
+        channel_id
+            .create_permission(
+                cache_http.http(),
+                PermissionOverwrite {
+                    allow: Permissions::from_bits_truncate(lock.previous_allow as u64),
+                    deny: Permissions::from_bits_truncate(lock.previous_deny as u64),
+                    kind: everyone,
+                },
+            )
+            .await
+    } else {
+        channel_id
+            .delete_permission(cache_http.http(), everyone)
+            .await
+    };
+
+    if let Err(err) = res {
+        let deleted = matches!(
+            &err,
+            serenity::Error::Http(HttpError::UnsuccessfulRequest(e)) if e.status_code.as_u16() == 404
+        );
+
+        if !deleted {
+            warn!("Got error while unlocking channel; err = {err:?}");
+            return Err(CommandError {
+                title: String::from("Could not unlock channel"),
+                hint: Some(String::from(
+                    "check if the bot has the manage channels and manage roles permissions in this channel",
+                )),
+                arg: None,
+            });
+        }
+    }
+
+    if let Err(err) = query!(
+        "DELETE FROM channel_locks WHERE channel_id = $1 AND type = 'lock'",
+        channel_id.get() as i64
+    )
+    .execute(&*SQL)
+    .await
+    {
+        warn!("Got error while removing channel lock; err = {err:?}");
+    }
+
+    Ok(())
+}
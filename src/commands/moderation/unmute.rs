@@ -3,8 +3,8 @@ use std::{sync::Arc, time::Duration};
 use ouroboros_macros::command;
 use serenity::{
     all::{
-        Context, CreateAllowedMentions, CreateEmbed, CreateMessage, Mentionable, Message,
-        Permissions,
+        Context, CreateAllowedMentions, CreateEmbed, CreateMessage, Member, Mentionable,
+        Message, Permissions, UserId,
     },
     async_trait,
 };
@@ -78,7 +78,15 @@ impl Command for Unmute {
             });
         };
 
-        let res = can_target(&ctx, &author_member, &member, Permissions::MODERATE_MEMBERS).await;
+        let res = can_target(
+            &ctx,
+            &author_member,
+            &member,
+            Permissions::MODERATE_MEMBERS,
+            self,
+            msg.channel_id,
+        )
+        .await;
 
         if !res {
             return Err(CommandError {
@@ -107,62 +115,9 @@ impl Command for Unmute {
             reason.push_str("...");
         }
 
-        let res = query!(
-            "UPDATE actions SET active = false, expires_at = NULL WHERE guild_id = $1 AND user_id = $2 AND type = 'mute' AND active = true;",
-            msg.guild_id.map(|g| g.get() as i64).unwrap_or(0),
-            member.user.id.get() as i64,
-        ).execute(&*SQL).await;
-
-        if let Err(err) = res {
-            warn!("Got error while unmuting; err = {err:?}");
-            return Err(CommandError {
-                title: String::from("Could not unmute member"),
-                hint: Some(String::from("please try again later")),
-                arg: None,
-            });
-        }
-
         let db_id = tinyid().await;
 
-        let res = query!(
-            "INSERT INTO actions (id, type, guild_id, user_id, moderator_id, reason) VALUES ($1, 'unmute', $2, $3, $4, $5)",
-            db_id,
-            msg.guild_id.map(|g| g.get() as i64).unwrap_or(0),
-            member.user.id.get() as i64,
-            msg.author.id.get() as i64,
-            reason.clone()
-        ).execute(&*SQL).await;
-
-        if let Err(err) = res {
-            warn!("Got error while unmuting; err = {err:?}");
-            return Err(CommandError {
-                title: String::from("Could not unmute member"),
-                hint: Some(String::from("please try again later")),
-                arg: None,
-            });
-        }
-
-        if let Err(err) = member.enable_communication(&ctx).await {
-            warn!("Got error while unmuting; err = {err:?}");
-
-            if query!("DELETE FROM actions WHERE id = $1", db_id)
-                .execute(&*SQL)
-                .await
-                .is_err()
-            {
-                error!(
-                    "Got an error while unmuting and an error with the database! Stray unmute entry in DB & manual action required; id = {db_id}; err = {err:?}"
-                );
-            }
-
-            return Err(CommandError {
-                title: String::from("Could not unmute member"),
-                hint: Some(String::from(
-                    "check if the bot has the timeout members permission or try again later",
-                )),
-                arg: None,
-            });
-        }
+        unmute_member(&ctx, &mut member, &db_id, msg.author.id, &reason).await?;
 
         let reply = CreateMessage::new()
             .add_embed(
@@ -231,3 +186,70 @@ impl Command for Unmute {
         }
     }
 }
+
+/// Deactivates the active mutes of a member, stores the unmute and lifts the timeout.
+/// The entry is rolled back if the timeout could not be lifted.
+pub async fn unmute_member(
+    ctx: &Context,
+    member: &mut Member,
+    db_id: &str,
+    moderator_id: UserId,
+    reason: &str,
+) -> Result<(), CommandError> {
+    let res = query!(
+        "UPDATE actions SET active = false, expires_at = NULL WHERE guild_id = $1 AND user_id = $2 AND type = 'mute' AND active = true;",
+        member.guild_id.get() as i64,
+        member.user.id.get() as i64,
+    ).execute(&*SQL).await;
+
+    if let Err(err) = res {
+        warn!("Got error while unmuting; err = {err:?}");
+        return Err(CommandError {
+            title: String::from("Could not unmute member"),
+            hint: Some(String::from("please try again later")),
+            arg: None,
+        });
+    }
+
+    let res = query!(
+        "INSERT INTO actions (id, type, guild_id, user_id, moderator_id, reason) VALUES ($1, 'unmute', $2, $3, $4, $5)",
+        db_id,
+        member.guild_id.get() as i64,
+        member.user.id.get() as i64,
+        moderator_id.get() as i64,
+        reason
+    ).execute(&*SQL).await;
+
+    if let Err(err) = res {
+        warn!("Got error while unmuting; err = {err:?}");
+        return Err(CommandError {
+            title: String::from("Could not unmute member"),
+            hint: Some(String::from("please try again later")),
+            arg: None,
+        });
+    }
+
+    if let Err(err) = member.enable_communication(ctx).await {
+        warn!("Got error while unmuting; err = {err:?}");
+
+        if query!("DELETE FROM actions WHERE id = $1", db_id)
+            .execute(&*SQL)
+            .await
+            .is_err()
+        {
+            error!(
+                "Got an error while unmuting and an error with the database! Stray unmute entry in DB & manual action required; id = {db_id}; err = {err:?}"
+            );
+        }
+
+        return Err(CommandError {
+            title: String::from("Could not unmute member"),
+            hint: Some(String::from(
+                "check if the bot has the timeout members permission or try again later",
+            )),
+            arg: None,
+        });
+    }
+
+    Ok(())
+}
@@ -0,0 +1,300 @@
+use std::{sync::Arc, time::Duration};
+
+use ouroboros_macros::command;
+use serenity::{
+    all::{
+        Context, CreateAllowedMentions, CreateEmbed, CreateMessage, GuildId, HttpError,
+        Mentionable, Message, Permissions, RoleId, UserId,
+    },
+    async_trait,
+};
+use sqlx::query;
+use tokio::time::sleep;
+use tracing::{error, warn};
+
+use crate::{
+    SQL,
+    commands::{
+        Command, CommandArgument, CommandCategory, CommandParameter, CommandPermissions,
+        CommandSyntax, TransformerFnArc, restriction_role,
+    },
+    constants::BRAND_BLUE,
+    event_handler::CommandError,
+    lexer::{InferType, Token},
+    transformers::Transformers,
+    utils::{LogType, guild_log, tinyid},
+};
+
+pub struct Unrestrict;
+
+impl Unrestrict {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Command for Unrestrict {
+    fn get_name(&self) -> &'static str {
+        "unrestrict"
+    }
+
+    fn get_short(&self) -> &'static str {
+        "Lifts a restriction from a member"
+    }
+
+    fn get_full(&self) -> &'static str {
+        "Removes a restriction role from a member and ends the restriction, so it isn't re-applied if they rejoin."
+    }
+
+    fn get_syntax(&self) -> Vec<CommandSyntax> {
+        vec![
+            CommandSyntax::User("user", true),
+            CommandSyntax::String("restriction", true),
+            CommandSyntax::Reason("reason"),
+        ]
+    }
+
+    fn get_category(&self) -> CommandCategory {
+        CommandCategory::Moderation
+    }
+
+    fn get_params(&self) -> Vec<&'static CommandParameter<'static>> {
+        vec![]
+    }
+
+    #[command]
+    async fn run(
+        &self,
+        ctx: Context,
+        msg: Message,
+        #[transformers::reply_user] user: User,
+        #[transformers::string] restriction: String,
+        #[transformers::reply_consume] reason: Option<String>,
+    ) -> Result<(), CommandError> {
+        let guild_id = msg.guild_id.unwrap();
+
+        let (restriction, role_id) =
+            restriction_role(guild_id, &restriction)
+                .await
+                .map_err(|err| CommandError {
+                    arg: Some(args[1].clone()),
+                    ..err
+                })?;
+
+        let inferred = args
+            .first()
+            .map(|a| matches!(a.inferred, Some(InferType::Message)))
+            .unwrap_or(false);
+        let mut reason = reason
+            .map(|s| {
+                if s.is_empty() || s.chars().all(char::is_whitespace) {
+                    String::from("No reason provided")
+                } else {
+                    s
+                }
+            })
+            .unwrap_or(String::from("No reason provided"));
+
+        if reason.len() > 500 {
+            reason.truncate(500);
+            reason.push_str("...");
+        }
+
+        let db_id = tinyid().await;
+
+        unrestrict_user(
+            &ctx,
+            guild_id,
+            user.id,
+            role_id,
+            &db_id,
+            msg.author.id,
+            &reason,
+        )
+        .await?;
+
+        let reply = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(format!(
+                        "**{} UNRESTRICTED**\n-# Log ID: `{db_id}` | Restriction: {restriction}\n```\n{reason}\n```",
+                        user.mention()
+                    ))
+                    .color(BRAND_BLUE),
+            )
+            .reference_message(&msg)
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        let reply_msg = msg.channel_id.send_message(&ctx, reply).await;
+
+        guild_log(
+            &ctx,
+            LogType::MemberModeration,
+            guild_id,
+            CreateMessage::new()
+                .add_embed(
+                    CreateEmbed::new()
+                        .description(format!(
+                            "**MEMBER UNRESTRICTED**\n-# Log ID: `{db_id}` | Actor: {} `{}` | Target: {} `{}` | Restriction: {restriction} {}\n```\n{reason}\n```",
+                            msg.author.mention(),
+                            msg.author.id.get(),
+                            user.mention(),
+                            user.id.get(),
+                            role_id.mention()
+                        ))
+                        .color(BRAND_BLUE)
+                )
+        ).await;
+
+        let reply_msg = match reply_msg {
+            Ok(m) => m,
+            Err(err) => {
+                warn!("Could not send message; err = {err:?}");
+                return Ok(());
+            }
+        };
+
+        if inferred && let Some(reply) = msg.referenced_message.clone() {
+            let _ = reply.delete(&ctx).await;
+        }
+
+        if inferred {
+            tokio::spawn(async move {
+                sleep(Duration::from_secs(5)).await;
+                let _ = msg.delete(&ctx).await;
+                let _ = reply_msg.delete(&ctx).await;
+            });
+        }
+
+        Ok(())
+    }
+
+    fn get_permissions(&self) -> CommandPermissions {
+        CommandPermissions {
+            required: vec![Permissions::MODERATE_MEMBERS],
+            one_of: vec![],
+            bot: [
+                CommandPermissions::baseline().as_slice(),
+                &[Permissions::MANAGE_ROLES],
+            ]
+            .concat(),
+        }
+    }
+}
+
+/// Ends the active restrictions of a user with the given role, stores the unrestrict and removes the role.
+/// Users who left the server only have their restrictions ended. The entry is rolled back if the role could not be removed.
+pub async fn unrestrict_user(
+    ctx: &Context,
+    guild_id: GuildId,
+    user_id: UserId,
+    role_id: RoleId,
+    db_id: &str,
+    moderator_id: UserId,
+    reason: &str,
+) -> Result<(), CommandError> {
+    let res = query!(
+        "UPDATE actions SET active = false WHERE guild_id = $1 AND user_id = $2 AND role_id = $3 AND type = 'restrict' AND active = true RETURNING id",
+        guild_id.get() as i64,
+        user_id.get() as i64,
+        role_id.get() as i64,
+    ).fetch_all(&*SQL).await;
+
+    let deactivated = match res {
+        Ok(rows) => rows.into_iter().map(|r| r.id).collect::<Vec<_>>(),
+        Err(err) => {
+            warn!("Got error while unrestricting; err = {err:?}");
+            return Err(CommandError {
+                title: String::from("Could not unrestrict member"),
+                hint: Some(String::from("please try again later")),
+                arg: None,
+            });
+        }
+    };
+
+    if deactivated.is_empty() {
+        return Err(CommandError {
+            title: String::from("This member does not have this restriction"),
+            hint: None,
+            arg: None,
+        });
+    }
+
+    let res = query!(
+        "INSERT INTO actions (id, type, guild_id, user_id, moderator_id, reason, role_id) VALUES ($1, 'unrestrict', $2, $3, $4, $5, $6)",
+        db_id,
+        guild_id.get() as i64,
+        user_id.get() as i64,
+        moderator_id.get() as i64,
+        reason,
+        role_id.get() as i64,
+    ).execute(&*SQL).await;
+
+    if let Err(err) = res {
+        warn!("Got error while unrestricting; err = {err:?}");
+
+        if query!(
+            "UPDATE actions SET active = true WHERE id = ANY($1)",
+            &deactivated
+        )
+        .execute(&*SQL)
+        .await
+        .is_err()
+        {
+            error!(
+                "Got an error while unrestricting and could not re-activate the restrictions! Manual action required; ids = {deactivated:?}; err = {err:?}"
+            );
+        }
+
+        return Err(CommandError {
+            title: String::from("Could not unrestrict member"),
+            hint: Some(String::from("please try again later")),
+            arg: None,
+        });
+    }
+
+    if let Err(err) = ctx
+        .http
+        .remove_member_role(guild_id, user_id, role_id, Some(reason))
+        .await
+    {
+        let left = matches!(
+            &err,
+            serenity::Error::Http(HttpError::UnsuccessfulRequest(e)) if e.status_code.as_u16() == 404
+        );
+
+        if !left {
+            warn!("Got error while unrestricting; err = {err:?}");
+
+            let rollback = async {
+                query!(
+                    "UPDATE actions SET active = true WHERE id = ANY($1)",
+                    &deactivated
+                )
+                .execute(&*SQL)
+                .await?;
+
+                query!("DELETE FROM actions WHERE id = $1", db_id)
+                    .execute(&*SQL)
+                    .await
+            };
+
+            if rollback.await.is_err() {
+                error!(
+                    "Got an error while unrestricting and an error with the database! Stray unrestrict entry in DB & manual action required; id = {db_id}; err = {err:?}"
+                );
+            }
+
+            return Err(CommandError {
+                title: String::from("Could not unrestrict member"),
+                hint: Some(String::from(
+                    "check if the bot has the manage roles permission and its role is above the restriction role",
+                )),
+                arg: None,
+            });
+        }
+    }
+
+    Ok(())
+}
@@ -14,10 +14,14 @@ use crate::{
         CommandSyntax, TransformerFnArc,
     },
     constants::BRAND_BLUE,
+    database::ActionType,
     event_handler::CommandError,
     lexer::{InferType, Token},
     transformers::Transformers,
-    utils::{CommandMessageResponse, LogType, can_target, guild_log, tinyid},
+    utils::{
+        CommandMessageResponse, LogType, TemplateValues, can_target, escalate, get_guild_option,
+        guild_log, guild_templates, tinyid,
+    },
 };
 use ouroboros_macros::command;
 
@@ -79,7 +83,15 @@ impl Command for Warn {
             });
         };
 
-        let res = can_target(&ctx, &author_member, &member, Permissions::MODERATE_MEMBERS).await;
+        let res = can_target(
+            &ctx,
+            &author_member,
+            &member,
+            Permissions::MODERATE_MEMBERS,
+            self,
+            msg.channel_id,
+        )
+        .await;
 
         if !res {
             return Err(CommandError {
@@ -144,21 +156,27 @@ impl Command for Warn {
             }
         };
 
-        let static_response_parts = (
-            format!("**{} WARNED**\n-# Log ID: `{db_id}`", member.mention()),
-            format!("\n```\n{reason}\n```"),
-        );
+        let (dm_template, response_template) =
+            guild_templates(member.guild_id, &ActionType::Warn).await;
+        let values = TemplateValues {
+            server: guild_name,
+            reason: reason.clone(),
+            log_id: db_id.clone(),
+            moderator: msg.author.mention().to_string(),
+            user: member.mention().to_string(),
+            ..Default::default()
+        };
+
+        let dm_members = get_guild_option(member.guild_id, "moderation.dm_members")
+            .await
+            .as_bool()
+            .unwrap_or(true);
 
         let mut cmd_response = CommandMessageResponse::new(member.user.id)
-            .dm_content(format!(
-                "**WARNED**\n-# Server: {}\n```\n{}\n```",
-                guild_name, reason
-            ))
-            .server_content(Box::new(move |a| {
-                format!("{}{a}{}", static_response_parts.0, static_response_parts.1)
-            }))
+            .dm_content(values.render(&dm_template))
+            .server_content(values.render_response(&response_template, String::new()))
             .automatically_delete(inferred)
-            .mark_silent(params.contains_key("silent"));
+            .mark_silent(params.contains_key("silent") || !dm_members);
 
         cmd_response.send_dm(&ctx).await;
         cmd_response.send_response(&ctx, &msg).await;
@@ -181,6 +199,8 @@ impl Command for Warn {
                 )
         ).await;
 
+        escalate(&ctx, &msg, &member, &db_id, params.contains_key("silent")).await?;
+
         Ok(())
     }
 
@@ -23,5 +23,10 @@ pub struct Environment {
     pub whitelist: Option<Vec<u64>>,
     pub repository: Option<String>,
     pub github_token: Option<String>,
-    pub webhook: Option<String>
+    pub webhook: Option<String>,
+    pub persist_message_cache: Option<bool>,
+    pub message_cache_retention_days: Option<u32>,
+    pub attachment_archive_dir: Option<String>,
+    pub attachment_archive_max_mb: Option<u64>,
+    pub attachment_archive_ttl_hours: Option<u64>,
 }
@@ -1,9 +1,11 @@
-use sqlx::query;
+use std::{fmt::Display, time::Instant};
+
+use sqlx::{Acquire, PgConnection, migrate::Migration, query, query_as};
 use tracing::info;
 
 use crate::SQL;
 
-#[derive(Debug, sqlx::Type, Clone)]
+#[derive(Debug, sqlx::Type, Clone, PartialEq, Eq)]
 #[sqlx(type_name = "action_type", rename_all = "lowercase")]
 pub enum ActionType {
     Warn,
@@ -13,6 +15,44 @@ pub enum ActionType {
     Mute,
     Unban,
     Unmute,
+    /// A restriction role, i.e. a mute or media ban role
+    Restrict,
+    Unrestrict,
+}
+
+impl ActionType {
+    pub fn all() -> Vec<ActionType> {
+        vec![
+            ActionType::Warn,
+            ActionType::Kick,
+            ActionType::Ban,
+            ActionType::Softban,
+            ActionType::Mute,
+            ActionType::Unban,
+            ActionType::Unmute,
+            ActionType::Restrict,
+            ActionType::Unrestrict,
+        ]
+    }
+}
+
+/// The state of an appeal, appeals can only be resolved once
+#[derive(Debug, sqlx::Type, Clone, PartialEq, Eq)]
+#[sqlx(type_name = "appeal_status", rename_all = "lowercase")]
+pub enum AppealStatus {
+    Pending,
+    Accepted,
+    Denied,
+}
+
+/// What a channel lock entry restores once it is lifted
+#[derive(Debug, sqlx::Type, Clone, Copy, PartialEq, Eq)]
+#[sqlx(type_name = "channel_lock_type", rename_all = "lowercase")]
+pub enum ChannelLockType {
+    /// The `@everyone` permission overwrite
+    Lock,
+    /// The slowmode interval
+    Slowmode,
 }
 
 impl std::fmt::Display for ActionType {
@@ -25,211 +65,606 @@ impl std::fmt::Display for ActionType {
             ActionType::Mute => write!(f, "mute"),
             ActionType::Unban => write!(f, "unban"),
             ActionType::Unmute => write!(f, "unmute"),
+            ActionType::Restrict => write!(f, "restrict"),
+            ActionType::Unrestrict => write!(f, "unrestrict"),
         }
     }
 }
 
-pub async fn run_migrations() {
-    info!("Running database migrations");
-
-    create_action_type_201420250826().await;
-    create_actions_223320250818().await;
-    create_guild_settings_195120250826().await;
-    add_log_bot_to_guild_settings_220420250829().await;
-    add_log_mod_to_guild_settings_021020250918().await;
-    remove_log_mod_and_change_channel_id_to_jsonb_150020250921().await;
-    add_message_cache_store_133120250922().await;
-    add_last_reapplied_at_to_actions_160120250923().await;
-    migrate_log_types_231320251115().await;
+/// A schema migration, applied migrations are recorded in `schema_migrations` together with a checksum of their sql.
+/// Never edit the sql of a migration once it has been released, add a new migration instead.
+struct SchemaMigration {
+    name: &'static str,
+    sql: &'static str,
 }
 
-pub async fn create_actions_223320250818() {
-    if let Err(err) = query!(
-        r#"
-        CREATE TABLE IF NOT EXISTS public.actions
-        (
-            guild_id bigint NOT NULL,
-            user_id bigint NOT NULL,
-            reason text COLLATE pg_catalog."default" NOT NULL,
-            moderator_id bigint NOT NULL,
-            created_at timestamp without time zone NOT NULL DEFAULT now(),
-            updated_at timestamp without time zone,
-            id character varying(128) COLLATE pg_catalog."default" NOT NULL,
-            type action_type NOT NULL DEFAULT 'warn'::action_type,
-            active boolean NOT NULL DEFAULT true,
-            expires_at timestamp without time zone,
-            CONSTRAINT warns_pkey PRIMARY KEY (id)
-        )
-        "#
-    )
-    .execute(&*SQL)
-    .await
-    {
-        panic!("Couldnt run database migration create_actions_223320250818; Err = {err:?}");
-    }
+/// All migrations in the order they get applied. The position in this list is the migration version.
+const MIGRATIONS: &[SchemaMigration] = &[
+    SchemaMigration {
+        name: "create_action_type_201420250826",
+        sql: r#"
+            DO $$
+            BEGIN
+                IF NOT EXISTS (
+                    SELECT 1
+                    FROM pg_type t
+                    JOIN pg_namespace n ON n.oid = t.typnamespace
+                    WHERE t.typname = 'action_type'
+                    AND n.nspname = 'public'
+                ) THEN
+                    CREATE TYPE public.action_type AS ENUM
+                        ('warn', 'ban', 'kick', 'softban', 'timeout', 'unban', 'mute', 'unmute');
+                END IF;
+            END$$;
+            "#,
+    },
+    SchemaMigration {
+        name: "create_actions_223320250818",
+        sql: r#"
+            CREATE TABLE IF NOT EXISTS public.actions
+            (
+                guild_id bigint NOT NULL,
+                user_id bigint NOT NULL,
+                reason text COLLATE pg_catalog."default" NOT NULL,
+                moderator_id bigint NOT NULL,
+                created_at timestamp without time zone NOT NULL DEFAULT now(),
+                updated_at timestamp without time zone,
+                id character varying(128) COLLATE pg_catalog."default" NOT NULL,
+                type action_type NOT NULL DEFAULT 'warn'::action_type,
+                active boolean NOT NULL DEFAULT true,
+                expires_at timestamp without time zone,
+                CONSTRAINT warns_pkey PRIMARY KEY (id)
+            )
+            "#,
+    },
+    SchemaMigration {
+        name: "create_guild_settings_195120250826",
+        sql: r#"
+            CREATE TABLE IF NOT EXISTS public.guild_settings
+            (
+                guild_id bigint NOT NULL PRIMARY KEY,
+                log_channel bigint
+            )
+            "#,
+    },
+    SchemaMigration {
+        name: "add_log_bot_to_guild_settings_220420250829",
+        sql: r#"
+            ALTER TABLE public.guild_settings
+            ADD COLUMN IF NOT EXISTS log_bot BOOLEAN
+            "#,
+    },
+    SchemaMigration {
+        name: "add_log_mod_to_guild_settings_021020250918",
+        sql: r#"
+            ALTER TABLE public.guild_settings
+            ADD COLUMN IF NOT EXISTS log_mod bigint
+            "#,
+    },
+    SchemaMigration {
+        name: "remove_log_mod_and_change_channel_id_to_jsonb_150020250921",
+        sql: r#"
+            ALTER TABLE public.guild_settings
+            DROP COLUMN IF EXISTS log_mod,
+            DROP COLUMN IF EXISTS log_channel,
+            ADD COLUMN IF NOT EXISTS log_channel_ids jsonb
+            "#,
+    },
+    SchemaMigration {
+        name: "add_message_cache_store_133120250922",
+        sql: r#"
+            CREATE TABLE IF NOT EXISTS public.message_cache_store
+            (
+                channel_id bigint NOT NULL,
+                message_count integer NOT NULL DEFAULT 0,
+                previous_action smallint NOT NULL DEFAULT 0 CHECK (previous_action BETWEEN -1 AND 1),
+                PRIMARY KEY (channel_id)
+            );
+            "#,
+    },
+    SchemaMigration {
+        name: "add_last_reapplied_at_to_actions_160120250923",
+        sql: r#"
+            ALTER TABLE public.actions
+            ADD COLUMN IF NOT EXISTS last_reapplied_at timestamp with time zone;
+            "#,
+    },
+    SchemaMigration {
+        name: "migrate_log_types_231320251115",
+        sql: r#"
+            UPDATE public.guild_settings
+            SET log_channel_ids = (
+                SELECT jsonb_object_agg(
+                    CASE
+                        WHEN key='member_ban' THEN 'member_moderation'
+                        WHEN key='member_unban' THEN 'member_moderation'
+                        WHEN key='member_cache' THEN 'member_update'
+                        WHEN key='member_kick' THEN 'member_moderation'
+                        WHEN key='member_mute' THEN 'member_moderation'
+                        WHEN key='member_unmute' THEN 'member_moderation'
+                        WHEN key='member_warn' THEN 'member_moderation'
+                        WHEN key='member_softban' THEN 'member_moderation'
+                        WHEN key='member_update' THEN 'member_update'
+                        WHEN key='action_update' THEN 'action_update'
+                        WHEN key='message_delete' THEN 'message_update'
+                        WHEN key='message_edit' THEN 'message_update'
+                        ELSE key
+                    END,
+                    value
+                )
+                FROM jsonb_each(log_channel_ids)
+            )
+            WHERE log_channel_ids IS NOT NULL;
+            "#,
+    },
+    SchemaMigration {
+        name: "add_prefix_and_aliases_to_guild_settings_034020261018",
+        sql: r#"
+            ALTER TABLE public.guild_settings
+            ADD COLUMN IF NOT EXISTS prefix text,
+            ADD COLUMN IF NOT EXISTS aliases jsonb;
+            "#,
+    },
+    SchemaMigration {
+        name: "add_escalation_policies_041020261018",
+        sql: r#"
+            ALTER TABLE public.guild_settings
+            ADD COLUMN IF NOT EXISTS escalation_policies jsonb;
+
+            ALTER TABLE public.actions
+            ADD COLUMN IF NOT EXISTS triggered_by character varying(128)
+                REFERENCES public.actions (id) ON DELETE SET NULL;
+            "#,
+    },
+    SchemaMigration {
+        name: "create_action_notes_043020261018",
+        sql: r#"
+            CREATE TABLE IF NOT EXISTS public.action_notes
+            (
+                id bigserial NOT NULL,
+                action_id character varying(128) COLLATE pg_catalog."default" NOT NULL,
+                guild_id bigint NOT NULL,
+                moderator_id bigint NOT NULL,
+                content text COLLATE pg_catalog."default" NOT NULL,
+                created_at timestamp without time zone NOT NULL DEFAULT now(),
+                CONSTRAINT action_notes_pkey PRIMARY KEY (id),
+                CONSTRAINT action_notes_action_id_fkey FOREIGN KEY (action_id)
+                    REFERENCES public.actions (id) ON DELETE CASCADE
+            );
+
+            CREATE INDEX IF NOT EXISTS action_notes_action_id_idx
+                ON public.action_notes (action_id);
+
+            CREATE TABLE IF NOT EXISTS public.action_note_attachments
+            (
+                id bigserial NOT NULL,
+                note_id bigint NOT NULL,
+                filename text COLLATE pg_catalog."default" NOT NULL,
+                content_type text COLLATE pg_catalog."default",
+                data bytea NOT NULL,
+                CONSTRAINT action_note_attachments_pkey PRIMARY KEY (id),
+                CONSTRAINT action_note_attachments_note_id_fkey FOREIGN KEY (note_id)
+                    REFERENCES public.action_notes (id) ON DELETE CASCADE
+            );
+
+            CREATE INDEX IF NOT EXISTS action_note_attachments_note_id_idx
+                ON public.action_note_attachments (note_id);
+            "#,
+    },
+    SchemaMigration {
+        name: "add_reason_search_index_to_actions_050020261018",
+        sql: r#"
+            CREATE INDEX IF NOT EXISTS actions_reason_search_idx
+                ON public.actions USING GIN (to_tsvector('english', reason));
+            "#,
+    },
+    SchemaMigration {
+        name: "create_appeals_053020261018",
+        sql: r#"
+            CREATE TYPE public.appeal_status AS ENUM ('pending', 'accepted', 'denied');
+
+            CREATE TABLE IF NOT EXISTS public.appeals
+            (
+                id character varying(128) COLLATE pg_catalog."default" NOT NULL,
+                action_id character varying(128) COLLATE pg_catalog."default" NOT NULL,
+                guild_id bigint NOT NULL,
+                user_id bigint NOT NULL,
+                content text COLLATE pg_catalog."default" NOT NULL,
+                status appeal_status NOT NULL DEFAULT 'pending'::appeal_status,
+                resolved_by bigint,
+                created_at timestamp without time zone NOT NULL DEFAULT now(),
+                resolved_at timestamp without time zone,
+                CONSTRAINT appeals_pkey PRIMARY KEY (id),
+                CONSTRAINT appeals_action_id_key UNIQUE (action_id),
+                CONSTRAINT appeals_action_id_fkey FOREIGN KEY (action_id)
+                    REFERENCES public.actions (id) ON DELETE CASCADE
+            );
+            "#,
+    },
+    SchemaMigration {
+        name: "add_batch_id_to_actions_060020261018",
+        sql: r#"
+            ALTER TABLE public.actions
+                ADD COLUMN IF NOT EXISTS batch_id character varying(128) COLLATE pg_catalog."default";
+
+            CREATE INDEX IF NOT EXISTS actions_batch_id_idx
+                ON public.actions (batch_id)
+                WHERE batch_id IS NOT NULL;
+            "#,
+    },
+    SchemaMigration {
+        name: "add_anti_raid_to_guild_settings_063020261018",
+        sql: r#"
+            ALTER TABLE public.guild_settings
+                ADD COLUMN IF NOT EXISTS anti_raid jsonb;
+            "#,
+    },
+    SchemaMigration {
+        name: "create_channel_locks_070020261018",
+        sql: r#"
+            CREATE TYPE public.channel_lock_type AS ENUM ('lock', 'slowmode');
+
+            CREATE TABLE IF NOT EXISTS public.channel_locks
+            (
+                channel_id bigint NOT NULL,
+                type channel_lock_type NOT NULL,
+                guild_id bigint NOT NULL,
+                moderator_id bigint NOT NULL,
+                reason text COLLATE pg_catalog."default" NOT NULL,
+                lockdown boolean NOT NULL DEFAULT false,
+                had_overwrite boolean NOT NULL DEFAULT false,
+                previous_allow bigint NOT NULL DEFAULT 0,
+                previous_deny bigint NOT NULL DEFAULT 0,
+                previous_slowmode integer NOT NULL DEFAULT 0,
+                created_at timestamp without time zone NOT NULL DEFAULT now(),
+                expires_at timestamp without time zone,
+                CONSTRAINT channel_locks_pkey PRIMARY KEY (channel_id, type)
+            );
+
+            ALTER TABLE public.guild_settings
+                ADD COLUMN IF NOT EXISTS lockdown_channels bigint[];
+            "#,
+    },
+    SchemaMigration {
+        name: "add_restrictions_073020261018",
+        sql: r#"
+            ALTER TYPE public.action_type ADD VALUE IF NOT EXISTS 'restrict';
+            ALTER TYPE public.action_type ADD VALUE IF NOT EXISTS 'unrestrict';
+
+            ALTER TABLE public.actions
+                ADD COLUMN IF NOT EXISTS role_id bigint;
+
+            ALTER TABLE public.guild_settings
+                ADD COLUMN IF NOT EXISTS restriction_roles jsonb;
+            "#,
+    },
+    SchemaMigration {
+        name: "add_message_cache_snapshot_080020261018",
+        sql: r#"
+            CREATE TABLE IF NOT EXISTS public.message_cache_snapshot
+            (
+                message_id bigint NOT NULL,
+                channel_id bigint NOT NULL,
+                guild_id bigint NOT NULL,
+                author_id bigint NOT NULL,
+                author_name text NOT NULL,
+                author_bot boolean NOT NULL DEFAULT false,
+                content text NOT NULL,
+                attachments jsonb NOT NULL DEFAULT '[]'::jsonb,
+                reply_to bigint,
+                PRIMARY KEY (message_id)
+            );
+
+            CREATE INDEX IF NOT EXISTS message_cache_snapshot_guild_id_idx
+                ON public.message_cache_snapshot (guild_id);
+
+            ALTER TABLE public.guild_settings
+                ADD COLUMN IF NOT EXISTS message_cache_opt_out boolean NOT NULL DEFAULT false;
+            "#,
+    },
+    SchemaMigration {
+        name: "add_archive_channels_083020261018",
+        sql: r#"
+            ALTER TABLE public.guild_settings
+                ADD COLUMN IF NOT EXISTS archive_channels bigint[];
+            "#,
+    },
+    SchemaMigration {
+        name: "add_message_templates_090020261018",
+        sql: r#"
+            ALTER TABLE public.guild_settings
+                ADD COLUMN IF NOT EXISTS message_templates jsonb;
+            "#,
+    },
+    SchemaMigration {
+        name: "add_guild_options_093020261018",
+        sql: r#"
+            ALTER TABLE public.guild_settings
+                ADD COLUMN IF NOT EXISTS options jsonb NOT NULL DEFAULT '{}'::jsonb;
+            "#,
+    },
+    SchemaMigration {
+        name: "add_note_attachment_urls_103020261018",
+        sql: r#"
+            ALTER TABLE public.action_note_attachments
+                ADD COLUMN IF NOT EXISTS url text COLLATE pg_catalog."default";
+            "#,
+    },
+    SchemaMigration {
+        name: "create_raid_verification_levels_110020261018",
+        sql: r#"
+            CREATE TABLE IF NOT EXISTS public.raid_verification_levels
+            (
+                guild_id bigint NOT NULL,
+                raid_id text COLLATE pg_catalog."default" NOT NULL,
+                verification_level smallint NOT NULL,
+                CONSTRAINT raid_verification_levels_pkey PRIMARY KEY (guild_id)
+            );
+            "#,
+    },
+];
+
+/// Arbitrary key for the advisory lock held while migrating, prevents two instances migrating at once
+const MIGRATION_LOCK_KEY: i64 = 0x6f75726f626f726f;
+
+#[derive(Debug)]
+pub enum MigrationError {
+    Database(sqlx::Error),
+    /// Applied migrations whose sql no longer matches the sql in the binary
+    ChecksumDrift(Vec<String>),
+    /// Applied migrations which this binary does not know about (the database is newer than the binary)
+    Unknown(Vec<String>),
+    Failed(String, sqlx::Error),
 }
 
-pub async fn create_guild_settings_195120250826() {
-    if let Err(err) = query!(
-        r#"
-        CREATE TABLE IF NOT EXISTS public.guild_settings
-        (
-            guild_id bigint NOT NULL PRIMARY KEY,
-            log_channel bigint
-        )
-        "#
-    )
-    .execute(&*SQL)
-    .await
-    {
-        panic!("Couldnt run database migration create_guild_settings_195120250826; Err = {err:?}");
+impl Display for MigrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Database(err) => write!(f, "could not query migration state; err = {err:?}"),
+            Self::ChecksumDrift(names) => write!(
+                f,
+                "applied migrations were changed after being applied: {}",
+                names.join(", ")
+            ),
+            Self::Unknown(names) => write!(
+                f,
+                "database has migrations applied which this binary does not know about: {}",
+                names.join(", ")
+            ),
+            Self::Failed(name, err) => {
+                write!(f, "migration {name} failed and was rolled back; err = {err:?}")
+            }
+        }
     }
 }
 
-pub async fn create_action_type_201420250826() {
-    if let Err(err) = query!(
-        r#"
-        DO $$
-        BEGIN
-            IF NOT EXISTS (
-                SELECT 1
-                FROM pg_type t
-                JOIN pg_namespace n ON n.oid = t.typnamespace
-                WHERE t.typname = 'action_type'
-                AND n.nspname = 'public'
-            ) THEN
-                CREATE TYPE public.action_type AS ENUM
-                    ('warn', 'ban', 'kick', 'softban', 'timeout', 'unban', 'mute', 'unmute');
-            END IF;
-        END$$;
-        "#
-    )
-    .execute(&*SQL)
-    .await
-    {
-        panic!("Couldnt run database migration create_guild_settings_195120250826; Err = {err:?}");
+impl std::error::Error for MigrationError {}
+
+impl From<sqlx::Error> for MigrationError {
+    fn from(value: sqlx::Error) -> Self {
+        Self::Database(value)
     }
 }
 
-pub async fn add_log_bot_to_guild_settings_220420250829() {
-    if let Err(err) = query!(
-        r#"
-        ALTER TABLE public.guild_settings
-        ADD COLUMN IF NOT EXISTS log_bot BOOLEAN
-        "#
-    )
-    .execute(&*SQL)
-    .await
-    {
-        panic!(
-            "Couldnt run database migration add_log_bot_to_guild_settings_220420250829; Err = {err:?}"
-        );
-    }
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MigrationState {
+    Applied(chrono::DateTime<chrono::Utc>),
+    Pending,
+    ChecksumDrift,
+    /// Applied but missing from this binary
+    Unknown,
 }
 
-pub async fn add_log_mod_to_guild_settings_021020250918() {
-    if let Err(err) = query!(
-        r#"
-        ALTER TABLE public.guild_settings
-        ADD COLUMN IF NOT EXISTS log_mod bigint
-        "#
-    )
-    .execute(&*SQL)
-    .await
-    {
-        panic!(
-            "Couldnt run database migration add_log_mod_to_guild_settings_021020250918; Err = {err:?}"
-        );
-    }
+#[derive(Debug)]
+struct AppliedMigrationRow {
+    version: i64,
+    name: String,
+    checksum: Vec<u8>,
+    applied_at: chrono::DateTime<chrono::Utc>,
 }
 
-pub async fn remove_log_mod_and_change_channel_id_to_jsonb_150020250921() {
-    if let Err(err) = query!(
-        r#"
-        ALTER TABLE public.guild_settings
-        DROP COLUMN IF EXISTS log_mod,
-        DROP COLUMN IF EXISTS log_channel,
-        ADD COLUMN IF NOT EXISTS log_channel_ids jsonb
-        "#
+fn checksum(migration: &SchemaMigration) -> Vec<u8> {
+    Migration::new(
+        0,
+        migration.name.into(),
+        sqlx::migrate::MigrationType::Simple,
+        migration.sql.into(),
+        false,
     )
-    .execute(&*SQL)
-    .await
-    {
-        panic!(
-            "Couldnt run database migration remove_log_mod_and_change_channel_id_to_jsonb_150020250921; Err = {err:?}"
-        );
-    }
+    .checksum
+    .into_owned()
 }
 
-pub async fn add_message_cache_store_133120250922() {
-    if let Err(err) = query!(
+async fn create_schema_migrations(conn: &mut PgConnection) -> Result<(), sqlx::Error> {
+    query!(
         r#"
-        CREATE TABLE IF NOT EXISTS public.message_cache_store
+        CREATE TABLE IF NOT EXISTS public.schema_migrations
         (
-            channel_id bigint NOT NULL,
-            message_count integer NOT NULL DEFAULT 0,
-            previous_action smallint NOT NULL DEFAULT 0 CHECK (previous_action BETWEEN -1 AND 1),
-            PRIMARY KEY (channel_id)
-        );
+            version bigint NOT NULL PRIMARY KEY,
+            name text NOT NULL,
+            checksum bytea NOT NULL,
+            applied_at timestamp with time zone NOT NULL DEFAULT now(),
+            execution_ms bigint NOT NULL
+        )
         "#
     )
-    .execute(&*SQL)
-    .await
-    {
-        panic!(
-            "Couldnt run database migration add_message_cache_store_133120250922; Err = {err:?}"
-        );
-    }
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
 }
 
-pub async fn add_last_reapplied_at_to_actions_160120250923() {
-    if let Err(err) = query!(
-        r#"
-        ALTER TABLE public.actions
-        ADD COLUMN IF NOT EXISTS last_reapplied_at timestamp with time zone;
-        "#
+/// The applied migrations, none if the table doesn't exist yet so looking at the status doesn't change the database
+async fn applied_migrations(
+    conn: &mut PgConnection,
+) -> Result<Vec<AppliedMigrationRow>, sqlx::Error> {
+    let exists =
+        query!(r#"SELECT to_regclass('public.schema_migrations') IS NOT NULL AS "exists!""#)
+            .fetch_one(&mut *conn)
+            .await?
+            .exists;
+
+    if !exists {
+        return Ok(vec![]);
+    }
+
+    query_as!(
+        AppliedMigrationRow,
+        "SELECT version, name, checksum, applied_at FROM schema_migrations ORDER BY version"
     )
-    .execute(&*SQL)
+    .fetch_all(&mut *conn)
     .await
-    {
-        panic!(
-            "Couldnt run database migration add_last_reapplied_at_to_actions_160120250923; Err = {err:?}"
-        );
+}
+
+/// Returns the state of every known and applied migration, by version.
+async fn migration_status(
+    conn: &mut PgConnection,
+) -> Result<Vec<(i64, String, MigrationState)>, MigrationError> {
+    let applied = applied_migrations(conn).await?;
+
+    let mut result = MIGRATIONS
+        .iter()
+        .enumerate()
+        .map(|(i, migration)| {
+            let version = i as i64 + 1;
+            let state = match applied.iter().find(|a| a.version == version) {
+                Some(a) if a.checksum != checksum(migration) || a.name != migration.name => {
+                    MigrationState::ChecksumDrift
+                }
+                Some(a) => MigrationState::Applied(a.applied_at),
+                None => MigrationState::Pending,
+            };
+
+            (version, migration.name.to_string(), state)
+        })
+        .collect::<Vec<_>>();
+
+    for a in applied {
+        if a.version > MIGRATIONS.len() as i64 {
+            result.push((a.version, a.name, MigrationState::Unknown));
+        }
     }
+
+    Ok(result)
 }
 
-pub async fn migrate_log_types_231320251115() {
-    let r = query!(
-        r#"
-        UPDATE public.guild_settings
-        SET log_channel_ids = (
-            SELECT jsonb_object_agg(
-                CASE
-                    WHEN key='member_ban' THEN 'member_moderation'
-                    WHEN key='member_unban' THEN 'member_moderation'
-                    WHEN key='member_cache' THEN 'member_update'
-                    WHEN key='member_kick' THEN 'member_moderation'
-                    WHEN key='member_mute' THEN 'member_moderation'
-                    WHEN key='member_unmute' THEN 'member_moderation'
-                    WHEN key='member_warn' THEN 'member_moderation'
-                    WHEN key='member_softban' THEN 'member_moderation'
-                    WHEN key='member_update' THEN 'member_update'
-                    WHEN key='action_update' THEN 'action_update'
-                    WHEN key='message_delete' THEN 'message_update'
-                    WHEN key='message_edit' THEN 'message_update'
-                    ELSE key
-                END,
-                value
-            )
-            FROM jsonb_each(log_channel_ids)
+/// Applies all pending migrations, each in its own transaction. Returns the amount of applied migrations.
+/// Refuses to migrate if an applied migration was changed or the database is ahead of this binary.
+pub async fn run_migrations() -> Result<usize, MigrationError> {
+    info!("Running database migrations");
+
+    // everything runs on the connection holding the lock, so a pool with a single connection can't deadlock
+    let mut conn = SQL.acquire().await?;
+
+    query!("SELECT pg_advisory_lock($1)", MIGRATION_LOCK_KEY)
+        .execute(&mut *conn)
+        .await?;
+
+    let result = apply_pending(&mut conn).await;
+
+    query!("SELECT pg_advisory_unlock($1)", MIGRATION_LOCK_KEY)
+        .fetch_one(&mut *conn)
+        .await?;
+
+    result
+}
+
+async fn apply_pending(conn: &mut PgConnection) -> Result<usize, MigrationError> {
+    create_schema_migrations(conn).await?;
+    let status = migration_status(conn).await?;
+
+    let drifted = status
+        .iter()
+        .filter(|(_, _, s)| *s == MigrationState::ChecksumDrift)
+        .map(|(_, n, _)| n.clone())
+        .collect::<Vec<_>>();
+
+    if !drifted.is_empty() {
+        return Err(MigrationError::ChecksumDrift(drifted));
+    }
+
+    let unknown = status
+        .iter()
+        .filter(|(_, _, s)| *s == MigrationState::Unknown)
+        .map(|(_, n, _)| n.clone())
+        .collect::<Vec<_>>();
+
+    if !unknown.is_empty() {
+        return Err(MigrationError::Unknown(unknown));
+    }
+
+    let mut applied = 0;
+
+    for (version, name, state) in status {
+        if state != MigrationState::Pending {
+            continue;
+        }
+
+        let migration = &MIGRATIONS[version as usize - 1];
+        info!("Applying migration {version} {name}");
+
+        let start = Instant::now();
+        let mut tx = conn.begin().await?;
+
+        if let Err(err) = sqlx::raw_sql(migration.sql).execute(&mut *tx).await {
+            return Err(MigrationError::Failed(name, err));
+        }
+
+        query!(
+            "INSERT INTO schema_migrations (version, name, checksum, execution_ms) VALUES ($1, $2, $3, $4)",
+            version,
+            migration.name,
+            checksum(migration),
+            start.elapsed().as_millis() as i64
         )
-        WHERE log_channel_ids IS NOT NULL;
-        "#
-    )
-    .execute(&*SQL)
-    .await;
+        .execute(&mut *tx)
+        .await
+        .map_err(|err| MigrationError::Failed(name.clone(), err))?;
+
+        tx.commit()
+            .await
+            .map_err(|err| MigrationError::Failed(name.clone(), err))?;
+
+        applied += 1;
+    }
+
+    Ok(applied)
+}
+
+/// Prints the state of all migrations, used by the `--migrate-status` cli flag.
+/// Returns false if the database is in a state the bot would refuse to start with.
+pub async fn print_migration_status() -> bool {
+    let status = match SQL.acquire().await {
+        Ok(mut conn) => migration_status(&mut conn).await,
+        Err(err) => Err(err.into()),
+    };
+
+    let status = match status {
+        Ok(s) => s,
+        Err(err) => {
+            println!("Could not fetch migration status; {err}");
+            return false;
+        }
+    };
 
-    if let Err(err) = r {
-        panic!("Couldnt run database migration migrate_log_types_231320251115; Err = {err:?}");
+    let mut ok = true;
+
+    for (version, name, state) in status {
+        let state = match state {
+            MigrationState::Applied(at) => format!("applied {}", at.format("%Y-%m-%d %H:%M:%S")),
+            MigrationState::Pending => String::from("pending"),
+            MigrationState::ChecksumDrift => {
+                ok = false;
+                String::from("CHECKSUM DRIFT")
+            }
+            MigrationState::Unknown => {
+                ok = false;
+                String::from("UNKNOWN (database is newer than this binary)")
+            }
+        };
+
+        println!("{version:>4} {name:<64} {state}");
     }
+
+    ok
 }
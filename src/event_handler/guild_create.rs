@@ -8,24 +8,24 @@ pub async fn guild_create(_handler: &Handler, ctx: Context, guild: Guild, is_new
     if let Some(new) = is_new
         && new
     {
-        if BOT_CONFIG.whitelist_enabled.is_none_or(|b| !b) {
-            return;
-        }
-
-        if BOT_CONFIG
-            .whitelist
-            .as_ref()
-            .is_none_or(|ids| !ids.contains(&guild.id.get()))
-            && let Err(err) = ctx.http.leave_guild(guild.id).await
+        if BOT_CONFIG.whitelist_enabled.is_some_and(|b| b)
+            && BOT_CONFIG
+                .whitelist
+                .as_ref()
+                .is_none_or(|ids| !ids.contains(&guild.id.get()))
         {
-            error!(
-                "Could not leave non-whitelisted guild! err = {err:?}; id = {}",
-                guild.id.get()
-            );
+            if let Err(err) = ctx.http.leave_guild(guild.id).await {
+                error!(
+                    "Could not leave non-whitelisted guild! err = {err:?}; id = {}",
+                    guild.id.get()
+                );
+            }
+
+            return;
         }
 
         if let Err(err) = query!(
-            "INSERT INTO actions (guild_id) values ($1);",
+            "INSERT INTO guild_settings (guild_id) VALUES ($1) ON CONFLICT (guild_id) DO NOTHING",
             guild.id.get() as i64
         )
         .execute(&*SQL)
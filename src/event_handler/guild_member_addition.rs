@@ -0,0 +1,245 @@
+use chrono::{Duration, Utc};
+use serenity::all::{Context, CreateEmbed, CreateMessage, EditMember, Member, Mentionable, RoleId};
+use sqlx::query;
+use tracing::{error, warn};
+
+use crate::{
+    SQL,
+    constants::{BRAND_BLUE, SOFT_YELLOW},
+    database::ActionType,
+    event_handler::Handler,
+    utils::{LogType, anti_raid_join, guild_log, humanize_duration, snowflake_to_timestamp},
+};
+
+pub async fn guild_member_addition(handler: &Handler, ctx: Context, new_member: Member) {
+    if new_member.user.bot {
+        return;
+    }
+
+    reapply_mute(&ctx, &new_member).await;
+    reapply_restrictions(&ctx, &new_member).await;
+    anti_raid_join(&ctx, &handler.raid_detector, &new_member).await;
+
+    let guild_id = new_member.guild_id;
+    let user = &new_member.user;
+
+    let created_at = snowflake_to_timestamp(user.id.get());
+    let account_age = humanize_duration(&(Utc::now() - created_at));
+
+    let res = query!(
+        r#"
+            SELECT id, type as "type!: ActionType", active AND (expires_at IS NULL OR expires_at > NOW()) as "active!"
+            FROM actions
+            WHERE guild_id = $1 AND user_id = $2
+            ORDER BY created_at DESC
+        "#,
+        guild_id.get() as i64,
+        user.id.get() as i64
+    )
+    .fetch_all(&*SQL)
+    .await;
+
+    let actions = match res {
+        Ok(a) => a,
+        Err(err) => {
+            warn!("Couldn't fetch prior actions of joining member; err = {err:?}");
+            vec![]
+        }
+    };
+
+    let prior_actions = if actions.is_empty() {
+        String::from("none")
+    } else {
+        ActionType::all()
+            .into_iter()
+            .filter_map(|t| {
+                let count = actions
+                    .iter()
+                    .filter(|a| a.r#type == t)
+                    .count();
+
+                (count > 0).then(|| format!("{count} {t}"))
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let warnings = actions
+        .iter()
+        .filter(|a| a.active)
+        .filter_map(|a| match a.r#type {
+            ActionType::Mute => Some(format!("\n**REJOINED WHILE MUTED**\n-# Log ID: `{}`", a.id)),
+            ActionType::Ban => Some(format!("\n**REJOINED WHILE BANNED**\n-# Log ID: `{}`", a.id)),
+            ActionType::Restrict => Some(format!("\n**REJOINED WHILE RESTRICTED**\n-# Log ID: `{}`", a.id)),
+            _ => None,
+        })
+        .collect::<String>();
+
+    guild_log(
+        &ctx,
+        LogType::MemberJoin,
+        guild_id,
+        CreateMessage::new().add_embed(
+            CreateEmbed::new()
+                .description(format!(
+                    "**MEMBER JOINED**\n-# Member: {} `{}` | Created: <t:{created}:d> <t:{created}:T> ({account_age} ago) | Prior actions: {prior_actions}{warnings}",
+                    user.mention(),
+                    user.id.get(),
+                    created = created_at.timestamp(),
+                ))
+                .thumbnail(user.face())
+                .color(if warnings.is_empty() { BRAND_BLUE } else { SOFT_YELLOW }),
+        ),
+    )
+    .await;
+}
+
+/// Re-applies the remaining duration of an active mute when a muted member rejoins.
+/// Timeouts over 27 days are capped, the expiring actions task keeps them alive afterwards.
+async fn reapply_mute(ctx: &Context, member: &Member) {
+    let res = query!(
+        r#"
+            SELECT id, expires_at
+            FROM actions
+            WHERE guild_id = $1 AND user_id = $2
+              AND type IN ('mute', 'timeout')
+              AND active = true
+              AND (expires_at IS NULL OR expires_at > NOW())
+            ORDER BY created_at DESC
+            LIMIT 1
+        "#,
+        member.guild_id.get() as i64,
+        member.user.id.get() as i64
+    )
+    .fetch_optional(&*SQL)
+    .await;
+
+    let entry = match res {
+        Ok(Some(e)) => e,
+        Ok(None) => return,
+        Err(err) => {
+            warn!("Couldn't fetch active mutes of joining member; err = {err:?}");
+            return;
+        }
+    };
+
+    let now = Utc::now();
+    let max_until = now + Duration::days(27);
+    let until = entry
+        .expires_at
+        .map(|e| std::cmp::min(e.and_utc(), max_until))
+        .unwrap_or(max_until);
+
+    let reason = format!(
+        "Ouroboros Managed Mute: log id `{}`. Please use Ouroboros to unmute to avoid accidental re-application!",
+        entry.id
+    );
+    let edit = EditMember::new()
+        .audit_log_reason(reason.as_str())
+        .disable_communication_until_datetime(until.into());
+
+    if let Err(err) = member.guild_id.edit_member(ctx, member, edit).await {
+        warn!(
+            "Couldn't re-apply mute of rejoining member; Guild = {} Id = {} err = {err:?}",
+            member.guild_id, member.user.id
+        );
+        return;
+    }
+
+    if let Err(err) = query!(
+        "UPDATE actions SET last_reapplied_at = NOW() WHERE id = $1",
+        entry.id
+    )
+    .execute(&*SQL)
+    .await
+    {
+        error!("Couldn't update last_reapplied_at after re-applying mute; id = {}; err = {err:?}", entry.id);
+    }
+
+    let remaining = entry
+        .expires_at
+        .map(|e| humanize_duration(&(e.and_utc() - now)))
+        .unwrap_or(String::from("permanent"));
+
+    guild_log(
+        ctx,
+        LogType::MemberModeration,
+        member.guild_id,
+        CreateMessage::new().add_embed(
+            CreateEmbed::new()
+                .description(format!(
+                    "**MUTE EVASION CAUGHT**\n-# Log ID: `{}` | Target: {} `{}` | Remaining: {remaining}\nThe member rejoined while muted, the mute has been re-applied.",
+                    entry.id,
+                    member.mention(),
+                    member.user.id.get()
+                ))
+                .color(SOFT_YELLOW),
+        ),
+    )
+    .await;
+}
+
+/// Re-applies the roles of active restrictions when a restricted member rejoins
+async fn reapply_restrictions(ctx: &Context, member: &Member) {
+    let res = query!(
+        r#"
+            SELECT id, role_id as "role_id!"
+            FROM actions
+            WHERE guild_id = $1 AND user_id = $2
+              AND type = 'restrict'
+              AND active = true
+              AND role_id IS NOT NULL
+              AND (expires_at IS NULL OR expires_at > NOW())
+        "#,
+        member.guild_id.get() as i64,
+        member.user.id.get() as i64
+    )
+    .fetch_all(&*SQL)
+    .await;
+
+    let entries = match res {
+        Ok(e) => e,
+        Err(err) => {
+            warn!("Couldn't fetch active restrictions of joining member; err = {err:?}");
+            return;
+        }
+    };
+
+    for entry in entries {
+        let role_id = RoleId::new(entry.role_id as u64);
+        let reason = format!(
+            "Ouroboros Managed Restriction: log id `{}`. Please use Ouroboros to unrestrict to avoid accidental re-application!",
+            entry.id
+        );
+
+        if let Err(err) = ctx
+            .http
+            .add_member_role(member.guild_id, member.user.id, role_id, Some(&reason))
+            .await
+        {
+            warn!(
+                "Couldn't re-apply restriction of rejoining member; Guild = {} Id = {} err = {err:?}",
+                member.guild_id, member.user.id
+            );
+            continue;
+        }
+
+        guild_log(
+            ctx,
+            LogType::MemberModeration,
+            member.guild_id,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .description(format!(
+                        "**RESTRICTION EVASION CAUGHT**\n-# Log ID: `{}` | Target: {} `{}` | Role: {}\nThe member rejoined while restricted, the restriction has been re-applied.",
+                        entry.id,
+                        member.mention(),
+                        member.user.id.get(),
+                        role_id.mention()
+                    ))
+                    .color(SOFT_YELLOW),
+            ),
+        )
+        .await;
+    }
+}
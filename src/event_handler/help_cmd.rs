@@ -22,6 +22,7 @@ impl Handler {
         msg: Message,
         args: Vec<Token>,
     ) -> Result<(), CommandError> {
+        let prefix = self.get_prefix(msg.guild_id).await;
         let mut args_iter = args.into_iter();
         if let Some(name_tok) = args_iter.next() {
             let Some(cmd) = self
@@ -100,7 +101,7 @@ impl Handler {
 
                 format!(
                     "Syntax:\n```\n{0}{1} {2}\n```\nExample:\n```{0}{1} {3}```",
-                    self.prefix,
+                    prefix,
                     cmd.get_name(),
                     def.join(" "),
                     example.join(" ")
@@ -229,7 +230,7 @@ impl Handler {
                     .description(get_page_body(current_page))
                     .footer(CreateEmbedFooter::new(format!(
                         "View additional information using {}help <command: String>",
-                        self.prefix
+                        prefix
                     )))
                     .color(BRAND_BLUE),
             )
@@ -305,7 +306,7 @@ impl Handler {
                                     .add_embed(
                                         CreateEmbed::new()
                                             .description(response)
-                                            .footer(CreateEmbedFooter::new(format!("View additional information using {}help <command: String>", self.prefix)))
+                                            .footer(CreateEmbedFooter::new(format!("View additional information using {}help <command: String>", prefix)))
                                             .color(BRAND_BLUE),
                                     )
                                     .components(vec![
@@ -339,7 +340,7 @@ impl Handler {
                                     .add_embed(
                                         CreateEmbed::new()
                                             .description(response)
-                                            .footer(CreateEmbedFooter::new(format!("View additional information using {}help <command: String>", self.prefix)))
+                                            .footer(CreateEmbedFooter::new(format!("View additional information using {}help <command: String>", prefix)))
                                             .color(BRAND_BLUE),
                                     )
                                     .components(vec![
@@ -369,7 +370,7 @@ impl Handler {
                                     .add_embed(
                                         CreateEmbed::new()
                                             .description(response)
-                                            .footer(CreateEmbedFooter::new(format!("View additional information using {}help <command: String>", self.prefix)))
+                                            .footer(CreateEmbedFooter::new(format!("View additional information using {}help <command: String>", prefix)))
                                             .color(BRAND_BLUE),
                                     )
                                     .components(vec![
@@ -398,7 +399,7 @@ impl Handler {
                                     .add_embed(
                                         CreateEmbed::new()
                                             .description(response)
-                                            .footer(CreateEmbedFooter::new(format!("View additional information using {}help <command: String>", self.prefix)))
+                                            .footer(CreateEmbedFooter::new(format!("View additional information using {}help <command: String>", prefix)))
                                             .color(BRAND_BLUE),
                                     )
                                     .components(vec![
@@ -0,0 +1,211 @@
+use std::time::Duration;
+
+use serenity::all::{
+    CommandInteraction, CommandType, Context, CreateAllowedMentions, CreateInputText,
+    CreateInteractionResponse, CreateInteractionResponseMessage, CreateQuickModal, GuildId,
+    InputTextStyle, Interaction, Mentionable, Message, ResolvedTarget, User,
+};
+
+use crate::{
+    event_handler::Handler,
+    utils::{
+        appeal_component, appeal_modal,
+        application_commands::{CONTEXT_MENU_ACTIONS, create_command_line, quote_reason},
+        command_processing::execute,
+        consume_serenity_error, raid_component,
+    },
+};
+
+pub async fn interaction_create(handler: &Handler, ctx: Context, interaction: Interaction) {
+    match interaction {
+        Interaction::Command(command) => match command.data.kind {
+            CommandType::User | CommandType::Message => {
+                context_menu(handler, ctx, command).await
+            }
+            _ => application_command(handler, ctx, command).await,
+        },
+        Interaction::Component(component) => {
+            appeal_component(&ctx, &component).await;
+            raid_component(&ctx, &handler.raid_detector, &component).await;
+        }
+        Interaction::Modal(modal) => appeal_modal(&ctx, &modal).await,
+        _ => {}
+    }
+}
+
+/// Runs a slash command through the regular command pipeline.
+/// The interaction is answered with the equivalent prefix command, that response then acts as the command message.
+async fn application_command(handler: &Handler, ctx: Context, interaction: CommandInteraction) {
+    let Some(guild_id) = interaction.guild_id else {
+        return;
+    };
+
+    let Some(command) = handler
+        .commands
+        .iter()
+        .find(|c| c.get_name() == interaction.data.name)
+    else {
+        return;
+    };
+
+    let line = create_command_line(command, &interaction);
+
+    if let Err(err) = interaction
+        .create_response(
+            &ctx,
+            command_line_response(handler, &interaction.user, guild_id, &line).await,
+        )
+        .await
+    {
+        consume_serenity_error(String::from("APPLICATION COMMAND RESPONSE"), err);
+        return;
+    }
+
+    let msg = match interaction.get_response(&ctx).await {
+        Ok(m) => m,
+        Err(err) => {
+            consume_serenity_error(String::from("APPLICATION COMMAND GET RESPONSE"), err);
+            return;
+        }
+    };
+
+    run_as(handler, ctx, msg, interaction.user, guild_id, line, None).await
+}
+
+/// Asks for a reason (and duration) using a modal and runs the moderation command of the context menu entry.
+/// Message targets are passed on as the replied-to message, so the command infers its target the same way a reply would.
+async fn context_menu(handler: &Handler, ctx: Context, interaction: CommandInteraction) {
+    let Some(guild_id) = interaction.guild_id else {
+        return;
+    };
+
+    let Some(action) = CONTEXT_MENU_ACTIONS
+        .iter()
+        .find(|a| a.name == interaction.data.name)
+    else {
+        return;
+    };
+
+    let (target, reference) = match interaction.data.target() {
+        Some(ResolvedTarget::User(user, _)) => (user.clone(), None),
+        Some(ResolvedTarget::Message(message)) => (message.author.clone(), Some(message.clone())),
+        _ => return,
+    };
+
+    let mut title = format!("{}: {}", action.name, target.name);
+
+    if title.chars().count() > 45 {
+        title = title.chars().take(42).collect();
+        title.push_str("...");
+    }
+
+    let mut modal = CreateQuickModal::new(title)
+        .timeout(Duration::from_secs(60 * 5))
+        .field(
+            CreateInputText::new(InputTextStyle::Paragraph, "Reason", "reason")
+                .placeholder("No reason provided")
+                .max_length(500)
+                .required(false),
+        );
+
+    if action.duration {
+        let mut field = CreateInputText::new(InputTextStyle::Short, "Duration", "duration")
+            .placeholder("i.e. 15m, 1h, 7d - leave empty or 0 for permanent")
+            .required(false);
+
+        if !action.default_duration.is_empty() {
+            field = field.value(action.default_duration);
+        }
+
+        modal = modal.field(field);
+    }
+
+    let response = match interaction.quick_modal(&ctx, modal).await {
+        Ok(Some(r)) => r,
+        Ok(None) => return,
+        Err(err) => {
+            consume_serenity_error(String::from("CONTEXT MENU MODAL"), err);
+            return;
+        }
+    };
+
+    let mut inputs = response.inputs.into_iter();
+    let reason = inputs.next().unwrap_or_default();
+    let duration = inputs.next().unwrap_or_default();
+
+    let mut parts = vec![action.command.to_string()];
+
+    if reference.is_none() {
+        parts.push(target.id.get().to_string());
+    }
+
+    if !duration.trim().is_empty() {
+        parts.push(duration.trim().to_string());
+    }
+
+    if !reason.trim().is_empty() {
+        parts.push(quote_reason(reason.trim()));
+    }
+
+    let line = parts.join(" ");
+
+    if let Err(err) = response
+        .interaction
+        .create_response(
+            &ctx,
+            command_line_response(handler, &interaction.user, guild_id, &line).await,
+        )
+        .await
+    {
+        consume_serenity_error(String::from("CONTEXT MENU RESPONSE"), err);
+        return;
+    }
+
+    let msg = match response.interaction.get_response(&ctx).await {
+        Ok(m) => m,
+        Err(err) => {
+            consume_serenity_error(String::from("CONTEXT MENU GET RESPONSE"), err);
+            return;
+        }
+    };
+
+    run_as(handler, ctx, msg, interaction.user, guild_id, line, reference).await
+}
+
+/// The public interaction response showing which prefix command is being run
+async fn command_line_response(
+    handler: &Handler,
+    user: &User,
+    guild_id: GuildId,
+    line: &str,
+) -> CreateInteractionResponse {
+    CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+            .content(format!(
+                "-# {} used `{}{}`",
+                user.mention(),
+                handler.get_prefix(Some(guild_id)).await,
+                line.replace('`', "'")
+            ))
+            .allowed_mentions(CreateAllowedMentions::new()),
+    )
+}
+
+/// Executes a command line using an interaction response as the command message, authored by the interaction user
+async fn run_as(
+    handler: &Handler,
+    ctx: Context,
+    mut msg: Message,
+    user: User,
+    guild_id: GuildId,
+    line: String,
+    reference: Option<Message>,
+) {
+    msg.guild_id = Some(guild_id);
+    msg.author = user;
+    msg.member = None;
+    msg.content = format!("{}{line}", handler.get_prefix(Some(guild_id)).await);
+    msg.referenced_message = reference.map(Box::new);
+
+    execute(handler, ctx, msg, line).await
+}
@@ -7,15 +7,27 @@ use serenity::all::{
 use crate::{
     constants::BRAND_RED,
     event_handler::{Handler, MessageDeleteEvent},
-    utils::{LogType, cache::partials::PartialMessage, guild_log, snowflake_to_timestamp},
+    utils::{
+        LogType,
+        cache::{attachment_archive::archived_or_download, partials::PartialMessage},
+        guild_log, snowflake_to_timestamp,
+    },
 };
 
 pub async fn message_delete(
-    _handler: &Handler,
+    handler: &Handler,
     ctx: Context,
     event: MessageDeleteEvent,
     old_if_available: Option<PartialMessage>,
 ) {
+    // purges delete old messages one by one and log them in their own transcript
+    if handler
+        .message_cache
+        .take_own_deletion(event.channel_id.get(), event.message_id.get())
+    {
+        return;
+    }
+
     let Some(msg) = old_if_available else {
         return;
     };
@@ -74,17 +86,18 @@ pub async fn message_delete(
     let mut files = vec![];
     let mut embed = CreateEmbed::new().color(BRAND_RED);
 
-    if let Some(author) = msg.author.to_user(&ctx).await
-    {
+    if let Some(author) = msg.author.to_user(&ctx).await {
         description.push_str(&format!("| Target: <@{}> ", msg.author.id));
         embed = embed.author(
             CreateEmbedAuthor::new(format!("{}: {}", msg.author.name, msg.author.id))
                 .icon_url(author.avatar_url().unwrap_or(author.default_avatar_url())),
         );
 
+        // the cdn url usually stops working once the message is deleted, so prefer the archived copy
         for attachment in msg.attachment_urls.iter() {
             let name = attachment.name.clone();
-            if let Ok(bytes) = attachment.download().await {
+            if let Some(bytes) = archived_or_download(&handler.attachment_archive, attachment).await
+            {
                 files.push(CreateAttachment::bytes(bytes, name));
             };
         }
@@ -114,3 +127,48 @@ pub async fn message_delete(
     )
     .await;
 }
+
+#[cfg(test)]
+mod tests {
+    use serenity::all::EventHandler;
+
+    use crate::testing::{self, TestBot, description};
+
+    #[test]
+    fn drops_deleted_messages_from_the_cache() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            let messages = ["first", "second", "third"]
+                .map(|content| bot.seed_message(bot.member_id, content));
+            for m in &messages {
+                bot.handler
+                    .message_cache
+                    .insert_message(bot.channel_id, m.clone());
+            }
+
+            bot.handler
+                .message_delete(
+                    bot.ctx.clone(),
+                    bot.channel_id.into(),
+                    messages[1].id,
+                    Some(bot.guild_id.into()),
+                )
+                .await;
+
+            assert!(description(&bot.logs()[0]).contains("second"));
+
+            let cache = &bot.handler.message_cache;
+            assert!(cache.get(bot.channel_id, messages[1].id.get()).is_none());
+            assert_eq!(
+                cache
+                    .get(bot.channel_id, messages[2].id.get())
+                    .map(|m| m.content),
+                Some(String::from("third"))
+            );
+            assert_eq!(cache.get_channel_len(bot.channel_id), 2);
+        });
+    }
+}
@@ -0,0 +1,150 @@
+use serenity::all::{
+    ChannelId, Context, CreateEmbed, CreateMessage, GuildId, MessageAction, MessageId,
+    audit_log::Action,
+};
+
+use crate::{
+    constants::BRAND_RED,
+    event_handler::Handler,
+    utils::{
+        LogType, Transcript, TranscriptMessage, cache::partials::PartialMessage, find_audit_log,
+        guild_log,
+    },
+};
+
+pub async fn message_delete_bulk(
+    _handler: &Handler,
+    ctx: Context,
+    channel_id: ChannelId,
+    message_ids: Vec<MessageId>,
+    guild_id: Option<GuildId>,
+    old_if_available: Vec<PartialMessage>,
+) {
+    let Some(guild_id) = guild_id else {
+        return;
+    };
+
+    // bulk delete audit log entries target the channel, the amount of messages is in the options
+    let actor_id = find_audit_log(
+        &ctx,
+        guild_id,
+        Action::Message(MessageAction::BulkDelete),
+        |e| e.target_id.is_some_and(|t| t.get() == channel_id.get()),
+    )
+    .await
+    .map(|e| e.user_id);
+
+    // purges log their own transcript
+    if actor_id == Some(ctx.cache.current_user().id) {
+        return;
+    }
+
+    // like single deletes, messages of bots are left out. They still count towards the amount of deleted
+    // messages, only deletions of nothing but bot messages are not logged at all
+    let cached = old_if_available.len();
+    let old_if_available = old_if_available
+        .into_iter()
+        .filter(|m| !m.author.bot)
+        .collect::<Vec<_>>();
+
+    if cached > 0 && cached == message_ids.len() && old_if_available.is_empty() {
+        return;
+    }
+
+    let mut description = format!(
+        "**MESSAGES BULK DELETED**\n-# Channel: <#{0}> ",
+        channel_id.get()
+    );
+
+    if let Some(moderator) = actor_id {
+        description.push_str(&format!("| Actor: <@{moderator}> "));
+    }
+
+    description.push_str(&format!(
+        "| Count: {} | Cached: {}",
+        message_ids.len(),
+        old_if_available.len()
+    ));
+
+    let mut message = CreateMessage::new();
+
+    if old_if_available.is_empty() {
+        description.push_str("\n-# None of the messages were found in cache");
+    } else {
+        let channel_name = channel_id
+            .name(&ctx)
+            .await
+            .unwrap_or(channel_id.get().to_string());
+        let transcript = Transcript::new(
+            format!("Bulk deleted messages in #{channel_name}"),
+            old_if_available.iter().map(TranscriptMessage::from),
+        );
+
+        message = message.add_files(transcript.to_attachments(&format!(
+            "bulk-delete-{}-{}",
+            channel_id.get(),
+            message_ids.iter().max().map(|id| id.get()).unwrap_or_default()
+        )));
+    }
+
+    guild_log(
+        &ctx,
+        LogType::MessageUpdate,
+        guild_id,
+        message.add_embed(CreateEmbed::new().description(description).color(BRAND_RED)),
+    )
+    .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::json::json;
+
+    use super::message_delete_bulk;
+    use crate::{
+        testing::{self, TestBot, snowflake},
+        utils::cache::partials::PartialMessage,
+    };
+
+    #[test]
+    fn skips_own_purges_and_bot_messages() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            let bulk_delete = async |authors: &[u64]| {
+                let messages = authors
+                    .iter()
+                    .map(|a| PartialMessage::from(bot.seed_message(*a, "deleted")))
+                    .collect::<Vec<_>>();
+
+                message_delete_bulk(
+                    &bot.handler,
+                    bot.ctx.clone(),
+                    bot.channel_id.into(),
+                    messages.iter().map(|m| m.id.into()).collect(),
+                    Some(bot.guild_id.into()),
+                    messages,
+                )
+                .await;
+            };
+
+            bulk_delete(&[bot.bot_id, bot.bot_id]).await;
+            assert!(bot.logs().is_empty());
+
+            bulk_delete(&[bot.bot_id, bot.member_id]).await;
+            assert_eq!(bot.logs().len(), 1);
+
+            bot.discord.state().audit_log.push(json!({
+                "id": snowflake().to_string(),
+                "action_type": 73, // MESSAGE_BULK_DELETE
+                "user_id": bot.bot_id.to_string(),
+                "target_id": bot.channel_id.to_string(),
+                "options": { "count": "2" },
+            }));
+            bulk_delete(&[bot.member_id, bot.member_id]).await;
+            assert_eq!(bot.logs().len(), 1);
+        });
+    }
+}
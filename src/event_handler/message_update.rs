@@ -7,11 +7,15 @@ use serenity::all::{
 use crate::{
     constants::SOFT_YELLOW,
     event_handler::Handler,
-    utils::{LogType, cache::partials::PartialMessage, create_diff, guild_log},
+    utils::{
+        LogType,
+        cache::{attachment_archive::archived_or_download, partials::PartialMessage},
+        create_diff, guild_log,
+    },
 };
 
 pub async fn message_update(
-    _handler: &Handler,
+    handler: &Handler,
     ctx: Context,
     old_if_available: Option<PartialMessage>,
     new: Option<Message>,
@@ -44,7 +48,7 @@ pub async fn message_update(
     }
 
     let guild_id = {
-        if let Some(Some(channel)) = new_msg.channel(&ctx).await.map(|c| c.guild()).ok() {
+        if let Ok(Some(channel)) = new_msg.channel(&ctx).await.map(|c| c.guild()) {
             channel.guild_id.get()
         } else {
             0
@@ -59,7 +63,24 @@ pub async fn message_update(
         guild_id
     );
 
-    let (desc, file) = match old_if_available {
+    // attachments can only be removed by an edit, snapshots from older versions have no attachment ids
+    let removed_attachments = old_if_available
+        .as_ref()
+        .map(|old| {
+            old.attachment_urls
+                .iter()
+                .filter(|a| {
+                    !new_msg
+                        .attachments
+                        .iter()
+                        .any(|n| n.id.get() == a.id || n.url == a.url)
+                })
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let (mut desc, file) = match old_if_available {
         Some(mut old) => {
             if old.content.is_empty() {
                 old.content = String::from("(no content)");
@@ -105,6 +126,26 @@ pub async fn message_update(
         }
     };
 
+    let mut files = vec![];
+
+    if !removed_attachments.is_empty() {
+        desc.push_str(&format!(
+            "\n-# Removed attachments: {}",
+            removed_attachments
+                .iter()
+                .map(|a| a.name.replace('`', ""))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
+
+        for attachment in removed_attachments.iter() {
+            if let Some(bytes) = archived_or_download(&handler.attachment_archive, attachment).await
+            {
+                files.push(CreateAttachment::bytes(bytes, attachment.name.clone()));
+            }
+        }
+    }
+
     let mut message = CreateMessage::new().add_embed(
         CreateEmbed::new()
             .color(SOFT_YELLOW)
@@ -128,6 +169,8 @@ pub async fn message_update(
         message = message.add_file(f);
     }
 
+    message = message.add_files(files);
+
     let guild_id = match new_msg.channel_id.to_channel(&ctx).await {
         Ok(Channel::Guild(g)) => g.guild_id.get(),
         _ => new_msg.guild_id.map(|g| g.get()).unwrap_or(1),
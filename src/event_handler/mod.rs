@@ -3,25 +3,23 @@ use std::{collections::HashMap, sync::Arc, time::Duration};
 use serenity::{
     all::{
         ChannelId, Context, CreateAllowedMentions, CreateEmbed, CreateMessage, EventHandler, Guild,
-        GuildId, GuildMemberUpdateEvent, Member, Message, MessageId, MessageUpdateEvent,
-        PartialGuild, Role, RoleId, User,
+        GuildId, GuildMemberUpdateEvent, Interaction, Member, Message, MessageId,
+        MessageUpdateEvent, PartialGuild, Role, RoleId, User,
     },
     async_trait,
 };
-use tokio::{
-    sync::{Mutex, MutexGuard},
-    time::sleep,
-};
+use tokio::{sync::Mutex, time::sleep};
 use tracing::{info, warn};
 
 use crate::{
     SQL,
     commands::{
-        About, Ban, Cache, ColonThree, Command, DefineLog, Duration as DurationCommand, ExtractId, Kick, Log, MsgDbg, Mute, PermDbg, Ping, Purge, Reason, Say, ScheduleDowntime, Softban, Stats, Unban, Unmute, Update, Warn
+        About, Alias, AntiRaid, Ban, Cache, ColonThree, Command, Config, DefineLog, Duration as DurationCommand, Escalation, ExtractId, Kick, Lock, Lockdown, Log, Massban, Masskick, Massmute, MsgDbg, Mute, Note, Overrides, PermDbg, Ping, Prefix, Privacy, Purge, Reason, Restrict, Restrictions, Say, Search, ScheduleDowntime, Slowmode, Softban, Stats, Templates, Transcript, Unban, Unlock, Unmute, Unrestrict, Update, Warn
     },
     constants::BRAND_RED,
     lexer::Token,
-    utils::{cache::{message_cache::MessageCache, permission_cache::PermissionCache}, consume_serenity_error},
+    tasks::delete_snapshot_messages,
+    utils::{RaidDetector, cache::{attachment_archive::{AttachmentArchive, archive_attachments}, message_cache::MessageCache, permission_cache::PermissionCache}, consume_serenity_error, get_guild_prefix},
 };
 #[derive(Debug)]
 pub struct CommandError {
@@ -77,13 +75,18 @@ mod help_cmd;
 
 // events
 mod guild_create;
+mod guild_member_addition;
 mod guild_member_removal;
+// the avatar comparison in guild_member_update is commented out until it is reworked, keep its imports and helper around
+#[allow(unused_imports, dead_code)]
 mod guild_member_update;
 mod guild_role_delete;
 mod guild_role_update;
 mod guild_update;
+mod interaction_create;
 mod message;
 mod message_delete;
+mod message_delete_bulk;
 mod message_update;
 mod shards_ready;
 
@@ -91,8 +94,10 @@ mod shards_ready;
 pub struct Handler {
     pub prefix: String,
     pub commands: Vec<Arc<dyn Command>>,
-    pub message_cache: Arc<Mutex<MessageCache>>,
+    pub message_cache: Arc<MessageCache>,
     pub permission_cache: Arc<Mutex<PermissionCache>>,
+    pub raid_detector: Arc<Mutex<RaidDetector>>,
+    pub attachment_archive: Arc<Mutex<AttachmentArchive>>,
 }
 
 impl Handler {
@@ -102,36 +107,55 @@ impl Handler {
             Arc::new(Stats::new()),
             Arc::new(Warn::new()),
             Arc::new(Log::new()),
+            Arc::new(Search::new()),
             Arc::new(Kick::new()),
             Arc::new(Softban::new()),
             Arc::new(Ban::new()),
             Arc::new(Mute::new()),
             Arc::new(Unban::new()),
             Arc::new(Unmute::new()),
+            Arc::new(Restrict::new()),
+            Arc::new(Unrestrict::new()),
+            Arc::new(Massban::new()),
+            Arc::new(Masskick::new()),
+            Arc::new(Massmute::new()),
+            Arc::new(Lock::new()),
+            Arc::new(Unlock::new()),
+            Arc::new(Lockdown::new()),
+            Arc::new(Slowmode::new()),
             Arc::new(Purge::new()),
+            Arc::new(Transcript::new()),
             Arc::new(MsgDbg::new()),
             Arc::new(ColonThree::new()),
             Arc::new(Reason::new()),
+            Arc::new(Note::new()),
             Arc::new(Update::new()),
-            // Arc::new(Config::new()),
+            Arc::new(Config::new()),
             Arc::new(Say::new()),
             Arc::new(About::new()),
             Arc::new(DurationCommand::new()),
             Arc::new(ExtractId::new()),
             Arc::new(Cache::new()),
             Arc::new(DefineLog::new()),
+            Arc::new(Prefix::new()),
+            Arc::new(Alias::new()),
+            Arc::new(Escalation::new()),
+            Arc::new(Overrides::new()),
+            Arc::new(Restrictions::new()),
+            Arc::new(Templates::new()),
+            Arc::new(Privacy::new()),
+            Arc::new(AntiRaid::new()),
             Arc::new(PermDbg::new()),
             Arc::new(ScheduleDowntime::new()),
         ];
 
-        let cache = Arc::new(Mutex::new(MessageCache::new()));
+        let cache = Arc::new(MessageCache::new());
         let cache_clone = cache.clone();
 
         tokio::spawn(async move {
             loop {
                 sleep(Duration::from_secs(43200)).await;
-                let lock = cache_clone.lock().await;
-                Self::update_cache_size(lock).await;
+                Self::update_cache_size(&cache_clone).await;
             }
         });
 
@@ -140,15 +164,26 @@ impl Handler {
             commands,
             message_cache: cache,
             permission_cache: Arc::new(Mutex::new(PermissionCache::new())),
+            raid_detector: Arc::new(Mutex::new(RaidDetector::new())),
+            attachment_archive: Arc::new(Mutex::new(AttachmentArchive::new())),
         }
     }
 }
 
 impl Handler {
+    /// Gets the command prefix of a guild, falling back to the prefix the handler was created with.
+    pub async fn get_prefix(&self, guild_id: Option<GuildId>) -> String {
+        match guild_id {
+            Some(id) => get_guild_prefix(id).await.unwrap_or(self.prefix.clone()),
+            None => self.prefix.clone(),
+        }
+    }
+
     pub async fn send_error(&self, ctx: &Context, msg: &Message, input: String, err: CommandError) {
         let error_message;
 
         if let Some(arg) = err.arg {
+            let prefix_len = self.get_prefix(msg.guild_id).await.chars().count();
             let mut hint = String::new();
 
             if let Some(h) = err.hint {
@@ -158,7 +193,7 @@ impl Handler {
             error_message = format!(
                 "**error:** argument {}\n```\n{input}\n{}{}\n{}\n```\n{}",
                 arg.iteration,
-                " ".repeat(arg.position + 1),
+                " ".repeat(arg.position + prefix_len),
                 "^".repeat(arg.length),
                 err.title,
                 hint
@@ -200,7 +235,7 @@ impl Handler {
         }
     }
 
-    pub async fn update_cache_size(mut cache: MutexGuard<'_, MessageCache>) {
+    pub async fn update_cache_size(cache: &MessageCache) {
         info!("Updating message cache sizes...");
 
         let inserts = cache.get_inserts();
@@ -261,10 +296,13 @@ impl Handler {
 #[async_trait]
 impl EventHandler for Handler {
     async fn message(&self, ctx: Context, msg: Message) {
-        {
-            let mut lock = self.message_cache.lock().await;
+        self.message_cache
+            .insert_message(msg.channel_id.get(), msg.clone());
+
+        if !msg.attachments.is_empty() {
+            let archive = self.attachment_archive.clone();
             let cloned = msg.clone();
-            lock.insert_message(cloned.channel_id.get(), cloned);
+            tokio::spawn(async move { archive_attachments(&archive, &cloned).await });
         }
 
         message::message(self, ctx, msg).await;
@@ -277,15 +315,15 @@ impl EventHandler for Handler {
         new: Option<Message>,
         event: MessageUpdateEvent,
     ) {
-        let old_if_available;
-
-        {
-            let mut lock = self.message_cache.lock().await;
-            old_if_available = lock.get(event.channel_id.get(), event.id.get()).cloned();
-
-            if let Ok(msg) = event.channel_id.message(&ctx, event.id).await {
-                lock.insert_message(event.channel_id.get(), msg);
-            }
+        let old_if_available = self
+            .message_cache
+            .get(event.channel_id.get(), event.id.get());
+
+        if let Ok(mut msg) = event.channel_id.message(&ctx, event.id).await {
+            // messages fetched over http don't carry the guild id
+            msg.guild_id = event.guild_id;
+            self.message_cache
+                .insert_message(event.channel_id.get(), msg);
         }
 
         message_update::message_update(self, ctx, old_if_available, new, event).await
@@ -298,17 +336,47 @@ impl EventHandler for Handler {
         deleted_message_id: MessageId,
         _guild_id: Option<GuildId>,
     ) {
-        let mut lock = self.message_cache.lock().await;
         let event = MessageDeleteEvent {
             channel_id,
             message_id: deleted_message_id,
         };
-        let old_if_available = lock
-            .get(event.channel_id.get(), event.message_id.get())
-            .cloned();
+        let old_if_available = self
+            .message_cache
+            .remove(event.channel_id.get(), event.message_id.get());
+        delete_snapshot_messages(&[event.message_id.get()]).await;
         message_delete::message_delete(self, ctx, event, old_if_available).await
     }
 
+    async fn message_delete_bulk(
+        &self,
+        ctx: Context,
+        channel_id: ChannelId,
+        multiple_deleted_messages_ids: Vec<MessageId>,
+        guild_id: Option<GuildId>,
+    ) {
+        let old_if_available = multiple_deleted_messages_ids
+            .iter()
+            .filter_map(|id| self.message_cache.remove(channel_id.get(), id.get()))
+            .collect::<Vec<_>>();
+        delete_snapshot_messages(
+            &multiple_deleted_messages_ids
+                .iter()
+                .map(|id| id.get())
+                .collect::<Vec<_>>(),
+        )
+        .await;
+
+        message_delete_bulk::message_delete_bulk(
+            self,
+            ctx,
+            channel_id,
+            multiple_deleted_messages_ids,
+            guild_id,
+            old_if_available,
+        )
+        .await
+    }
+
     async fn guild_create(&self, ctx: Context, guild: Guild, is_new: Option<bool>) {
         guild_create::guild_create(self, ctx, guild, is_new).await
     }
@@ -317,6 +385,10 @@ impl EventHandler for Handler {
         shards_ready::shards_ready(self, ctx, total_shards).await
     }
 
+    async fn guild_member_addition(&self, ctx: Context, new_member: Member) {
+        guild_member_addition::guild_member_addition(self, ctx, new_member).await
+    }
+
     async fn guild_member_update(
         &self,
         ctx: Context,
@@ -378,4 +450,8 @@ impl EventHandler for Handler {
     ) {
         guild_update::guild_update(self, ctx, old_data_if_available, new_data).await
     }
+
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        interaction_create::interaction_create(self, ctx, interaction).await
+    }
 }
@@ -1,15 +1,20 @@
 use std::fs;
 
 use serenity::{
-    all::{ActivityData, Context, Permissions, RoleId},
+    all::{ActivityData, Command as ApplicationCommand, Context, Permissions, RoleId},
     futures::StreamExt,
 };
 use sqlx::query;
 use tracing::{error, info};
 
 use crate::{
-    BOT_CONFIG, GUILD_SETTINGS, SQL, event_handler::Handler,
-    utils::cache::permission_cache::CommandPermissionRequest,
+    BOT_CONFIG, GUILD_SETTINGS, SQL,
+    event_handler::Handler,
+    tasks::restore_message_cache,
+    utils::{
+        application_commands::{create_application_command, create_context_menu_commands},
+        cache::permission_cache::CommandPermissionRequest,
+    },
 };
 
 pub async fn shards_ready(handler: &Handler, ctx: Context, _total_shards: u32) {
@@ -17,6 +22,8 @@ pub async fn shards_ready(handler: &Handler, ctx: Context, _total_shards: u32) {
     check_whitelist(&ctx).await;
     update_guild_settings(&ctx).await;
     fill_message_cache(handler, &ctx).await;
+    restore_message_cache(&handler.message_cache).await;
+    register_application_commands(handler, &ctx).await;
     fill_permission_cache(handler, &ctx).await;
     set_activity(handler, &ctx).await;
 }
@@ -127,20 +134,20 @@ pub async fn fill_message_cache(handler: &Handler, ctx: &Context) {
         }
     };
 
-    let mut lock = handler.message_cache.lock().await;
-
     for guild in ctx.cache.guilds() {
         let Some(cached) = guild.to_guild_cached(&ctx.cache) else {
             continue;
         };
 
         for id in cached.channels.keys() {
-            lock.assign_count(id.get(), 100);
+            handler.message_cache.assign_count(id.get(), 100);
         }
     }
 
     for record in existing_data {
-        lock.assign_count(record.channel_id as u64, record.message_count as usize);
+        handler
+            .message_cache
+            .assign_count(record.channel_id as u64, record.message_count as usize);
     }
 }
 
@@ -185,11 +192,11 @@ pub async fn fill_permission_cache(handler: &Handler, ctx: &Context) {
                 || role.has_permission(Permissions::KICK_MEMBERS)
                 || role.has_permission(Permissions::ADMINISTRATOR)
             {
-                valid_roles.push(id.clone());
+                valid_roles.push(*id);
             }
         }
 
-        let id = ctx.cache.current_user().id.clone();
+        let id = ctx.cache.current_user().id;
         let Ok(current_user) = partial.member(&ctx, id).await else {
             continue;
         };
@@ -201,7 +208,7 @@ pub async fn fill_permission_cache(handler: &Handler, ctx: &Context) {
                 continue;
             };
 
-            if member.roles.iter().any(|r| valid_roles.contains(&r)) {
+            if member.roles.iter().any(|r| valid_roles.contains(r)) {
                 let mut cache = handler.permission_cache.lock().await;
 
                 cache
@@ -224,6 +231,21 @@ pub async fn fill_permission_cache(handler: &Handler, ctx: &Context) {
     }
 }
 
+pub async fn register_application_commands(handler: &Handler, ctx: &Context) {
+    info!("Registering application commands");
+    let mut commands = create_context_menu_commands(&handler.commands);
+
+    for command in handler.commands.iter() {
+        if let Some(c) = create_application_command(ctx, command).await {
+            commands.push(c);
+        }
+    }
+
+    if let Err(err) = ApplicationCommand::set_global_commands(&ctx, commands).await {
+        error!("Couldnt register application commands; err = {err:?}");
+    }
+}
+
 async fn set_activity(handler: &Handler, ctx: &Context) {
     ctx.set_activity(Some(ActivityData::watching(format!(
         "Moderating Members... | {}help",
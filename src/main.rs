@@ -9,10 +9,10 @@ use serenity::{
 };
 use sqlx::{PgPool, postgres::PgPoolOptions};
 use tokio::{fs::File, io::AsyncReadExt, sync::Mutex, time::sleep};
-use tracing::error;
+use tracing::{error, info};
 
 use crate::{
-    auto_once::AutoOnceLock, config::{Config, Environment}, event_handler::Handler, utils::{GuildSettings, send_error}
+    auto_once::AutoOnceLock, config::{Config, Environment}, event_handler::Handler, utils::{GuildSettings, RaidDetector, cache::message_cache::MessageCache, send_error}
 };
 use std::process::Command as SystemCommand;
 
@@ -22,6 +22,27 @@ impl TypeMapKey for ShardManagerContainer {
     type Value = Arc<ShardManager>;
 }
 
+/// The names of all registered commands, used by commands which need to know about other commands
+pub struct CommandNamesContainer;
+
+impl TypeMapKey for CommandNamesContainer {
+    type Value = Arc<Vec<&'static str>>;
+}
+
+/// The anti-raid state of the event handler, for commands which end raid mode
+pub struct RaidDetectorContainer;
+
+impl TypeMapKey for RaidDetectorContainer {
+    type Value = Arc<Mutex<RaidDetector>>;
+}
+
+/// The message cache of the event handler, for commands which look up messages that may have been deleted
+pub struct MessageCacheContainer;
+
+impl TypeMapKey for MessageCacheContainer {
+    type Value = Arc<MessageCache>;
+}
+
 mod commands;
 mod config;
 mod constants;
@@ -32,6 +53,8 @@ mod tasks;
 mod transformers;
 mod utils;
 mod auto_once;
+#[cfg(test)]
+mod testing;
 
 pub static START_TIME: AutoOnceLock<Instant> = AutoOnceLock::new();
 pub static SQL: AutoOnceLock<PgPool> = AutoOnceLock::new();
@@ -49,7 +72,6 @@ async fn main() {
         .find(|a| a.starts_with("--update"))
     {
         use std::process::exit;
-        use tracing::info;
 
         info!("Starting update process");
         if let Err(err) = update(arg) {
@@ -92,7 +114,19 @@ async fn main() {
         }.await
     });
 
-    database::run_migrations().await;
+    if env::args().any(|a| a == "--migrate-status") {
+        let ok = database::print_migration_status().await;
+        std::process::exit(if ok { 0 } else { 1 });
+    }
+
+    match database::run_migrations().await {
+        Ok(applied) => info!("Applied {applied} database migrations"),
+        Err(err) => panic!("Couldnt run database migrations; {err}"),
+    }
+
+    if env::args().any(|a| a == "--migrate-only") {
+        return;
+    }
 
     GUILD_SETTINGS
         .set(Mutex::new(GuildSettings::new()))
@@ -103,10 +137,8 @@ async fn main() {
     panic::set_hook(Box::new(|info| {
         let payload_str = if let Some(s) = info.payload().downcast_ref::<&str>() {
             Some(s.to_string())
-        } else if let Some(s) = info.payload().downcast_ref::<String>() {
-            Some(s.clone())
         } else {
-            None
+            info.payload().downcast_ref::<String>().cloned()
         };
 
         send_error(String::from("Thread Panic"), format!("Panic info: {info:?}; Payload: {payload_str:?}"));
@@ -117,6 +149,17 @@ async fn main() {
     let mut cache_settings = Settings::default();
     cache_settings.max_messages = 0;
     let handler = Handler::new(active_env.prefix.clone());
+    let message_cache = handler.message_cache.clone();
+    let raid_detector = handler.raid_detector.clone();
+    let attachment_archive = handler.attachment_archive.clone();
+    let command_names = Arc::new(
+        handler
+            .commands
+            .iter()
+            .map(|c| c.get_name())
+            .chain(["help"])
+            .collect::<Vec<_>>(),
+    );
 
     let mut client = Client::builder(&active_env.token, intents)
         .event_handler(handler)
@@ -130,14 +173,45 @@ async fn main() {
         .write()
         .await
         .insert::<ShardManagerContainer>(shard_manager);
+    client
+        .data
+        .write()
+        .await
+        .insert::<CommandNamesContainer>(command_names);
+    client
+        .data
+        .write()
+        .await
+        .insert::<MessageCacheContainer>(message_cache.clone());
+    client
+        .data
+        .write()
+        .await
+        .insert::<RaidDetectorContainer>(raid_detector.clone());
 
     let http = client.http.clone();
+    let shutdown_cache = message_cache.clone();
+    let shutdown_manager = client.shard_manager.clone();
 
     tokio::spawn(async move {
+        shutdown_signal().await;
+        info!("Shutting down...");
+        tasks::snapshot_message_cache(&shutdown_cache).await;
+        shutdown_manager.shutdown_all().await;
+    });
+
+    tokio::spawn(async move {
+        tasks::restore_raid_verification_levels(&http).await;
+
         loop {
             sleep(Duration::from_secs(60 * 5)).await;
             tasks::check_expiring_bans(&http).await;
             tasks::check_expiring_timeouts(&http).await;
+            tasks::check_expiring_restrictions(&http).await;
+            tasks::check_expiring_channel_locks(&http).await;
+            tasks::end_quiet_raid_modes(&http, &raid_detector).await;
+            tasks::snapshot_message_cache(&message_cache).await;
+            tasks::expire_archived_attachments(&attachment_archive).await;
         }
     });
 
@@ -146,6 +220,27 @@ async fn main() {
     }
 }
 
+/// Resolves once the process is asked to stop, either by ctrl-c or a terminate signal
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let Ok(mut terminate) = signal(SignalKind::terminate()) else {
+            let _ = tokio::signal::ctrl_c().await;
+            return;
+        };
+
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
+
 #[allow(unreachable_code, dead_code)]
 fn update(arg: &str) -> std::io::Result<()> {
     let exe = env::current_exe()?;
@@ -0,0 +1,21 @@
+use std::sync::Arc;
+
+use tokio::sync::Mutex;
+use tracing::info;
+
+use crate::utils::cache::attachment_archive::{AttachmentArchive, remove_archived_files};
+
+/// Deletes archived attachments which are older than the configured ttl
+pub async fn expire_archived_attachments(archive: &Arc<Mutex<AttachmentArchive>>) {
+    let expired = {
+        let mut lock = archive.lock().await;
+        lock.expire()
+    };
+
+    if expired.is_empty() {
+        return;
+    }
+
+    info!("Removing {} expired archived attachments", expired.len());
+    remove_archived_files(expired).await;
+}
@@ -1,4 +1,4 @@
-use serenity::all::{CacheHttp, EditMember, Guild, GuildId};
+use serenity::all::{CacheHttp, EditMember, Guild, GuildId, HttpError, RoleId, UserId};
 use sqlx::query;
 use tracing::{error, info, warn};
 
@@ -67,7 +67,7 @@ pub async fn check_expiring_timeouts(cache_http: impl CacheHttp) {
         r#"
         SELECT id, guild_id, user_id, expires_at, last_reapplied_at
         FROM actions
-        WHERE type = 'timeout'
+        WHERE type IN ('mute', 'timeout')
           AND active = true;
         "#
     )
@@ -171,3 +171,220 @@ pub async fn check_expiring_timeouts(cache_http: impl CacheHttp) {
 
     info!("task check_expiring_timeouts finished");
 }
+
+pub async fn check_expiring_restrictions(cache_http: impl CacheHttp) {
+    info!("check_expiring_restrictions asynchronous task running...");
+
+    let data = match query!(
+        r#"
+        SELECT id, guild_id, user_id, role_id as "role_id!" FROM actions WHERE type = 'restrict' AND active = true AND role_id IS NOT NULL AND expires_at < NOW();
+        "#
+    ).fetch_all(&*SQL).await {
+        Ok(d) => d,
+        Err(e) => {
+            error!("task check_expiring_restrictions couldnt fetch necessary data; Err = {e:?}");
+            return;
+        }
+    };
+
+    let mut updated: Vec<String> = vec![];
+
+    for entry in data {
+        let reason = format!("Ouroboros Managed Restriction: log id `{}` expired", entry.id);
+
+        if let Err(err) = cache_http
+            .http()
+            .remove_member_role(
+                GuildId::new(entry.guild_id as u64),
+                UserId::new(entry.user_id as u64),
+                RoleId::new(entry.role_id as u64),
+                Some(&reason),
+            )
+            .await
+        {
+            // members who left don't have the role anymore, the restriction just ends
+            let left = matches!(
+                &err,
+                serenity::Error::Http(HttpError::UnsuccessfulRequest(e)) if e.status_code.as_u16() == 404
+            );
+
+            if !left {
+                warn!(
+                    "task check_expiring_restrictions couldnt remove role; Guild = {:?} Id = {:?} Err = {:?}",
+                    entry.guild_id, entry.user_id, err
+                );
+                continue;
+            }
+        }
+
+        updated.push(entry.id);
+    }
+
+    if query!(
+        r#"
+        UPDATE actions SET active = false WHERE id = ANY($1);
+        "#,
+        &updated
+    )
+    .execute(&*SQL)
+    .await
+    .is_err()
+    {
+        error!(
+            "task check_expiring_restrictions couldnt update entries; entries = {:?}",
+            updated
+        );
+    } else {
+        info!("task check_expiring_restrictions finished");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Method;
+    use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+
+    use super::{check_expiring_bans, check_expiring_restrictions, check_expiring_timeouts};
+    use crate::{
+        SQL,
+        testing::{self, TestBot, snowflake},
+    };
+
+    /// Stores an action the way the commands do, returning its id
+    async fn insert_action(
+        bot: &TestBot,
+        kind: &str,
+        expires_at: Option<NaiveDateTime>,
+        last_reapplied_at: Option<DateTime<Utc>>,
+        role_id: Option<u64>,
+    ) -> String {
+        let id = format!("test{}", snowflake());
+
+        sqlx::query(
+            "INSERT INTO actions (id, type, guild_id, user_id, moderator_id, reason, expires_at, last_reapplied_at, role_id) VALUES ($1, $2::action_type, $3, $4, $5, 'test', $6, $7, $8)",
+        )
+        .bind(&id)
+        .bind(kind)
+        .bind(bot.guild_id as i64)
+        .bind(bot.member_id as i64)
+        .bind(bot.moderator_id as i64)
+        .bind(expires_at)
+        .bind(last_reapplied_at)
+        .bind(role_id.map(|r| r as i64))
+        .execute(&*SQL)
+        .await
+        .unwrap();
+
+        id
+    }
+
+    async fn is_active(id: &str) -> bool {
+        sqlx::query_scalar("SELECT active FROM actions WHERE id = $1")
+            .bind(id)
+            .fetch_one(&*SQL)
+            .await
+            .unwrap()
+    }
+
+    #[test]
+    fn lifts_expired_bans() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            bot.discord
+                .state()
+                .bans
+                .insert((bot.guild_id, bot.member_id));
+            let expired = insert_action(
+                &bot,
+                "ban",
+                Some((Utc::now() - Duration::hours(1)).naive_utc()),
+                None,
+                None,
+            )
+            .await;
+
+            check_expiring_bans(&bot.ctx).await;
+
+            let unbans = bot.discord.requests(
+                Method::DELETE,
+                &format!("/guilds/{}/bans/{}", bot.guild_id, bot.member_id),
+            );
+            assert_eq!(unbans.len(), 1);
+            assert!(bot.discord.state().bans.is_empty());
+            assert!(!is_active(&expired).await);
+        });
+    }
+
+    #[test]
+    fn reapplies_long_timeouts() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            let now = Utc::now();
+            let permanent =
+                insert_action(&bot, "mute", None, Some(now - Duration::days(21)), None).await;
+
+            check_expiring_timeouts(&bot.ctx).await;
+
+            let edits = bot.discord.requests(
+                Method::PATCH,
+                &format!("/guilds/{}/members/{}", bot.guild_id, bot.member_id),
+            );
+            assert_eq!(edits.len(), 1);
+            assert!(edits[0].payload()["communication_disabled_until"].is_string());
+
+            let reapplied: Option<DateTime<Utc>> =
+                sqlx::query_scalar("SELECT last_reapplied_at FROM actions WHERE id = $1")
+                    .bind(&permanent)
+                    .fetch_one(&*SQL)
+                    .await
+                    .unwrap();
+            assert!(reapplied.is_some_and(|r| r > now - Duration::minutes(1)));
+
+            // a recently reapplied timeout is left alone
+            check_expiring_timeouts(&bot.ctx).await;
+
+            let edits = bot.discord.requests(
+                Method::PATCH,
+                &format!("/guilds/{}/members/{}", bot.guild_id, bot.member_id),
+            );
+            assert_eq!(edits.len(), 1);
+        });
+    }
+
+    #[test]
+    fn removes_expired_restriction_roles() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            let role_id = snowflake();
+            let expired = insert_action(
+                &bot,
+                "restrict",
+                Some((Utc::now() - Duration::minutes(5)).naive_utc()),
+                None,
+                Some(role_id),
+            )
+            .await;
+
+            check_expiring_restrictions(&bot.ctx).await;
+
+            let removals = bot.discord.requests(
+                Method::DELETE,
+                &format!(
+                    "/guilds/{}/members/{}/roles/{role_id}",
+                    bot.guild_id, bot.member_id
+                ),
+            );
+            assert_eq!(removals.len(), 1);
+            assert!(!is_active(&expired).await);
+        });
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+use serenity::all::{CacheHttp, ChannelId, CreateEmbed, CreateMessage, GuildId, Mentionable};
+use sqlx::query;
+use tracing::{error, info, warn};
+
+use crate::{
+    SQL,
+    commands::{revert_slowmode, unlock_channel},
+    constants::BRAND_BLUE,
+    database::ChannelLockType,
+    utils::{LogType, guild_log},
+};
+
+pub async fn check_expiring_channel_locks(ctx: impl CacheHttp) {
+    info!("check_expiring_channel_locks asynchronous task running...");
+
+    let data = match query!(
+        r#"
+        SELECT channel_id, guild_id, type as "type!: ChannelLockType", lockdown FROM channel_locks WHERE expires_at < NOW();
+        "#
+    ).fetch_all(&*SQL).await {
+        Ok(d) => d,
+        Err(e) => {
+            error!("task check_expiring_channel_locks couldnt fetch necessary data; Err = {e:?}");
+            return;
+        }
+    };
+
+    // lockdowns are summarized in a single log per guild instead of one log per channel
+    let mut lockdowns: HashMap<GuildId, usize> = HashMap::new();
+
+    for entry in data {
+        let guild_id = GuildId::new(entry.guild_id as u64);
+        let channel_id = ChannelId::new(entry.channel_id as u64);
+
+        let description = match entry.r#type {
+            ChannelLockType::Lock => {
+                if let Err(err) = unlock_channel(&ctx, guild_id, channel_id).await {
+                    warn!(
+                        "task check_expiring_channel_locks couldnt unlock channel; Channel = {:?} Err = {:?}",
+                        entry.channel_id, err.title
+                    );
+                    continue;
+                }
+
+                let _ = channel_id
+                    .send_message(
+                        &ctx,
+                        CreateMessage::new().add_embed(
+                            CreateEmbed::new()
+                                .description("**CHANNEL UNLOCKED**\n-# Lock expired")
+                                .color(BRAND_BLUE),
+                        ),
+                    )
+                    .await;
+
+                if entry.lockdown {
+                    *lockdowns.entry(guild_id).or_default() += 1;
+                    continue;
+                }
+
+                format!(
+                    "**CHANNEL UNLOCKED**\n-# Channel: {} `{}` | Lock expired",
+                    channel_id.mention(),
+                    channel_id.get()
+                )
+            }
+            ChannelLockType::Slowmode => match revert_slowmode(&ctx, guild_id, channel_id).await {
+                Ok(seconds) => format!(
+                    "**SLOWMODE REVERTED**\n-# Channel: {} `{}` | Interval: {seconds}s | Temporary slowmode expired",
+                    channel_id.mention(),
+                    channel_id.get()
+                ),
+                Err(err) => {
+                    warn!(
+                        "task check_expiring_channel_locks couldnt revert slowmode; Channel = {:?} Err = {:?}",
+                        entry.channel_id, err.title
+                    );
+                    continue;
+                }
+            },
+        };
+
+        guild_log(
+            &ctx,
+            LogType::ChannelModeration,
+            guild_id,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .description(description)
+                    .color(BRAND_BLUE),
+            ),
+        )
+        .await;
+    }
+
+    for (guild_id, count) in lockdowns {
+        guild_log(
+            &ctx,
+            LogType::ChannelModeration,
+            guild_id,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .description(format!(
+                        "**LOCKDOWN ENDED**\n-# Unlocked: {count} | Lockdown expired"
+                    ))
+                    .color(BRAND_BLUE),
+            ),
+        )
+        .await;
+    }
+}
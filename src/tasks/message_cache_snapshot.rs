@@ -0,0 +1,238 @@
+use chrono::{DateTime, Duration, Utc};
+use serenity::{
+    all::GuildId,
+    json::{self, Value},
+};
+use sqlx::{PgExecutor, query};
+use tracing::{error, info};
+
+use crate::{
+    BOT_CONFIG, SQL,
+    utils::{
+        cache::{
+            message_cache::MessageCache,
+            partials::{PartialAttachment, PartialMessage, PartialUser},
+        },
+        snowflake_to_timestamp,
+    },
+};
+
+/// How long snapshotted messages are kept if the config doesn't specify a retention
+const DEFAULT_RETENTION_DAYS: u32 = 7;
+/// The first second of 2015 in milliseconds, snowflakes count from it
+const DISCORD_EPOCH: i64 = 1420070400000;
+
+/// Whether the message cache should be snapshotted, disabled unless turned on in the config
+fn persistence_enabled() -> bool {
+    BOT_CONFIG.persist_message_cache.unwrap_or(false)
+}
+
+/// Messages sent before this point are neither snapshotted nor restored
+fn retention_cutoff() -> DateTime<Utc> {
+    let days = BOT_CONFIG
+        .message_cache_retention_days
+        .unwrap_or(DEFAULT_RETENTION_DAYS);
+
+    Utc::now() - Duration::days(days as i64)
+}
+
+/// Stores the current contents of the message cache, updating messages which were stored before,
+/// and prunes stored messages past the retention.
+/// Messages of guilds which opted out, direct messages and messages past the retention are left out.
+pub async fn snapshot_message_cache(cache: &MessageCache) {
+    if !persistence_enabled() {
+        return;
+    }
+
+    info!("snapshot_message_cache asynchronous task running...");
+
+    let messages = cache.all_messages();
+
+    let cutoff = retention_cutoff();
+    let messages = messages
+        .into_iter()
+        .filter(|m| m.guild_id.is_some() && snowflake_to_timestamp(m.id) > cutoff)
+        .collect::<Vec<_>>();
+
+    let mut message_ids: Vec<i64> = Vec::with_capacity(messages.len());
+    let mut channel_ids: Vec<i64> = Vec::with_capacity(messages.len());
+    let mut guild_ids: Vec<i64> = Vec::with_capacity(messages.len());
+    let mut author_ids: Vec<i64> = Vec::with_capacity(messages.len());
+    let mut author_names: Vec<String> = Vec::with_capacity(messages.len());
+    let mut author_bots: Vec<bool> = Vec::with_capacity(messages.len());
+    let mut contents: Vec<String> = Vec::with_capacity(messages.len());
+    let mut attachments: Vec<Value> = Vec::with_capacity(messages.len());
+    let mut replies: Vec<Option<i64>> = Vec::with_capacity(messages.len());
+
+    for m in messages {
+        message_ids.push(m.id as i64);
+        channel_ids.push(m.channel_id as i64);
+        guild_ids.push(m.guild_id.unwrap_or_default() as i64);
+        author_ids.push(m.author.id as i64);
+        // postgres text can't hold nul characters
+        author_names.push(m.author.name.replace('\0', ""));
+        author_bots.push(m.author.bot);
+        contents.push(m.content.replace('\0', ""));
+        attachments.push(json::to_value(&m.attachment_urls).unwrap_or(Value::Array(vec![])));
+        replies.push(m.reply_to.map(|r| r as i64));
+    }
+
+    let count = message_ids.len();
+
+    let mut tx = match SQL.begin().await {
+        Ok(t) => t,
+        Err(err) => {
+            error!("task snapshot_message_cache couldnt start a transaction; Err = {err:?}");
+            return;
+        }
+    };
+
+    // snowflakes start with their timestamp, older messages have lower ids
+    let oldest_id = ((cutoff.timestamp_millis() - DISCORD_EPOCH) << 22).max(0);
+
+    if let Err(err) = query!(
+        "DELETE FROM message_cache_snapshot WHERE message_id < $1",
+        oldest_id
+    )
+    .execute(&mut *tx)
+    .await
+    {
+        error!("task snapshot_message_cache couldnt prune the snapshot; Err = {err:?}");
+        return;
+    }
+
+    if let Err(err) = query!(
+        r#"
+            INSERT INTO message_cache_snapshot
+                (message_id, channel_id, guild_id, author_id, author_name, author_bot, content, attachments, reply_to)
+            SELECT m.* FROM UNNEST(
+                $1::BIGINT[], $2::BIGINT[], $3::BIGINT[], $4::BIGINT[], $5::TEXT[],
+                $6::BOOLEAN[], $7::TEXT[], $8::JSONB[], $9::BIGINT[]
+            ) AS m(message_id, channel_id, guild_id, author_id, author_name, author_bot, content, attachments, reply_to)
+            WHERE NOT EXISTS (
+                SELECT 1 FROM guild_settings g WHERE g.guild_id = m.guild_id AND g.message_cache_opt_out
+            )
+            ON CONFLICT (message_id) DO UPDATE SET
+                author_name = EXCLUDED.author_name,
+                content = EXCLUDED.content,
+                attachments = EXCLUDED.attachments
+        "#,
+        &message_ids,
+        &channel_ids,
+        &guild_ids,
+        &author_ids,
+        &author_names,
+        &author_bots,
+        &contents,
+        &attachments,
+        &replies as &[Option<i64>],
+    )
+    .execute(&mut *tx)
+    .await
+    {
+        error!("task snapshot_message_cache couldnt store the snapshot; Err = {err:?}");
+        return;
+    }
+
+    if let Err(err) = tx.commit().await {
+        error!("task snapshot_message_cache couldnt commit the snapshot; Err = {err:?}");
+        return;
+    }
+
+    info!("Snapshotted {count} cached messages");
+}
+
+/// Forgets deleted messages which were stored by an earlier snapshot
+pub async fn delete_snapshot_messages(message_ids: &[u64]) {
+    if !persistence_enabled() || message_ids.is_empty() {
+        return;
+    }
+
+    let message_ids = message_ids.iter().map(|id| *id as i64).collect::<Vec<_>>();
+
+    if let Err(err) = query!(
+        "DELETE FROM message_cache_snapshot WHERE message_id = ANY($1)",
+        &message_ids
+    )
+    .execute(&*SQL)
+    .await
+    {
+        error!("Couldnt delete messages from the message cache snapshot; err = {err:?}");
+    }
+}
+
+/// Fills the message cache with the last snapshot, cache sizes have to be assigned before
+pub async fn restore_message_cache(cache: &MessageCache) {
+    if !persistence_enabled() {
+        return;
+    }
+
+    let rows = match query!(
+        r#"
+            SELECT s.message_id, s.channel_id, s.guild_id, s.author_id, s.author_name, s.author_bot,
+                s.content, s.attachments, s.reply_to
+            FROM message_cache_snapshot s
+            WHERE NOT EXISTS (
+                SELECT 1 FROM guild_settings g WHERE g.guild_id = s.guild_id AND g.message_cache_opt_out
+            )
+            ORDER BY s.message_id ASC
+        "#
+    )
+    .fetch_all(&*SQL)
+    .await
+    {
+        Ok(r) => r,
+        Err(err) => {
+            error!("Couldnt fetch the message cache snapshot; err = {err:?}");
+            return;
+        }
+    };
+
+    let cutoff = retention_cutoff();
+    let mut restored = 0;
+
+    for row in rows {
+        if snowflake_to_timestamp(row.message_id as u64) <= cutoff {
+            continue;
+        }
+
+        cache.restore(PartialMessage {
+            id: row.message_id as u64,
+            guild_id: Some(row.guild_id as u64),
+            channel_id: row.channel_id as u64,
+            content: row.content,
+            author: PartialUser {
+                id: row.author_id as u64,
+                name: row.author_name,
+                bot: row.author_bot,
+            },
+            attachment_urls: json::from_value::<Vec<PartialAttachment>>(row.attachments)
+                .unwrap_or_default(),
+            reply_to: row.reply_to.map(|r| r as u64),
+        });
+
+        restored += 1;
+    }
+
+    info!("Restored {restored} messages from the message cache snapshot");
+}
+
+/// Deletes the stored messages of a guild if it opted out of message cache snapshots.
+/// Runs after every change of the opt out, whether made by the `privacy` or the `config` command.
+pub async fn delete_opted_out_snapshot(
+    executor: impl PgExecutor<'_>,
+    guild_id: GuildId,
+) -> Result<(), sqlx::Error> {
+    query!(
+        r#"
+            DELETE FROM message_cache_snapshot s
+            USING guild_settings g
+            WHERE s.guild_id = $1 AND g.guild_id = $1 AND g.message_cache_opt_out
+        "#,
+        guild_id.get() as i64
+    )
+    .execute(executor)
+    .await?;
+
+    Ok(())
+}
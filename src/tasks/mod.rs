@@ -1,3 +1,20 @@
 mod expiring_actions;
 pub use expiring_actions::check_expiring_bans;
+pub use expiring_actions::check_expiring_restrictions;
 pub use expiring_actions::check_expiring_timeouts;
+
+mod expiring_channel_locks;
+pub use expiring_channel_locks::check_expiring_channel_locks;
+
+mod raid_mode;
+pub use raid_mode::end_quiet_raid_modes;
+pub use raid_mode::restore_raid_verification_levels;
+
+mod message_cache_snapshot;
+pub use message_cache_snapshot::delete_opted_out_snapshot;
+pub use message_cache_snapshot::delete_snapshot_messages;
+pub use message_cache_snapshot::restore_message_cache;
+pub use message_cache_snapshot::snapshot_message_cache;
+
+mod attachment_archive;
+pub use attachment_archive::expire_archived_attachments;
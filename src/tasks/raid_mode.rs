@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use chrono::Duration;
+use serenity::all::{CacheHttp, GuildId, VerificationLevel};
+use sqlx::query;
+use tokio::sync::Mutex;
+use tracing::{error, info};
+
+use crate::{
+    GUILD_SETTINGS, SQL,
+    utils::{AntiRaidConfig, RaidDetector, end_raid_mode, restore_verification_level},
+};
+
+pub async fn end_quiet_raid_modes(ctx: impl CacheHttp, detector: &Mutex<RaidDetector>) {
+    let guilds = detector.lock().await.active_guilds();
+
+    if guilds.is_empty() {
+        return;
+    }
+
+    info!("end_quiet_raid_modes asynchronous task running...");
+
+    let mut cooldowns = HashMap::new();
+
+    for guild_id in guilds {
+        let mut lock = GUILD_SETTINGS.lock().await;
+        let config = lock
+            .get(guild_id.get())
+            .await
+            .map(|s| s.moderation.anti_raid)
+            .unwrap_or_default();
+
+        cooldowns.insert(guild_id, Duration::seconds(config.cooldown));
+    }
+
+    let quiet = detector.lock().await.quiet_guilds(|g| {
+        cooldowns
+            .get(&g)
+            .copied()
+            .unwrap_or(Duration::seconds(AntiRaidConfig::default().cooldown))
+    });
+
+    for guild_id in quiet {
+        end_raid_mode(&ctx, detector, guild_id, None).await;
+    }
+}
+
+/// Raid mode doesn't survive restarts, restores the verification levels of raid modes which were active when the bot stopped
+pub async fn restore_raid_verification_levels(ctx: impl CacheHttp) {
+    let data = match query!(
+        "SELECT guild_id, raid_id, verification_level FROM raid_verification_levels"
+    )
+    .fetch_all(&*SQL)
+    .await
+    {
+        Ok(d) => d,
+        Err(e) => {
+            error!(
+                "task restore_raid_verification_levels couldnt fetch necessary data; Err = {e:?}"
+            );
+            return;
+        }
+    };
+
+    if data.is_empty() {
+        return;
+    }
+
+    info!("restore_raid_verification_levels asynchronous task running...");
+
+    for entry in data {
+        restore_verification_level(
+            &ctx,
+            GuildId::new(entry.guild_id as u64),
+            VerificationLevel::from(entry.verification_level as u8),
+            &entry.raid_id,
+        )
+        .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::Method;
+
+    use super::restore_raid_verification_levels;
+    use crate::{
+        SQL,
+        testing::{self, TestBot},
+    };
+
+    #[test]
+    fn restores_verification_levels_of_stopped_raid_modes() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            sqlx::query(
+                "INSERT INTO raid_verification_levels (guild_id, raid_id, verification_level) VALUES ($1, 'raid01', 1)",
+            )
+            .bind(bot.guild_id as i64)
+            .execute(&*SQL)
+            .await
+            .unwrap();
+
+            restore_raid_verification_levels(&bot.ctx).await;
+
+            let edits = bot
+                .discord
+                .requests(Method::PATCH, &format!("/guilds/{}", bot.guild_id));
+            assert_eq!(edits.len(), 1);
+            assert_eq!(edits[0].payload()["verification_level"], 1);
+
+            let left: i64 = sqlx::query_scalar(
+                "SELECT COUNT(*) FROM raid_verification_levels WHERE guild_id = $1",
+            )
+            .bind(bot.guild_id as i64)
+            .fetch_one(&*SQL)
+            .await
+            .unwrap();
+            assert_eq!(left, 0);
+        });
+    }
+}
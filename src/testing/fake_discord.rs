@@ -0,0 +1,674 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, MutexGuard, PoisonError},
+};
+
+use axum::{
+    Router,
+    body::Bytes,
+    extract::State,
+    http::{Method, StatusCode, Uri, header},
+    response::{IntoResponse, Response},
+};
+use serenity::json::{self, Value, json};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::WebSocketStream;
+
+/// A request the bot made to the fake api, the path is relative to `/api/v10`
+#[derive(Debug, Clone)]
+pub struct RecordedRequest {
+    pub method: Method,
+    pub path: String,
+    pub query: HashMap<String, String>,
+    pub body: String,
+}
+
+impl RecordedRequest {
+    /// The json payload of the request, multipart requests carry it in the `payload_json` part
+    pub fn payload(&self) -> Value {
+        payload_json(&self.body).unwrap_or(Value::Null)
+    }
+}
+
+/// Everything the fake api knows about, tests seed it through [`FakeDiscord::state`] and inspect it afterwards
+#[derive(Default)]
+pub struct FakeState {
+    pub guilds: HashMap<u64, Value>,
+    pub members: HashMap<(u64, u64), Value>,
+    pub channels: HashMap<u64, Value>,
+    pub users: HashMap<u64, Value>,
+    /// Messages of each channel, oldest first
+    pub messages: HashMap<u64, Vec<Value>>,
+    pub bans: HashSet<(u64, u64)>,
+    /// Entries returned for every audit log request, newest first
+    pub audit_log: Vec<Value>,
+    /// Users whose DMs are closed, sending them a message fails like it does on Discord
+    pub closed_dms: HashSet<u64>,
+    /// Routes which fail with missing permissions, as method and path relative to `/api/v10`
+    pub failing: HashSet<(Method, String)>,
+    pub requests: Vec<RecordedRequest>,
+    dm_channels: HashMap<u64, u64>,
+    next_id: u64,
+    bot: Value,
+}
+
+/// Stand-in for the parts of the Discord REST api and gateway the bot uses.
+/// Http clients are pointed at it as a proxy, the gateway only accepts connections so shard messengers can be built.
+pub struct FakeDiscord {
+    pub url: String,
+    pub gateway_url: String,
+    state: Arc<Mutex<FakeState>>,
+}
+
+impl FakeDiscord {
+    pub async fn start(bot: Value, first_id: u64) -> Self {
+        let state = Arc::new(Mutex::new(FakeState {
+            next_id: first_id,
+            bot,
+            ..Default::default()
+        }));
+
+        let api = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("could not bind the fake api");
+        let url = format!("http://{}", api.local_addr().unwrap());
+        let app = Router::new().fallback(handle).with_state(state.clone());
+
+        tokio::spawn(async move { axum::serve(api, app).await });
+
+        let gateway = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("could not bind the fake gateway");
+        let gateway_url = format!("ws://{}", gateway.local_addr().unwrap());
+
+        tokio::spawn(async move {
+            // connections are kept open but never spoken to, no events are dispatched in tests
+            let mut connections: Vec<WebSocketStream<TcpStream>> = vec![];
+
+            while let Ok((stream, _)) = gateway.accept().await {
+                if let Ok(ws) = tokio_tungstenite::accept_async(stream).await {
+                    connections.push(ws);
+                }
+            }
+        });
+
+        Self {
+            url,
+            gateway_url,
+            state,
+        }
+    }
+
+    pub fn state(&self) -> MutexGuard<'_, FakeState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Requests matching a method and a path relative to `/api/v10`, i.e. `/guilds/1/bans/2`
+    pub fn requests(&self, method: Method, path: &str) -> Vec<RecordedRequest> {
+        self.state()
+            .requests
+            .iter()
+            .filter(|r| r.method == method && r.path == path)
+            .cloned()
+            .collect()
+    }
+
+    /// Payloads of the messages the bot sent to a channel
+    pub fn sent_messages(&self, channel_id: u64) -> Vec<Value> {
+        self.requests(Method::POST, &format!("/channels/{channel_id}/messages"))
+            .iter()
+            .map(RecordedRequest::payload)
+            .collect()
+    }
+
+    /// Payloads of the direct messages the bot sent to a user
+    pub fn sent_dms(&self, user_id: u64) -> Vec<Value> {
+        let channel = self.state().dm_channels.get(&user_id).copied();
+
+        channel.map(|c| self.sent_messages(c)).unwrap_or_default()
+    }
+}
+
+async fn handle(
+    State(state): State<Arc<Mutex<FakeState>>>,
+    method: Method,
+    uri: Uri,
+    body: Bytes,
+) -> Response {
+    let path = uri
+        .path()
+        .strip_prefix("/api/v10")
+        .unwrap_or(uri.path())
+        .to_string();
+    let query = uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter_map(|p| p.split_once('='))
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect::<HashMap<_, _>>();
+
+    let request = RecordedRequest {
+        method,
+        path,
+        query,
+        body: String::from_utf8_lossy(&body).into_owned(),
+    };
+
+    let mut state = state.lock().unwrap_or_else(PoisonError::into_inner);
+    let (status, body) = state.respond(&request);
+    state.requests.push(request);
+
+    match body {
+        Some(body) => (
+            status,
+            [(header::CONTENT_TYPE, "application/json")],
+            json::to_string(&body).unwrap_or_default(),
+        )
+            .into_response(),
+        None => status.into_response(),
+    }
+}
+
+type FakeResponse = (StatusCode, Option<Value>);
+
+fn ok(value: Value) -> FakeResponse {
+    (StatusCode::OK, Some(value))
+}
+
+fn no_content() -> FakeResponse {
+    (StatusCode::NO_CONTENT, None)
+}
+
+fn error(status: StatusCode, code: u64, message: &str) -> FakeResponse {
+    (status, Some(json!({ "code": code, "message": message })))
+}
+
+fn not_found(message: &str) -> FakeResponse {
+    error(StatusCode::NOT_FOUND, 10000, message)
+}
+
+impl FakeState {
+    fn id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn respond(&mut self, request: &RecordedRequest) -> FakeResponse {
+        if self
+            .failing
+            .contains(&(request.method.clone(), request.path.clone()))
+        {
+            return error(StatusCode::FORBIDDEN, 50013, "Missing Permissions");
+        }
+
+        let segments = request
+            .path
+            .trim_matches('/')
+            .split('/')
+            .collect::<Vec<_>>();
+        let id = |i: usize| segments.get(i).and_then(|s| s.parse::<u64>().ok());
+
+        match (request.method.as_str(), segments.as_slice()) {
+            ("GET", ["guilds", _]) => match self.guilds.get(&id(1).unwrap_or_default()) {
+                Some(g) => ok(g.clone()),
+                None => not_found("Unknown Guild"),
+            },
+
+            ("PATCH", ["guilds", _]) => {
+                let Some(guild) = self.guilds.get_mut(&id(1).unwrap_or_default()) else {
+                    return not_found("Unknown Guild");
+                };
+
+                if let Value::Object(changes) = request.payload() {
+                    for (k, v) in changes {
+                        guild[k] = v;
+                    }
+                }
+
+                ok(guild.clone())
+            }
+
+            ("GET", ["guilds", _, "roles"]) => match self.guilds.get(&id(1).unwrap_or_default()) {
+                Some(g) => ok(g["roles"].clone()),
+                None => not_found("Unknown Guild"),
+            },
+
+            ("GET", ["guilds", _, "audit-logs"]) => ok(json!({
+                "audit_log_entries": self.audit_log,
+                "users": [],
+                "webhooks": [],
+                "integrations": [],
+                "threads": [],
+                "application_commands": [],
+                "auto_moderation_rules": [],
+                "guild_scheduled_events": [],
+            })),
+
+            ("GET", ["guilds", _, "members", _]) => {
+                match self
+                    .members
+                    .get(&(id(1).unwrap_or_default(), id(3).unwrap_or_default()))
+                {
+                    Some(m) => ok(m.clone()),
+                    None => not_found("Unknown Member"),
+                }
+            }
+
+            ("PATCH", ["guilds", _, "members", _]) => {
+                let key = (id(1).unwrap_or_default(), id(3).unwrap_or_default());
+                let Some(member) = self.members.get_mut(&key) else {
+                    return not_found("Unknown Member");
+                };
+
+                if let Value::Object(changes) = request.payload() {
+                    for (k, v) in changes {
+                        member[k] = v;
+                    }
+                }
+
+                ok(member.clone())
+            }
+
+            ("DELETE", ["guilds", _, "members", _]) => {
+                let key = (id(1).unwrap_or_default(), id(3).unwrap_or_default());
+
+                match self.members.remove(&key) {
+                    Some(_) => no_content(),
+                    None => not_found("Unknown Member"),
+                }
+            }
+
+            ("PUT" | "DELETE", ["guilds", _, "members", _, "roles", role]) => {
+                let key = (id(1).unwrap_or_default(), id(3).unwrap_or_default());
+                let Some(member) = self.members.get_mut(&key) else {
+                    return not_found("Unknown Member");
+                };
+
+                let mut roles = member["roles"]
+                    .as_array()
+                    .cloned()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(|r| r.as_str() != Some(role))
+                    .collect::<Vec<_>>();
+
+                if request.method == Method::PUT {
+                    roles.push(json!(role));
+                }
+
+                member["roles"] = Value::Array(roles);
+                no_content()
+            }
+
+            ("PUT", ["guilds", _, "bans", _]) => {
+                let (guild, user) = (id(1).unwrap_or_default(), id(3).unwrap_or_default());
+
+                if !self.guilds.contains_key(&guild) {
+                    return not_found("Unknown Guild");
+                }
+
+                self.members.remove(&(guild, user));
+                self.bans.insert((guild, user));
+                no_content()
+            }
+
+            ("DELETE", ["guilds", _, "bans", _]) => {
+                if self
+                    .bans
+                    .remove(&(id(1).unwrap_or_default(), id(3).unwrap_or_default()))
+                {
+                    no_content()
+                } else {
+                    not_found("Unknown Ban")
+                }
+            }
+
+            ("GET", ["guilds", _, "channels"]) => {
+                let guild_id = id(1).unwrap_or_default().to_string();
+
+                ok(Value::Array(
+                    self.channels
+                        .values()
+                        .filter(|c| c["guild_id"] == guild_id.as_str())
+                        .cloned()
+                        .collect(),
+                ))
+            }
+
+            ("GET", ["channels", _]) => match self.channels.get(&id(1).unwrap_or_default()) {
+                Some(c) => ok(c.clone()),
+                None => not_found("Unknown Channel"),
+            },
+
+            ("POST", ["channels", _, "typing"]) => no_content(),
+
+            ("PUT", ["channels", _, "permissions", target]) => {
+                let Some(channel) = self.channels.get_mut(&id(1).unwrap_or_default()) else {
+                    return not_found("Unknown Channel");
+                };
+
+                let mut overwrite = request.payload();
+                overwrite["id"] = json!(target);
+
+                let overwrites = channel["permission_overwrites"].as_array_mut().unwrap();
+                overwrites.retain(|o| o["id"] != *target);
+                overwrites.push(overwrite);
+                no_content()
+            }
+
+            ("GET", ["channels", _, "messages"]) => {
+                let before = request
+                    .query
+                    .get("before")
+                    .and_then(|b| b.parse::<u64>().ok());
+                let after = request
+                    .query
+                    .get("after")
+                    .and_then(|a| a.parse::<u64>().ok());
+                let limit = request
+                    .query
+                    .get("limit")
+                    .and_then(|l| l.parse::<usize>().ok())
+                    .unwrap_or(50);
+
+                let messages = self
+                    .messages
+                    .get(&id(1).unwrap_or_default())
+                    .cloned()
+                    .unwrap_or_default()
+                    .into_iter()
+                    .rev()
+                    .filter(|m| {
+                        let id = message_id(m);
+                        before.is_none_or(|b| id < b) && after.is_none_or(|a| id > a)
+                    })
+                    .take(limit)
+                    .collect::<Vec<_>>();
+
+                ok(Value::Array(messages))
+            }
+
+            ("GET", ["channels", _, "messages", _]) => {
+                let message = self
+                    .messages
+                    .get(&id(1).unwrap_or_default())
+                    .and_then(|m| m.iter().find(|m| Some(message_id(m)) == id(3)));
+
+                match message {
+                    Some(m) => ok(m.clone()),
+                    None => not_found("Unknown Message"),
+                }
+            }
+
+            ("POST", ["channels", _, "messages"]) => {
+                let channel_id = id(1).unwrap_or_default();
+
+                let Some(channel) = self.channels.get(&channel_id).cloned() else {
+                    return not_found("Unknown Channel");
+                };
+
+                if let Some(recipient) = snowflake_of(&channel["recipients"][0]["id"])
+                    && self.closed_dms.contains(&recipient)
+                {
+                    return error(
+                        StatusCode::FORBIDDEN,
+                        50007,
+                        "Cannot send messages to this user",
+                    );
+                }
+
+                let payload = request.payload();
+                let id = self.id();
+                let mut message = message_json(
+                    id,
+                    channel_id,
+                    snowflake_of(&channel["guild_id"]),
+                    self.bot.clone(),
+                    payload["content"].as_str().unwrap_or_default(),
+                );
+                message["embeds"] = payload.get("embeds").cloned().unwrap_or(json!([]));
+
+                self.messages
+                    .entry(channel_id)
+                    .or_default()
+                    .push(message.clone());
+
+                ok(message)
+            }
+
+            ("PATCH", ["channels", _, "messages", _]) => {
+                let payload = request.payload();
+                let message = self
+                    .messages
+                    .get_mut(&id(1).unwrap_or_default())
+                    .and_then(|m| m.iter_mut().find(|m| Some(message_id(m)) == id(3)));
+
+                match message {
+                    Some(m) => {
+                        for key in ["content", "embeds"] {
+                            if let Some(value) = payload.get(key) {
+                                m[key] = value.clone();
+                            }
+                        }
+
+                        ok(m.clone())
+                    }
+                    None => not_found("Unknown Message"),
+                }
+            }
+
+            ("DELETE", ["channels", _, "messages", _]) => {
+                let messages = self.messages.entry(id(1).unwrap_or_default()).or_default();
+                let count = messages.len();
+                messages.retain(|m| Some(message_id(m)) != id(3));
+
+                if messages.len() == count {
+                    not_found("Unknown Message")
+                } else {
+                    no_content()
+                }
+            }
+
+            ("POST", ["channels", _, "messages", "bulk-delete"]) => {
+                let ids = request.payload()["messages"]
+                    .as_array()
+                    .cloned()
+                    .unwrap_or_default()
+                    .iter()
+                    .filter_map(snowflake_of)
+                    .collect::<HashSet<_>>();
+
+                self.messages
+                    .entry(id(1).unwrap_or_default())
+                    .or_default()
+                    .retain(|m| !ids.contains(&message_id(m)));
+
+                no_content()
+            }
+
+            ("GET", ["users", _]) => match self.users.get(&id(1).unwrap_or_default()) {
+                Some(u) => ok(u.clone()),
+                None => not_found("Unknown User"),
+            },
+
+            ("POST", ["users", "@me", "channels"]) => {
+                let Some(recipient) = snowflake_of(&request.payload()["recipient_id"]) else {
+                    return error(StatusCode::BAD_REQUEST, 50035, "Invalid Form Body");
+                };
+
+                let Some(user) = self.users.get(&recipient).cloned() else {
+                    return not_found("Unknown User");
+                };
+
+                let channel_id = match self.dm_channels.get(&recipient) {
+                    Some(c) => *c,
+                    None => {
+                        let id = self.id();
+                        self.dm_channels.insert(recipient, id);
+                        id
+                    }
+                };
+
+                let channel = json!({
+                    "id": channel_id.to_string(),
+                    "type": 1,
+                    "recipients": [user],
+                    "last_message_id": null,
+                });
+                self.channels.insert(channel_id, channel.clone());
+
+                ok(channel)
+            }
+
+            _ => not_found("404: Not Found"),
+        }
+    }
+}
+
+/// Snowflakes are sent as strings, but numbers are accepted as well
+fn snowflake_of(value: &Value) -> Option<u64> {
+    value
+        .as_str()
+        .and_then(|s| s.parse().ok())
+        .or_else(|| value.as_u64())
+}
+
+fn message_id(message: &Value) -> u64 {
+    snowflake_of(&message["id"]).unwrap_or_default()
+}
+
+/// Gets the json payload out of a json or multipart request body
+fn payload_json(body: &str) -> Option<Value> {
+    if body.trim_start().starts_with('{') {
+        return json::from_str(body).ok();
+    }
+
+    let part = body.split("name=\"payload_json\"").nth(1)?;
+    let (_, rest) = part.split_once("\r\n\r\n")?;
+    let end = rest.find("\r\n--").unwrap_or(rest.len());
+
+    json::from_str(&rest[..end]).ok()
+}
+
+pub fn user_json(id: u64, name: &str, bot: bool) -> Value {
+    json!({
+        "id": id.to_string(),
+        "username": name,
+        "global_name": null,
+        "discriminator": "0",
+        "avatar": null,
+        "bot": bot,
+    })
+}
+
+pub fn role_json(id: u64, name: &str, position: u16, permissions: u64) -> Value {
+    json!({
+        "id": id.to_string(),
+        "name": name,
+        "color": 0,
+        "hoist": false,
+        "icon": null,
+        "unicode_emoji": null,
+        "position": position,
+        "permissions": permissions.to_string(),
+        "managed": false,
+        "mentionable": false,
+        "flags": 0,
+    })
+}
+
+pub fn guild_json(id: u64, name: &str, owner_id: u64, roles: Vec<Value>) -> Value {
+    json!({
+        "id": id.to_string(),
+        "name": name,
+        "icon": null,
+        "icon_hash": null,
+        "splash": null,
+        "discovery_splash": null,
+        "owner_id": owner_id.to_string(),
+        "afk_channel_id": null,
+        "afk_timeout": 300,
+        "verification_level": 0,
+        "default_message_notifications": 0,
+        "explicit_content_filter": 0,
+        "roles": roles,
+        "emojis": [],
+        "features": [],
+        "mfa_level": 0,
+        "application_id": null,
+        "system_channel_id": null,
+        "system_channel_flags": 0,
+        "rules_channel_id": null,
+        "vanity_url_code": null,
+        "description": null,
+        "banner": null,
+        "premium_tier": 0,
+        "preferred_locale": "en-US",
+        "public_updates_channel_id": null,
+        "nsfw_level": 0,
+        "stickers": [],
+        "premium_progress_bar_enabled": false,
+    })
+}
+
+pub fn member_json(guild_id: u64, user: Value, roles: &[u64]) -> Value {
+    json!({
+        "user": user,
+        "nick": null,
+        "avatar": null,
+        "roles": roles.iter().map(|r| r.to_string()).collect::<Vec<_>>(),
+        "joined_at": "2024-01-01T00:00:00.000000+00:00",
+        "premium_since": null,
+        "deaf": false,
+        "mute": false,
+        "flags": 0,
+        "pending": false,
+        "communication_disabled_until": null,
+        "guild_id": guild_id.to_string(),
+    })
+}
+
+pub fn channel_json(id: u64, guild_id: u64, name: &str) -> Value {
+    json!({
+        "id": id.to_string(),
+        "type": 0,
+        "guild_id": guild_id.to_string(),
+        "name": name,
+        "position": 0,
+        "permission_overwrites": [],
+        "nsfw": false,
+        "topic": null,
+        "last_message_id": null,
+        "rate_limit_per_user": 0,
+        "parent_id": null,
+    })
+}
+
+pub fn message_json(
+    id: u64,
+    channel_id: u64,
+    guild_id: Option<u64>,
+    author: Value,
+    content: &str,
+) -> Value {
+    let timestamp = crate::utils::snowflake_to_timestamp(id).to_rfc3339();
+
+    json!({
+        "id": id.to_string(),
+        "channel_id": channel_id.to_string(),
+        "guild_id": guild_id.map(|g| g.to_string()),
+        "author": author,
+        "content": content,
+        "timestamp": timestamp,
+        "edited_timestamp": null,
+        "tts": false,
+        "mention_everyone": false,
+        "mentions": [],
+        "mention_roles": [],
+        "attachments": [],
+        "embeds": [],
+        "pinned": false,
+        "type": 0,
+    })
+}
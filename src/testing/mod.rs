@@ -0,0 +1,397 @@
+//! Offline test harness.
+//! Commands run end to end through [`process`] against a [`FakeDiscord`] stand-in and a throwaway Postgres database,
+//! which is created from `TEST_DATABASE_URL` once per test run. Tests are skipped when the variable isn't set,
+//! unless `CI` is set, where a missing database fails them instead of letting them pass silently.
+
+pub mod fake_discord;
+
+use std::{
+    env,
+    future::Future,
+    str::FromStr,
+    sync::{
+        Arc, LazyLock, OnceLock,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use chrono::{DateTime, Utc};
+use serenity::{
+    all::{
+        Cache, Context, GatewayIntents, HttpBuilder, Message, ReadyEvent, Shard, ShardId,
+        ShardInfo, ShardManager, ShardManagerOptions, ShardMessenger, ShardRunner,
+        ShardRunnerOptions,
+    },
+    json::{Value, from_value, json},
+    prelude::{RwLock, TypeMap},
+};
+use sqlx::{
+    PgPool,
+    postgres::{PgConnectOptions, PgPoolOptions},
+};
+use tokio::{
+    runtime::{Builder, Runtime},
+    sync::{Mutex, OnceCell},
+};
+
+use crate::{
+    BOT_CONFIG, CommandNamesContainer, GUILD_SETTINGS, MessageCacheContainer,
+    RaidDetectorContainer, SQL, ShardManagerContainer,
+    config::Environment,
+    database,
+    event_handler::Handler,
+    testing::fake_discord::{
+        FakeDiscord, channel_json, guild_json, member_json, message_json, role_json, user_json,
+    },
+    utils::{GuildSettings, LogType, command_processing::process},
+};
+
+pub const PREFIX: &str = "!";
+
+const DISCORD_EPOCH: u64 = 1420070400000;
+
+/// All tests share one runtime, the database pool would break once the runtime it was created on shuts down
+static RUNTIME: LazyLock<Runtime> = LazyLock::new(|| {
+    Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .expect("could not build the test runtime")
+});
+
+static DATABASE: OnceCell<bool> = OnceCell::const_new();
+
+/// Runs a test on the shared runtime
+pub fn run<F: Future>(future: F) -> F::Output {
+    RUNTIME.block_on(future)
+}
+
+/// Creates a snowflake for the current time, unique within the test run
+pub fn snowflake() -> u64 {
+    snowflake_at(Utc::now())
+}
+
+/// Creates a snowflake for a point in time, unique within the test run
+pub fn snowflake_at(time: DateTime<Utc>) -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+
+    let increment = COUNTER.fetch_add(1, Ordering::Relaxed) & 0x3FFFFF;
+
+    ((time.timestamp_millis() as u64 - DISCORD_EPOCH) << 22) | increment
+}
+
+/// Sets up the database and globals once, returns false if no test database is configured
+async fn setup() -> bool {
+    *DATABASE
+        .get_or_init(|| async {
+            let Ok(url) = env::var("TEST_DATABASE_URL") else {
+                if env::var_os("CI").is_some() {
+                    panic!("TEST_DATABASE_URL has to be set in CI");
+                }
+
+                eprintln!("TEST_DATABASE_URL is not set, skipping tests which need a database");
+                return false;
+            };
+
+            let admin = PgPool::connect(&url)
+                .await
+                .expect("could not connect to TEST_DATABASE_URL");
+
+            // databases of earlier runs which are still in use can't be dropped and are left alone
+            let stale: Vec<String> = sqlx::query_scalar(
+                "SELECT datname FROM pg_database WHERE datname LIKE 'ouroboros_test_%'",
+            )
+            .fetch_all(&admin)
+            .await
+            .unwrap_or_default();
+
+            for name in stale {
+                let _ = sqlx::query(&format!("DROP DATABASE IF EXISTS \"{name}\""))
+                    .execute(&admin)
+                    .await;
+            }
+
+            let name = format!("ouroboros_test_{}_{}", std::process::id(), snowflake());
+            sqlx::query(&format!("CREATE DATABASE \"{name}\""))
+                .execute(&admin)
+                .await
+                .expect("could not create the test database");
+            admin.close().await;
+
+            let options = PgConnectOptions::from_str(&url)
+                .expect("TEST_DATABASE_URL is not a valid postgres url")
+                .database(&name);
+            let pool = PgPoolOptions::new()
+                .max_connections(10)
+                .connect_with(options)
+                .await
+                .expect("could not connect to the test database");
+
+            let _ = SQL.set(pool);
+            database::run_migrations()
+                .await
+                .expect("could not run migrations on the test database");
+
+            let _ = GUILD_SETTINGS.set(Mutex::new(GuildSettings::new()));
+            let _ = BOT_CONFIG.set(Environment {
+                token: String::from("test"),
+                prefix: String::from(PREFIX),
+                attachment_archive_max_mb: Some(0),
+                ..Default::default()
+            });
+
+            true
+        })
+        .await
+}
+
+/// A bot connected to its own fake Discord with a single guild.
+/// The guild has an owner, a moderator with ban, timeout and manage messages permissions, a regular member,
+/// a general channel and a log channel which receives every log type.
+pub struct TestBot {
+    pub discord: FakeDiscord,
+    pub ctx: Context,
+    pub handler: Handler,
+    pub guild_id: u64,
+    pub owner_id: u64,
+    pub moderator_id: u64,
+    pub member_id: u64,
+    pub bot_id: u64,
+    pub channel_id: u64,
+    pub log_channel_id: u64,
+    _runner: ShardRunner,
+}
+
+impl TestBot {
+    /// Returns `None` if no test database is configured
+    pub async fn new() -> Option<Self> {
+        if !setup().await {
+            return None;
+        }
+
+        let guild_id = snowflake();
+        let owner_id = snowflake();
+        let moderator_id = snowflake();
+        let member_id = snowflake();
+        let bot_id = snowflake();
+        let channel_id = snowflake();
+        let log_channel_id = snowflake();
+        let mod_role_id = snowflake();
+        let bot_role_id = snowflake();
+
+        let bot = user_json(bot_id, "ouroboros", true);
+        let discord = FakeDiscord::start(bot.clone(), snowflake()).await;
+
+        {
+            let mut state = discord.state();
+
+            let moderation = 1 << 2 | 1 << 13 | 1 << 40; // BAN_MEMBERS | MANAGE_MESSAGES | MODERATE_MEMBERS
+            let roles = vec![
+                role_json(guild_id, "@everyone", 0, 1 << 10 | 1 << 11), // VIEW_CHANNEL | SEND_MESSAGES
+                role_json(mod_role_id, "Mod", 1, moderation),
+                role_json(bot_role_id, "Ouroboros", 2, 1 << 3), // ADMINISTRATOR
+            ];
+            state.guilds.insert(
+                guild_id,
+                guild_json(guild_id, "Test Server", owner_id, roles),
+            );
+
+            for (id, name, roles) in [
+                (owner_id, "owner", vec![]),
+                (moderator_id, "moderator", vec![mod_role_id]),
+                (member_id, "member", vec![]),
+            ] {
+                let user = user_json(id, name, false);
+                state.users.insert(id, user.clone());
+                state
+                    .members
+                    .insert((guild_id, id), member_json(guild_id, user, &roles));
+            }
+
+            state.users.insert(bot_id, bot.clone());
+            state.members.insert(
+                (guild_id, bot_id),
+                member_json(guild_id, bot.clone(), &[bot_role_id]),
+            );
+
+            state
+                .channels
+                .insert(channel_id, channel_json(channel_id, guild_id, "general"));
+            state.channels.insert(
+                log_channel_id,
+                channel_json(log_channel_id, guild_id, "logs"),
+            );
+        }
+
+        let log_channel_ids = LogType::all()
+            .into_iter()
+            .map(|t| (t, log_channel_id))
+            .collect::<std::collections::HashMap<_, _>>();
+
+        sqlx::query("INSERT INTO guild_settings (guild_id, log_channel_ids) VALUES ($1, $2)")
+            .bind(guild_id as i64)
+            .bind(sqlx::types::Json(log_channel_ids))
+            .execute(&*SQL)
+            .await
+            .expect("could not store test guild settings");
+        GUILD_SETTINGS.lock().await.invalidate();
+
+        let http = Arc::new(
+            HttpBuilder::new("test")
+                .proxy(discord.url.clone())
+                .ratelimiter_disabled(true)
+                .build(),
+        );
+
+        let cache = Arc::new(Cache::new());
+        let mut ready: ReadyEvent = from_value(json!({
+            "v": 10,
+            "user": bot,
+            "guilds": [],
+            "session_id": "test",
+            "resume_gateway_url": discord.gateway_url,
+            "shard": null,
+            "application": { "id": bot_id.to_string(), "flags": 0 },
+        }))
+        .expect("could not build the ready event");
+        cache.update(&mut ready);
+
+        let data = Arc::new(RwLock::new(TypeMap::new()));
+        let ws_url = Arc::new(tokio::sync::Mutex::new(discord.gateway_url.clone()));
+        let intents = GatewayIntents::all();
+
+        let (manager, _) = ShardManager::new(ShardManagerOptions {
+            data: data.clone(),
+            event_handlers: vec![],
+            raw_event_handlers: vec![],
+            framework: Arc::new(OnceLock::new()),
+            shard_index: 0,
+            shard_init: 0,
+            shard_total: 1,
+            ws_url: ws_url.clone(),
+            cache: cache.clone(),
+            http: http.clone(),
+            intents,
+            presence: None,
+        });
+
+        let shard = Shard::new(
+            ws_url,
+            "test",
+            ShardInfo {
+                id: ShardId(0),
+                total: 1,
+            },
+            intents,
+            None,
+        )
+        .await
+        .expect("could not connect to the fake gateway");
+
+        let runner = ShardRunner::new(ShardRunnerOptions {
+            data: data.clone(),
+            event_handlers: vec![],
+            raw_event_handlers: vec![],
+            framework: None,
+            manager: manager.clone(),
+            shard,
+            cache: cache.clone(),
+            http: http.clone(),
+        });
+
+        let handler = Handler::new(String::from(PREFIX));
+
+        {
+            let mut data = data.write().await;
+            data.insert::<ShardManagerContainer>(manager);
+            data.insert::<CommandNamesContainer>(Arc::new(
+                handler
+                    .commands
+                    .iter()
+                    .map(|c| c.get_name())
+                    .chain(["help"])
+                    .collect(),
+            ));
+            data.insert::<MessageCacheContainer>(handler.message_cache.clone());
+            data.insert::<RaidDetectorContainer>(handler.raid_detector.clone());
+        }
+
+        let ctx = Context {
+            data,
+            shard: ShardMessenger::new(&runner),
+            shard_id: ShardId(0),
+            http,
+            cache,
+        };
+
+        Some(Self {
+            discord,
+            ctx,
+            handler,
+            guild_id,
+            owner_id,
+            moderator_id,
+            member_id,
+            bot_id,
+            channel_id,
+            log_channel_id,
+            _runner: runner,
+        })
+    }
+
+    /// Stores a message sent by `author` in the general channel and returns it
+    pub fn seed_message(&self, author: u64, content: &str) -> Message {
+        let mut state = self.discord.state();
+        let user = state.users[&author].clone();
+        let message = message_json(
+            snowflake(),
+            self.channel_id,
+            Some(self.guild_id),
+            user,
+            content,
+        );
+
+        state
+            .messages
+            .entry(self.channel_id)
+            .or_default()
+            .push(message.clone());
+
+        from_value(message).expect("could not build a test message")
+    }
+
+    /// Sends `content` as `author` in the general channel and waits until the bot is done processing it
+    pub async fn command(&self, author: u64, content: &str) -> Message {
+        let msg = self.seed_message(author, content);
+        process(&self.handler, self.ctx.clone(), msg.clone()).await;
+        msg
+    }
+
+    /// Messages the bot sent to the general channel as they are now, including later edits
+    pub fn replies(&self) -> Vec<Value> {
+        let bot = self.bot_id.to_string();
+
+        self.discord
+            .state()
+            .messages
+            .get(&self.channel_id)
+            .map(|m| {
+                m.iter()
+                    .filter(|m| m["author"]["id"] == bot.as_str())
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Payloads of the log messages the bot sent
+    pub fn logs(&self) -> Vec<Value> {
+        self.discord.sent_messages(self.log_channel_id)
+    }
+}
+
+/// The description of the first embed of a sent message
+pub fn description(payload: &Value) -> &str {
+    payload["embeds"][0]["description"]
+        .as_str()
+        .unwrap_or_default()
+}
@@ -4,8 +4,10 @@ use serenity::all::{Context, Message};
 
 use crate::{
     commands::{CommandArgument, TransformerReturn},
+    BOT_CONFIG,
     lexer::Token,
     transformers::Transformers,
+    utils::get_guild_prefix,
 };
 
 impl Transformers {
@@ -32,11 +34,17 @@ impl Transformers {
                     new_token.position = t.position;
                     new_token.iteration = t.iteration;
 
-                    if t.quoted {
-                        msg.content[t.position..].to_string().clone()
-                    } else {
-                        msg.content[t.position + 1..].to_string().clone()
+                    // token positions are char indices relative to the command line, skip the prefix of the message
+                    let prefix_len = match msg.guild_id {
+                        Some(id) => get_guild_prefix(id).await,
+                        None => None,
                     }
+                    .unwrap_or(BOT_CONFIG.prefix.clone())
+                    .chars()
+                    .count();
+
+                    let start = t.position + prefix_len - usize::from(t.quoted);
+                    msg.content.chars().skip(start).collect()
                 } else {
                     String::new()
                 }
@@ -49,3 +57,42 @@ impl Transformers {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        commands::CommandArgument,
+        lexer::lex,
+        testing::{self, TestBot},
+        transformers::Transformers,
+    };
+
+    #[test]
+    fn consumes_after_multibyte_arguments() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            for (line, expected) in [
+                ("restrictions add müted Muted Role", "Muted Role"),
+                ("restrictions add müted \"Muted Role\"", "\"Muted Role\""),
+            ] {
+                let msg = bot.seed_message(bot.owner_id, &format!("!{line}"));
+                let mut args = lex(line.to_string())
+                    .into_iter()
+                    .skip(3)
+                    .collect::<Vec<_>>()
+                    .into_iter()
+                    .peekable();
+
+                let Ok(token) = Transformers::consume(&bot.ctx, &msg, &mut args).await else {
+                    panic!("could not consume `{line}`");
+                };
+                assert!(
+                    matches!(token.contents, Some(CommandArgument::String(s)) if s == expected)
+                );
+            }
+        });
+    }
+}
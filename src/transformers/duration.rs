@@ -1,6 +1,6 @@
 use std::{iter::Peekable, vec::IntoIter};
 
-use chrono::Duration;
+use chrono::{Duration, Utc};
 use serenity::all::{Context, Message};
 
 use crate::{
@@ -8,8 +8,14 @@ use crate::{
     event_handler::{CommandError, MissingArgumentError},
     lexer::Token,
     transformers::Transformers,
+    utils::parse_duration,
 };
 
+/// The most arguments a single duration may span, i.e. `1 week 2 days 3 hours`
+const MAX_DURATION_TOKENS: usize = 6;
+
+const DURATION_HINT: &str = "provide a duration like 15m, 1h30m, 1.5h or 3 days, a date like 2026-12-01 or `until friday` (times are in UTC)";
+
 impl Transformers {
     pub fn duration<'a>(
         _ctx: &'a Context,
@@ -17,75 +23,74 @@ impl Transformers {
         args: &'a mut Peekable<IntoIter<Token>>,
     ) -> TransformerReturn<'a> {
         Box::pin(async move {
-            let Some(mut input) = args.next() else {
+            let Some(first) = args.peek().cloned() else {
                 return Err(TransformerError::MissingArgumentError(
                     MissingArgumentError(String::from("Duration")),
                 ));
             };
 
-            let s = input.raw.clone();
-
-            if s == "0" {
-                input.contents = Some(CommandArgument::Duration(Duration::default()));
-                return Ok(input);
-            } else if s.chars().count() < 2 {
-                return Err(TransformerError::CommandError(CommandError {
-                    arg: Some(input),
-                    title: String::from("Could not turn input to a <Duration>"),
-                    hint: Some(String::from(
-                        "provide a valid number and a unit (s, m, h, d, w, M, y), i.e. 1h (1 hour) or 25d (25 days)",
-                    )),
-                }));
+            match take_duration(args) {
+                Some(Ok(token)) => Ok(token),
+                Some(Err(err)) => Err(TransformerError::CommandError(err)),
+                None => {
+                    args.next();
+
+                    Err(TransformerError::CommandError(CommandError {
+                        arg: Some(first),
+                        title: String::from("Could not turn input to a <Duration>"),
+                        hint: Some(String::from(DURATION_HINT)),
+                    }))
+                }
             }
+        })
+    }
+}
 
-            let (digits, last) = s.split_at(s.len() - 1);
+/// Takes the longest run of arguments which forms a duration, leaving the arguments alone if none does.
+/// Durations like `3 days` or `until friday` span several arguments, quoted arguments are never joined.
+/// Dates which already passed are taken but result in an error.
+pub fn take_duration(args: &mut Peekable<IntoIter<Token>>) -> Option<Result<Token, CommandError>> {
+    let mut lookahead = args.clone();
+    let mut candidates = vec![lookahead.next()?];
 
-            if !"smhdwMy".contains(last) {
-                return Err(TransformerError::CommandError(CommandError {
-                    arg: Some(input),
-                    title: String::from("Could not turn input to a <Duration>"),
-                    hint: Some(String::from(
-                        "provide a valid number and a unit (s, m, h, d, w, M, y), i.e. 1h (1 hour) or 25d (25 days)",
-                    )),
-                }));
-            }
+    while candidates.len() < MAX_DURATION_TOKENS
+        && !candidates[0].quoted
+        && let Some(next) = lookahead.next()
+        && !next.quoted
+    {
+        candidates.push(next);
+    }
 
-            let Ok(numbers) = digits.parse::<u32>() else {
-                return Err(TransformerError::CommandError(CommandError {
-                    arg: Some(input),
-                    title: String::from("Could not turn input to a <Duration>"),
-                    hint: Some(String::from(
-                        "provide a valid number and a unit (s, m, h, d, w, M, y), i.e. 1h (1 hour) or 25d (25 days)",
-                    )),
-                }));
-            };
+    let now = Utc::now();
+    let (count, raw, parsed) = (1..=candidates.len()).rev().find_map(|count| {
+        let raw = candidates[..count]
+            .iter()
+            .map(|t| t.raw.as_str())
+            .collect::<Vec<_>>()
+            .join(" ");
 
-            let numbers = numbers as i64;
+        parse_duration(&raw, now).map(|p| (count, raw, p))
+    })?;
 
-            match last {
-                "s" => input.contents = Some(CommandArgument::Duration(Duration::seconds(numbers))),
-                "m" => input.contents = Some(CommandArgument::Duration(Duration::minutes(numbers))),
-                "h" => input.contents = Some(CommandArgument::Duration(Duration::hours(numbers))),
-                "d" => input.contents = Some(CommandArgument::Duration(Duration::days(numbers))),
-                "w" => input.contents = Some(CommandArgument::Duration(Duration::weeks(numbers))),
-                "M" => {
-                    input.contents = Some(CommandArgument::Duration(Duration::days(numbers * 30)))
-                }
-                "y" => {
-                    input.contents = Some(CommandArgument::Duration(Duration::days(numbers * 365)))
-                }
-                _ => {
-                    return Err(TransformerError::CommandError(CommandError {
-                        arg: Some(input),
-                        title: String::from("Could not turn input to a <Duration>"),
-                        hint: Some(String::from(
-                            "provide a valid number and a unit (s, m, h, d, w, M, y), i.e. 1h (1 hour) or 25d (25 days)",
-                        )),
-                    }));
-                }
-            };
+    for _ in 0..count {
+        args.next();
+    }
 
-            Ok(input)
-        })
+    let last = &candidates[count - 1];
+    let mut input = candidates[0].clone();
+    input.length = last.position + last.length - input.position;
+    input.raw = raw;
+
+    let duration = parsed.duration_from(now);
+
+    if duration < Duration::zero() {
+        return Some(Err(CommandError {
+            arg: Some(input),
+            title: String::from("The given date has already passed"),
+            hint: Some(String::from("dates and times are in UTC")),
+        }));
     }
+
+    input.contents = Some(CommandArgument::Duration(duration));
+    Some(Ok(input))
 }
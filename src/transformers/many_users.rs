@@ -1,77 +1,139 @@
-// use std::{iter::Peekable, vec::IntoIter};
-
-// use serenity::all::{Context, Message, User};
-
-// use crate::{
-//     commands::{CommandArgument, TransformerReturn},
-//     lexer::Token,
-//     transformers::Transformers,
-// };
-
-// impl Transformers {
-//     pub fn many_users<'a>(
-//         ctx: &'a Context,
-//         _msg: &'a Message,
-//         args: &'a mut Peekable<IntoIter<Token>>,
-//     ) -> TransformerReturn<'a> {
-//         Box::pin(async move {
-//             let mut users: Vec<User> = vec![];
-
-//             let mut out = Token {
-//                 contents: None,
-//                 raw: String::new(),
-//                 position: 0,
-//                 length: 0,
-//                 iteration: 0,
-//                 quoted: false,
-//                 inferred: None,
-//             };
-
-//             while let Some(input) = args.peek() {
-//                 let id = if let Ok(id) = input.raw.parse::<u64>() {
-//                     id
-//                 } else if input.raw.starts_with("<@") && input.raw.ends_with(">") {
-//                     let new_input = input
-//                         .raw
-//                         .strip_prefix("<@")
-//                         .unwrap()
-//                         .strip_suffix(">")
-//                         .unwrap();
-
-//                     if let Ok(id) = new_input.parse::<u64>() {
-//                         id
-//                     } else {
-//                         break;
-//                     }
-//                 } else {
-//                     let user_cache = ctx.cache.users();
-//                     let opt_user = user_cache.iter().find(|u| u.name == input.raw);
-
-//                     if let Some(user) = opt_user {
-//                         users.push(user.clone());
-//                         args.next();
-//                         continue;
-//                     }
-
-//                     continue;
-//                 };
-
-//                 let user = {
-//                     if let Some(user) = ctx.cache.user(id) {
-//                         user.clone()
-//                     } else if let Ok(user) = ctx.http.get_user(id.into()).await {
-//                         user.clone()
-//                     } else {
-//                         continue;
-//                     }
-//                 };
-
-//                 args.next();
-//                 users.push(user);
-//             }
-
-//             out.contents = Some(CommandArgument::ManyUsers(users));
-//             Ok(out)
-//         })
-//     }
-// }
+use std::{iter::Peekable, vec::IntoIter};
+
+use serenity::all::{Context, Message, User, UserId};
+
+use crate::{
+    commands::{CommandArgument, TransformerError, TransformerReturn},
+    event_handler::{CommandError, MissingArgumentError},
+    lexer::Token,
+    transformers::Transformers,
+};
+
+/// The maximum amount of users a single argument can hold
+const MAX_MANY_USERS: usize = 200;
+
+/// Parses an ID or mention, `None` if the input is neither.
+/// Plain numbers have to be as long as a snowflake, so a reason like `3 raids` isn't taken for a user.
+fn parse_user_id(input: &str) -> Option<u64> {
+    let id = match input.strip_prefix("<@").and_then(|i| i.strip_suffix(">")) {
+        Some(mention) => mention.strip_prefix("!").unwrap_or(mention),
+        None if input.len() >= 17 => input,
+        None => return None,
+    };
+
+    id.parse::<u64>().ok().filter(|id| *id != 0)
+}
+
+impl Transformers {
+    /// Consumes IDs and mentions until the first token which is neither.
+    /// Pasted lists separated by commas or new lines are accepted as well, duplicates are dropped.
+    /// Users are taken from the cache where possible, as a raid can easily list hundreds of them.
+    pub fn many_users<'a>(
+        ctx: &'a Context,
+        _msg: &'a Message,
+        args: &'a mut Peekable<IntoIter<Token>>,
+    ) -> TransformerReturn<'a> {
+        Box::pin(async move {
+            let mut ids: Vec<u64> = vec![];
+
+            let mut out = Token {
+                contents: None,
+                raw: String::new(),
+                position: 0,
+                length: 0,
+                iteration: 0,
+                quoted: false,
+                inferred: None,
+            };
+
+            while let Some(input) = args.peek() {
+                let parts = input
+                    .raw
+                    .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+                    .filter(|p| !p.is_empty())
+                    .map(parse_user_id)
+                    .collect::<Option<Vec<_>>>();
+
+                let Some(parts) = parts.filter(|p| !p.is_empty()) else {
+                    break;
+                };
+
+                if out.raw.is_empty() {
+                    out.position = input.position;
+                    out.iteration = input.iteration;
+                } else {
+                    out.raw.push(' ');
+                }
+
+                out.raw.push_str(&input.raw);
+                out.length = input.position + input.length - out.position;
+
+                for id in parts {
+                    if !ids.contains(&id) {
+                        ids.push(id);
+                    }
+                }
+
+                args.next();
+            }
+
+            if ids.is_empty() {
+                return Err(TransformerError::MissingArgumentError(
+                    MissingArgumentError(String::from("Users")),
+                ));
+            }
+
+            if ids.len() > MAX_MANY_USERS {
+                return Err(TransformerError::CommandError(CommandError {
+                    title: format!("At most {MAX_MANY_USERS} users can be targeted at once"),
+                    hint: None,
+                    arg: Some(out),
+                }));
+            }
+
+            let mut users: Vec<User> = vec![];
+
+            for id in ids {
+                let user = if let Some(user) = ctx.cache.user(id) {
+                    user.clone()
+                } else if let Ok(user) = ctx.http.get_user(UserId::new(id)).await {
+                    user
+                } else {
+                    return Err(TransformerError::CommandError(CommandError {
+                        title: format!("Could not find the <Discord User> `{id}`"),
+                        hint: Some(String::from(
+                            "make sure the IDs or mentions you provided are valid and that their associated users exist!",
+                        )),
+                        arg: Some(out),
+                    }));
+                };
+
+                users.push(user);
+            }
+
+            out.contents = Some(CommandArgument::ManyUsers(users));
+            Ok(out)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_user_id;
+
+    #[test]
+    fn parses_only_snowflakes_and_mentions() {
+        assert_eq!(
+            parse_user_id("123456789012345678"),
+            Some(123456789012345678)
+        );
+        assert_eq!(parse_user_id("<@123>"), Some(123));
+        assert_eq!(
+            parse_user_id("<@!123456789012345678>"),
+            Some(123456789012345678)
+        );
+        assert_eq!(parse_user_id("3"), None);
+        assert_eq!(parse_user_id("2024"), None);
+        assert_eq!(parse_user_id("spam"), None);
+    }
+}
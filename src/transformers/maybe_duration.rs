@@ -7,31 +7,33 @@ use crate::{
     commands::{CommandArgument, TransformerError, TransformerReturn},
     event_handler::MissingArgumentError,
     lexer::Token,
-    transformers::Transformers,
+    transformers::{Transformers, duration::take_duration},
 };
 
 impl Transformers {
     pub fn maybe_duration<'a>(
-        ctx: &'a Context,
-        msg: &'a Message,
+        _ctx: &'a Context,
+        _msg: &'a Message,
         args: &'a mut Peekable<IntoIter<Token>>,
     ) -> TransformerReturn<'a> {
         Box::pin(async move {
-            let Some(input) = args.peek() else {
+            if args.peek().is_none() {
                 return Err(TransformerError::MissingArgumentError(
                     MissingArgumentError(String::from("Duration")),
                 ));
             };
 
-            let mut fake_args = vec![input.clone()].into_iter().peekable();
+            // quoted arguments are never optional durations, i.e. reasons starting with `1d`
+            let taken = match args.peek() {
+                Some(t) if t.quoted => None,
+                _ => take_duration(args),
+            };
 
-            let input = match Self::duration(ctx, msg, &mut fake_args).await {
-                Ok(t) => {
-                    args.next();
-                    t
-                }
+            let input = match taken {
+                Some(Ok(t)) => t,
+                Some(Err(err)) => return Err(TransformerError::CommandError(err)),
 
-                _ => Token {
+                None => Token {
                     contents: Some(CommandArgument::Duration(Duration::zero())),
                     raw: String::new(),
                     position: 0,
@@ -0,0 +1,50 @@
+use std::{iter::Peekable, vec::IntoIter};
+
+use serenity::all::{Context, Message};
+
+use crate::{
+    commands::{CommandArgument, TransformerError, TransformerReturn},
+    event_handler::MissingArgumentError,
+    lexer::Token,
+    transformers::Transformers,
+};
+
+impl Transformers {
+    /// Like `guild_channel` but only consumes the input if it is a channel ID or mention,
+    /// so the argument can be left out in favour of the current channel
+    pub fn maybe_guild_channel<'a>(
+        ctx: &'a Context,
+        msg: &'a Message,
+        args: &'a mut Peekable<IntoIter<Token>>,
+    ) -> TransformerReturn<'a> {
+        Box::pin(async move {
+            let Some(input) = args.peek() else {
+                return Err(TransformerError::MissingArgumentError(
+                    MissingArgumentError(String::from("Channel")),
+                ));
+            };
+
+            // short numbers are left alone, they are more likely to be a duration or an amount than a snowflake
+            let is_channel = (input.raw.len() >= 17 && input.raw.parse::<u64>().is_ok())
+                || (input.raw.starts_with("<#") && input.raw.ends_with(">"));
+
+            if is_channel {
+                let mut fake_args = vec![input.clone()].into_iter().peekable();
+                let channel = Self::guild_channel(ctx, msg, &mut fake_args).await?;
+
+                args.next();
+                return Ok(channel);
+            }
+
+            Ok(Token {
+                contents: Some(CommandArgument::None),
+                raw: String::new(),
+                position: 0,
+                length: 0,
+                iteration: 0,
+                quoted: false,
+                inferred: None,
+            })
+        })
+    }
+}
@@ -7,6 +7,7 @@ mod guild_channel;
 mod i32;
 mod many_users;
 mod maybe_duration;
+mod maybe_guild_channel;
 mod member;
 mod none;
 mod reply_consume;
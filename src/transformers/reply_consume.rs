@@ -16,7 +16,15 @@ impl Transformers {
         args: &'a mut Peekable<IntoIter<Token>>,
     ) -> TransformerReturn<'a> {
         Box::pin(async move {
-            if args.peek().is_some() {
+            if args.len() == 1
+                && let Some(t) = args.next_if(|t| t.quoted)
+            {
+                // a reason which is quoted as a whole (i.e. from slash commands) is taken without its quotes
+                Ok(Token {
+                    contents: Some(CommandArgument::String(t.raw.clone())),
+                    ..t
+                })
+            } else if args.peek().is_some() {
                 return Transformers::consume(ctx, msg, args).await;
             } else if let Some(reply) = msg.referenced_message.clone() {
                 let (content, infer_type) = if let Some(embed) = reply.embeds.first()
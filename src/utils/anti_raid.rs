@@ -0,0 +1,575 @@
+use std::collections::{HashMap, VecDeque};
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serenity::all::{
+    ButtonStyle, CacheHttp, ChannelId, ComponentInteraction, Context, CreateActionRow,
+    CreateButton, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage,
+    CreateMessage, EditGuild, EditMessage, GuildId, Member, Mentionable, MessageId, Permissions,
+    UserId, VerificationLevel,
+};
+use sqlx::query;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+use crate::{
+    GUILD_SETTINGS, SQL,
+    commands::{kick_member, mute_member},
+    constants::{BRAND_BLUE, BRAND_RED},
+    event_handler::CommandError,
+    utils::{
+        LogType, consume_pgsql_error, consume_serenity_error, guild_log, humanize_duration,
+        run_mass_action, snowflake_to_timestamp, tinyid,
+    },
+};
+
+/// Custom id prefix of the end raid mode button, the guild id follows after a `:`
+const RAID_END_BUTTON: &str = "raid_end";
+/// The least amount of recent joins the new account ratio is checked on
+const MIN_RATIO_SAMPLE: usize = 3;
+
+/// The action applied to members joining during raid mode
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RaidAction {
+    Timeout,
+    Kick,
+}
+
+/// The anti-raid settings of a guild, durations are in seconds
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct AntiRaidConfig {
+    pub enabled: bool,
+    /// The amount of joins within `window` which triggers raid mode
+    pub joins: u32,
+    pub window: i64,
+    /// Accounts younger than this count as new accounts
+    pub account_age: i64,
+    /// The percentage of new accounts among the joins within `window` which triggers raid mode
+    pub new_ratio: u8,
+    pub action: RaidAction,
+    /// How long members are timed out for
+    pub duration: i64,
+    /// Raises the verification level to high while raid mode is active
+    pub raise_verification: bool,
+    /// Raid mode ends after this long without any joins
+    pub cooldown: i64,
+}
+
+impl Default for AntiRaidConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            joins: 10,
+            window: 60,
+            account_age: 60 * 60 * 24 * 7,
+            new_ratio: 80,
+            action: RaidAction::Timeout,
+            duration: 60 * 60 * 24,
+            raise_verification: false,
+            cooldown: 60 * 15,
+        }
+    }
+}
+
+impl AntiRaidConfig {
+    /// Checks every value is within the range the `antiraid` command allows
+    pub fn validate(&self) -> Result<(), String> {
+        if !(2..=100).contains(&self.joins) {
+            return Err(String::from("The amount of joins must be between 2 and 100"));
+        }
+
+        if !(1..=100).contains(&self.new_ratio) {
+            return Err(String::from(
+                "The ratio must be a percentage between 1 and 100",
+            ));
+        }
+
+        for (key, seconds, min, max) in [
+            ("window", self.window, Duration::seconds(5), Duration::hours(1)),
+            ("age", self.account_age, Duration::zero(), Duration::days(365)),
+            ("duration", self.duration, Duration::minutes(1), Duration::days(28)),
+            ("cooldown", self.cooldown, Duration::minutes(1), Duration::days(1)),
+        ] {
+            if seconds < min.num_seconds() || seconds > max.num_seconds() {
+                return Err(format!(
+                    "The {key} must be between {} and {}",
+                    humanize_duration(&min),
+                    humanize_duration(&max)
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// A short description of the action, i.e. `timeout for 1 day`
+    pub fn describe_action(&self) -> String {
+        match self.action {
+            RaidAction::Timeout => format!(
+                "timeout for {}",
+                humanize_duration(&Duration::seconds(self.duration))
+            ),
+            RaidAction::Kick => String::from("kick"),
+        }
+    }
+}
+
+struct RecentJoin {
+    user_id: UserId,
+    at: DateTime<Utc>,
+    new_account: bool,
+}
+
+/// An active raid mode, every action taken during it shares the raid id as batch id
+pub struct RaidMode {
+    pub raid_id: String,
+    pub started_at: DateTime<Utc>,
+    pub last_join: DateTime<Utc>,
+    previous_verification: Option<VerificationLevel>,
+    alert: Option<(ChannelId, MessageId)>,
+}
+
+/// Tracks recent joins per guild and which guilds are in raid mode
+#[derive(Default)]
+pub struct RaidDetector {
+    joins: HashMap<GuildId, VecDeque<RecentJoin>>,
+    active: HashMap<GuildId, RaidMode>,
+}
+
+impl RaidDetector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, guild_id: GuildId) -> Option<&RaidMode> {
+        self.active.get(&guild_id)
+    }
+
+    /// The guilds currently in raid mode
+    pub fn active_guilds(&self) -> Vec<GuildId> {
+        self.active.keys().copied().collect()
+    }
+
+    /// The guilds in raid mode which had no joins since `cooldown`
+    pub fn quiet_guilds(&self, cooldown: impl Fn(GuildId) -> Duration) -> Vec<GuildId> {
+        let now = Utc::now();
+
+        self.active
+            .iter()
+            .filter(|(g, m)| now - m.last_join >= cooldown(**g))
+            .map(|(g, _)| *g)
+            .collect()
+    }
+
+    /// Records a join, returns the trigger description and the members to act on if raid mode should start
+    fn record_join(
+        &mut self,
+        config: &AntiRaidConfig,
+        guild_id: GuildId,
+        join: RecentJoin,
+    ) -> Option<(String, Vec<UserId>)> {
+        let window = Duration::seconds(config.window);
+        let joins = self.joins.entry(guild_id).or_default();
+
+        joins.push_back(join);
+
+        while joins.front().is_some_and(|j| Utc::now() - j.at > window) {
+            joins.pop_front();
+        }
+
+        let new_accounts = joins.iter().filter(|j| j.new_account).count();
+        let window_string = humanize_duration(&window);
+
+        let trigger = if joins.len() >= config.joins as usize {
+            format!("{} joins within {window_string}", joins.len())
+        } else if joins.len() >= MIN_RATIO_SAMPLE.max(config.joins as usize / 2)
+            && new_accounts * 100 >= joins.len() * config.new_ratio as usize
+        {
+            format!(
+                "{new_accounts} of {} joins within {window_string} are new accounts",
+                joins.len()
+            )
+        } else {
+            return None;
+        };
+
+        let users = joins.drain(..).map(|j| j.user_id).collect();
+        Some((trigger, users))
+    }
+}
+
+async fn anti_raid_config(guild_id: GuildId) -> AntiRaidConfig {
+    let mut lock = GUILD_SETTINGS.lock().await;
+    lock.get(guild_id.get())
+        .await
+        .map(|s| s.moderation.anti_raid)
+        .unwrap_or_default()
+}
+
+/// Watches joins for raids. Starts raid mode when the join velocity or the share of new accounts trips a threshold,
+/// members joining during raid mode are timed out or kicked by the bot.
+pub async fn anti_raid_join(ctx: &Context, detector: &Mutex<RaidDetector>, member: &Member) {
+    if member.user.bot {
+        return;
+    }
+
+    let guild_id = member.guild_id;
+    let config = anti_raid_config(guild_id).await;
+
+    if !config.enabled {
+        return;
+    }
+
+    let now = Utc::now();
+    let new_account =
+        now - snowflake_to_timestamp(member.user.id.get()) < Duration::seconds(config.account_age);
+
+    let (raid_id, trigger, users) = {
+        let mut lock = detector.lock().await;
+
+        if let Some(mode) = lock.active.get_mut(&guild_id) {
+            mode.last_join = now;
+            let raid_id = mode.raid_id.clone();
+            drop(lock);
+
+            if let Err(err) = raid_action(ctx, member, &config, &raid_id).await {
+                warn!("Couldn't apply raid action; err = {err:?}");
+            }
+
+            return;
+        }
+
+        let join = RecentJoin {
+            user_id: member.user.id,
+            at: now,
+            new_account,
+        };
+
+        let Some((trigger, users)) = lock.record_join(&config, guild_id, join) else {
+            return;
+        };
+
+        // only generated once raid mode starts, the id generator is never held while waiting on the detector
+        let raid_id = tinyid().await;
+
+        lock.active.insert(
+            guild_id,
+            RaidMode {
+                raid_id: raid_id.clone(),
+                started_at: now,
+                last_join: now,
+                previous_verification: None,
+                alert: None,
+            },
+        );
+
+        (raid_id, trigger, users)
+    };
+
+    start_raid_mode(ctx, detector, guild_id, &config, &raid_id, &trigger, users).await;
+}
+
+async fn start_raid_mode(
+    ctx: &Context,
+    detector: &Mutex<RaidDetector>,
+    guild_id: GuildId,
+    config: &AntiRaidConfig,
+    raid_id: &str,
+    trigger: &str,
+    users: Vec<UserId>,
+) {
+    let mut previous_verification = None;
+
+    if config.raise_verification {
+        match guild_id.to_partial_guild(ctx).await {
+            Ok(guild) if guild.verification_level < VerificationLevel::High => {
+                match guild_id
+                    .edit(
+                        ctx,
+                        EditGuild::new()
+                            .verification_level(VerificationLevel::High)
+                            .audit_log_reason(&format!("Raid mode `{raid_id}` started")),
+                    )
+                    .await
+                {
+                    Ok(_) => {
+                        previous_verification = Some(guild.verification_level);
+
+                        // kept until raid mode ends, so the level is restored on startup if the bot stops before
+                        if let Err(err) = query!(
+                            "INSERT INTO raid_verification_levels (guild_id, raid_id, verification_level) VALUES ($1, $2, $3) ON CONFLICT (guild_id) DO NOTHING",
+                            guild_id.get() as i64,
+                            raid_id,
+                            u8::from(guild.verification_level) as i16,
+                        )
+                        .execute(&*SQL)
+                        .await
+                        {
+                            consume_pgsql_error(String::from("RAID VERIFICATION LEVEL"), err);
+                        }
+                    }
+                    Err(err) => warn!("Couldn't raise verification level; err = {err:?}"),
+                }
+            }
+            Ok(_) => {}
+            Err(err) => warn!("Couldn't fetch guild to raise verification level; err = {err:?}"),
+        }
+    }
+
+    let verification = if previous_verification.is_some() {
+        " | Verification raised to high"
+    } else {
+        ""
+    };
+
+    let mut alert = None;
+
+    if let Some(channel) = LogType::MemberModeration.channel_id(guild_id).await {
+        let message = CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(format!(
+                        "**RAID MODE STARTED**\n-# Raid ID: `{raid_id}` | Action: {}{verification}\n```\n{trigger}\n```\nMembers joining during raid mode are handled automatically. \
+                        Raid mode ends after {} without joins.",
+                        config.describe_action(),
+                        humanize_duration(&Duration::seconds(config.cooldown))
+                    ))
+                    .color(BRAND_RED),
+            )
+            .components(vec![CreateActionRow::Buttons(vec![
+                CreateButton::new(format!("{RAID_END_BUTTON}:{}", guild_id.get()))
+                    .label("End raid mode")
+                    .style(ButtonStyle::Danger),
+            ])]);
+
+        match channel.send_message(ctx, message).await {
+            Ok(m) => alert = Some((channel, m.id)),
+            Err(err) => warn!("Could not send raid alert; err = {err:?}"),
+        }
+    }
+
+    let ended = {
+        let mut lock = detector.lock().await;
+
+        match lock.active.get_mut(&guild_id) {
+            Some(mode) if mode.raid_id == raid_id => {
+                mode.previous_verification = previous_verification;
+                mode.alert = alert;
+                false
+            }
+            _ => true,
+        }
+    };
+
+    // raid mode was ended while it was starting, nothing else would restore the level until a restart
+    if ended && let Some(level) = previous_verification {
+        restore_verification_level(ctx, guild_id, level, raid_id).await;
+    }
+
+    let members = run_mass_action(users, |user_id| async move {
+        guild_id
+            .member(ctx, user_id)
+            .await
+            .map_err(|_| CommandError {
+                title: String::from("Not a member of this server"),
+                hint: None,
+                arg: None,
+            })
+    })
+    .await;
+
+    for (_, member) in members {
+        if let Ok(member) = member
+            && let Err(err) = raid_action(ctx, &member, config, raid_id).await
+        {
+            warn!("Couldn't apply raid action; err = {err:?}");
+        }
+    }
+}
+
+/// Times out or kicks a member with the bot as moderator, the action is grouped under the raid id
+async fn raid_action(
+    ctx: &Context,
+    member: &Member,
+    config: &AntiRaidConfig,
+    raid_id: &str,
+) -> Result<(), CommandError> {
+    let db_id = tinyid().await;
+    let bot_id = ctx.cache.current_user().id;
+    let reason = format!("Anti-raid: joined during raid mode `{raid_id}`");
+
+    match config.action {
+        RaidAction::Timeout => {
+            mute_member(
+                ctx,
+                member,
+                &db_id,
+                bot_id,
+                &reason,
+                Some(Utc::now() + Duration::seconds(config.duration)),
+                None,
+                Some(raid_id),
+            )
+            .await?
+        }
+        RaidAction::Kick => {
+            kick_member(ctx, member, &db_id, bot_id, &reason, Some(raid_id), None).await?
+        }
+    }
+
+    Ok(())
+}
+
+/// Ends raid mode, restoring the verification level and closing the alert.
+/// `ended_by` is `None` when raid mode ended automatically. Returns false if the guild was not in raid mode.
+pub async fn end_raid_mode(
+    cache_http: impl CacheHttp,
+    detector: &Mutex<RaidDetector>,
+    guild_id: GuildId,
+    ended_by: Option<UserId>,
+) -> bool {
+    let Some(mode) = detector.lock().await.active.remove(&guild_id) else {
+        return false;
+    };
+
+    if let Some(level) = mode.previous_verification {
+        restore_verification_level(&cache_http, guild_id, level, &mode.raid_id).await;
+    }
+
+    let actions = match query!(
+        r#"SELECT COUNT(*) as "count!" FROM actions WHERE batch_id = $1"#,
+        mode.raid_id
+    )
+    .fetch_one(&*SQL)
+    .await
+    {
+        Ok(r) => r.count,
+        Err(err) => {
+            consume_pgsql_error(String::from("RAID ACTION COUNT"), err);
+            0
+        }
+    };
+
+    let ended = match ended_by {
+        Some(user) => format!("Ended by {} `{}`", user.mention(), user.get()),
+        None => String::from("Ended automatically"),
+    };
+
+    let summary = format!(
+        "-# Raid ID: `{}` | Lasted: {} | Actions: {actions} | {ended}",
+        mode.raid_id,
+        humanize_duration(&(Utc::now() - mode.started_at))
+    );
+
+    if let Some((channel, message)) = mode.alert {
+        let _ = channel
+            .edit_message(
+                &cache_http,
+                message,
+                EditMessage::new()
+                    .embed(
+                        CreateEmbed::new()
+                            .description(format!("**RAID MODE ENDED**\n{summary}"))
+                            .color(BRAND_BLUE),
+                    )
+                    .components(vec![]),
+            )
+            .await;
+    }
+
+    guild_log(
+        &cache_http,
+        LogType::MemberModeration,
+        guild_id,
+        CreateMessage::new().add_embed(
+            CreateEmbed::new()
+                .description(format!("**RAID MODE ENDED**\n{summary}"))
+                .color(BRAND_BLUE),
+        ),
+    )
+    .await;
+
+    true
+}
+
+/// Handles the end raid mode button of raid alerts, other components are ignored
+pub async fn raid_component(
+    ctx: &Context,
+    detector: &Mutex<RaidDetector>,
+    interaction: &ComponentInteraction,
+) {
+    let Some((RAID_END_BUTTON, guild_id)) = interaction.data.custom_id.split_once(':') else {
+        return;
+    };
+
+    let Some(guild_id) = guild_id.parse::<u64>().ok().map(GuildId::new) else {
+        return;
+    };
+
+    let permissions = interaction
+        .member
+        .as_ref()
+        .and_then(|m| m.permissions)
+        .unwrap_or_default();
+
+    let error = if interaction.guild_id != Some(guild_id) {
+        Some("This button does not belong to this server")
+    } else if !permissions.intersects(
+        Permissions::ADMINISTRATOR | Permissions::MANAGE_GUILD | Permissions::BAN_MEMBERS,
+    ) {
+        Some("You need the manage server or ban members permission to end raid mode")
+    } else if !end_raid_mode(ctx, detector, guild_id, Some(interaction.user.id)).await {
+        Some("Raid mode is not active")
+    } else {
+        None
+    };
+
+    let response = match error {
+        Some(error) => CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new()
+                .embed(
+                    CreateEmbed::new()
+                        .description(format!("**{error}**"))
+                        .color(BRAND_RED),
+                )
+                .ephemeral(true),
+        ),
+        None => CreateInteractionResponse::Acknowledge,
+    };
+
+    if let Err(err) = interaction.create_response(ctx, response).await {
+        consume_serenity_error(String::from("RAID END RESPONSE"), err);
+    }
+}
+
+/// Restores the verification level raid mode raised, forgetting the stored level once it was restored
+pub async fn restore_verification_level(
+    cache_http: impl CacheHttp,
+    guild_id: GuildId,
+    level: VerificationLevel,
+    raid_id: &str,
+) {
+    if let Err(err) = guild_id
+        .edit(
+            &cache_http,
+            EditGuild::new()
+                .verification_level(level)
+                .audit_log_reason(&format!("Raid mode `{raid_id}` ended")),
+        )
+        .await
+    {
+        warn!("Couldn't restore verification level; err = {err:?}");
+        return;
+    }
+
+    if let Err(err) = query!(
+        "DELETE FROM raid_verification_levels WHERE guild_id = $1",
+        guild_id.get() as i64
+    )
+    .execute(&*SQL)
+    .await
+    {
+        consume_pgsql_error(String::from("RAID VERIFICATION LEVEL"), err);
+    }
+}
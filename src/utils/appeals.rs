@@ -0,0 +1,538 @@
+use serenity::all::{
+    ActionRowComponent, ButtonStyle, ComponentInteraction, Context, CreateActionRow,
+    CreateButton, CreateEmbed, CreateInputText, CreateInteractionResponse,
+    CreateInteractionResponseFollowup, CreateInteractionResponseMessage, CreateMessage,
+    CreateModal, EditInteractionResponse, GuildId, InputTextStyle,
+    Mentionable, ModalInteraction, Permissions, UserId,
+};
+use sqlx::{query, query_as};
+use tracing::warn;
+
+use crate::{
+    SQL,
+    commands::{unban_user, unmute_member},
+    constants::{BRAND_BLUE, BRAND_RED},
+    database::{ActionType, AppealStatus},
+    event_handler::CommandError,
+    utils::{LogType, consume_pgsql_error, consume_serenity_error, guild_log, tinyid},
+};
+
+/// Custom id prefixes of the appeal components, the action or appeal id follows after a `:`
+const APPEAL_BUTTON: &str = "appeal";
+const APPEAL_MODAL: &str = "appeal_modal";
+const APPEAL_ACCEPT: &str = "appeal_accept";
+const APPEAL_DENY: &str = "appeal_deny";
+
+/// The maximum length of an appeal
+const MAX_APPEAL_LENGTH: u16 = 1000;
+
+/// An action looked up by the appeal button and modal
+struct AppealableAction {
+    guild_id: i64,
+    user_id: i64,
+    action_type: ActionType,
+    active: bool,
+    appealed: bool,
+}
+
+/// The appeal button attached to ban and mute DMs.
+/// Empty if the guild has no appeals log channel, as nobody would see the appeal.
+pub async fn appeal_components(guild_id: GuildId, action_id: &str) -> Vec<CreateActionRow> {
+    if LogType::Appeals.channel_id(guild_id).await.is_none() {
+        return vec![];
+    }
+
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("{APPEAL_BUTTON}:{action_id}"))
+            .label("Appeal")
+            .style(ButtonStyle::Secondary),
+    ])]
+}
+
+/// Handles the appeal button in DMs and the accept/deny buttons in the appeals log, other components are ignored
+pub async fn appeal_component(ctx: &Context, interaction: &ComponentInteraction) {
+    let Some((kind, id)) = interaction.data.custom_id.split_once(':') else {
+        return;
+    };
+
+    match kind {
+        APPEAL_BUTTON => {
+            let response = open_appeal(id, interaction.user.id)
+                .await
+                .unwrap_or_else(error_response);
+
+            if let Err(err) = interaction.create_response(ctx, response).await {
+                consume_serenity_error(String::from("APPEAL COMPONENT RESPONSE"), err);
+            }
+        }
+        APPEAL_ACCEPT | APPEAL_DENY => {
+            // lifting the action and DMing the user can take longer than Discord waits for a response
+            if let Err(err) = interaction.defer(ctx).await {
+                consume_serenity_error(String::from("APPEAL COMPONENT DEFER"), err);
+                return;
+            }
+
+            let res = match resolve_appeal(ctx, interaction, id, kind == APPEAL_ACCEPT).await {
+                Ok(edit) => interaction.edit_response(ctx, edit).await.map(|_| ()),
+                Err(err) => interaction
+                    .create_followup(
+                        ctx,
+                        CreateInteractionResponseFollowup::new()
+                            .embed(error_embed(err))
+                            .ephemeral(true),
+                    )
+                    .await
+                    .map(|_| ()),
+            };
+
+            if let Err(err) = res {
+                consume_serenity_error(String::from("APPEAL COMPONENT RESPONSE"), err);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Stores a submitted appeal and posts it to the appeals log
+pub async fn appeal_modal(ctx: &Context, interaction: &ModalInteraction) {
+    let Some((APPEAL_MODAL, action_id)) = interaction.data.custom_id.split_once(':') else {
+        return;
+    };
+
+    let response = submit_appeal(ctx, interaction, action_id)
+        .await
+        .unwrap_or_else(error_response);
+
+    if let Err(err) = interaction.create_response(ctx, response).await {
+        consume_serenity_error(String::from("APPEAL MODAL RESPONSE"), err);
+    }
+}
+
+fn error_embed(err: CommandError) -> CreateEmbed {
+    let hint = err.hint.map(|h| format!("\n-# {h}")).unwrap_or_default();
+
+    CreateEmbed::new()
+        .description(format!("**{}**{hint}", err.title))
+        .color(BRAND_RED)
+}
+
+fn error_response(err: CommandError) -> CreateInteractionResponse {
+    CreateInteractionResponse::Message(
+        CreateInteractionResponseMessage::new()
+            .embed(error_embed(err))
+            .ephemeral(true),
+    )
+}
+
+/// Looks up an action and checks that `user_id` may still appeal it
+async fn find_appealable(action_id: &str, user_id: UserId) -> Result<AppealableAction, CommandError> {
+    let res = query_as!(
+        AppealableAction,
+        r#"
+            SELECT guild_id, user_id, type as "action_type!: ActionType", active,
+                EXISTS(SELECT 1 FROM appeals WHERE action_id = actions.id) as "appealed!"
+            FROM actions
+            WHERE id = $1
+        "#,
+        action_id
+    )
+    .fetch_optional(&*SQL)
+    .await;
+
+    let action = match res {
+        Ok(Some(a)) if a.user_id == user_id.get() as i64 => a,
+        Ok(_) => {
+            return Err(CommandError {
+                title: String::from("This action could not be found"),
+                hint: None,
+                arg: None,
+            });
+        }
+        Err(err) => {
+            warn!("Couldn't fetch appealed action; err = {err:?}");
+            return Err(CommandError {
+                title: String::from("Unable to query the database"),
+                hint: Some(String::from("try again later")),
+                arg: None,
+            });
+        }
+    };
+
+    if !matches!(action.action_type, ActionType::Ban | ActionType::Mute) {
+        return Err(CommandError {
+            title: String::from("Only bans and mutes can be appealed"),
+            hint: None,
+            arg: None,
+        });
+    }
+
+    if action.appealed {
+        return Err(CommandError {
+            title: String::from("You have already appealed this action"),
+            hint: None,
+            arg: None,
+        });
+    }
+
+    if !action.active {
+        return Err(CommandError {
+            title: String::from("This action is no longer active"),
+            hint: None,
+            arg: None,
+        });
+    }
+
+    if LogType::Appeals
+        .channel_id(GuildId::new(action.guild_id as u64))
+        .await
+        .is_none()
+    {
+        return Err(CommandError {
+            title: String::from("This server does not accept appeals"),
+            hint: None,
+            arg: None,
+        });
+    }
+
+    Ok(action)
+}
+
+/// Opens the appeal modal for the appeal button
+async fn open_appeal(
+    action_id: &str,
+    user_id: UserId,
+) -> Result<CreateInteractionResponse, CommandError> {
+    find_appealable(action_id, user_id).await?;
+
+    Ok(CreateInteractionResponse::Modal(
+        CreateModal::new(format!("{APPEAL_MODAL}:{action_id}"), "Appeal").components(vec![
+            CreateActionRow::InputText(
+                CreateInputText::new(
+                    InputTextStyle::Paragraph,
+                    "Why should this action be lifted?",
+                    "content",
+                )
+                .max_length(MAX_APPEAL_LENGTH)
+                .required(true),
+            ),
+        ]),
+    ))
+}
+
+async fn submit_appeal(
+    ctx: &Context,
+    interaction: &ModalInteraction,
+    action_id: &str,
+) -> Result<CreateInteractionResponse, CommandError> {
+    let action = find_appealable(action_id, interaction.user.id).await?;
+
+    let content = interaction
+        .data
+        .components
+        .iter()
+        .flat_map(|r| r.components.iter())
+        .find_map(|c| match c {
+            ActionRowComponent::InputText(t) => t.value.clone(),
+            _ => None,
+        })
+        .map(|c| c.trim().to_string())
+        .unwrap_or_default();
+
+    if content.is_empty() {
+        return Err(CommandError {
+            title: String::from("Your appeal is empty"),
+            hint: None,
+            arg: None,
+        });
+    }
+
+    let appeal_id = tinyid().await;
+
+    let res = query!(
+        "INSERT INTO appeals (id, action_id, guild_id, user_id, content) VALUES ($1, $2, $3, $4, $5)",
+        appeal_id,
+        action_id,
+        action.guild_id,
+        action.user_id,
+        content
+    )
+    .execute(&*SQL)
+    .await;
+
+    if let Err(err) = res {
+        consume_pgsql_error(String::from("APPEAL INSERT"), err);
+        return Err(CommandError {
+            title: String::from("Could not submit your appeal"),
+            hint: Some(String::from("please try again later")),
+            arg: None,
+        });
+    }
+
+    guild_log(
+        ctx,
+        LogType::Appeals,
+        GuildId::new(action.guild_id as u64),
+        CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(format!(
+                        "**NEW APPEAL**\n-# Appeal ID: `{appeal_id}` | Log ID: `{action_id}` | Target: {} `{}` | Action: {}\n```\n{}\n```",
+                        interaction.user.mention(),
+                        interaction.user.id.get(),
+                        action.action_type,
+                        content.replace("```", "\\`\\`\\`")
+                    ))
+                    .color(BRAND_BLUE),
+            )
+            .components(vec![CreateActionRow::Buttons(vec![
+                CreateButton::new(format!("{APPEAL_ACCEPT}:{appeal_id}"))
+                    .label("Accept")
+                    .style(ButtonStyle::Success),
+                CreateButton::new(format!("{APPEAL_DENY}:{appeal_id}"))
+                    .label("Deny")
+                    .style(ButtonStyle::Danger),
+            ])]),
+    )
+    .await;
+
+    let submitted = vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(format!("{APPEAL_BUTTON}:{action_id}"))
+            .label("Appeal submitted")
+            .style(ButtonStyle::Secondary)
+            .disabled(true),
+    ])];
+
+    Ok(match interaction.message {
+        Some(_) => CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new().components(submitted),
+        ),
+        None => CreateInteractionResponse::Message(
+            CreateInteractionResponseMessage::new().embed(
+                CreateEmbed::new()
+                    .description(format!("**APPEAL SUBMITTED**\n-# Appeal ID: `{appeal_id}`"))
+                    .color(BRAND_BLUE),
+            ),
+        ),
+    })
+}
+
+/// Accepts or denies an appeal from the appeals log, the interaction has to be deferred.
+/// Accepting lifts the ban or mute the same way the unban and unmute commands do, the user is notified either way.
+/// Actions which ended since the appeal was submitted are not lifted again, the user is told so instead.
+async fn resolve_appeal(
+    ctx: &Context,
+    interaction: &ComponentInteraction,
+    appeal_id: &str,
+    accept: bool,
+) -> Result<EditInteractionResponse, CommandError> {
+    let Some(guild_id) = interaction.guild_id else {
+        return Err(CommandError {
+            title: String::from("Appeals can only be resolved in a server"),
+            hint: None,
+            arg: None,
+        });
+    };
+
+    let res = query!(
+        r#"
+            SELECT appeals.action_id, appeals.user_id, appeals.status as "status!: AppealStatus",
+                actions.type as "action_type!: ActionType", actions.active
+            FROM appeals
+            JOIN actions ON actions.id = appeals.action_id
+            WHERE appeals.id = $1 AND appeals.guild_id = $2
+        "#,
+        appeal_id,
+        guild_id.get() as i64
+    )
+    .fetch_optional(&*SQL)
+    .await;
+
+    let appeal = match res {
+        Ok(Some(a)) => a,
+        Ok(None) => {
+            return Err(CommandError {
+                title: String::from("Appeal not found"),
+                hint: None,
+                arg: None,
+            });
+        }
+        Err(err) => {
+            warn!("Couldn't fetch appeal; err = {err:?}");
+            return Err(CommandError {
+                title: String::from("Unable to query the database"),
+                hint: Some(String::from("try again later")),
+                arg: None,
+            });
+        }
+    };
+
+    let (required, action_name) = match appeal.action_type {
+        ActionType::Ban => (Permissions::BAN_MEMBERS, "ban"),
+        _ => (Permissions::MODERATE_MEMBERS, "mute"),
+    };
+
+    let permissions = interaction
+        .member
+        .as_ref()
+        .and_then(|m| m.permissions)
+        .unwrap_or_default();
+
+    if !permissions.contains(required) && !permissions.administrator() {
+        return Err(CommandError {
+            title: String::from("You may not resolve this appeal"),
+            hint: Some(format!("resolving {action_name} appeals requires the {required} permission")),
+            arg: None,
+        });
+    }
+
+    let status = if accept {
+        AppealStatus::Accepted
+    } else {
+        AppealStatus::Denied
+    };
+
+    // claiming the appeal first prevents two moderators resolving it at once
+    let res = query!(
+        "UPDATE appeals SET status = $2, resolved_by = $3, resolved_at = NOW() WHERE id = $1 AND status = 'pending'",
+        appeal_id,
+        status as AppealStatus,
+        interaction.user.id.get() as i64
+    )
+    .execute(&*SQL)
+    .await;
+
+    match res {
+        Ok(r) if r.rows_affected() == 0 => {
+            return Err(CommandError {
+                title: String::from("This appeal has already been resolved"),
+                hint: None,
+                arg: None,
+            });
+        }
+        Ok(_) => {}
+        Err(err) => {
+            consume_pgsql_error(String::from("APPEAL RESOLVE"), err);
+            return Err(CommandError {
+                title: String::from("Could not resolve the appeal"),
+                hint: Some(String::from("please try again later")),
+                arg: None,
+            });
+        }
+    }
+
+    let user_id = UserId::new(appeal.user_id as u64);
+
+    if accept && appeal.active {
+        let db_id = tinyid().await;
+        let reason = format!("Appeal `{appeal_id}` accepted");
+
+        let res = match appeal.action_type {
+            ActionType::Ban => {
+                unban_user(ctx, guild_id, user_id, &db_id, interaction.user.id, &reason).await
+            }
+            _ => match guild_id.member(ctx, user_id).await {
+                Ok(mut member) => {
+                    unmute_member(ctx, &mut member, &db_id, interaction.user.id, &reason).await
+                }
+                Err(_) => Err(CommandError {
+                    title: String::from("Could not find the member"),
+                    hint: Some(String::from("the member may have left the server")),
+                    arg: None,
+                }),
+            },
+        };
+
+        if let Err(err) = res {
+            if let Err(err) = query!(
+                "UPDATE appeals SET status = 'pending', resolved_by = NULL, resolved_at = NULL WHERE id = $1",
+                appeal_id
+            )
+            .execute(&*SQL)
+            .await
+            {
+                consume_pgsql_error(String::from("APPEAL RESOLVE ROLLBACK"), err);
+            }
+
+            return Err(err);
+        }
+
+        let log_title = match appeal.action_type {
+            ActionType::Ban => "MEMBER UNBANNED",
+            _ => "MEMBER UNMUTED",
+        };
+
+        guild_log(
+            ctx,
+            LogType::MemberModeration,
+            guild_id,
+            CreateMessage::new().add_embed(
+                CreateEmbed::new()
+                    .description(format!(
+                        "**{log_title}**\n-# Log ID: `{db_id}` | Actor: {} `{}` | Target: {} `{}`\n```\n{reason}\n```",
+                        interaction.user.mention(),
+                        interaction.user.id.get(),
+                        user_id.mention(),
+                        user_id.get()
+                    ))
+                    .color(BRAND_BLUE),
+            ),
+        )
+        .await;
+    }
+
+    let guild_name = match guild_id.to_partial_guild(ctx).await {
+        Ok(p) => p.name.clone(),
+        Err(_) => String::from("UNKNOWN_GUILD"),
+    };
+
+    let dm = if accept && appeal.active {
+        format!(
+            "**APPEAL ACCEPTED**\n-# Server: {guild_name} | Log ID: `{}`\nYour {action_name} has been lifted.",
+            appeal.action_id
+        )
+    } else if accept {
+        format!(
+            "**APPEAL ACCEPTED**\n-# Server: {guild_name} | Log ID: `{}`\nYour {action_name} had already ended, so nothing had to be lifted.",
+            appeal.action_id
+        )
+    } else {
+        format!(
+            "**APPEAL DENIED**\n-# Server: {guild_name} | Log ID: `{}`\nYour appeal has been reviewed and denied.",
+            appeal.action_id
+        )
+    };
+
+    let dm_failed = user_id
+        .direct_message(
+            ctx,
+            CreateMessage::new().add_embed(CreateEmbed::new().description(dm).color(BRAND_BLUE)),
+        )
+        .await
+        .is_err();
+
+    let description = interaction
+        .message
+        .embeds
+        .first()
+        .and_then(|e| e.description.clone())
+        .unwrap_or_default();
+
+    let state = match (accept, appeal.active) {
+        (true, true) => String::from("Accepted"),
+        (true, false) => format!("Accepted ({action_name} had already ended)"),
+        (false, _) => String::from("Denied"),
+    };
+
+    Ok(EditInteractionResponse::new()
+        .embed(
+            CreateEmbed::new()
+                .description(format!(
+                    "{description}\n-# {state} by {} `{}`{}",
+                    interaction.user.mention(),
+                    interaction.user.id.get(),
+                    if dm_failed { " | DM failed" } else { "" }
+                ))
+                .color(BRAND_BLUE),
+        )
+        .components(vec![]))
+}
@@ -0,0 +1,269 @@
+use std::sync::Arc;
+
+use serenity::all::{
+    CommandDataOptionValue, CommandInteraction, CommandOptionType, CommandType, Context,
+    CreateCommand, CreateCommandOption, Message,
+};
+
+use crate::{
+    commands::{Command, CommandArgument, CommandCategory, CommandParameter, CommandSyntax},
+    lexer::Token,
+};
+
+/// Discord caps command and option descriptions at 100 characters
+const MAX_DESCRIPTION_LENGTH: usize = 100;
+
+/// A user/message context menu entry which asks for a reason (and duration) and runs a moderation command on the target
+pub struct ContextMenuAction {
+    /// The name shown in the context menu
+    pub name: &'static str,
+    /// The name of the command to run
+    pub command: &'static str,
+    /// Whether the modal asks for a duration
+    pub duration: bool,
+    /// The value the duration field is prefilled with
+    pub default_duration: &'static str,
+}
+
+pub const CONTEXT_MENU_ACTIONS: [ContextMenuAction; 3] = [
+    ContextMenuAction {
+        name: "Warn",
+        command: "warn",
+        duration: false,
+        default_duration: "",
+    },
+    ContextMenuAction {
+        name: "Mute 1h",
+        command: "mute",
+        duration: true,
+        default_duration: "1h",
+    },
+    ContextMenuAction {
+        name: "Ban",
+        command: "ban",
+        duration: true,
+        default_duration: "",
+    },
+];
+
+/// Creates the slash command definition of a command from its syntax and parameters.
+/// Returns `None` for commands which should not be exposed as application commands (developer commands).
+pub async fn create_application_command(
+    ctx: &Context,
+    command: &Arc<dyn Command>,
+) -> Option<CreateCommand> {
+    if command.get_category() == CommandCategory::Developer {
+        return None;
+    }
+
+    let mut options: Vec<(String, bool, CreateCommandOption)> = vec![];
+
+    for syntax in command.get_syntax() {
+        let name = syntax.get_name();
+        let required = syntax.is_required();
+
+        if options.iter().any(|(n, _, _)| *n == name) {
+            continue;
+        }
+
+        let option = CreateCommandOption::new(
+            option_type(&syntax),
+            name.clone(),
+            truncate_description(syntax.get_def()),
+        )
+        .required(required);
+
+        options.push((name, required, option));
+    }
+
+    // Discord requires all required options to come before optional ones.
+    // Options are matched by name in `create_command_line`, so moving them keeps the prefix argument order intact
+    options.sort_by_key(|(_, required, _)| !required);
+
+    for param in command.get_params() {
+        if options.iter().any(|(n, _, _)| n == param.name) {
+            continue;
+        }
+
+        let kind = if is_flag(ctx, param).await {
+            CommandOptionType::Boolean
+        } else {
+            CommandOptionType::String
+        };
+
+        let option =
+            CreateCommandOption::new(kind, param.name, truncate_description(param.desc.to_string()));
+        options.push((param.name.to_string(), false, option));
+    }
+
+    Some(
+        CreateCommand::new(command.get_name())
+            .description(truncate_description(command.get_short().to_string()))
+            .dm_permission(false)
+            .set_options(options.into_iter().map(|(_, _, o)| o).collect()),
+    )
+}
+
+/// Creates the user and message context menu entries for all `CONTEXT_MENU_ACTIONS`.
+/// Like slash commands they are visible to every member, permissions are checked when the command runs.
+pub fn create_context_menu_commands(commands: &[Arc<dyn Command>]) -> Vec<CreateCommand> {
+    let mut result = vec![];
+
+    for action in CONTEXT_MENU_ACTIONS.iter() {
+        if !commands.iter().any(|c| c.get_name() == action.command) {
+            continue;
+        }
+
+        for kind in [CommandType::User, CommandType::Message] {
+            result.push(
+                CreateCommand::new(action.name)
+                    .kind(kind)
+                    .dm_permission(false),
+            );
+        }
+    }
+
+    result
+}
+
+/// Turns the options of a slash command invocation back into the command line a prefix user would have typed (without the prefix).
+pub fn create_command_line(command: &Arc<dyn Command>, interaction: &CommandInteraction) -> String {
+    let options = &interaction.data.options;
+    let mut parts = vec![command.get_name().to_string()];
+
+    for syntax in command.get_syntax() {
+        let name = syntax.get_name();
+        let Some(option) = options.iter().find(|o| o.name == name) else {
+            continue;
+        };
+
+        let value = match (&syntax, &option.value) {
+            (_, CommandDataOptionValue::User(id)) => id.get().to_string(),
+            (_, CommandDataOptionValue::Channel(id)) => format!("<#{}>", id.get()),
+            (_, CommandDataOptionValue::Integer(i)) => i.to_string(),
+            (CommandSyntax::Reason(_), CommandDataOptionValue::String(s)) => quote_reason(s),
+            (
+                CommandSyntax::Consume(_) | CommandSyntax::Filters,
+                CommandDataOptionValue::String(s),
+            ) => s.clone(),
+            (_, CommandDataOptionValue::String(s)) => quote(s),
+            _ => continue,
+        };
+
+        parts.push(value);
+    }
+
+    for param in command.get_params() {
+        let Some(option) = options.iter().find(|o| o.name == param.name) else {
+            continue;
+        };
+
+        match &option.value {
+            CommandDataOptionValue::Boolean(true) => parts.push(format!("+{}", param.name)),
+            CommandDataOptionValue::String(s) => parts.push(format!("+{} {}", param.name, quote(s))),
+            _ => {}
+        }
+    }
+
+    parts.join(" ")
+}
+
+fn option_type(syntax: &CommandSyntax) -> CommandOptionType {
+    match syntax {
+        CommandSyntax::User(_, _) | CommandSyntax::Member(_, _) => CommandOptionType::User,
+        CommandSyntax::Channel(_, _) => CommandOptionType::Channel,
+        CommandSyntax::Number(_, _) => CommandOptionType::Integer,
+        CommandSyntax::Consume(_)
+        | CommandSyntax::Reason(_)
+        | CommandSyntax::String(_, _)
+        | CommandSyntax::Users(_, _)
+        | CommandSyntax::Duration(_, _)
+        | CommandSyntax::Filters
+        | CommandSyntax::Or(_, _) => CommandOptionType::String,
+    }
+}
+
+/// Parameters whose transformer does not consume any input (i.e. `+silent`) are exposed as boolean options
+async fn is_flag(ctx: &Context, param: &CommandParameter<'static>) -> bool {
+    let msg = Message::default();
+    let mut empty = Vec::<Token>::new().into_iter().peekable();
+
+    matches!(
+        (param.transformer)(ctx, &msg, &mut empty).await,
+        Ok(Token {
+            contents: Some(CommandArgument::None),
+            ..
+        })
+    )
+}
+
+/// Quotes a value for the lexer if it would otherwise be split into multiple tokens
+fn quote(value: &str) -> String {
+    if !value.is_empty() && !value.contains(char::is_whitespace) && !value.contains(['"', '\'']) {
+        return value.to_string();
+    }
+
+    quote_reason(value)
+}
+
+/// Always quotes reasons, so flags (`+silent`) and durations at their start are taken as part of the reason
+pub fn quote_reason(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn truncate_description(mut description: String) -> String {
+    if description.is_empty() {
+        return String::from("-");
+    }
+
+    if description.chars().count() > MAX_DESCRIPTION_LENGTH {
+        description = description
+            .chars()
+            .take(MAX_DESCRIPTION_LENGTH - 3)
+            .collect();
+        description.push_str("...");
+    }
+
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::json::{self, Value};
+
+    use super::create_application_command;
+    use crate::{
+        commands::CommandCategory,
+        testing::{self, TestBot},
+    };
+
+    #[test]
+    fn puts_required_options_first() {
+        testing::run(async {
+            let Some(bot) = TestBot::new().await else {
+                return;
+            };
+
+            for command in bot.handler.commands.iter() {
+                if command.get_category() == CommandCategory::Developer {
+                    continue;
+                }
+
+                let definition = create_application_command(&bot.ctx, command).await;
+                let definition = json::to_value(definition.unwrap()).unwrap();
+                let required: Vec<bool> = definition["options"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|o| o["required"] == Value::Bool(true))
+                    .collect();
+
+                assert!(
+                    required.is_sorted_by_key(|r| !r),
+                    "{} has required options after optional ones",
+                    command.get_name()
+                );
+            }
+        });
+    }
+}
@@ -0,0 +1,319 @@
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use serenity::all::Message;
+use tokio::sync::Mutex;
+use tracing::warn;
+
+use crate::{BOT_CONFIG, GUILD_SETTINGS, utils::cache::partials::PartialAttachment};
+
+/// Attachments bigger than this are never archived
+pub const MAX_ARCHIVED_FILE_SIZE: u64 = 25 * 1024 * 1024;
+
+const DEFAULT_ARCHIVE_DIR: &str = "./attachment_archive";
+const DEFAULT_ARCHIVE_MAX_MB: u64 = 512;
+const DEFAULT_ARCHIVE_TTL_HOURS: u64 = 72;
+
+struct ArchivedAttachment {
+    path: PathBuf,
+    size: u64,
+    stored_at: SystemTime,
+    last_access: SystemTime,
+}
+
+/// Index of attachment files stored on disk, keyed by attachment id.
+/// Files are removed once they are older than the ttl, or least recently used first once the size cap is reached.
+pub struct AttachmentArchive {
+    dir: PathBuf,
+    max_size: u64,
+    ttl: Duration,
+    entries: HashMap<u64, ArchivedAttachment>,
+    total_size: u64,
+}
+
+impl AttachmentArchive {
+    /// Creates the archive from the bot config and indexes the files already in the archive directory
+    pub fn new() -> Self {
+        let dir = PathBuf::from(
+            BOT_CONFIG
+                .attachment_archive_dir
+                .clone()
+                .unwrap_or(String::from(DEFAULT_ARCHIVE_DIR)),
+        );
+        let max_size = BOT_CONFIG
+            .attachment_archive_max_mb
+            .unwrap_or(DEFAULT_ARCHIVE_MAX_MB)
+            * 1024
+            * 1024;
+        let ttl = Duration::from_secs(
+            BOT_CONFIG
+                .attachment_archive_ttl_hours
+                .unwrap_or(DEFAULT_ARCHIVE_TTL_HOURS)
+                * 3600,
+        );
+
+        let mut archive = Self {
+            dir,
+            max_size,
+            ttl,
+            entries: HashMap::new(),
+            total_size: 0,
+        };
+
+        if archive.enabled() {
+            archive.load();
+        }
+
+        archive
+    }
+
+    /// Archiving is turned off by setting the size cap to 0
+    pub fn enabled(&self) -> bool {
+        self.max_size > 0
+    }
+
+    pub fn contains(&self, attachment_id: u64) -> bool {
+        self.entries.contains_key(&attachment_id)
+    }
+
+    /// Gets the path of an archived attachment and marks it as recently used
+    pub fn get(&mut self, attachment_id: u64) -> Option<PathBuf> {
+        let entry = self.entries.get_mut(&attachment_id)?;
+        entry.last_access = SystemTime::now();
+        Some(entry.path.clone())
+    }
+
+    /// Where the file of an attachment is stored, the id prefix is what the index is rebuilt from
+    pub fn path_for(&self, attachment_id: u64, name: &str) -> PathBuf {
+        let name = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .take(100)
+            .collect::<String>();
+
+        self.dir.join(format!("{attachment_id}-{name}"))
+    }
+
+    /// Adds a file which was written to `path` to the index.
+    /// Returns the files which were evicted to stay under the size cap, they have to be deleted by the caller.
+    pub fn insert(&mut self, attachment_id: u64, path: PathBuf, size: u64) -> Vec<PathBuf> {
+        if size > self.max_size {
+            return vec![path];
+        }
+
+        let now = SystemTime::now();
+        let mut evicted = self.evict(size);
+
+        if let Some(previous) = self.entries.insert(
+            attachment_id,
+            ArchivedAttachment {
+                path,
+                size,
+                stored_at: now,
+                last_access: now,
+            },
+        ) {
+            self.total_size -= previous.size;
+
+            if previous.path != self.entries[&attachment_id].path {
+                evicted.push(previous.path);
+            }
+        }
+
+        self.total_size += size;
+        evicted
+    }
+
+    /// Removes all entries older than the ttl from the index, returning their files
+    pub fn expire(&mut self) -> Vec<PathBuf> {
+        let now = SystemTime::now();
+        let expired = self
+            .entries
+            .iter()
+            .filter(|(_, e)| {
+                now.duration_since(e.stored_at)
+                    .is_ok_and(|age| age > self.ttl)
+            })
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+
+        expired
+            .into_iter()
+            .filter_map(|id| self.remove(id))
+            .collect()
+    }
+
+    fn remove(&mut self, attachment_id: u64) -> Option<PathBuf> {
+        let entry = self.entries.remove(&attachment_id)?;
+        self.total_size -= entry.size;
+        Some(entry.path)
+    }
+
+    /// Removes the least recently used entries until `incoming` more bytes fit under the size cap
+    fn evict(&mut self, incoming: u64) -> Vec<PathBuf> {
+        let mut evicted = vec![];
+
+        while self.total_size + incoming > self.max_size {
+            let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, e)| e.last_access)
+                .map(|(id, _)| *id)
+            else {
+                break;
+            };
+
+            if let Some(path) = self.remove(oldest) {
+                evicted.push(path);
+            }
+        }
+
+        evicted
+    }
+
+    /// Indexes the files of an earlier run, the directory is only created once the first attachment is archived
+    fn load(&mut self) {
+        let Ok(dir) = std::fs::read_dir(&self.dir) else {
+            return;
+        };
+
+        let mut evicted = vec![];
+
+        for file in dir.flatten() {
+            let Ok(meta) = file.metadata() else {
+                continue;
+            };
+
+            let Some(attachment_id) = file
+                .file_name()
+                .to_str()
+                .and_then(|n| n.split_once('-'))
+                .and_then(|(id, _)| id.parse::<u64>().ok())
+            else {
+                continue;
+            };
+
+            if !meta.is_file() {
+                continue;
+            }
+
+            let stored_at = meta.modified().unwrap_or(SystemTime::now());
+
+            evicted.extend(self.evict(meta.len()));
+            self.entries.insert(
+                attachment_id,
+                ArchivedAttachment {
+                    path: file.path(),
+                    size: meta.len(),
+                    stored_at,
+                    last_access: stored_at,
+                },
+            );
+            self.total_size += meta.len();
+        }
+
+        for path in evicted {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Deletes files which were dropped from the archive index
+pub async fn remove_archived_files(paths: Vec<PathBuf>) {
+    for path in paths {
+        if let Err(err) = tokio::fs::remove_file(&path).await {
+            warn!("Could not remove archived attachment {path:?}; err = {err:?}");
+        }
+    }
+}
+
+/// Downloads and stores the attachments of a message if its channel is monitored by the guild
+pub async fn archive_attachments(archive: &Arc<Mutex<AttachmentArchive>>, msg: &Message) {
+    let Some(guild_id) = msg.guild_id else {
+        return;
+    };
+
+    let monitored = {
+        let mut lock = GUILD_SETTINGS.lock().await;
+        lock.get(guild_id.get())
+            .await
+            .is_ok_and(|s| s.log.archive_channels.contains(&msg.channel_id.get()))
+    };
+
+    if !monitored {
+        return;
+    }
+
+    for attachment in msg.attachments.iter() {
+        if attachment.size as u64 > MAX_ARCHIVED_FILE_SIZE {
+            continue;
+        }
+
+        let path = {
+            let lock = archive.lock().await;
+
+            if !lock.enabled() || lock.contains(attachment.id.get()) {
+                continue;
+            }
+
+            lock.path_for(attachment.id.get(), &attachment.filename)
+        };
+
+        if let Some(dir) = path.parent()
+            && let Err(err) = tokio::fs::create_dir_all(dir).await
+        {
+            warn!("Could not create attachment archive directory; err = {err:?}");
+            return;
+        }
+
+        let bytes = match attachment.download().await {
+            Ok(b) => b,
+            Err(err) => {
+                warn!("Could not download attachment for the archive; err = {err:?}");
+                continue;
+            }
+        };
+
+        if let Err(err) = tokio::fs::write(&path, &bytes).await {
+            warn!("Could not write archived attachment {path:?}; err = {err:?}");
+            continue;
+        }
+
+        let evicted = {
+            let mut lock = archive.lock().await;
+            lock.insert(attachment.id.get(), path, bytes.len() as u64)
+        };
+
+        remove_archived_files(evicted).await;
+    }
+}
+
+/// Gets the bytes of an attachment from the archive, falling back to downloading it
+pub async fn archived_or_download(
+    archive: &Arc<Mutex<AttachmentArchive>>,
+    attachment: &PartialAttachment,
+) -> Option<Vec<u8>> {
+    let path = {
+        let mut lock = archive.lock().await;
+        lock.get(attachment.id)
+    };
+
+    if let Some(path) = path {
+        match tokio::fs::read(&path).await {
+            Ok(bytes) => return Some(bytes),
+            Err(err) => warn!("Could not read archived attachment {path:?}; err = {err:?}"),
+        }
+    }
+
+    attachment.download().await.ok()
+}
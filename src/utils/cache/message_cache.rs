@@ -1,27 +1,61 @@
-use std::collections::{HashMap, VecDeque};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Mutex, MutexGuard, PoisonError},
+};
 
 use serenity::all::Message;
 
 use crate::utils::cache::partials::PartialMessage;
 
+/// Amount of independently locked shards, every channel lives in exactly one of them
+const SHARD_COUNT: usize = 64;
+
+/// Size of channels which don't have a stored size yet
+const DEFAULT_QUEUE_SIZE: usize = 100;
+
+/// Message cache split into shards of channels, so events in different channels rarely wait on each other.
+/// Shard locks are never held across an await point, which is why the methods are synchronous.
 pub struct MessageCache {
+    shards: Vec<Mutex<CacheShard>>,
+}
+
+#[derive(Default)]
+struct CacheShard {
     sizes: HashMap<u64, usize>,
     messages: HashMap<u64, MessageQueue>,
     inserts: HashMap<u64, usize>,
+    /// Messages the bot is deleting itself, by channel
+    own_deletions: HashMap<u64, HashSet<u64>>,
 }
 
 impl MessageCache {
     pub fn new() -> Self {
         Self {
-            sizes: HashMap::new(),
-            messages: HashMap::new(),
-            inserts: HashMap::new(),
+            shards: (0..SHARD_COUNT)
+                .map(|_| Mutex::new(CacheShard::default()))
+                .collect(),
         }
     }
 
-    pub fn assign_count(&mut self, channel: u64, count: usize) {
-        self.sizes.insert(channel, count);
-        let entry = self
+    fn shard(&self, channel: u64) -> MutexGuard<'_, CacheShard> {
+        // the low bits of a snowflake are a per process counter, mix in the timestamp bits
+        let index = (channel ^ (channel >> 22)) as usize % SHARD_COUNT;
+
+        self.shards[index]
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn shards(&self) -> impl Iterator<Item = MutexGuard<'_, CacheShard>> {
+        self.shards
+            .iter()
+            .map(|s| s.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    pub fn assign_count(&self, channel: u64, count: usize) {
+        let mut shard = self.shard(channel);
+        shard.sizes.insert(channel, count);
+        let entry = shard
             .messages
             .entry(channel)
             .or_insert(MessageQueue::with_capacity(count));
@@ -35,29 +69,47 @@ impl MessageCache {
         }
     }
 
-    pub fn clear_inserts(&mut self) {
-        self.inserts = self
-            .inserts
-            .clone()
-            .into_keys()
-            .map(|i| (i, 0_usize))
-            .collect::<HashMap<_, _>>();
+    pub fn clear_inserts(&self) {
+        for mut shard in self.shards() {
+            shard.inserts.values_mut().for_each(|i| *i = 0);
+        }
     }
 
-    pub fn insert_message(&mut self, channel_id: u64, msg: Message) {
+    pub fn insert_message(&self, channel_id: u64, msg: Message) {
         let partial = PartialMessage::from(msg);
         self.insert(channel_id, partial);
     }
 
-    pub fn insert(&mut self, channel_id: u64, message: PartialMessage) {
-        *self.inserts.entry(channel_id).or_default() += 1;
-        let queue_size = *self.sizes.entry(channel_id).or_insert(100);
+    pub fn insert(&self, channel_id: u64, message: PartialMessage) {
+        let mut shard = self.shard(channel_id);
+        *shard.inserts.entry(channel_id).or_default() += 1;
+        let queue_size = *shard.sizes.entry(channel_id).or_insert(DEFAULT_QUEUE_SIZE);
 
         if queue_size == 0 {
             return;
         }
 
-        let queue = self.messages.entry(channel_id).or_default();
+        let queue = shard.messages.entry(channel_id).or_default();
+
+        if queue.len() >= queue_size && queue.get(message.id).is_none() {
+            queue.pop();
+        }
+
+        queue.insert(message);
+    }
+
+    /// Inserts a message restored from a snapshot, messages already in the cache are kept and inserts aren't counted
+    pub fn restore(&self, message: PartialMessage) {
+        let mut shard = self.shard(message.channel_id);
+        let queue_size = *shard
+            .sizes
+            .entry(message.channel_id)
+            .or_insert(DEFAULT_QUEUE_SIZE);
+        let queue = shard.messages.entry(message.channel_id).or_default();
+
+        if queue_size == 0 || queue.get(message.id).is_some() {
+            return;
+        }
 
         if queue.len() >= queue_size {
             queue.pop();
@@ -66,47 +118,102 @@ impl MessageCache {
         queue.insert(message);
     }
 
-    pub fn get(&mut self, channel: u64, message: u64) -> Option<&PartialMessage> {
-        let queue = self.messages.entry(channel).or_default();
-        queue.get(message)
+    /// Clones every cached message, used to snapshot the cache
+    pub fn all_messages(&self) -> Vec<PartialMessage> {
+        self.shards()
+            .flat_map(|s| {
+                s.messages
+                    .values()
+                    .flat_map(|q| q.items.iter().cloned())
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    pub fn get(&self, channel: u64, message: u64) -> Option<PartialMessage> {
+        let shard = self.shard(channel);
+        shard.messages.get(&channel)?.get(message).cloned()
+    }
+
+    /// Removes a deleted message from the cache, so it is neither kept around nor snapshotted
+    pub fn remove(&self, channel: u64, message: u64) -> Option<PartialMessage> {
+        let mut shard = self.shard(channel);
+        shard.messages.get_mut(&channel)?.remove(message)
+    }
+
+    /// Marks messages the bot is about to delete itself, so their delete events can be told apart
+    pub fn expect_own_deletion(&self, channel: u64, message: u64) {
+        self.shard(channel)
+            .own_deletions
+            .entry(channel)
+            .or_default()
+            .insert(message);
+    }
+
+    /// Removes the mark of a message, returns true if the bot deleted it itself
+    pub fn take_own_deletion(&self, channel: u64, message: u64) -> bool {
+        let mut shard = self.shard(channel);
+        let Some(messages) = shard.own_deletions.get_mut(&channel) else {
+            return false;
+        };
+
+        let removed = messages.remove(&message);
+
+        if messages.is_empty() {
+            shard.own_deletions.remove(&channel);
+        }
+
+        removed
     }
 
     pub fn get_inserts(&self) -> HashMap<u64, usize> {
-        self.inserts.clone()
+        self.shards().flat_map(|s| s.inserts.clone()).collect()
     }
 
     pub fn get_sizes(&self) -> HashMap<u64, usize> {
-        self.sizes.clone()
+        self.shards().flat_map(|s| s.sizes.clone()).collect()
     }
 
     pub fn get_channel_len(&self, channel: u64) -> usize {
-        self.messages
+        self.shard(channel)
+            .messages
             .get(&channel)
             .map(|c| c.len())
             .unwrap_or_default()
     }
 }
 
+impl Default for MessageCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Messages of a channel in insertion order.
+/// The index stores the absolute position of each message, `offset` is the absolute position of the front,
+/// so evicting the oldest message doesn't have to touch the positions of the others.
 struct MessageQueue {
     pub items: VecDeque<PartialMessage>,
     index: HashMap<u64, usize>,
+    offset: usize,
 }
 
 impl MessageQueue {
     fn with_capacity(capacity: usize) -> Self {
         Self {
             items: VecDeque::with_capacity(capacity),
-            index: Default::default(),
+            index: HashMap::with_capacity(capacity),
+            offset: 0,
         }
     }
 
     fn insert(&mut self, msg: PartialMessage) {
         let id = msg.id;
 
-        if let Some(&idx) = self.index.get(&id) {
-            self.items[idx] = msg;
+        if let Some(&position) = self.index.get(&id) {
+            self.items[position - self.offset] = msg;
         } else {
-            self.index.insert(id, self.items.len());
+            self.index.insert(id, self.offset + self.items.len());
             self.items.push_back(msg);
         }
     }
@@ -116,25 +223,36 @@ impl MessageQueue {
     }
 
     fn get(&self, id: u64) -> Option<&PartialMessage> {
-        self.index.get(&id).map(|&i| &self.items[i])
+        self.index
+            .get(&id)
+            .map(|&position| &self.items[position - self.offset])
+    }
+
+    fn remove(&mut self, id: u64) -> Option<PartialMessage> {
+        let position = self.index.remove(&id)?;
+        let msg = self.items.remove(position - self.offset)?;
+
+        // messages after the removed one move to the front by one
+        for p in self.index.values_mut() {
+            if *p > position {
+                *p -= 1;
+            }
+        }
+
+        Some(msg)
     }
 
     fn pop(&mut self) {
         if let Some(msg) = self.items.pop_front() {
             self.index.remove(&msg.id);
-
-            for (i, m) in self.items.iter().enumerate() {
-                self.index.insert(m.id, i);
-            }
+            self.offset += 1;
         }
     }
 }
 
 impl Default for MessageQueue {
     fn default() -> Self {
-        Self {
-            items: VecDeque::with_capacity(100),
-            index: Default::default(),
-        }
+        Self::with_capacity(DEFAULT_QUEUE_SIZE)
     }
 }
+
@@ -1,3 +1,4 @@
+pub mod attachment_archive;
 pub mod message_cache;
 pub mod partials;
 pub mod permission_cache;
@@ -1,3 +1,4 @@
+use serde::{Deserialize, Serialize};
 use serenity::all::{CacheHttp, Message, User};
 
 #[derive(Clone, Debug)]
@@ -7,8 +8,11 @@ pub struct PartialUser {
     pub bot: bool,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PartialAttachment {
+    /// Missing in snapshots taken before attachments were archived
+    #[serde(default)]
+    pub id: u64,
     pub name: String,
     pub url: String,
 }
@@ -16,18 +20,19 @@ pub struct PartialAttachment {
 #[derive(Clone, Debug)]
 pub struct PartialMessage {
     pub id: u64,
-    // pub guild_id: Option<u64>,
+    pub guild_id: Option<u64>,
     pub channel_id: u64,
     pub content: String,
     pub author: PartialUser,
     pub attachment_urls: Vec<PartialAttachment>,
+    pub reply_to: Option<u64>,
 }
 
 impl From<Message> for PartialMessage {
     fn from(value: Message) -> Self {
         Self {
             id: value.id.get(),
-            // guild_id: value.guild_id.map(|g| g.get()),
+            guild_id: value.guild_id.map(|g| g.get()),
             channel_id: value.channel_id.get(),
             content: value.content,
             author: PartialUser {
@@ -39,10 +44,15 @@ impl From<Message> for PartialMessage {
                 .attachments
                 .into_iter()
                 .map(|a| PartialAttachment {
+                    id: a.id.get(),
                     name: a.filename,
                     url: a.url,
                 })
                 .collect(),
+            reply_to: value
+                .message_reference
+                .and_then(|r| r.message_id)
+                .map(|id| id.get()),
         }
     }
 }
@@ -5,7 +5,10 @@ use tokio::sync::Mutex;
 use crate::{
     commands::Command,
     event_handler::Handler,
-    utils::{check_guild_permission, permissions::check_channel_permission},
+    utils::{
+        CommandOverride, check_guild_permission, get_command_overrides,
+        permissions::check_channel_permission, resolve_command_override,
+    },
 };
 
 #[derive(Default)]
@@ -78,26 +81,33 @@ impl PermissionCache {
 #[derive(Default)]
 struct GuildPermissionCache {
     inner: HashMap<u64, Arc<Mutex<CommandPermissionCacheInfo>>>,
+    /// The overrides the cached results were evaluated with
+    overrides: Arc<Vec<CommandOverride>>,
 }
 
 impl GuildPermissionCache {
     pub async fn can_run(&mut self, request: CommandPermissionRequest) -> CommandPermissionResult {
         let perms = request.command.get_permissions();
 
-        if perms.one_of.is_empty() && perms.required.is_empty() {
+        // overrides are part of the guild settings, results of outdated overrides are dropped
+        let overrides = get_command_overrides(request.guild.id).await;
+        if !Arc::ptr_eq(&overrides, &self.overrides) && overrides != self.overrides {
+            self.inner = Default::default();
+            self.overrides = overrides;
+        }
+
+        if perms.one_of.is_empty() && perms.required.is_empty() && self.overrides.is_empty() {
             return CommandPermissionResult::Success;
         }
 
         let user_id = request.member.user.id.get();
-        let command_name = request.command.get_name().to_string();
+        let cache_key = (request.channel.id.get(), request.command.get_name().to_string());
         let user_entry_arc = Arc::clone(self.inner.entry(user_id).or_default());
         let mut user_entry = user_entry_arc.lock().await;
 
         if !user_entry.valid {
-            let allowed = Self::evaluate_permissions(request.clone()).await;
-            user_entry
-                .allowed
-                .insert(command_name.clone(), allowed.clone());
+            let allowed = Self::evaluate_permissions(request.clone(), &self.overrides).await;
+            user_entry.allowed.insert(cache_key, allowed.clone());
             user_entry.valid = true;
 
             let handler = request.handler.clone();
@@ -106,6 +116,7 @@ impl GuildPermissionCache {
             let channel = request.channel.clone();
             let current_user = request.current_user.clone();
             let entry_ref = Arc::clone(&user_entry_arc);
+            let overrides = Arc::clone(&self.overrides);
 
             tokio::spawn(async move {
                 let member = member;
@@ -114,7 +125,8 @@ impl GuildPermissionCache {
                 for command in handler.commands.clone() {
                     let perms = command.get_permissions();
 
-                    if perms.one_of.is_empty() && perms.required.is_empty() {
+                    if perms.one_of.is_empty() && perms.required.is_empty() && overrides.is_empty()
+                    {
                         continue;
                     }
 
@@ -127,21 +139,22 @@ impl GuildPermissionCache {
                         handler: handler.clone(),
                     };
 
-                    let ok = Self::evaluate_permissions(req).await;
+                    let ok = Self::evaluate_permissions(req, &overrides).await;
 
                     let mut lock = entry_ref.lock().await;
-                    lock.allowed.insert(command.get_name().to_string(), ok);
+                    lock.allowed
+                        .insert((channel.id.get(), command.get_name().to_string()), ok);
                 }
             });
 
             return allowed;
         }
 
-        match user_entry.allowed.get(&command_name) {
+        match user_entry.allowed.get(&cache_key) {
             Some(v) => v.clone(),
             None => {
-                let allowed = Self::evaluate_permissions(request).await;
-                user_entry.allowed.insert(command_name, allowed.clone());
+                let allowed = Self::evaluate_permissions(request, &self.overrides).await;
+                user_entry.allowed.insert(cache_key, allowed.clone());
                 allowed
             }
         }
@@ -153,7 +166,10 @@ impl GuildPermissionCache {
         entry.allowed = Default::default();
     }
 
-    async fn evaluate_permissions(request: CommandPermissionRequest) -> CommandPermissionResult {
+    async fn evaluate_permissions(
+        request: CommandPermissionRequest,
+        overrides: &[CommandOverride],
+    ) -> CommandPermissionResult {
         let permissions = request.command.get_permissions();
         let guild = request.guild;
         let member = request.member;
@@ -164,6 +180,32 @@ impl GuildPermissionCache {
             return CommandPermissionResult::Success;
         }
 
+        // the everyone role has the id of the guild, channel overrides also apply to threads and channels of a category
+        let roles = member
+            .roles
+            .iter()
+            .map(|r| r.get())
+            .chain([guild.id.get()])
+            .collect::<Vec<_>>();
+        let channels = [Some(request.channel.id), request.channel.parent_id]
+            .into_iter()
+            .flatten()
+            .map(|c| c.get())
+            .collect::<Vec<_>>();
+
+        match resolve_command_override(
+            overrides,
+            request.command.get_name(),
+            request.command.get_category(),
+            member.user.id.get(),
+            &roles,
+            &channels,
+        ) {
+            Some(true) => return CommandPermissionResult::Success,
+            Some(false) => return CommandPermissionResult::Denied,
+            None => {}
+        }
+
         for permission in permissions.required {
             if !check_guild_permission(&guild, &member, permission).await {
                 return CommandPermissionResult::FailedUserRequired;
@@ -186,7 +228,8 @@ impl GuildPermissionCache {
 
 #[derive(Default, Debug, Clone)]
 struct CommandPermissionCacheInfo {
-    pub allowed: HashMap<String, CommandPermissionResult>,
+    /// Results keyed by channel and command, channel overrides make results differ between channels
+    pub allowed: HashMap<(u64, String), CommandPermissionResult>,
     pub valid: bool,
 }
 
@@ -200,17 +243,14 @@ pub struct CommandPermissionRequest {
     pub handler: Handler,
 }
 
-#[derive(PartialEq, Debug, Clone)]
+#[derive(PartialEq, Debug, Clone, Default)]
 pub enum CommandPermissionResult {
     Success,
     FailedBot(Permissions),
     FailedUserOneOf,
     FailedUserRequired,
+    /// A command override denies the command
+    Denied,
+    #[default]
     Uninitialised,
 }
-
-impl Default for CommandPermissionResult {
-    fn default() -> Self {
-        Self::Uninitialised
-    }
-}
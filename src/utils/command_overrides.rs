@@ -0,0 +1,188 @@
+use serde::{Deserialize, Serialize};
+use serenity::all::{ChannelId, Mentionable, RoleId, UserId};
+
+use crate::commands::CommandCategory;
+
+/// The maximum amount of overrides a guild can have
+pub const MAX_COMMAND_OVERRIDES: usize = 100;
+
+/// The commands an override applies to
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OverrideScope {
+    Command(String),
+    Category(CommandCategory),
+}
+
+impl OverrideScope {
+    pub fn matches(&self, command: &str, category: CommandCategory) -> bool {
+        match self {
+            OverrideScope::Command(name) => name == command,
+            OverrideScope::Category(c) => *c == category,
+        }
+    }
+}
+
+impl std::fmt::Display for OverrideScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OverrideScope::Command(name) => write!(f, "`{name}`"),
+            OverrideScope::Category(category) => write!(f, "{category} commands"),
+        }
+    }
+}
+
+/// Who an override applies to, role overrides with the id of the guild apply to everyone
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "type", content = "id", rename_all = "lowercase")]
+pub enum OverrideTarget {
+    User(u64),
+    Role(u64),
+    Channel(u64),
+}
+
+impl OverrideTarget {
+    /// Mentions the target, `guild_id` is used to show the everyone role as `@everyone`
+    pub fn mention(&self, guild_id: u64) -> String {
+        match *self {
+            OverrideTarget::User(id) => UserId::new(id).mention().to_string(),
+            OverrideTarget::Role(id) if id == guild_id => String::from("@everyone"),
+            OverrideTarget::Role(id) => RoleId::new(id).mention().to_string(),
+            OverrideTarget::Channel(id) => ChannelId::new(id).mention().to_string(),
+        }
+    }
+}
+
+/// Grants or denies a command or a whole category regardless of Discord permissions
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct CommandOverride {
+    pub scope: OverrideScope,
+    pub target: OverrideTarget,
+    pub allow: bool,
+}
+
+/// Validates the overrides of a guild as the `overrides` command would, help and developer commands can't be overridden
+pub fn validate_command_overrides(
+    overrides: &[CommandOverride],
+    command_names: &[&str],
+) -> Result<(), String> {
+    if overrides.len() > MAX_COMMAND_OVERRIDES {
+        return Err(format!(
+            "Servers can have at most {MAX_COMMAND_OVERRIDES} overrides"
+        ));
+    }
+
+    for o in overrides {
+        match &o.scope {
+            OverrideScope::Command(name)
+                if name == "help" || !command_names.contains(&name.as_str()) =>
+            {
+                return Err(format!("`{name}` is not a command which can be overridden"));
+            }
+            OverrideScope::Category(CommandCategory::Developer) => {
+                return Err(String::from("Developer commands can't be overridden"));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Decides whether an override grants (`Some(true)`) or denies (`Some(false)`) a command, `None` if none applies.
+/// User overrides beat role overrides which beat channel overrides. For the same target overrides of the command
+/// beat overrides of its category and denies beat grants.
+pub fn resolve_command_override(
+    overrides: &[CommandOverride],
+    command: &str,
+    category: CommandCategory,
+    user_id: u64,
+    roles: &[u64],
+    channels: &[u64],
+) -> Option<bool> {
+    overrides
+        .iter()
+        .filter(|o| o.scope.matches(command, category))
+        .filter_map(|o| {
+            let level = match o.target {
+                OverrideTarget::User(id) if id == user_id => 3,
+                OverrideTarget::Role(id) if roles.contains(&id) => 2,
+                OverrideTarget::Channel(id) if channels.contains(&id) => 1,
+                _ => return None,
+            };
+
+            let is_command = matches!(o.scope, OverrideScope::Command(_));
+            Some(((level, is_command, !o.allow), o.allow))
+        })
+        .max_by_key(|(rank, _)| *rank)
+        .map(|(_, allow)| allow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CommandOverride, OverrideScope, OverrideTarget, resolve_command_override};
+    use crate::commands::CommandCategory;
+
+    fn o(scope: OverrideScope, target: OverrideTarget, allow: bool) -> CommandOverride {
+        CommandOverride {
+            scope,
+            target,
+            allow,
+        }
+    }
+
+    fn command(name: &str) -> OverrideScope {
+        OverrideScope::Command(String::from(name))
+    }
+
+    #[test]
+    fn resolves_by_specificity() {
+        let overrides = vec![
+            o(
+                OverrideScope::Category(CommandCategory::Moderation),
+                OverrideTarget::Role(10),
+                true,
+            ),
+            o(command("ban"), OverrideTarget::Role(10), false),
+            o(command("warn"), OverrideTarget::Channel(20), false),
+            o(command("warn"), OverrideTarget::User(1), true),
+        ];
+        let resolve = |command: &str, user_id: u64, roles: &[u64]| {
+            resolve_command_override(
+                &overrides,
+                command,
+                CommandCategory::Moderation,
+                user_id,
+                roles,
+                &[20],
+            )
+        };
+
+        assert_eq!(resolve("mute", 2, &[10]), Some(true));
+        assert_eq!(resolve("ban", 2, &[10]), Some(false));
+        assert_eq!(resolve("warn", 2, &[10]), Some(true));
+        assert_eq!(resolve("warn", 2, &[]), Some(false));
+        assert_eq!(resolve("warn", 1, &[]), Some(true));
+        assert_eq!(resolve("mute", 2, &[]), None);
+    }
+
+    #[test]
+    fn denies_beat_grants() {
+        let overrides = vec![
+            o(command("ban"), OverrideTarget::Role(10), true),
+            o(command("ban"), OverrideTarget::Role(11), false),
+        ];
+
+        assert_eq!(
+            resolve_command_override(
+                &overrides,
+                "ban",
+                CommandCategory::Moderation,
+                1,
+                &[10, 11],
+                &[],
+            ),
+            Some(false)
+        );
+    }
+}
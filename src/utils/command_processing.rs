@@ -9,17 +9,42 @@ use crate::{
     lexer::{Token, lex},
     utils::{
         cache::permission_cache::{CommandPermissionRequest, CommandPermissionResult},
-        extract_command_parameters, is_developer,
+        extract_command_parameters, get_guild_alias, is_developer,
     },
 };
 
-pub async fn process(handler: &Handler, ctx: Context, mut msg: Message) {
-    if !msg.content.starts_with(handler.prefix.as_str()) || msg.guild_id.is_none() {
+/// Replaced by the first argument when an alias is used
+pub const ALIAS_USER_PLACEHOLDER: &str = "{user}";
+/// The maximum amount of aliases a guild can define
+pub const MAX_ALIASES: usize = 50;
+/// The maximum length of an alias name
+pub const MAX_ALIAS_NAME_LENGTH: usize = 32;
+/// The maximum length of the command line an alias expands to
+pub const MAX_ALIAS_EXPANSION_LENGTH: usize = 200;
+
+pub async fn process(handler: &Handler, ctx: Context, msg: Message) {
+    if msg.guild_id.is_none() {
         return;
     }
 
-    let mut contents = msg.content.clone();
-    let strip = contents.strip_prefix(handler.prefix.as_str()).unwrap_or("");
+    let prefix = handler.get_prefix(msg.guild_id).await;
+
+    let Some(strip) = msg.content.strip_prefix(prefix.as_str()) else {
+        return;
+    };
+
+    let strip = strip.to_string();
+    execute(handler, ctx, msg, strip).await
+}
+
+/// Runs a command line (the message contents without the prefix) as the author of `msg`.
+/// Shared between prefix commands and application commands so both go through the same permission checks and runners.
+pub async fn execute(handler: &Handler, ctx: Context, mut msg: Message, strip: String) {
+    let prefix = handler.get_prefix(msg.guild_id).await;
+    let strip = resolve_alias(handler, &msg, strip).await;
+    let mut contents = format!("{prefix}{strip}");
+    msg.content = contents.clone();
+    let strip = strip.as_str();
     let tokens = lex(String::from(strip));
     let mut parts = tokens.into_iter().peekable();
     let command_name = parts.next().map(|s| s.raw).unwrap_or_default();
@@ -34,11 +59,11 @@ pub async fn process(handler: &Handler, ctx: Context, mut msg: Message) {
 
         return;
     } else if command_name == "cachedbg" && is_developer(&msg.author) {
-        let lock = handler.message_cache.lock().await;
-        let mut sizes = lock.get_sizes();
+        let cache = &handler.message_cache;
+        let mut sizes = cache.get_sizes();
         let size = sizes.entry(msg.channel_id.get()).or_insert(100);
-        let count = lock.get_channel_len(msg.channel_id.get());
-        let mut inserts = lock.get_inserts();
+        let count = cache.get_channel_len(msg.channel_id.get());
+        let mut inserts = cache.get_inserts();
         let insert_count = inserts.entry(msg.channel_id.get()).or_insert(0);
 
         let reply = CreateMessage::new()
@@ -93,7 +118,7 @@ pub async fn process(handler: &Handler, ctx: Context, mut msg: Message) {
                         &msg,
                         msg.content.clone(),
                         CommandError {
-                            title: format!("You do not have permissions to execute this command."),
+                            title: String::from("You do not have permissions to execute this command."),
                             hint: Some(String::from("could not get guild object")),
                             arg: None,
                         },
@@ -109,7 +134,7 @@ pub async fn process(handler: &Handler, ctx: Context, mut msg: Message) {
                         &msg,
                         msg.content.clone(),
                         CommandError {
-                            title: format!("You do not have permissions to execute this command."),
+                            title: String::from("You do not have permissions to execute this command."),
                             hint: Some(String::from("could not get channel object")),
                             arg: None,
                         },
@@ -118,7 +143,7 @@ pub async fn process(handler: &Handler, ctx: Context, mut msg: Message) {
                 return;
             };
 
-            let id = ctx.cache.current_user().id.clone();
+            let id = ctx.cache.current_user().id;
             let Ok(current_user) = guild.member(&ctx, id).await else {
                 handler
                     .send_error(
@@ -126,7 +151,7 @@ pub async fn process(handler: &Handler, ctx: Context, mut msg: Message) {
                         &msg,
                         msg.content.clone(),
                         CommandError {
-                            title: format!("You do not have permissions to execute this command."),
+                            title: String::from("You do not have permissions to execute this command."),
                             hint: Some(String::from("could not get current member object")),
                             arg: None,
                         },
@@ -139,9 +164,9 @@ pub async fn process(handler: &Handler, ctx: Context, mut msg: Message) {
                 current_user,
                 handler: handler.clone(),
                 command: c.clone(),
-                channel: channel,
-                member: member,
-                guild: guild,
+                channel,
+                member,
+                guild,
             };
 
             let mut lock = handler.permission_cache.lock().await;
@@ -160,6 +185,9 @@ pub async fn process(handler: &Handler, ctx: Context, mut msg: Message) {
                     CommandPermissionResult::FailedUserRequired => String::from(
                         "You do not have the required permissiosn to execute this command.",
                     ),
+                    CommandPermissionResult::Denied => String::from(
+                        "This command has been disabled for you here by the server administrators.",
+                    ),
                     CommandPermissionResult::Uninitialised => {
                         String::from("You arent supposed to see this! Report this to the devs ;(")
                     }
@@ -189,7 +217,7 @@ pub async fn process(handler: &Handler, ctx: Context, mut msg: Message) {
 
             if let Ok(params) = res {
                 command_params = params.0;
-                contents = format!("{}{}", handler.prefix, params.1.clone());
+                contents = format!("{prefix}{}", params.1.clone());
                 msg.content = contents.clone();
                 parts = lex(params.1).into_iter().peekable();
                 parts.next();
@@ -261,3 +289,110 @@ pub async fn process(handler: &Handler, ctx: Context, mut msg: Message) {
         }
     }
 }
+
+/// Expands the command name of a command line if it matches one of the guilds aliases.
+/// A `{user}` placeholder in the alias is replaced by the first argument, or dropped when replying so the target gets inferred from the reply.
+async fn resolve_alias(handler: &Handler, msg: &Message, strip: String) -> String {
+    let line = strip.trim_start();
+    let (name, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let name = name.to_lowercase();
+
+    if name == "help" || handler.commands.iter().any(|c| c.get_name() == name) {
+        return strip;
+    }
+
+    let Some(guild_id) = msg.guild_id else {
+        return strip;
+    };
+
+    let Some(mut expansion) = get_guild_alias(guild_id, &name).await else {
+        return strip;
+    };
+
+    let mut rest = rest.trim_start();
+
+    if expansion.contains(ALIAS_USER_PLACEHOLDER) {
+        let user = if msg.referenced_message.is_some() {
+            ""
+        } else {
+            let (user, remaining) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+            rest = remaining.trim_start();
+            user
+        };
+
+        expansion = expansion
+            .replace(ALIAS_USER_PLACEHOLDER, user)
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ");
+    }
+
+    if rest.is_empty() {
+        expansion
+    } else {
+        format!("{expansion} {rest}")
+    }
+}
+
+/// Aliases can't shadow commands since commands are resolved first
+pub fn validate_alias_name(name: &str, command_names: &[&str]) -> Result<(), String> {
+    if name.is_empty()
+        || name.chars().count() > MAX_ALIAS_NAME_LENGTH
+        || !name
+            .chars()
+            .all(|c| (c.is_alphanumeric() && !c.is_uppercase()) || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "Alias names must be at most {MAX_ALIAS_NAME_LENGTH} characters long and only contain lowercase letters, numbers, - and _"
+        ));
+    }
+
+    if command_names.contains(&name) {
+        return Err(String::from(
+            "An alias can not have the same name as a command",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Expansions are resolved once, so they have to start with a command and not another alias
+pub fn validate_alias_expansion(expansion: &str, command_names: &[&str]) -> Result<(), String> {
+    let target = expansion
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+
+    if !command_names.contains(&target.as_str()) {
+        return Err(String::from(
+            "Aliases must start with the name of a command, not another alias",
+        ));
+    }
+
+    if expansion.chars().count() > MAX_ALIAS_EXPANSION_LENGTH || expansion.contains('`') {
+        return Err(format!(
+            "Aliases must be at most {MAX_ALIAS_EXPANSION_LENGTH} characters long and not contain backticks"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Validates all aliases of a guild, as the `alias` command would
+pub fn validate_aliases(
+    aliases: &HashMap<String, String>,
+    command_names: &[&str],
+) -> Result<(), String> {
+    if aliases.len() > MAX_ALIASES {
+        return Err(format!("Servers can have at most {MAX_ALIASES} aliases"));
+    }
+
+    for (name, expansion) in aliases {
+        validate_alias_name(name, command_names)
+            .and_then(|_| validate_alias_expansion(expansion, command_names))
+            .map_err(|err| format!("`{name}`: {err}"))?;
+    }
+
+    Ok(())
+}
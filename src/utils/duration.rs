@@ -0,0 +1,247 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc, Weekday};
+
+/// Durations longer than this are rejected, they would overflow once added to the current time
+const MAX_DURATION_DAYS: i64 = 1000 * 365;
+
+/// A duration given by a moderator, either relative (`1h30m`) or up to a point in time (`until friday`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParsedDuration {
+    Relative(Duration),
+    Until(DateTime<Utc>),
+}
+
+impl ParsedDuration {
+    /// The length of the duration when it starts at `now`, negative if an absolute time already passed
+    pub fn duration_from(&self, now: DateTime<Utc>) -> Duration {
+        match self {
+            Self::Relative(d) => *d,
+            Self::Until(t) => *t - now,
+        }
+    }
+}
+
+/// Parses durations like `15m`, `1h30m`, `1.5h`, `3 days` or `2 weeks 1 day`, absolute dates like `2026-12-01`
+/// or `2026-12-01 18:00` and `until` followed by a date, a time, a weekday or `tomorrow`. Times are in UTC.
+/// Single letter units are case sensitive, `m` is minutes and `M` is months.
+pub fn parse_duration(input: &str, now: DateTime<Utc>) -> Option<ParsedDuration> {
+    let input = input.trim();
+
+    if input == "0" {
+        return Some(ParsedDuration::Relative(Duration::zero()));
+    }
+
+    if let Some((keyword, rest)) = input.split_once(char::is_whitespace)
+        && keyword.eq_ignore_ascii_case("until")
+    {
+        return parse_point_in_time(rest.trim(), now).map(ParsedDuration::Until);
+    }
+
+    if let Some(date) = parse_date(input) {
+        return Some(ParsedDuration::Until(date));
+    }
+
+    parse_relative(input).map(ParsedDuration::Relative)
+}
+
+fn parse_relative(input: &str) -> Option<Duration> {
+    let mut chars = input.chars().peekable();
+    let mut seconds = 0.0;
+    let mut components = 0;
+
+    loop {
+        while chars.next_if(|c| c.is_whitespace() || *c == ',').is_some() {}
+
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut number = String::new();
+        while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || *c == '.') {
+            number.push(c);
+        }
+
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+        let mut unit = String::new();
+        while let Some(c) = chars.next_if(|c| c.is_alphabetic()) {
+            unit.push(c);
+        }
+
+        // "1 hour and 30 minutes"
+        if number.is_empty() && components > 0 && unit.eq_ignore_ascii_case("and") {
+            continue;
+        }
+
+        let number = number.parse::<f64>().ok()?;
+        seconds += number * unit_seconds(&unit)? as f64;
+        components += 1;
+    }
+
+    if components == 0 || !seconds.is_finite() || seconds > (MAX_DURATION_DAYS * 86400) as f64 {
+        return None;
+    }
+
+    Some(Duration::milliseconds((seconds * 1000.0).round() as i64))
+}
+
+fn unit_seconds(unit: &str) -> Option<i64> {
+    if unit == "M" {
+        return Some(30 * 86400);
+    }
+
+    Some(match unit.to_lowercase().as_str() {
+        "s" | "sec" | "secs" | "second" | "seconds" => 1,
+        "m" | "min" | "mins" | "minute" | "minutes" => 60,
+        "h" | "hr" | "hrs" | "hour" | "hours" => 3600,
+        "d" | "day" | "days" => 86400,
+        "w" | "wk" | "wks" | "week" | "weeks" => 7 * 86400,
+        "mo" | "mos" | "month" | "months" => 30 * 86400,
+        "y" | "yr" | "yrs" | "year" | "years" => 365 * 86400,
+        _ => return None,
+    })
+}
+
+/// Dates and dates with a time, `2026-12-01` starts at midnight
+fn parse_date(input: &str) -> Option<DateTime<Utc>> {
+    for format in ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S"] {
+        if let Ok(date) = NaiveDateTime::parse_from_str(input, format) {
+            return Some(date.and_utc());
+        }
+    }
+
+    NaiveDate::parse_from_str(input, "%Y-%m-%d")
+        .ok()
+        .map(|d| d.and_time(NaiveTime::MIN).and_utc())
+}
+
+/// Everything `until` accepts, weekdays and `tomorrow` refer to the start of the day
+fn parse_point_in_time(input: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    if let Some(date) = parse_date(input) {
+        return Some(date);
+    }
+
+    let today = now.date_naive();
+    let lower = input.to_lowercase();
+
+    if lower == "tomorrow" {
+        return Some(
+            (today + Duration::days(1))
+                .and_time(NaiveTime::MIN)
+                .and_utc(),
+        );
+    }
+
+    if let Ok(weekday) = lower.parse::<Weekday>() {
+        let days =
+            (weekday.num_days_from_monday() + 7 - today.weekday().num_days_from_monday()) % 7;
+        // the same weekday means next week, today already started
+        let days = if days == 0 { 7 } else { days };
+
+        return Some(
+            (today + Duration::days(days as i64))
+                .and_time(NaiveTime::MIN)
+                .and_utc(),
+        );
+    }
+
+    let time = NaiveTime::parse_from_str(input, "%H:%M").ok()?;
+    let at = today.and_time(time).and_utc();
+
+    if at > now {
+        Some(at)
+    } else {
+        Some(at + Duration::days(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{DateTime, Duration, TimeZone, Utc};
+
+    use super::{ParsedDuration, parse_duration};
+
+    /// A wednesday
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 14, 12, 0, 0).unwrap()
+    }
+
+    fn relative(input: &str) -> Option<Duration> {
+        match parse_duration(input, now())? {
+            ParsedDuration::Relative(d) => Some(d),
+            ParsedDuration::Until(_) => None,
+        }
+    }
+
+    fn until(input: &str) -> Option<DateTime<Utc>> {
+        match parse_duration(input, now())? {
+            ParsedDuration::Until(t) => Some(t),
+            ParsedDuration::Relative(_) => None,
+        }
+    }
+
+    #[test]
+    fn parses_single_units() {
+        assert_eq!(relative("0"), Some(Duration::zero()));
+        assert_eq!(relative("15m"), Some(Duration::minutes(15)));
+        assert_eq!(relative("2M"), Some(Duration::days(60)));
+        assert_eq!(relative("1y"), Some(Duration::days(365)));
+        assert_eq!(relative("3 days"), Some(Duration::days(3)));
+        assert_eq!(relative("2weeks"), Some(Duration::weeks(2)));
+        assert_eq!(relative("1 Hour"), Some(Duration::hours(1)));
+    }
+
+    #[test]
+    fn parses_compound_and_decimal_durations() {
+        assert_eq!(relative("1h30m"), Some(Duration::minutes(90)));
+        assert_eq!(relative("2d12h"), Some(Duration::hours(60)));
+        assert_eq!(relative("1.5h"), Some(Duration::minutes(90)));
+        assert_eq!(relative("1h 30m"), Some(Duration::minutes(90)));
+        assert_eq!(
+            relative("1 hour and 30 minutes"),
+            Some(Duration::minutes(90))
+        );
+        assert_eq!(relative("1 week, 2 days"), Some(Duration::days(9)));
+    }
+
+    #[test]
+    fn rejects_invalid_durations() {
+        for input in [
+            "", "m", "15", "1h30", "15x", "1..5h", "and 1h", "spam", "1h spam", "9999999y",
+        ] {
+            assert_eq!(parse_duration(input, now()), None, "{input}");
+        }
+    }
+
+    #[test]
+    fn parses_absolute_dates_and_times() {
+        let midnight = |d| Utc.with_ymd_and_hms(2026, 10, d, 0, 0, 0).unwrap();
+
+        assert_eq!(
+            until("2026-12-01"),
+            Some(Utc.with_ymd_and_hms(2026, 12, 1, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            until("2026-12-01 18:30"),
+            Some(Utc.with_ymd_and_hms(2026, 12, 1, 18, 30, 0).unwrap())
+        );
+        assert_eq!(until("until friday"), Some(midnight(16)));
+        assert_eq!(until("until Wednesday"), Some(midnight(21)));
+        assert_eq!(until("until tomorrow"), Some(midnight(15)));
+        assert_eq!(
+            until("until 18:00"),
+            Some(Utc.with_ymd_and_hms(2026, 10, 14, 18, 0, 0).unwrap())
+        );
+        assert_eq!(
+            until("until 09:00"),
+            Some(Utc.with_ymd_and_hms(2026, 10, 15, 9, 0, 0).unwrap())
+        );
+        assert_eq!(until("friday"), None);
+        assert_eq!(until("until someday"), None);
+    }
+
+    #[test]
+    fn past_dates_are_negative() {
+        let parsed = parse_duration("2020-01-01", now()).unwrap();
+        assert!(parsed.duration_from(now()) < Duration::zero());
+    }
+}
@@ -0,0 +1,268 @@
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serenity::all::{Context, CreateEmbed, CreateMessage, Member, Mentionable, Message};
+use sqlx::query;
+use tracing::warn;
+
+use crate::{
+    GUILD_SETTINGS, SQL,
+    commands::{ban_user, mute_member},
+    constants::BRAND_BLUE,
+    database::ActionType,
+    event_handler::CommandError,
+    utils::{
+        CommandMessageResponse, LogType, TemplateValues, appeal_components, get_guild_option,
+        guild_log, guild_templates, humanize_duration, tinyid,
+    },
+};
+
+/// The maximum amount of escalation policies a guild can define
+pub const MAX_ESCALATION_POLICIES: usize = 10;
+
+/// The action taken when an escalation policy fires, ordered by severity
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum EscalationAction {
+    Mute,
+    Ban,
+}
+
+/// A rule like "3 active warns within 30 days -> 1 day mute"
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct EscalationPolicy {
+    /// The amount of active warns which triggers the policy
+    pub warns: u32,
+    /// Only warns created within this many seconds are counted, all active warns if `None`
+    pub within: Option<i64>,
+    pub action: EscalationAction,
+    /// The duration of the action in seconds, permanent if `None`
+    pub duration: Option<i64>,
+}
+
+impl EscalationPolicy {
+    /// A short description of the policy, i.e. `3 warns within 30 days -> mute for 1 day`
+    pub fn describe(&self) -> String {
+        let within = self
+            .within
+            .map(|w| format!(" within {}", humanize_duration(&Duration::seconds(w))))
+            .unwrap_or_default();
+
+        let duration = self
+            .duration
+            .map(|d| format!("for {}", humanize_duration(&Duration::seconds(d))))
+            .unwrap_or(String::from("permanently"));
+
+        let action = match self.action {
+            EscalationAction::Mute => "mute",
+            EscalationAction::Ban => "ban",
+        };
+
+        format!("{} warns{within} -> {action} {duration}", self.warns)
+    }
+
+    /// Checks the policy can be applied, mutes are timeouts and can't exceed 28 days
+    pub fn validate(&self) -> Result<(), String> {
+        if self.warns == 0 {
+            return Err(String::from("The amount of warns must be a positive number"));
+        }
+
+        if self.within.is_some_and(|w| w <= 0) || self.duration.is_some_and(|d| d <= 0) {
+            return Err(String::from("Durations must be positive"));
+        }
+
+        if self.action == EscalationAction::Mute
+            && self
+                .duration
+                .is_none_or(|d| d > Duration::days(28).num_seconds())
+        {
+            return Err(String::from("Mutes need a duration of at most 28 days"));
+        }
+
+        Ok(())
+    }
+}
+
+/// Validates all escalation policies of a guild, as the `escalation` command would
+pub fn validate_escalation_policies(policies: &[EscalationPolicy]) -> Result<(), String> {
+    if policies.len() > MAX_ESCALATION_POLICIES {
+        return Err(format!(
+            "Servers can have at most {MAX_ESCALATION_POLICIES} escalation policies"
+        ));
+    }
+
+    policies
+        .iter()
+        .try_for_each(|p| p.validate().map_err(|err| format!("`{}`: {err}", p.describe())))
+}
+
+/// Evaluates the escalation policies of a guild after `warn_id` has been issued to `member`.
+/// When a policy fires its action is applied through the same code paths as the mute and ban commands with the bot as the moderator.
+/// If multiple policies fire at once only the most severe one is applied.
+pub async fn escalate(
+    ctx: &Context,
+    msg: &Message,
+    member: &Member,
+    warn_id: &str,
+    silent: bool,
+) -> Result<(), CommandError> {
+    let policies = {
+        let mut lock = GUILD_SETTINGS.lock().await;
+        lock.get(member.guild_id.get())
+            .await
+            .map(|s| s.moderation.escalation_policies)
+            .unwrap_or_default()
+    };
+
+    if policies.is_empty() {
+        return Ok(());
+    }
+
+    let res = query!(
+        "SELECT created_at FROM actions WHERE guild_id = $1 AND user_id = $2 AND type = 'warn' AND active = true",
+        member.guild_id.get() as i64,
+        member.user.id.get() as i64,
+    )
+    .fetch_all(&*SQL)
+    .await;
+
+    let warns = match res {
+        Ok(w) => w,
+        Err(err) => {
+            warn!("Couldn't fetch warns for escalation; err = {err:?}");
+            return Err(CommandError {
+                title: String::from("Could not check escalation policies"),
+                hint: Some(String::from("the warn has been issued, please check the users log")),
+                arg: None,
+            });
+        }
+    };
+
+    let now = Utc::now().naive_utc();
+
+    let Some(policy) = policies
+        .into_iter()
+        .filter(|p| {
+            let count = warns
+                .iter()
+                .filter(|w| p.within.is_none_or(|s| now - w.created_at <= Duration::seconds(s)))
+                .count();
+
+            count == p.warns as usize
+        })
+        .max_by_key(|p| (p.action, p.duration.unwrap_or(i64::MAX)))
+    else {
+        return Ok(());
+    };
+
+    let db_id = tinyid().await;
+    let bot_id = ctx.cache.current_user().id;
+    let reason = format!("Automatic escalation: {}", policy.describe());
+    let duration = policy.duration.map(Duration::seconds);
+
+    let time_string = duration
+        .map(|d| humanize_duration(&d))
+        .unwrap_or(String::from("permanent"));
+
+    let guild_name = match member.guild_id.to_partial_guild(ctx).await {
+        Ok(p) => p.name.clone(),
+        Err(_) => String::from("UNKNOWN_GUILD"),
+    };
+
+    let (action_type, log_title) = match policy.action {
+        EscalationAction::Mute => (ActionType::Mute, "MEMBER TIMEOUT"),
+        EscalationAction::Ban => (ActionType::Ban, "MEMBER BANNED"),
+    };
+
+    let (dm_template, response_template) = guild_templates(member.guild_id, &action_type).await;
+    let values = TemplateValues {
+        server: guild_name,
+        duration: time_string.clone(),
+        reason: reason.clone(),
+        log_id: db_id.clone(),
+        moderator: bot_id.mention().to_string(),
+        user: member.mention().to_string(),
+        ..Default::default()
+    };
+
+    let dm_members = get_guild_option(member.guild_id, "moderation.dm_members")
+        .await
+        .as_bool()
+        .unwrap_or(true);
+
+    let mut cmd_response = CommandMessageResponse::new(member.user.id)
+        .dm_content(values.render(&dm_template))
+        .server_content(values.render_response(
+            &response_template,
+            format!(" | Triggered by: `{warn_id}`"),
+        ))
+        .dm_components(appeal_components(member.guild_id, &db_id).await)
+        .mark_silent(silent || !dm_members);
+
+    let res = match policy.action {
+        EscalationAction::Mute => {
+            let res = mute_member(
+                ctx,
+                member,
+                &db_id,
+                bot_id,
+                &reason,
+                duration.map(|d| Utc::now() + d),
+                Some(warn_id),
+                None,
+            )
+            .await;
+
+            if res.is_ok() {
+                cmd_response.send_dm(ctx).await;
+            }
+
+            res
+        }
+        EscalationAction::Ban => {
+            ban_user(
+                ctx,
+                member.guild_id,
+                &member.user,
+                &db_id,
+                bot_id,
+                &reason,
+                duration.map(|d| (Utc::now() + d).naive_utc()),
+                0,
+                Some(warn_id),
+                None,
+                Some(&cmd_response),
+            )
+            .await
+        }
+    };
+
+    if let Err(err) = res {
+        return Err(CommandError {
+            title: format!("Escalation failed: {}", err.title),
+            ..err
+        });
+    }
+
+    cmd_response.send_response(ctx, msg).await;
+
+    guild_log(
+        ctx,
+        LogType::MemberModeration,
+        member.guild_id,
+        CreateMessage::new().add_embed(
+            CreateEmbed::new()
+                .description(format!(
+                    "**{log_title}**\n-# Log ID: `{db_id}` | Actor: {} `{}` | Target: {} `{}` | Duration: {time_string}\n-# Policy: {} | Triggered by: `{warn_id}`\n```\n{reason}\n```",
+                    bot_id.mention(),
+                    bot_id.get(),
+                    member.mention(),
+                    member.user.id.get(),
+                    policy.describe(),
+                ))
+                .color(BRAND_BLUE),
+        ),
+    )
+    .await;
+
+    Ok(())
+}
@@ -96,3 +96,51 @@ pub fn create_diff(a: String, b: String) -> String {
 
     final_string
 }
+
+/// Units used to render durations, months are 30 days and years 365 days like the duration transformer
+const DURATION_UNITS: [(&str, i64); 6] = [
+    ("year", 365 * 86400),
+    ("month", 30 * 86400),
+    ("day", 86400),
+    ("hour", 3600),
+    ("minute", 60),
+    ("second", 1),
+];
+
+/// Turns a duration into a human readable string using its two largest units, i.e. `2 days` or `1 hour 30 minutes`
+pub fn humanize_duration(duration: &chrono::Duration) -> String {
+    let mut remaining = duration.num_seconds().abs();
+    let mut parts = vec![];
+
+    for (unit, seconds) in DURATION_UNITS {
+        let count = remaining / seconds;
+
+        if count == 0 {
+            continue;
+        }
+
+        remaining -= count * seconds;
+        parts.push(format!("{count} {unit}{}", if count == 1 { "" } else { "s" }));
+
+        if parts.len() == 2 {
+            break;
+        }
+    }
+
+    if parts.is_empty() {
+        return String::from("0 seconds");
+    }
+
+    parts.join(" ")
+}
+
+/// Renders a duration starting now along with the point in time it ends, so the parsed input can be double checked
+pub fn humanize_expiry(duration: &chrono::Duration) -> String {
+    let end = chrono::Utc::now() + *duration;
+
+    format!(
+        "{} (until <t:{}:f>)",
+        humanize_duration(duration),
+        end.timestamp()
+    )
+}
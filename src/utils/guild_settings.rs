@@ -1,11 +1,16 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 use serde::Serialize;
+use serenity::json::{self, Value};
 use sqlx::{prelude::FromRow, query_as, types::Json};
 
+use serenity::all::GuildId;
+
 use crate::{
-    SQL,
-    utils::{AnyError, LogType},
+    GUILD_SETTINGS, SQL,
+    utils::{
+        AntiRaidConfig, AnyError, CommandOverride, EscalationPolicy, LogType, find_setting,
+    },
 };
 
 #[derive(Debug, Serialize, Clone, Default)]
@@ -18,6 +23,16 @@ struct GuildSettingsRow {
     guild_id: i64,
     log_bot: Option<bool>,
     log_channel_ids: Option<Json<HashMap<LogType, u64>>>,
+    prefix: Option<String>,
+    aliases: Option<Json<HashMap<String, String>>>,
+    escalation_policies: Option<Json<Vec<EscalationPolicy>>>,
+    anti_raid: Option<Json<AntiRaidConfig>>,
+    lockdown_channels: Option<Vec<i64>>,
+    restriction_roles: Option<Json<HashMap<String, u64>>>,
+    message_cache_opt_out: bool,
+    archive_channels: Option<Vec<i64>>,
+    message_templates: Option<Json<HashMap<String, String>>>,
+    options: Json<HashMap<String, Value>>,
 }
 
 impl GuildSettings {
@@ -33,6 +48,11 @@ impl GuildSettings {
     }
 
     pub async fn get(&mut self, guild: u64) -> Result<Settings, AnyError> {
+        self.get_ref(guild).await.cloned()
+    }
+
+    /// Borrows the settings of a guild, for lookups which run on every message and shouldn't clone all of them
+    async fn get_ref(&mut self, guild: u64) -> Result<&Settings, AnyError> {
         if self.invalid {
             let new_data = self.fetch_data().await?;
             self.inner = new_data;
@@ -40,7 +60,7 @@ impl GuildSettings {
         }
 
         match self.inner.get(&guild) {
-            Some(s) => Ok(s.clone()),
+            Some(s) => Ok(s),
             None => Err(AnyError::new("guild_not_found")),
         }
     }
@@ -51,7 +71,17 @@ impl GuildSettings {
             r#"SELECT
                 guild_id,
                 log_bot,
-                log_channel_ids as "log_channel_ids?: sqlx::types::Json<HashMap<LogType, u64>>"
+                log_channel_ids as "log_channel_ids?: sqlx::types::Json<HashMap<LogType, u64>>",
+                prefix,
+                aliases as "aliases?: sqlx::types::Json<HashMap<String, String>>",
+                escalation_policies as "escalation_policies?: sqlx::types::Json<Vec<EscalationPolicy>>",
+                anti_raid as "anti_raid?: sqlx::types::Json<AntiRaidConfig>",
+                lockdown_channels,
+                restriction_roles as "restriction_roles?: sqlx::types::Json<HashMap<String, u64>>",
+                message_cache_opt_out,
+                archive_channels,
+                message_templates as "message_templates?: sqlx::types::Json<HashMap<String, String>>",
+                options as "options: sqlx::types::Json<HashMap<String, Value>>"
             FROM guild_settings"#
         )
         .fetch_all(&*SQL)
@@ -69,7 +99,46 @@ impl GuildSettings {
                                 .map(|j| j.0)
                                 .unwrap_or_default(),
                             log_bots: record.log_bot,
+                            message_cache_opt_out: record.message_cache_opt_out,
+                            archive_channels: record
+                                .archive_channels
+                                .unwrap_or_default()
+                                .into_iter()
+                                .map(|c| c as u64)
+                                .collect(),
+                        },
+                        commands: SettingsCommands {
+                            prefix: record.prefix,
+                            aliases: record.aliases.map(|j| j.0).unwrap_or_default(),
+                            overrides: record
+                                .options
+                                .get("commands.overrides")
+                                .and_then(|v| json::from_value(v.clone()).ok())
+                                .map(Arc::new)
+                                .unwrap_or_default(),
                         },
+                        moderation: SettingsModeration {
+                            escalation_policies: record
+                                .escalation_policies
+                                .map(|j| j.0)
+                                .unwrap_or_default(),
+                            anti_raid: record.anti_raid.map(|j| j.0).unwrap_or_default(),
+                            lockdown_channels: record
+                                .lockdown_channels
+                                .unwrap_or_default()
+                                .into_iter()
+                                .map(|c| c as u64)
+                                .collect(),
+                            restriction_roles: record
+                                .restriction_roles
+                                .map(|j| j.0)
+                                .unwrap_or_default(),
+                            message_templates: record
+                                .message_templates
+                                .map(|j| j.0)
+                                .unwrap_or_default(),
+                        },
+                        options: record.options.0,
                     },
                 );
             });
@@ -84,10 +153,95 @@ impl GuildSettings {
 #[derive(Debug, Serialize, Clone, Default)]
 pub struct Settings {
     pub log: SettingsLog,
+    pub commands: SettingsCommands,
+    pub moderation: SettingsModeration,
+    /// Settings of the registry which don't have their own column, see [`SETTINGS`](super::SETTINGS)
+    pub options: HashMap<String, Value>,
+}
+
+impl Settings {
+    /// The value of a registry setting stored in `options`, or its default
+    pub fn option(&self, key: &str) -> Value {
+        self.options
+            .get(key)
+            .cloned()
+            .or_else(|| find_setting(key).map(|s| (s.default)()))
+            .unwrap_or_default()
+    }
 }
 
 #[derive(Debug, Serialize, Clone, Default)]
 pub struct SettingsLog {
     pub log_channel_ids: HashMap<LogType, u64>,
     pub log_bots: Option<bool>,
+    /// Excludes the messages of the guild from message cache snapshots
+    pub message_cache_opt_out: bool,
+    /// Channels whose attachments are archived as soon as they are sent
+    pub archive_channels: Vec<u64>,
+}
+
+/// Gets the custom command prefix of a guild, if it has one.
+pub async fn get_guild_prefix(guild_id: GuildId) -> Option<String> {
+    let mut lock = GUILD_SETTINGS.lock().await;
+    lock.get_ref(guild_id.get())
+        .await
+        .ok()?
+        .commands
+        .prefix
+        .clone()
+}
+
+/// Gets the command line an alias of a guild expands to.
+pub async fn get_guild_alias(guild_id: GuildId, name: &str) -> Option<String> {
+    let mut lock = GUILD_SETTINGS.lock().await;
+    lock.get_ref(guild_id.get())
+        .await
+        .ok()?
+        .commands
+        .aliases
+        .get(name)
+        .cloned()
+}
+
+/// Gets the command permission overrides of a guild.
+/// They are shared with the settings, so permission checks don't clone them on every command.
+pub async fn get_command_overrides(guild_id: GuildId) -> Arc<Vec<CommandOverride>> {
+    let mut lock = GUILD_SETTINGS.lock().await;
+    lock.get_ref(guild_id.get())
+        .await
+        .map(|s| Arc::clone(&s.commands.overrides))
+        .unwrap_or_default()
+}
+
+/// Gets the value of a registry setting without a column of a guild, or its default.
+pub async fn get_guild_option(guild_id: GuildId, key: &str) -> Value {
+    let mut lock = GUILD_SETTINGS.lock().await;
+
+    match lock.get(guild_id.get()).await {
+        Ok(settings) => settings.option(key),
+        Err(_) => find_setting(key).map(|s| (s.default)()).unwrap_or_default(),
+    }
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct SettingsCommands {
+    /// Overrides the prefix from the bot config
+    pub prefix: Option<String>,
+    /// User defined command names mapped to the command line they expand to
+    pub aliases: HashMap<String, String>,
+    /// Commands and categories granted or denied to users, roles and channels
+    pub overrides: Arc<Vec<CommandOverride>>,
+}
+
+#[derive(Debug, Serialize, Clone, Default)]
+pub struct SettingsModeration {
+    /// Actions automatically taken once a member collects enough warns
+    pub escalation_policies: Vec<EscalationPolicy>,
+    pub anti_raid: AntiRaidConfig,
+    /// The channels locked by the `lockdown` command
+    pub lockdown_channels: Vec<u64>,
+    /// Restriction names mapped to the role applied by the `restrict` command
+    pub restriction_roles: HashMap<String, u64>,
+    /// Custom DM and response wording keyed by action and kind, i.e. `ban.dm`
+    pub message_templates: HashMap<String, String>,
 }
@@ -8,7 +8,7 @@ pub async fn get_all_guilds(http: impl CacheHttp) -> Vec<GuildInfo> {
         let guilds = http.http().get_guilds(last_page, None).await;
 
         if let Ok(guilds) = guilds {
-            if guilds.len() == 0 {
+            if guilds.is_empty() {
                 break;
             }
 
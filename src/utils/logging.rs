@@ -14,6 +14,9 @@ pub enum LogType {
     MessageUpdate,
     OuroborosAnnonucements,
     AvatarUpdate,
+    Appeals,
+    MemberJoin,
+    ChannelModeration,
 }
 
 impl LogType {
@@ -24,7 +27,10 @@ impl LogType {
             LogType::ActionUpdate => "Action Update",
             LogType::MessageUpdate => "Message Delete",
             LogType::OuroborosAnnonucements => "Ouroboros Announcements",
-            LogType::AvatarUpdate => "Member Avatar Updates"
+            LogType::AvatarUpdate => "Member Avatar Updates",
+            LogType::Appeals => "Appeals",
+            LogType::MemberJoin => "Member Joins",
+            LogType::ChannelModeration => "Channel Moderation",
         })
     }
 
@@ -36,6 +42,9 @@ impl LogType {
             LogType::MessageUpdate => "Message deletions and edits",
             LogType::OuroborosAnnonucements => "Scheduled bot downtime, updates",
             LogType::AvatarUpdate => "Avatar updates (Can get very spammy in large servers!)",
+            LogType::Appeals => "Ban and mute appeals, enables the appeal button in DMs",
+            LogType::MemberJoin => "Joins with account age and prior actions",
+            LogType::ChannelModeration => "Channel locks, lockdowns and slowmode changes",
         })
     }
 
@@ -47,6 +56,9 @@ impl LogType {
             LogType::MessageUpdate,
             LogType::OuroborosAnnonucements,
             LogType::AvatarUpdate,
+            LogType::Appeals,
+            LogType::MemberJoin,
+            LogType::ChannelModeration,
         ]
     }
 
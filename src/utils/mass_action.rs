@@ -0,0 +1,260 @@
+use std::time::Duration;
+
+use serenity::{
+    all::{
+        ButtonStyle, Context, CreateActionRow, CreateAllowedMentions, CreateAttachment,
+        CreateButton, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage,
+        CreateMessage, EditMessage, Mentionable, Message, User,
+    },
+    futures::{StreamExt, stream},
+};
+
+use crate::{
+    constants::BRAND_BLUE,
+    event_handler::CommandError,
+    utils::{LogType, consume_serenity_error, guild_log},
+};
+
+/// How many targets of a mass action are processed at once.
+/// Serenity queues requests per rate limit bucket and retries on 429s, this only keeps a batch from hogging the global limit.
+const MASS_ACTION_CONCURRENCY: usize = 5;
+/// The amount of targets mentioned in the confirmation prompt
+const MAX_LISTED_TARGETS: usize = 30;
+/// The amount of failures shown in the summary, all failures are listed in the attached report
+const MAX_LISTED_FAILURES: usize = 10;
+
+/// The outcome of a mass action for a single target, the log id of the created action on success
+pub type MassActionResult = (User, Result<String, CommandError>);
+
+/// Mentions the targets of a mass action, shortened for large batches
+pub fn describe_targets(users: &[User]) -> String {
+    let mut listed = users
+        .iter()
+        .take(MAX_LISTED_TARGETS)
+        .map(|u| u.mention().to_string())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    if users.len() > MAX_LISTED_TARGETS {
+        listed.push_str(&format!(" and {} more", users.len() - MAX_LISTED_TARGETS));
+    }
+
+    listed
+}
+
+fn confirm_buttons(disabled: bool) -> Vec<CreateActionRow> {
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new("confirm")
+            .label("Confirm")
+            .style(ButtonStyle::Danger)
+            .disabled(disabled),
+        CreateButton::new("cancel")
+            .label("Cancel")
+            .style(ButtonStyle::Secondary)
+            .disabled(disabled),
+    ])]
+}
+
+/// Asks the author to confirm a mass action and returns the prompt once confirmed.
+/// `None` if the action was cancelled or the prompt timed out, the prompt is updated accordingly.
+pub async fn confirm_mass_action(
+    ctx: &Context,
+    msg: &Message,
+    description: String,
+) -> Result<Option<Message>, CommandError> {
+    let reply = CreateMessage::new()
+        .add_embed(
+            CreateEmbed::new()
+                .description(description.clone())
+                .color(BRAND_BLUE),
+        )
+        .components(confirm_buttons(false))
+        .reference_message(msg)
+        .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+    let mut prompt = match msg.channel_id.send_message(ctx, reply).await {
+        Ok(m) => m,
+        Err(err) => {
+            consume_serenity_error(String::from("MASS ACTION PROMPT"), err);
+            return Err(CommandError {
+                title: String::from("Could not send message"),
+                hint: None,
+                arg: None,
+            });
+        }
+    };
+
+    loop {
+        let Some(interaction) = prompt
+            .await_component_interaction(&ctx.shard)
+            .timeout(Duration::from_secs(60))
+            .await
+        else {
+            let _ = prompt
+                .edit(
+                    ctx,
+                    EditMessage::new()
+                        .embed(
+                            CreateEmbed::new()
+                                .description(format!("{description}\n-# Timed out"))
+                                .color(BRAND_BLUE),
+                        )
+                        .components(confirm_buttons(true)),
+                )
+                .await;
+
+            return Ok(None);
+        };
+
+        if interaction.user.id != msg.author.id {
+            if let Err(err) = interaction
+                .create_response(
+                    ctx,
+                    CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::new()
+                            .content("You are not the author of the original message!")
+                            .ephemeral(true),
+                    ),
+                )
+                .await
+            {
+                consume_serenity_error(String::from("MASS ACTION INTERACTION RESPONSE"), err);
+            }
+
+            continue;
+        }
+
+        let confirmed = interaction.data.custom_id == "confirm";
+        let status = if confirmed {
+            "Processing..."
+        } else {
+            "Cancelled"
+        };
+
+        if let Err(err) = interaction
+            .create_response(
+                ctx,
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .embed(
+                            CreateEmbed::new()
+                                .description(format!("{description}\n-# {status}"))
+                                .color(BRAND_BLUE),
+                        )
+                        .components(confirm_buttons(true)),
+                ),
+            )
+            .await
+        {
+            consume_serenity_error(String::from("MASS ACTION INTERACTION RESPONSE"), err);
+        }
+
+        return Ok(confirmed.then_some(prompt));
+    }
+}
+
+/// Runs `action` for every target with limited concurrency
+pub async fn run_mass_action<T, R, F, Fut>(
+    targets: Vec<T>,
+    action: F,
+) -> Vec<(T, Result<R, CommandError>)>
+where
+    T: Clone,
+    F: Fn(T) -> Fut,
+    Fut: Future<Output = Result<R, CommandError>>,
+{
+    stream::iter(targets)
+        .map(|target| {
+            let fut = action(target.clone());
+            async move { (target, fut.await) }
+        })
+        .buffer_unordered(MASS_ACTION_CONCURRENCY)
+        .collect()
+        .await
+}
+
+/// Replaces the prompt with a summary and posts a single log with the result of every target attached.
+/// `batch_id` is the id the actions were stored with, `title` is the name of the action (i.e. `BAN`)
+/// and `details` is appended to the summary line.
+#[allow(clippy::too_many_arguments)]
+pub async fn finish_mass_action(
+    ctx: &Context,
+    msg: &Message,
+    mut prompt: Message,
+    batch_id: &str,
+    title: &str,
+    details: &str,
+    reason: &str,
+    results: Vec<MassActionResult>,
+) {
+    let succeeded = results.iter().filter(|(_, r)| r.is_ok()).count();
+    let failed = results.len() - succeeded;
+    let summary = format!(
+        "Batch ID: `{batch_id}` | Targets: {} | Succeeded: {succeeded} | Failed: {failed}{details}",
+        results.len(),
+    );
+
+    let mut failures = results
+        .iter()
+        .filter_map(|(u, r)| {
+            r.as_ref()
+                .err()
+                .map(|e| format!("{} {}", u.mention(), e.title))
+        })
+        .take(MAX_LISTED_FAILURES)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if failed > MAX_LISTED_FAILURES {
+        failures.push_str(&format!("\nand {} more", failed - MAX_LISTED_FAILURES));
+    }
+
+    if !failures.is_empty() {
+        failures.insert(0, '\n');
+    }
+
+    let report = results
+        .iter()
+        .map(|(u, r)| match r {
+            Ok(id) => format!("{} ({}) - {id}", u.id, u.name),
+            Err(err) => format!("{} ({}) - failed: {}", u.id, u.name, err.title),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let _ = prompt
+        .edit(
+            ctx,
+            EditMessage::new()
+                .embed(
+                    CreateEmbed::new()
+                        .description(format!(
+                            "**MASS {title}**\n-# {summary}{failures}\n```\n{reason}\n```"
+                        ))
+                        .color(BRAND_BLUE),
+                )
+                .components(vec![]),
+        )
+        .await;
+
+    guild_log(
+        ctx,
+        LogType::MemberModeration,
+        msg.guild_id.unwrap(),
+        CreateMessage::new()
+            .add_embed(
+                CreateEmbed::new()
+                    .description(format!(
+                        "**MASS {title}**\n-# Actor: {} `{}` | {summary}\n```\n{reason}\n```",
+                        msg.author.mention(),
+                        msg.author.id.get(),
+                    ))
+                    .color(BRAND_BLUE),
+            )
+            .add_file(CreateAttachment::bytes(
+                report.into_bytes(),
+                format!("batch-{batch_id}.txt"),
+            )),
+    )
+    .await;
+}
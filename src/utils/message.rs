@@ -1,12 +1,17 @@
-use std::{collections::HashMap, panic, sync::Arc, time::Duration};
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
 use serenity::{
     FutureExt,
     all::{
-        Context, CreateAllowedMentions, CreateEmbed, CreateMessage, EditMessage, Message, UserId,
+        Context, CreateActionRow, CreateAllowedMentions, CreateEmbed, CreateMessage,
+        EditMessage, Message, UserId,
     },
 };
-use tokio::{sync::Mutex, task::JoinHandle, time::sleep};
+use tokio::{
+    sync::Mutex,
+    task::{JoinError, JoinHandle},
+    time::sleep,
+};
 use tracing::warn;
 
 use crate::{
@@ -18,17 +23,27 @@ use crate::{
 pub struct CommandMessageResponse {
     server_content: Box<dyn Fn(String) -> String + Send + Sync>,
     dm_content: String,
+    dm_components: Vec<CreateActionRow>,
     user: UserId,
     delete: bool,
     join_thread: Arc<Mutex<Option<JoinHandle<bool>>>>,
     silent: bool,
 }
 
+/// The addition to the response for the result of the DM task, which returns whether sending the DM failed
+fn dm_addition(res: Result<bool, JoinError>) -> String {
+    match res {
+        Ok(false) => String::new(),
+        _ => String::from(" | DM failed"),
+    }
+}
+
 impl CommandMessageResponse {
     pub fn new(user_id: UserId) -> Self {
         Self {
             server_content: Box::new(|a| a),
             dm_content: String::default(),
+            dm_components: vec![],
             user: user_id,
             delete: false,
             join_thread: Arc::new(Mutex::new(None)),
@@ -46,6 +61,11 @@ impl CommandMessageResponse {
         self
     }
 
+    pub fn dm_components(mut self, components: Vec<CreateActionRow>) -> Self {
+        self.dm_components = components;
+        self
+    }
+
     pub fn automatically_delete(mut self, delete: bool) -> Self {
         self.delete = delete;
         self
@@ -57,16 +77,22 @@ impl CommandMessageResponse {
     }
 
     pub async fn send_dm(&self, ctx: &Context) {
+        if self.silent {
+            return;
+        }
+
         let ctx_clone = ctx.clone();
         let desc = self.dm_content.clone();
-        let user = self.user.clone();
+        let components = self.dm_components.clone();
+        let user = self.user;
 
         {
             let mut lock = self.join_thread.lock().await;
 
             *lock = Some(tokio::spawn(async move {
                 let dm = CreateMessage::new()
-                    .add_embed(CreateEmbed::new().description(desc).color(BRAND_BLUE));
+                    .add_embed(CreateEmbed::new().description(desc).color(BRAND_BLUE))
+                    .components(components);
 
                 user.direct_message(&ctx_clone, dm).await.is_err()
             }));
@@ -79,13 +105,14 @@ impl CommandMessageResponse {
         } else {
             let mut lock = self.join_thread.lock().await;
 
-            let res = match lock.as_mut().map(|h| h.now_or_never()) {
-                Some(Some(Ok(b))) if b => String::new(),
-                _ => String::from(" | DM failed")
-            };
-
-            lock.take();
-            res
+            // a DM which is still being sent is awaited once the response went out and the response edited
+            match lock.as_mut().map(|h| h.now_or_never()) {
+                Some(Some(res)) => {
+                    lock.take();
+                    dm_addition(res)
+                }
+                _ => String::new(),
+            }
         };
 
         let embed = CreateEmbed::new()
@@ -106,20 +133,20 @@ impl CommandMessageResponse {
         };
 
         let mut lock = self.join_thread.lock().await;
-        if let Some(handle) = lock.as_mut() {
-            let addition = match handle.await {
-                Ok(b) if b => String::new(),
-                _ => String::from("| DM failed"),
-            };
-            let desc = (*self.server_content)(addition);
-
-            let _ = msg
-                .edit(
-                    &ctx,
-                    EditMessage::new()
-                        .embed(CreateEmbed::new().description(desc).color(BRAND_BLUE)),
-                )
-                .await;
+        if let Some(handle) = lock.take() {
+            let addition = dm_addition(handle.await);
+
+            if !addition.is_empty() {
+                let desc = (*self.server_content)(addition);
+
+                let _ = msg
+                    .edit(
+                        &ctx,
+                        EditMessage::new()
+                            .embed(CreateEmbed::new().description(desc).color(BRAND_BLUE)),
+                    )
+                    .await;
+            }
         }
 
         if self.delete {
@@ -145,6 +172,11 @@ pub async fn extract_command_parameters<'a>(
     let mut to_remove = Vec::new();
 
     while let Some(token) = lex.next() {
+        // quoted text is never a parameter, i.e. reasons passed by context menus and slash commands
+        if token.quoted {
+            continue;
+        }
+
         let Some((positive, arg_name)) = ({
             token
                 .raw
@@ -170,7 +202,7 @@ pub async fn extract_command_parameters<'a>(
                 }
 
                 let mut last_consumed = None;
-                let mut cloned_iter = cloned.rev().into_iter();
+                let mut cloned_iter = cloned.rev();
                 let pos_to_search = lex.peek().map(|t| t.position).unwrap_or(0);
 
                 while let Some(token) = cloned_iter.next() {
@@ -1,5 +1,6 @@
 mod permissions;
 pub use permissions::can_target;
+pub use permissions::can_use_in_channel;
 pub use permissions::check_guild_permission;
 pub use permissions::is_developer;
 pub use permissions::permissions_for_channel;
@@ -13,6 +14,27 @@ mod random;
 // pub use random::random;
 pub use random::tinyid;
 
+mod anti_raid;
+pub use anti_raid::AntiRaidConfig;
+pub use anti_raid::RaidAction;
+pub use anti_raid::RaidDetector;
+pub use anti_raid::anti_raid_join;
+pub use anti_raid::end_raid_mode;
+pub use anti_raid::raid_component;
+pub use anti_raid::restore_verification_level;
+
+mod appeals;
+pub use appeals::appeal_component;
+pub use appeals::appeal_components;
+pub use appeals::appeal_modal;
+
+mod escalation;
+pub use escalation::EscalationAction;
+pub use escalation::EscalationPolicy;
+pub use escalation::MAX_ESCALATION_POLICIES;
+pub use escalation::validate_escalation_policies;
+pub use escalation::escalate;
+
 mod guild_settings;
 pub use guild_settings::*;
 
@@ -23,11 +45,54 @@ mod message;
 pub use message::CommandMessageResponse;
 pub use message::extract_command_parameters;
 
+pub mod application_commands;
 pub mod cache;
 pub mod command_processing;
 
 mod formatting;
 pub use formatting::create_diff;
+pub use formatting::humanize_duration;
+pub use formatting::humanize_expiry;
+
+mod duration;
+pub use duration::ParsedDuration;
+pub use duration::parse_duration;
+
+mod templates;
+pub use templates::TemplateKind;
+pub use templates::TemplateValues;
+pub use templates::default_template;
+pub use templates::guild_templates;
+pub use templates::template_key;
+pub use templates::templated_actions;
+pub use templates::validate_template;
+pub use templates::validate_templates;
+
+mod restrictions;
+pub use restrictions::MAX_RESTRICTIONS;
+pub use restrictions::validate_restriction_name;
+pub use restrictions::validate_restriction_role;
+pub use restrictions::validate_restrictions;
+
+mod settings_registry;
+pub use settings_registry::SETTINGS;
+pub use settings_registry::SettingContext;
+pub use settings_registry::SettingDefinition;
+pub use settings_registry::find_setting;
+
+mod command_overrides;
+pub use command_overrides::CommandOverride;
+pub use command_overrides::MAX_COMMAND_OVERRIDES;
+pub use command_overrides::OverrideScope;
+pub use command_overrides::OverrideTarget;
+pub use command_overrides::resolve_command_override;
+pub use command_overrides::validate_command_overrides;
+
+mod mass_action;
+pub use mass_action::confirm_mass_action;
+pub use mass_action::describe_targets;
+pub use mass_action::finish_mass_action;
+pub use mass_action::run_mass_action;
 
 mod guilds;
 pub use guilds::get_all_guilds;
@@ -39,3 +104,13 @@ mod webhook;
 pub use webhook::send_error;
 pub use webhook::consume_serenity_error;
 pub use webhook::consume_pgsql_error;
+
+mod transcript;
+pub use transcript::MAX_TRANSCRIPT_MESSAGES;
+pub use transcript::Transcript;
+pub use transcript::TranscriptMessage;
+pub use transcript::fetch_transcript_messages;
+
+mod uploads;
+pub use uploads::describe_not_attached;
+pub use uploads::split_upload_budget;
@@ -1,8 +1,13 @@
 use serenity::all::{
-    Context, GuildChannel, Member, PartialGuild, PermissionOverwriteType, Permissions, User,
+    ChannelId, Context, GuildChannel, Member, PartialGuild, PermissionOverwriteType, Permissions,
+    User,
 };
 
-use crate::BOT_CONFIG;
+use crate::{
+    BOT_CONFIG,
+    commands::Command,
+    utils::{get_command_overrides, resolve_command_override},
+};
 
 /// Checks if a member has a permission in the guild. Ingnores channel overrides.
 pub async fn check_guild_permission(
@@ -127,12 +132,15 @@ pub fn is_developer(user: &User) -> bool {
         .is_some_and(|i| i.contains(&user.id.get()))
 }
 
-/// Checks if a user can target another user with a specific permission (i.e. can user ban target?)
+/// Checks if a user can target another user with a specific permission (i.e. can user ban target?).
+/// Members granted the command by an override may lack the permission, for them the plain role hierarchy decides.
 pub async fn can_target(
     ctx: &Context,
     user: &Member,
     target: &Member,
     permission: Permissions,
+    command: &dyn Command,
+    channel_id: ChannelId,
 ) -> bool {
     if let Ok(partial) = user.guild_id.to_partial_guild(ctx).await {
         if user.user.id == partial.owner_id {
@@ -143,12 +151,13 @@ pub async fn can_target(
         };
     }
 
-    let get_highest_role_pos = async |mem: &Member| {
+    // the position of the highest role with the permission, or of the highest role at all if `any` is set
+    let get_highest_role_pos = async |mem: &Member, any: bool| {
         let mut matching = -1;
 
         // fetch roles if they dont exist in the cache
         let mut roles = {
-            if let Some(roles) = mem.roles(&ctx) {
+            if let Some(roles) = mem.roles(ctx) {
                 roles
             } else {
                 if let Ok(roles) = mem.guild_id.roles(&ctx).await {
@@ -162,7 +171,8 @@ pub async fn can_target(
         roles.sort();
 
         for role in roles {
-            if role.has_permission(permission)
+            if any
+                || role.has_permission(permission)
                 || role.has_permission(Permissions::ADMINISTRATOR)
             {
                 matching = role.position as i32;
@@ -172,7 +182,94 @@ pub async fn can_target(
         matching
     };
 
-    let user_highest_matching_role_pos = get_highest_role_pos(user).await;
-    let target_highest_matching_role_pos = get_highest_role_pos(target).await;
+    let user_highest_matching_role_pos = get_highest_role_pos(user, false).await;
+
+    if user_highest_matching_role_pos == -1
+        && granted_by_override(ctx, user, command, channel_id).await
+    {
+        return get_highest_role_pos(user, true).await > get_highest_role_pos(target, true).await;
+    }
+
+    let target_highest_matching_role_pos = get_highest_role_pos(target, false).await;
     user_highest_matching_role_pos > target_highest_matching_role_pos
 }
+
+/// Checks if a member may use a command on another channel than the one it was run in (i.e. `lock #general`),
+/// the permission checks before running a command only cover the channel it was run in.
+/// Overrides of the target channel are respected, threads use the permissions of their parent channel.
+pub async fn can_use_in_channel(
+    ctx: &Context,
+    member: &Member,
+    channel: &GuildChannel,
+    permission: Permissions,
+    command: &dyn Command,
+) -> bool {
+    if let Some(allow) = command_override(ctx, member, command, channel.id).await {
+        return allow;
+    }
+
+    let Ok(guild) = member.guild_id.to_partial_guild(ctx).await else {
+        return false;
+    };
+
+    let parent = match channel.parent_id {
+        Some(parent_id) if channel.thread_metadata.is_some() => {
+            match parent_id.to_channel(ctx).await.map(|c| c.guild()) {
+                Ok(Some(parent)) => Some(parent),
+                _ => return false,
+            }
+        }
+        _ => None,
+    };
+
+    check_channel_permission(&guild, parent.as_ref().unwrap_or(channel), member, permission)
+}
+
+/// Checks if an override grants a member a command in a channel, resolved like the permission cache does
+async fn granted_by_override(
+    ctx: &Context,
+    member: &Member,
+    command: &dyn Command,
+    channel_id: ChannelId,
+) -> bool {
+    command_override(ctx, member, command, channel_id).await == Some(true)
+}
+
+/// Resolves the overrides of a command for a member in a channel, `None` if none applies
+async fn command_override(
+    ctx: &Context,
+    member: &Member,
+    command: &dyn Command,
+    channel_id: ChannelId,
+) -> Option<bool> {
+    let overrides = get_command_overrides(member.guild_id).await;
+
+    if overrides.is_empty() {
+        return None;
+    }
+
+    let roles = member
+        .roles
+        .iter()
+        .map(|r| r.get())
+        .chain([member.guild_id.get()])
+        .collect::<Vec<_>>();
+    let parent_id = match channel_id.to_channel(ctx).await.map(|c| c.guild()) {
+        Ok(Some(channel)) => channel.parent_id,
+        _ => None,
+    };
+    let channels = [Some(channel_id), parent_id]
+        .into_iter()
+        .flatten()
+        .map(|c| c.get())
+        .collect::<Vec<_>>();
+
+    resolve_command_override(
+        &overrides,
+        command.get_name(),
+        command.get_category(),
+        member.user.id.get(),
+        &roles,
+        &channels,
+    )
+}
@@ -0,0 +1,60 @@
+use std::collections::HashMap;
+
+use serenity::all::{Role, RoleId};
+
+/// The maximum amount of restrictions a guild can define
+pub const MAX_RESTRICTIONS: usize = 20;
+/// The maximum length of a restriction name
+pub const MAX_RESTRICTION_NAME_LENGTH: usize = 32;
+
+pub fn validate_restriction_name(name: &str) -> Result<(), String> {
+    if name.is_empty()
+        || name.chars().count() > MAX_RESTRICTION_NAME_LENGTH
+        || !name
+            .chars()
+            .all(|c| (c.is_alphanumeric() && !c.is_uppercase()) || c == '-' || c == '_')
+    {
+        return Err(format!(
+            "Restriction names can be at most {MAX_RESTRICTION_NAME_LENGTH} characters and may only contain lowercase letters, numbers, - and _"
+        ));
+    }
+
+    Ok(())
+}
+
+/// @everyone and roles managed by integrations can't be handed out by the bot
+pub fn validate_restriction_role(role: &Role) -> Result<(), String> {
+    if role.id.get() == role.guild_id.get() || role.managed {
+        return Err(String::from(
+            "@everyone and roles managed by integrations can't be used as restriction roles",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Validates all restrictions of a guild against its roles, as the `restrictions` command would
+pub fn validate_restrictions(
+    restrictions: &HashMap<String, u64>,
+    roles: &HashMap<RoleId, Role>,
+) -> Result<(), String> {
+    if restrictions.len() > MAX_RESTRICTIONS {
+        return Err(format!(
+            "Servers can have at most {MAX_RESTRICTIONS} restrictions"
+        ));
+    }
+
+    for (name, role_id) in restrictions {
+        validate_restriction_name(name).map_err(|err| format!("`{name}`: {err}"))?;
+
+        let Some(role) = roles.get(&RoleId::new((*role_id).max(1))) else {
+            return Err(format!(
+                "`{name}`: <@&{role_id}> is not a role of this server"
+            ));
+        };
+
+        validate_restriction_role(role).map_err(|err| format!("`{name}`: {err}"))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,460 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::de::DeserializeOwned;
+use serenity::{
+    all::{Context, GuildId, Role, RoleId},
+    json::{Value, from_value, json},
+};
+
+use crate::{
+    CommandNamesContainer,
+    utils::{
+        AntiRaidConfig, CommandOverride, EscalationPolicy, LogType,
+        command_processing::validate_aliases, validate_command_overrides,
+        validate_escalation_policies, validate_restrictions, validate_templates,
+    },
+};
+
+/// The type of a setting, decides how `config set` parses input and which values are valid
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingType {
+    Bool,
+    /// Text which can be unset with `none`
+    Text {
+        max_length: usize,
+    },
+    /// A list of text channels of the guild
+    Channels {
+        max: usize,
+    },
+    /// A structured value edited with its own command, `config` only exports and imports it
+    Managed {
+        command: &'static str,
+    },
+}
+
+impl std::fmt::Display for SettingType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SettingType::Bool => write!(f, "Bool"),
+            SettingType::Text { .. } => write!(f, "Text"),
+            SettingType::Channels { .. } => write!(f, "Channels"),
+            SettingType::Managed { .. } => write!(f, "Managed"),
+        }
+    }
+}
+
+/// A per-guild setting.
+/// Settings without a column are stored in the `options` map of `guild_settings`, new settings only need an entry here.
+pub struct SettingDefinition {
+    /// `<group>.<name>`, groups mirror the sections of [`Settings`](super::Settings)
+    pub key: &'static str,
+    pub kind: SettingType,
+    pub description: &'static str,
+    /// The `guild_settings` column holding the value
+    pub column: Option<&'static str>,
+    pub default: fn() -> Value,
+    /// Checks beyond the type, managed values run the same checks as the command editing them
+    pub validate: fn(&Value, &SettingContext) -> Result<(), String>,
+}
+
+/// What validation needs to know about the guild a value is set for
+#[derive(Default)]
+pub struct SettingContext {
+    pub roles: HashMap<RoleId, Role>,
+    pub channels: HashSet<u64>,
+    /// The names of all commands, aliases can't shadow them and have to expand to one
+    pub command_names: Vec<&'static str>,
+}
+
+impl SettingContext {
+    pub async fn fetch(ctx: &Context, guild_id: GuildId) -> Result<Self, String> {
+        let Ok(roles) = guild_id.roles(ctx).await else {
+            return Err(String::from("could not get the roles of the server"));
+        };
+
+        let Ok(channels) = guild_id.channels(ctx).await else {
+            return Err(String::from("could not get the channels of the server"));
+        };
+
+        let command_names = {
+            let data = ctx.data.read().await;
+            data.get::<CommandNamesContainer>()
+                .map(|names| names.to_vec())
+                .unwrap_or_default()
+        };
+
+        Ok(Self {
+            roles,
+            channels: channels.keys().map(|c| c.get()).collect(),
+            command_names,
+        })
+    }
+
+    fn check_channel(&self, id: u64) -> Result<(), String> {
+        if self.channels.contains(&id) {
+            Ok(())
+        } else {
+            Err(format!("<#{id}> is not a channel of this server"))
+        }
+    }
+}
+
+impl SettingDefinition {
+    /// Parses the input of `config set`, `none` resets text and channel settings
+    pub fn parse_input(&self, input: &str) -> Result<Value, String> {
+        let input = input.trim();
+
+        match self.kind {
+            SettingType::Bool => match input.to_lowercase().as_str() {
+                "true" | "yes" | "on" | "enable" | "enabled" => Ok(json!(true)),
+                "false" | "no" | "off" | "disable" | "disabled" => Ok(json!(false)),
+                _ => Err(String::from("expected true or false")),
+            },
+            SettingType::Text { .. } if input.eq_ignore_ascii_case("none") => Ok(Value::Null),
+            SettingType::Text { .. } => Ok(json!(input)),
+            SettingType::Channels { .. } if input.eq_ignore_ascii_case("none") => Ok(json!([])),
+            SettingType::Channels { .. } => input
+                .split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|c| !c.is_empty())
+                .map(|c| {
+                    c.strip_prefix("<#")
+                        .and_then(|c| c.strip_suffix('>'))
+                        .unwrap_or(c)
+                        .parse::<u64>()
+                        .map_err(|_| format!("`{c}` is not a channel mention or id"))
+                })
+                .collect::<Result<Vec<_>, _>>()
+                .map(|ids| json!(ids)),
+            SettingType::Managed { command } => Err(format!(
+                "this setting is edited with the `{command}` command"
+            )),
+        }
+    }
+
+    /// Checks that a value fits the type and validation of the setting, null is always valid and means the default
+    pub fn check(&self, value: &Value, ctx: &SettingContext) -> Result<(), String> {
+        match (self.kind, value) {
+            (_, Value::Null) => return Ok(()),
+            (SettingType::Bool, Value::Bool(_)) => {}
+            (SettingType::Bool, _) => return Err(String::from("expected true or false")),
+            (SettingType::Text { max_length }, Value::String(text)) => {
+                if text.chars().count() > max_length {
+                    return Err(format!("must be at most {max_length} characters long"));
+                }
+            }
+            (SettingType::Text { .. }, _) => return Err(String::from("expected text")),
+            (SettingType::Channels { max }, Value::Array(ids)) => {
+                if ids.iter().any(|id| !id.is_u64()) {
+                    return Err(String::from("expected a list of channel ids"));
+                }
+
+                if ids.iter().collect::<HashSet<_>>().len() != ids.len() {
+                    return Err(String::from("channels can only be listed once"));
+                }
+
+                if ids.len() > max {
+                    return Err(format!("at most {max} channels can be set"));
+                }
+
+                ids.iter()
+                    .filter_map(|id| id.as_u64())
+                    .try_for_each(|id| ctx.check_channel(id))?;
+            }
+            (SettingType::Channels { .. }, _) => {
+                return Err(String::from("expected a list of channel ids"));
+            }
+            (SettingType::Managed { .. }, _) => {}
+        }
+
+        (self.validate)(value, ctx)
+    }
+
+    /// Formats a value for display, channels as mentions
+    pub fn display(&self, value: &Value) -> String {
+        match (self.kind, value) {
+            (SettingType::Channels { .. }, Value::Array(ids)) if ids.is_empty() => {
+                String::from("none")
+            }
+            (SettingType::Channels { .. }, Value::Array(ids)) => ids
+                .iter()
+                .map(|id| format!("<#{id}>"))
+                .collect::<Vec<_>>()
+                .join(" "),
+            (_, Value::Null) => String::from("none"),
+            (_, Value::String(text)) => format!("`{text}`"),
+            (_, value) => format!("`{value}`"),
+        }
+    }
+}
+
+fn valid(_: &Value, _: &SettingContext) -> Result<(), String> {
+    Ok(())
+}
+
+/// Deserializes a managed value into the type the bot reads it as
+fn deserialize<T: DeserializeOwned>(value: &Value) -> Result<T, String> {
+    from_value::<T>(value.clone()).map_err(|err| err.to_string())
+}
+
+/// Every per-guild setting, in the order `config list` shows them
+pub static SETTINGS: &[SettingDefinition] = &[
+    SettingDefinition {
+        key: "log.log_bots",
+        kind: SettingType::Bool,
+        description: "Include bots in server activity logs",
+        column: Some("log_bot"),
+        default: || json!(false),
+        validate: valid,
+    },
+    SettingDefinition {
+        key: "log.channels",
+        kind: SettingType::Managed { command: "dlog" },
+        description: "The channel each log type is sent to",
+        column: Some("log_channel_ids"),
+        default: || json!({}),
+        validate: |value, ctx| {
+            deserialize::<HashMap<LogType, u64>>(value)?
+                .values()
+                .try_for_each(|id| ctx.check_channel(*id))
+        },
+    },
+    SettingDefinition {
+        key: "log.message_cache_opt_out",
+        kind: SettingType::Bool,
+        description: "Exclude messages of this server from persisted message cache snapshots",
+        column: Some("message_cache_opt_out"),
+        default: || json!(false),
+        validate: valid,
+    },
+    SettingDefinition {
+        key: "log.archive_channels",
+        kind: SettingType::Channels { max: 10 },
+        description: "Channels whose attachments are archived and re-uploaded to delete and edit logs",
+        column: Some("archive_channels"),
+        default: || json!([]),
+        validate: valid,
+    },
+    SettingDefinition {
+        key: "commands.prefix",
+        kind: SettingType::Text { max_length: 5 },
+        description: "The command prefix of this server, `none` uses the default prefix",
+        column: Some("prefix"),
+        default: || Value::Null,
+        validate: |value, _| match value.as_str() {
+            Some(p) if p.is_empty() || p.contains(char::is_whitespace) || p.contains('`') => Err(
+                String::from("prefixes can't be empty or contain whitespace or backticks"),
+            ),
+            _ => Ok(()),
+        },
+    },
+    SettingDefinition {
+        key: "commands.aliases",
+        kind: SettingType::Managed { command: "alias" },
+        description: "Custom command names and the command line they expand to",
+        column: Some("aliases"),
+        default: || json!({}),
+        validate: |value, ctx| validate_aliases(&deserialize(value)?, &ctx.command_names),
+    },
+    SettingDefinition {
+        key: "commands.overrides",
+        kind: SettingType::Managed {
+            command: "overrides",
+        },
+        description: "Commands and categories granted or denied to users, roles and channels",
+        column: None,
+        default: || json!([]),
+        validate: |value, ctx| {
+            validate_command_overrides(
+                &deserialize::<Vec<CommandOverride>>(value)?,
+                &ctx.command_names,
+            )
+        },
+    },
+    SettingDefinition {
+        key: "moderation.dm_members",
+        kind: SettingType::Bool,
+        description: "DM members when they are warned, kicked, banned, muted or restricted",
+        column: None,
+        default: || json!(true),
+        validate: valid,
+    },
+    SettingDefinition {
+        key: "moderation.escalation_policies",
+        kind: SettingType::Managed {
+            command: "escalation",
+        },
+        description: "Actions taken automatically once members collect enough warns",
+        column: Some("escalation_policies"),
+        default: || json!([]),
+        validate: |value, _| {
+            validate_escalation_policies(&deserialize::<Vec<EscalationPolicy>>(value)?)
+        },
+    },
+    SettingDefinition {
+        key: "moderation.anti_raid",
+        kind: SettingType::Managed {
+            command: "antiraid",
+        },
+        description: "Join burst detection and raid mode",
+        column: Some("anti_raid"),
+        default: || Value::Null,
+        validate: |value, _| deserialize::<AntiRaidConfig>(value)?.validate(),
+    },
+    SettingDefinition {
+        key: "moderation.lockdown_channels",
+        kind: SettingType::Channels { max: 50 },
+        description: "The channels locked by the `lockdown` command",
+        column: Some("lockdown_channels"),
+        default: || json!([]),
+        validate: valid,
+    },
+    SettingDefinition {
+        key: "moderation.restriction_roles",
+        kind: SettingType::Managed {
+            command: "restrictions",
+        },
+        description: "Restriction names and the role the `restrict` command applies",
+        column: Some("restriction_roles"),
+        default: || json!({}),
+        validate: |value, ctx| validate_restrictions(&deserialize(value)?, &ctx.roles),
+    },
+    SettingDefinition {
+        key: "moderation.message_templates",
+        kind: SettingType::Managed {
+            command: "templates",
+        },
+        description: "Custom wording of moderation DMs and responses",
+        column: Some("message_templates"),
+        default: || json!({}),
+        validate: |value, _| validate_templates(&deserialize(value)?),
+    },
+];
+
+/// Finds a setting by its key, ignoring case
+pub fn find_setting(key: &str) -> Option<&'static SettingDefinition> {
+    SETTINGS.iter().find(|s| s.key.eq_ignore_ascii_case(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use serenity::json::{Value, json};
+
+    use super::{SETTINGS, SettingContext, find_setting};
+
+    #[test]
+    fn defaults_are_valid() {
+        for setting in SETTINGS {
+            assert_eq!(
+                setting.check(&(setting.default)(), &SettingContext::default()),
+                Ok(()),
+                "{}",
+                setting.key
+            );
+        }
+    }
+
+    #[test]
+    fn parses_and_checks_input() {
+        let ctx = SettingContext::default();
+
+        let prefix = find_setting("commands.prefix").unwrap();
+        assert_eq!(prefix.parse_input("none"), Ok(Value::Null));
+        assert!(prefix.check(&json!("??"), &ctx).is_ok());
+        assert!(prefix.check(&json!("toolong"), &ctx).is_err());
+        assert!(prefix.check(&json!("a b"), &ctx).is_err());
+
+        let channels = find_setting("moderation.lockdown_channels").unwrap();
+        assert_eq!(channels.parse_input("<#1>, 2"), Ok(json!([1, 2])));
+        assert!(channels.parse_input("#general").is_err());
+        assert!(channels.check(&json!([1, 1]), &ctx).is_err());
+
+        let policies = find_setting("moderation.escalation_policies").unwrap();
+        assert!(policies.parse_input("[]").is_err());
+        assert!(
+            policies
+                .check(
+                    &json!([{ "warns": 3, "action": "mute", "duration": 86400 }]),
+                    &ctx
+                )
+                .is_ok()
+        );
+        assert!(
+            policies
+                .check(&json!([{ "warns": "three" }]), &ctx)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn managed_values_follow_command_rules() {
+        let ctx = SettingContext {
+            channels: [1].into(),
+            command_names: vec!["ban", "mute"],
+            ..Default::default()
+        };
+
+        let channels = find_setting("moderation.lockdown_channels").unwrap();
+        assert!(channels.check(&json!([1]), &ctx).is_ok());
+        assert!(channels.check(&json!([2]), &ctx).is_err());
+
+        let aliases = find_setting("commands.aliases").unwrap();
+        assert!(aliases.check(&json!({ "b": "ban {user}" }), &ctx).is_ok());
+        assert!(
+            aliases
+                .check(&json!({ "ban": "mute {user}" }), &ctx)
+                .is_err()
+        );
+        assert!(aliases.check(&json!({ "b": "m {user}" }), &ctx).is_err());
+        assert!(aliases.check(&json!({ "b": "ban `x`" }), &ctx).is_err());
+
+        let policies = find_setting("moderation.escalation_policies").unwrap();
+        let long_mute = json!([{ "warns": 3, "action": "mute", "duration": 30 * 86400 }]);
+        assert!(policies.check(&long_mute, &ctx).is_err());
+        let too_many = json!(vec![json!({ "warns": 3, "action": "ban" }); 11]);
+        assert!(policies.check(&too_many, &ctx).is_err());
+
+        let templates = find_setting("moderation.message_templates").unwrap();
+        assert!(
+            templates
+                .check(&json!({ "ban.dm": "Bye {user}" }), &ctx)
+                .is_ok()
+        );
+        assert!(
+            templates
+                .check(&json!({ "ban.dm": "Bye {nick}" }), &ctx)
+                .is_err()
+        );
+        assert!(
+            templates
+                .check(&json!({ "ping.dm": "Pong" }), &ctx)
+                .is_err()
+        );
+
+        let restrictions = find_setting("moderation.restriction_roles").unwrap();
+        assert!(restrictions.check(&json!({ "media": 5 }), &ctx).is_err());
+
+        let overrides = find_setting("commands.overrides").unwrap();
+        let grant = |scope: Value| json!([{ "scope": scope, "target": { "type": "role", "id": 5 }, "allow": true }]);
+        assert!(
+            overrides
+                .check(&grant(json!({ "command": "ban" })), &ctx)
+                .is_ok()
+        );
+        assert!(
+            overrides
+                .check(&grant(json!({ "category": "moderation" })), &ctx)
+                .is_ok()
+        );
+        assert!(
+            overrides
+                .check(&grant(json!({ "command": "kick" })), &ctx)
+                .is_err()
+        );
+        assert!(
+            overrides
+                .check(&grant(json!({ "category": "developer" })), &ctx)
+                .is_err()
+        );
+    }
+}
@@ -0,0 +1,340 @@
+use std::collections::HashMap;
+
+use serenity::all::GuildId;
+
+use crate::{GUILD_SETTINGS, database::ActionType};
+
+/// The maximum length of a custom template
+pub const MAX_TEMPLATE_LENGTH: usize = 2000;
+
+/// Placeholders every template can use
+const PLACEHOLDERS: [&str; 6] = [
+    "server",
+    "duration",
+    "reason",
+    "log_id",
+    "moderator",
+    "user",
+];
+
+/// The DM sent to a member and the response posted in the channel of the command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateKind {
+    Dm,
+    Response,
+}
+
+impl TemplateKind {
+    pub fn all() -> [TemplateKind; 2] {
+        [TemplateKind::Dm, TemplateKind::Response]
+    }
+
+    pub fn from_name(name: &str) -> Option<TemplateKind> {
+        Self::all()
+            .into_iter()
+            .find(|k| k.to_string().eq_ignore_ascii_case(name))
+    }
+}
+
+impl std::fmt::Display for TemplateKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TemplateKind::Dm => write!(f, "dm"),
+            TemplateKind::Response => write!(f, "response"),
+        }
+    }
+}
+
+/// The actions which DM members and can therefore be templated
+pub fn templated_actions() -> Vec<ActionType> {
+    vec![
+        ActionType::Warn,
+        ActionType::Kick,
+        ActionType::Ban,
+        ActionType::Softban,
+        ActionType::Mute,
+        ActionType::Restrict,
+    ]
+}
+
+/// The key a custom template is stored under in `guild_settings.message_templates`, i.e. `ban.dm`
+pub fn template_key(action: &ActionType, kind: TemplateKind) -> String {
+    format!("{action}.{kind}")
+}
+
+/// The built in wording, used unless a guild set its own template
+pub fn default_template(action: &ActionType, kind: TemplateKind) -> &'static str {
+    match (action, kind) {
+        (ActionType::Ban, TemplateKind::Dm) => {
+            "**BANNED**\n-# Server: {server} | Duration: {duration}\n```\n{reason}\n```"
+        }
+        (ActionType::Ban, TemplateKind::Response) => {
+            "**{user} BANNED**\n-# Log ID: `{log_id}` | Duration: {duration}{status}\n```\n{reason}\n```"
+        }
+        (ActionType::Mute, TemplateKind::Dm) => {
+            "**TIMEOUT**\n-# Server: {server} | Duration: {duration}\n```\n{reason}\n```"
+        }
+        (ActionType::Mute, TemplateKind::Response) => {
+            "**{user} TIMEOUT**\n-# Log ID: `{log_id}` | Duration: {duration}{status}\n```\n{reason}\n```"
+        }
+        (ActionType::Kick | ActionType::Softban, TemplateKind::Dm) => {
+            "**KICKED**\n-# Server: {server}\n```\n{reason}\n```"
+        }
+        (ActionType::Kick, TemplateKind::Response) => {
+            "**{user} KICKED**\n-# Log ID: `{log_id}`{status}\n```\n{reason}\n```"
+        }
+        (ActionType::Softban, TemplateKind::Response) => {
+            "**{user} SOFTBANNED**\n-# Log ID: `{log_id}`{status}\n```\n{reason}\n```"
+        }
+        (ActionType::Warn, TemplateKind::Dm) => {
+            "**WARNED**\n-# Server: {server}\n```\n{reason}\n```"
+        }
+        (ActionType::Warn, TemplateKind::Response) => {
+            "**{user} WARNED**\n-# Log ID: `{log_id}`{status}\n```\n{reason}\n```"
+        }
+        (ActionType::Restrict, TemplateKind::Dm) => {
+            "**RESTRICTED**\n-# Server: {server} | Restriction: {restriction} | Duration: {duration}\n```\n{reason}\n```"
+        }
+        (ActionType::Restrict, TemplateKind::Response) => {
+            "**{user} RESTRICTED**\n-# Log ID: `{log_id}` | Restriction: {restriction} | Duration: {duration}{status}\n```\n{reason}\n```"
+        }
+        (_, TemplateKind::Dm) => "**{server}**\n```\n{reason}\n```",
+        (_, TemplateKind::Response) => {
+            "**{user}**\n-# Log ID: `{log_id}`{status}\n```\n{reason}\n```"
+        }
+    }
+}
+
+/// The templates a guild uses for an action, falling back to the defaults
+pub async fn guild_templates(guild_id: GuildId, action: &ActionType) -> (String, String) {
+    let custom = {
+        let mut lock = GUILD_SETTINGS.lock().await;
+        lock.get(guild_id.get())
+            .await
+            .map(|s| s.moderation.message_templates)
+            .unwrap_or_default()
+    };
+
+    let template = |kind| {
+        custom
+            .get(&template_key(action, kind))
+            .cloned()
+            .unwrap_or(String::from(default_template(action, kind)))
+    };
+
+    (template(TemplateKind::Dm), template(TemplateKind::Response))
+}
+
+/// Returns the placeholders of a template which don't exist for the action
+pub fn unknown_placeholders(
+    template: &str,
+    action: &ActionType,
+    kind: TemplateKind,
+) -> Vec<String> {
+    let mut unknown = vec![];
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        rest = &rest[start + 1..];
+
+        let Some(end) = rest.find('}') else {
+            break;
+        };
+
+        let name = &rest[..end];
+        let is_placeholder =
+            !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        let known = PLACEHOLDERS.contains(&name)
+            || (name == "restriction" && *action == ActionType::Restrict)
+            || (name == "status" && kind == TemplateKind::Response);
+
+        if is_placeholder && !known {
+            unknown.push(format!("{{{name}}}"));
+        }
+    }
+
+    unknown
+}
+
+/// Checks the length and placeholders of a custom template
+pub fn validate_template(
+    template: &str,
+    action: &ActionType,
+    kind: TemplateKind,
+) -> Result<(), String> {
+    if template.trim().is_empty() {
+        return Err(String::from("Templates can't be empty"));
+    }
+
+    if template.chars().count() > MAX_TEMPLATE_LENGTH {
+        return Err(format!(
+            "Templates can be at most {MAX_TEMPLATE_LENGTH} characters long"
+        ));
+    }
+
+    let unknown = unknown_placeholders(template, action, kind);
+    if !unknown.is_empty() {
+        return Err(format!("Unknown placeholders: {}", unknown.join(", ")));
+    }
+
+    Ok(())
+}
+
+/// Validates all custom templates of a guild, as the `templates` command would
+pub fn validate_templates(templates: &HashMap<String, String>) -> Result<(), String> {
+    for (key, template) in templates {
+        let Some((action, kind)) = templated_actions().into_iter().find_map(|action| {
+            TemplateKind::all()
+                .into_iter()
+                .find(|kind| template_key(&action, *kind) == *key)
+                .map(|kind| (action, kind))
+        }) else {
+            return Err(format!("`{key}` is not a template"));
+        };
+
+        validate_template(template, &action, kind).map_err(|err| format!("`{key}`: {err}"))?;
+    }
+
+    Ok(())
+}
+
+/// The values filled into the placeholders of a template
+#[derive(Debug, Clone, Default)]
+pub struct TemplateValues {
+    pub server: String,
+    pub duration: String,
+    pub reason: String,
+    pub log_id: String,
+    pub moderator: String,
+    pub user: String,
+    /// The restriction name, only used by restrict templates
+    pub restriction: String,
+}
+
+impl TemplateValues {
+    fn value(&self, name: &str) -> Option<&str> {
+        Some(match name {
+            "server" => &self.server,
+            "duration" => &self.duration,
+            "reason" => &self.reason,
+            "log_id" => &self.log_id,
+            "moderator" => &self.moderator,
+            "user" => &self.user,
+            "restriction" => &self.restriction,
+            _ => return None,
+        })
+    }
+
+    /// Fills in the placeholders, unknown placeholders are kept as is.
+    /// Values are never searched for placeholders themselves, so a reason can't inject other values.
+    pub fn render(&self, template: &str) -> String {
+        let mut out = String::with_capacity(template.len());
+        let mut rest = template;
+
+        while let Some(start) = rest.find('{') {
+            out.push_str(&rest[..start]);
+            rest = &rest[start..];
+
+            match rest
+                .find('}')
+                .and_then(|end| self.value(&rest[1..end]).map(|v| (end, v)))
+            {
+                Some((end, value)) => {
+                    out.push_str(value);
+                    rest = &rest[end + 1..];
+                }
+                None => {
+                    out.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+
+        out.push_str(rest);
+        out
+    }
+
+    /// Renders a response template into the content of a [`CommandMessageResponse`](super::CommandMessageResponse).
+    /// `{status}` is replaced by `details` followed by the DM status, templates without it get the status appended.
+    pub fn render_response(
+        &self,
+        template: &str,
+        details: String,
+    ) -> Box<dyn Fn(String) -> String + Send + Sync> {
+        let parts = template
+            .split("{status}")
+            .map(|p| self.render(p))
+            .collect::<Vec<_>>();
+
+        Box::new(move |addition| {
+            let status = format!("{details}{addition}");
+
+            if parts.len() > 1 {
+                return parts.join(&status);
+            }
+
+            let status = status.trim_start_matches([' ', '|']);
+            if status.is_empty() {
+                parts.concat()
+            } else {
+                format!("{}\n-# {status}", parts.concat())
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TemplateKind, TemplateValues, unknown_placeholders};
+    use crate::database::ActionType;
+
+    fn values() -> TemplateValues {
+        TemplateValues {
+            server: String::from("Test Server"),
+            duration: String::from("1 day"),
+            reason: String::from("spamming {server}"),
+            log_id: String::from("abc123"),
+            moderator: String::from("<@1>"),
+            user: String::from("<@2>"),
+            restriction: String::new(),
+        }
+    }
+
+    #[test]
+    fn renders_placeholders_once() {
+        assert_eq!(
+            values().render("**{server}** {duration} {unknown} {reason} {"),
+            "**Test Server** 1 day {unknown} spamming {server} {"
+        );
+    }
+
+    #[test]
+    fn renders_the_status_of_responses() {
+        let with_status = values().render_response("{user} `{log_id}`{status}", String::new());
+        assert_eq!(
+            with_status(String::from(" | DM failed")),
+            "<@2> `abc123` | DM failed"
+        );
+
+        let without_status = values().render_response("{user} was warned", String::new());
+        assert_eq!(without_status(String::new()), "<@2> was warned");
+        assert_eq!(
+            without_status(String::from(" | DM failed")),
+            "<@2> was warned\n-# DM failed"
+        );
+    }
+
+    #[test]
+    fn finds_unknown_placeholders() {
+        let template = "{server} {status} {restriction} {nope} {not a placeholder}";
+
+        assert_eq!(
+            unknown_placeholders(template, &ActionType::Restrict, TemplateKind::Response),
+            vec!["{nope}"]
+        );
+        assert_eq!(
+            unknown_placeholders(template, &ActionType::Ban, TemplateKind::Dm),
+            vec!["{status}", "{restriction}", "{nope}"]
+        );
+    }
+}
@@ -0,0 +1,300 @@
+use std::{collections::HashMap, fmt::Write};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serenity::{
+    all::{CacheHttp, ChannelId, CreateAttachment, GetMessages, Message, MessageId},
+    json,
+};
+
+use crate::utils::{cache::partials::PartialMessage, snowflake_to_timestamp};
+
+/// The maximum amount of messages fetched for a single transcript
+pub const MAX_TRANSCRIPT_MESSAGES: usize = 1000;
+
+#[derive(Debug, Serialize, Clone)]
+pub struct TranscriptAttachment {
+    pub name: String,
+    pub url: String,
+}
+
+/// A single message as it is rendered in a transcript
+#[derive(Debug, Serialize, Clone)]
+pub struct TranscriptMessage {
+    pub id: u64,
+    pub channel_id: u64,
+    pub author_id: u64,
+    pub author_name: String,
+    pub bot: bool,
+    pub timestamp: String,
+    pub edited_timestamp: Option<String>,
+    pub content: String,
+    pub attachments: Vec<TranscriptAttachment>,
+    pub embeds: usize,
+    pub reply_to: Option<u64>,
+}
+
+impl From<&Message> for TranscriptMessage {
+    fn from(value: &Message) -> Self {
+        Self {
+            id: value.id.get(),
+            channel_id: value.channel_id.get(),
+            author_id: value.author.id.get(),
+            author_name: value.author.name.clone(),
+            bot: value.author.bot,
+            timestamp: value.timestamp.to_rfc3339().unwrap_or_default(),
+            edited_timestamp: value.edited_timestamp.and_then(|t| t.to_rfc3339()),
+            content: value.content.clone(),
+            attachments: value
+                .attachments
+                .iter()
+                .map(|a| TranscriptAttachment {
+                    name: a.filename.clone(),
+                    url: a.url.clone(),
+                })
+                .collect(),
+            embeds: value.embeds.len(),
+            reply_to: value
+                .message_reference
+                .as_ref()
+                .and_then(|r| r.message_id)
+                .map(|id| id.get()),
+        }
+    }
+}
+
+impl From<&PartialMessage> for TranscriptMessage {
+    fn from(value: &PartialMessage) -> Self {
+        Self {
+            id: value.id,
+            channel_id: value.channel_id,
+            author_id: value.author.id,
+            author_name: value.author.name.clone(),
+            bot: value.author.bot,
+            timestamp: snowflake_to_timestamp(value.id).to_rfc3339(),
+            edited_timestamp: None,
+            content: value.content.clone(),
+            attachments: value
+                .attachment_urls
+                .iter()
+                .map(|a| TranscriptAttachment {
+                    name: a.name.clone(),
+                    url: a.url.clone(),
+                })
+                .collect(),
+            embeds: 0,
+            reply_to: value.reply_to,
+        }
+    }
+}
+
+/// A list of messages which can be rendered into a self-contained HTML file and a JSON file
+#[derive(Debug, Serialize, Clone)]
+pub struct Transcript {
+    pub title: String,
+    pub generated_at: String,
+    pub messages: Vec<TranscriptMessage>,
+}
+
+impl Transcript {
+    /// Creates a transcript, messages are sorted from oldest to newest
+    pub fn new(
+        title: impl Into<String>,
+        messages: impl IntoIterator<Item = TranscriptMessage>,
+    ) -> Self {
+        let mut messages = messages.into_iter().collect::<Vec<_>>();
+        messages.sort_by_key(|m| m.id);
+        messages.dedup_by_key(|m| m.id);
+
+        Self {
+            title: title.into(),
+            generated_at: Utc::now().to_rfc3339(),
+            messages,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn to_html(&self) -> String {
+        let by_id = self
+            .messages
+            .iter()
+            .map(|m| (m.id, m))
+            .collect::<HashMap<_, _>>();
+
+        let mut out = String::new();
+
+        let _ = write!(
+            out,
+            "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<header>\n<h1>{title}</h1>\n<p>{} messages | Generated {} UTC</p>\n</header>\n<main>\n",
+            self.messages.len(),
+            format_timestamp(&self.generated_at),
+            title = escape_html(&self.title),
+        );
+
+        for m in &self.messages {
+            let _ = writeln!(out, "<article id=\"m{}\">", m.id);
+
+            if let Some(reply) = m.reply_to {
+                match by_id.get(&reply) {
+                    Some(replied) => {
+                        let snippet = replied.content.chars().take(80).collect::<String>();
+                        let _ = writeln!(
+                            out,
+                            "<div class=\"reply\">&#8618; <a href=\"#m{reply}\">{}</a> {}</div>",
+                            escape_html(&replied.author_name),
+                            escape_html(&snippet)
+                        );
+                    }
+                    None => {
+                        let _ = writeln!(
+                            out,
+                            "<div class=\"reply\">&#8618; replying to message {reply}</div>"
+                        );
+                    }
+                }
+            }
+
+            let _ = writeln!(
+                out,
+                "<div class=\"meta\"><span class=\"author\">{}</span>{} <span class=\"id\">{}</span> <time datetime=\"{}\">{}</time>{}</div>",
+                escape_html(&m.author_name),
+                if m.bot {
+                    " <span class=\"bot\">BOT</span>"
+                } else {
+                    ""
+                },
+                m.author_id,
+                m.timestamp,
+                format_timestamp(&m.timestamp),
+                if m.edited_timestamp.is_some() {
+                    " <span class=\"edited\">(edited)</span>"
+                } else {
+                    ""
+                },
+            );
+
+            if !m.content.is_empty() {
+                let _ = writeln!(
+                    out,
+                    "<div class=\"content\">{}</div>",
+                    escape_html(&m.content)
+                );
+            }
+
+            for attachment in &m.attachments {
+                let url = escape_html(&attachment.url);
+                let name = escape_html(&attachment.name);
+
+                if is_image(&attachment.name) {
+                    let _ = writeln!(
+                        out,
+                        "<div class=\"attachment\"><a href=\"{url}\"><img src=\"{url}\" alt=\"{name}\" loading=\"lazy\"></a></div>"
+                    );
+                } else {
+                    let _ = writeln!(
+                        out,
+                        "<div class=\"attachment\"><a href=\"{url}\">{name}</a></div>"
+                    );
+                }
+            }
+
+            if m.embeds > 0 {
+                let _ = writeln!(out, "<div class=\"embeds\">{} embed(s)</div>", m.embeds);
+            }
+
+            out.push_str("</article>\n");
+        }
+
+        out.push_str("</main>\n</body>\n</html>\n");
+        out
+    }
+
+    /// The HTML and JSON renders as files, named `<name>.html` and `<name>.json`
+    pub fn to_attachments(&self, name: &str) -> Vec<CreateAttachment> {
+        vec![
+            CreateAttachment::bytes(self.to_html().into_bytes(), format!("{name}.html")),
+            CreateAttachment::bytes(self.to_json().into_bytes(), format!("{name}.json")),
+        ]
+    }
+}
+
+/// Fetches up to `count` messages of a channel sent before a message, newest message first
+pub async fn fetch_transcript_messages(
+    ctx: impl CacheHttp,
+    channel_id: ChannelId,
+    before: MessageId,
+    count: usize,
+) -> Result<Vec<Message>, serenity::Error> {
+    let count = count.min(MAX_TRANSCRIPT_MESSAGES);
+    let mut cursor = before;
+    let mut messages = vec![];
+
+    while messages.len() < count {
+        let limit = (count - messages.len()).min(100) as u8;
+        let page = channel_id
+            .messages(&ctx, GetMessages::new().before(cursor).limit(limit))
+            .await?;
+
+        let Some(last) = page.last() else {
+            break;
+        };
+
+        cursor = last.id;
+        let done = page.len() < limit as usize;
+        messages.extend(page);
+
+        if done {
+            break;
+        }
+    }
+
+    Ok(messages)
+}
+
+const STYLE: &str = "body{margin:0;background:#313338;color:#dbdee1;font-family:sans-serif;font-size:15px}\
+header{padding:16px 24px;background:#2b2d31;border-bottom:1px solid #1e1f22}\
+h1{margin:0 0 4px;font-size:20px;color:#f2f3f5}header p{margin:0;color:#949ba4;font-size:13px}\
+main{padding:8px 24px}article{padding:6px 0;border-bottom:1px solid #3f4147}\
+.meta{font-size:13px;color:#949ba4}.author{font-weight:bold;color:#f2f3f5;font-size:15px}\
+.bot{background:#5865f2;color:#fff;border-radius:3px;padding:0 4px;font-size:10px}\
+.reply{font-size:13px;color:#949ba4}.reply a,.attachment a{color:#00a8fc}\
+.content{white-space:pre-wrap;word-wrap:break-word;margin-top:2px}\
+.attachment img{max-width:400px;max-height:300px;margin-top:4px;border-radius:4px}\
+.embeds,.edited{font-size:12px;color:#949ba4}";
+
+fn escape_html(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+
+    for ch in input.chars() {
+        match ch {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(ch),
+        }
+    }
+
+    out
+}
+
+fn format_timestamp(rfc3339: &str) -> String {
+    DateTime::parse_from_rfc3339(rfc3339)
+        .map(|t| {
+            t.with_timezone(&Utc)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_default()
+}
+
+fn is_image(name: &str) -> bool {
+    let name = name.to_lowercase();
+    [".png", ".jpg", ".jpeg", ".gif", ".webp"]
+        .iter()
+        .any(|ext| name.ends_with(ext))
+}
@@ -0,0 +1,92 @@
+/// Discords upload limit for a single message in servers without boosts (10 MiB)
+pub const MAX_UPLOAD_SIZE: usize = 10 * 1024 * 1024;
+/// The total size of the files sent with one message, leaving room for the rest of the multipart body
+const UPLOAD_BUDGET: usize = MAX_UPLOAD_SIZE - 512 * 1024;
+/// The maximum amount of files Discord accepts per message
+const MAX_UPLOAD_FILES: usize = 10;
+/// The maximum length of the list of files which were not attached, keeping embeds below their limits
+const MAX_NOT_ATTACHED_LENGTH: usize = 1000;
+
+/// Splits files into the ones which fit into a single message and the ones over the upload budget, keeping their order
+pub fn split_upload_budget<T>(files: Vec<T>, size: impl Fn(&T) -> usize) -> (Vec<T>, Vec<T>) {
+    let mut total = 0;
+    let mut attached = vec![];
+    let mut over = vec![];
+
+    for file in files {
+        let file_size = size(&file);
+
+        if attached.len() < MAX_UPLOAD_FILES && total + file_size <= UPLOAD_BUDGET {
+            total += file_size;
+            attached.push(file);
+        } else {
+            over.push(file);
+        }
+    }
+
+    (attached, over)
+}
+
+/// Lists files which were not attached, linking the ones which still have an url, i.e.
+/// `` -# Not attached, over the upload limit: [a.png](<url>), `b.html` ``
+pub fn describe_not_attached<'a>(
+    files: impl IntoIterator<Item = (&'a str, Option<&'a str>)>,
+) -> String {
+    let mut list = String::new();
+
+    for (name, url) in files {
+        let link = match url {
+            Some(url) => format!("[{}](<{url}>)", name.replace(['[', ']'], "")),
+            None => format!("`{}`", name.replace('`', "")),
+        };
+
+        // the file names alone are still useful once the links don't fit anymore
+        let entry = if list.len() + link.len() > MAX_NOT_ATTACHED_LENGTH {
+            format!("`{}`", name.replace('`', ""))
+        } else {
+            link
+        };
+
+        if !list.is_empty() {
+            list.push_str(", ");
+        }
+
+        list.push_str(&entry);
+    }
+
+    if list.is_empty() {
+        return list;
+    }
+
+    format!("-# Not attached, over the upload limit: {list}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{UPLOAD_BUDGET, describe_not_attached, split_upload_budget};
+
+    #[test]
+    fn splits_files_over_the_budget() {
+        let files = vec![6 * 1024 * 1024, 4 * 1024 * 1024, 1024, 8 * 1024 * 1024];
+        let (attached, over) = split_upload_budget(files, |f| *f);
+
+        assert_eq!(attached, vec![6 * 1024 * 1024, 1024]);
+        assert_eq!(over, vec![4 * 1024 * 1024, 8 * 1024 * 1024]);
+        assert!(attached.iter().sum::<usize>() <= UPLOAD_BUDGET);
+
+        let (attached, over) = split_upload_budget(vec![1; 12], |f| *f);
+        assert_eq!((attached.len(), over.len()), (10, 2));
+    }
+
+    #[test]
+    fn links_files_which_were_not_attached() {
+        assert_eq!(describe_not_attached([]), "");
+        assert_eq!(
+            describe_not_attached([
+                ("a.png", Some("https://cdn.discordapp.com/a.png")),
+                ("b.html", None)
+            ]),
+            "-# Not attached, over the upload limit: [a.png](<https://cdn.discordapp.com/a.png>), `b.html`"
+        );
+    }
+}